@@ -6,7 +6,7 @@ use crate::config::{PermissionMode, TaskConfig};
 use crate::error::{Error, Result};
 use crate::event::*;
 use crate::process::{spawn_and_stream, StreamHandle};
-use crate::runner::AgentRunner;
+use crate::runner::{AgentRunner, PromptPassing};
 
 /// Adapter for Claude Code (`claude` CLI).
 ///
@@ -14,7 +14,7 @@ use crate::runner::AgentRunner;
 ///   claude -p "<prompt>" --output-format stream-json --verbose
 ///
 /// Stream format: NDJSON with event types:
-///   - { type: "system", subtype: "init", session_id, model, cwd }
+///   - { type: "system", subtype: "init", session_id, model, cwd, claude_code_version? }
 ///   - { type: "assistant", message: { role, content: [{ type: "text", text }, { type: "tool_use", ... }] } }
 ///   - { type: "user", message: { role, content: [{ type: "tool_result", ... }] } }
 ///   - { type: "result", subtype: "success"|"error_*", result, session_id, duration_ms, ... }
@@ -36,8 +36,6 @@ impl AgentRunner for ClaudeRunner {
 
     fn build_args(&self, config: &TaskConfig) -> Vec<String> {
         let mut args = vec![
-            "-p".to_string(),
-            config.prompt.clone(),
             "--output-format".to_string(),
             "stream-json".to_string(),
             "--verbose".to_string(),
@@ -45,7 +43,7 @@ impl AgentRunner for ClaudeRunner {
 
         if let Some(ref model) = config.model {
             args.push("--model".to_string());
-            args.push(model.clone());
+            args.push(self.normalize_model(model));
         }
 
         match config.permission_mode {
@@ -78,10 +76,20 @@ impl AgentRunner for ClaudeRunner {
             args.push(asp.clone());
         }
 
+        if let Some(ref id) = config.resume_session_id {
+            args.push("--resume".to_string());
+            args.push(id.clone());
+        }
+
         args.extend(config.extra_args.iter().cloned());
+        self.prompt_passing().apply(&mut args, &config.prompt);
         args
     }
 
+    fn prompt_passing(&self) -> PromptPassing {
+        PromptPassing::Flag("-p")
+    }
+
     fn build_env(&self, _config: &TaskConfig) -> Vec<(String, String)> {
         // Claude Code reads ANTHROPIC_API_KEY from the environment directly.
         vec![]
@@ -92,7 +100,14 @@ impl AgentRunner for ClaudeRunner {
         config: &TaskConfig,
         cancel_token: Option<tokio_util::sync::CancellationToken>,
     ) -> Result<StreamHandle> {
-        spawn_and_stream(self, config, parse_claude_line, cancel_token).await
+        let include_reasoning = config.include_reasoning;
+        spawn_and_stream(
+            self,
+            config,
+            move |line| parse_claude_line(line, include_reasoning),
+            cancel_token,
+        )
+        .await
     }
 
     fn capabilities(&self) -> crate::runner::AgentCapabilities {
@@ -102,11 +117,21 @@ impl AgentRunner for ClaudeRunner {
             supports_model: true,
             supports_max_turns: true,
             supports_append_system_prompt: true,
+            supports_images: false,
+            supports_streaming: true,
+            supports_resume: true,
+            supports_tool_allowlist: true,
+            supports_reasoning: true,
         }
     }
+
+    fn supports_permission_mode(&self, _mode: PermissionMode) -> bool {
+        // `--permission-mode plan` is Claude's own read-only mode.
+        true
+    }
 }
 
-fn parse_claude_line(line: &str) -> Vec<Result<Event>> {
+fn parse_claude_line(line: &str, include_reasoning: bool) -> Vec<Result<Event>> {
     let value: serde_json::Value = match serde_json::from_str(line) {
         Ok(v) => v,
         Err(e) => return vec![Err(Error::ParseError(format!("invalid JSON: {e}: {line}")))],
@@ -117,7 +142,7 @@ fn parse_claude_line(line: &str) -> Vec<Result<Event>> {
         None => return vec![],
     };
 
-    match event_type {
+    let events = match event_type {
         "system" => {
             let subtype = value.get("subtype").and_then(|v| v.as_str()).unwrap_or("");
             if subtype == "init" {
@@ -136,7 +161,13 @@ fn parse_claude_line(line: &str) -> Vec<Result<Event>> {
                         .get("cwd")
                         .and_then(|v| v.as_str())
                         .map(|s| s.to_string()),
+                    permission_mode: None,
+                    agent_version: value
+                        .get("claude_code_version")
+                        .and_then(|v| v.as_str())
+                        .map(|s| s.to_string()),
                     timestamp_ms: 0,
+                    raw_type: None,
                 }))]
             } else {
                 vec![]
@@ -145,22 +176,47 @@ fn parse_claude_line(line: &str) -> Vec<Result<Event>> {
 
         "assistant" => {
             // An assistant message can contain both text blocks and tool_use blocks.
-            // We emit a Message event for the text and ToolStart events for each tool_use.
+            // Events follow block order: adjacent text blocks merge into one
+            // Message, and each tool_use becomes a ToolStart where it appears.
             let mut events = Vec::new();
 
             let content = value.pointer("/message/content").and_then(|v| v.as_array());
             if let Some(blocks) = content {
-                let mut text_parts = Vec::new();
+                let mut text = String::new();
+                let flush_text = |text: &mut String, events: &mut Vec<Result<Event>>| {
+                    if !text.is_empty() {
+                        events.push(Ok(Event::Message(MessageEvent {
+                            role: Role::Assistant,
+                            text: std::mem::take(text),
+                            usage: None,
+                            timestamp_ms: 0,
+                            raw_type: None,
+                        })));
+                    }
+                };
 
                 for block in blocks {
                     let block_type = block.get("type").and_then(|v| v.as_str()).unwrap_or("");
                     match block_type {
                         "text" => {
                             if let Some(t) = block.get("text").and_then(|v| v.as_str()) {
-                                text_parts.push(t);
+                                text.push_str(t);
+                            }
+                        }
+                        "thinking" if include_reasoning => {
+                            let thinking =
+                                block.get("thinking").and_then(|v| v.as_str()).unwrap_or("");
+                            if !thinking.is_empty() {
+                                flush_text(&mut text, &mut events);
+                                events.push(Ok(Event::Reasoning(ReasoningEvent {
+                                    text: thinking.to_string(),
+                                    timestamp_ms: 0,
+                                    raw_type: None,
+                                })));
                             }
                         }
                         "tool_use" => {
+                            flush_text(&mut text, &mut events);
                             let call_id = block
                                 .get("id")
                                 .and_then(|v| v.as_str())
@@ -177,25 +233,14 @@ fn parse_claude_line(line: &str) -> Vec<Result<Event>> {
                                 tool_name,
                                 input,
                                 timestamp_ms: 0,
+                                raw_type: None,
                             })));
                         }
                         _ => {}
                     }
                 }
 
-                let text = text_parts.join("");
-                if !text.is_empty() {
-                    // Insert text message before tool events.
-                    events.insert(
-                        0,
-                        Ok(Event::Message(MessageEvent {
-                            role: Role::Assistant,
-                            text,
-                            usage: None,
-                            timestamp_ms: 0,
-                        })),
-                    );
-                }
+                flush_text(&mut text, &mut events);
             }
 
             events
@@ -242,6 +287,7 @@ fn parse_claude_line(line: &str) -> Vec<Result<Event>> {
                             output,
                             usage: None,
                             timestamp_ms: 0,
+                            raw_type: None,
                         })));
                     }
                 }
@@ -262,6 +308,7 @@ fn parse_claude_line(line: &str) -> Vec<Result<Event>> {
                 events.push(Ok(Event::TextDelta(TextDeltaEvent {
                     text: delta_text.to_string(),
                     timestamp_ms: 0,
+                    raw_type: None,
                 })));
             }
 
@@ -272,6 +319,7 @@ fn parse_claude_line(line: &str) -> Vec<Result<Event>> {
                     events.push(Ok(Event::UsageDelta(UsageDeltaEvent {
                         usage,
                         timestamp_ms: 0,
+                        raw_type: None,
                     })));
                 }
             }
@@ -284,7 +332,7 @@ fn parse_claude_line(line: &str) -> Vec<Result<Event>> {
                 .get("subtype")
                 .and_then(|v| v.as_str())
                 .unwrap_or("success");
-            let success = subtype == "success";
+            let success = matches!(subtype, "success" | "checkpoint");
             let result_text = value
                 .get("result")
                 .and_then(|v| v.as_str())
@@ -307,12 +355,16 @@ fn parse_claude_line(line: &str) -> Vec<Result<Event>> {
                 duration_ms,
                 total_cost_usd,
                 usage,
+                exit_code: super::result_exit_code(&value),
+                partial: super::result_is_partial(&value),
                 timestamp_ms: 0,
+                raw_type: None,
             }))]
         }
 
         _ => vec![],
-    }
+    };
+    super::tag_raw_type(events, event_type)
 }
 
 fn parse_usage_data(value: &serde_json::Value) -> UsageData {
@@ -341,7 +393,7 @@ mod tests {
     #[test]
     fn parse_init_event() {
         let line = r#"{"type":"system","subtype":"init","session_id":"abc-123","model":"opus","cwd":"/tmp"}"#;
-        let events = parse_claude_line(line);
+        let events = parse_claude_line(line, false);
         assert_eq!(events.len(), 1);
         let event = events.into_iter().next().unwrap().unwrap();
         match event {
@@ -355,10 +407,20 @@ mod tests {
         }
     }
 
+    #[test]
+    fn parsed_events_record_raw_type() {
+        let line = r#"{"type":"assistant","message":{"role":"assistant","content":[{"type":"text","text":"Hi"},{"type":"tool_use","id":"tu-1","name":"bash","input":{}}]}}"#;
+        let events = parse_claude_line(line, false);
+        assert_eq!(events.len(), 2);
+        for event in events {
+            assert_eq!(event.unwrap().raw_type(), Some("assistant"));
+        }
+    }
+
     #[test]
     fn parse_assistant_message() {
         let line = r#"{"type":"assistant","message":{"role":"assistant","content":[{"type":"text","text":"Hello world"}]}}"#;
-        let events = parse_claude_line(line);
+        let events = parse_claude_line(line, false);
         assert_eq!(events.len(), 1);
         let event = events.into_iter().next().unwrap().unwrap();
         match event {
@@ -373,16 +435,51 @@ mod tests {
     #[test]
     fn parse_assistant_with_tool_use() {
         let line = r#"{"type":"assistant","message":{"role":"assistant","content":[{"type":"text","text":"Let me check"},{"type":"tool_use","id":"tu-1","name":"bash","input":{"command":"ls"}}]}}"#;
-        let events = parse_claude_line(line);
+        let events = parse_claude_line(line, false);
         assert_eq!(events.len(), 2);
         assert!(matches!(&events[0], Ok(Event::Message(m)) if m.text == "Let me check"));
         assert!(matches!(&events[1], Ok(Event::ToolStart(t)) if t.tool_name == "bash" && t.call_id == "tu-1"));
     }
 
+    #[test]
+    fn parse_assistant_preserves_block_order() {
+        let line = r#"{"type":"assistant","message":{"role":"assistant","content":[{"type":"text","text":"First "},{"type":"text","text":"look"},{"type":"tool_use","id":"tu-1","name":"bash","input":{}},{"type":"text","text":"Now edit"},{"type":"tool_use","id":"tu-2","name":"edit","input":{}}]}}"#;
+        let events: Vec<Event> = parse_claude_line(line, false)
+            .into_iter()
+            .map(|r| r.unwrap())
+            .collect();
+        assert_eq!(events.len(), 4);
+        assert!(matches!(&events[0], Event::Message(m) if m.text == "First look"));
+        assert!(matches!(&events[1], Event::ToolStart(t) if t.call_id == "tu-1"));
+        assert!(matches!(&events[2], Event::Message(m) if m.text == "Now edit"));
+        assert!(matches!(&events[3], Event::ToolStart(t) if t.call_id == "tu-2"));
+    }
+
+    #[test]
+    fn parse_thinking_blocks_only_when_included() {
+        let line = r#"{"type":"assistant","message":{"role":"assistant","content":[{"type":"thinking","thinking":"Check the tests first.","signature":"sig"},{"type":"text","text":"On it"}]}}"#;
+        let events: Vec<Event> = parse_claude_line(line, false)
+            .into_iter()
+            .map(|r| r.unwrap())
+            .collect();
+        assert_eq!(events.len(), 1);
+        assert!(matches!(&events[0], Event::Message(m) if m.text == "On it"));
+
+        let events: Vec<Event> = parse_claude_line(line, true)
+            .into_iter()
+            .map(|r| r.unwrap())
+            .collect();
+        assert_eq!(events.len(), 2);
+        assert!(
+            matches!(&events[0], Event::Reasoning(r) if r.text == "Check the tests first." && r.raw_type.as_deref() == Some("assistant"))
+        );
+        assert!(matches!(&events[1], Event::Message(m) if m.text == "On it"));
+    }
+
     #[test]
     fn parse_user_tool_result() {
         let line = r#"{"type":"user","message":{"role":"user","content":[{"type":"tool_result","tool_use_id":"tu-1","content":"file.txt\nREADME.md"}]}}"#;
-        let events = parse_claude_line(line);
+        let events = parse_claude_line(line, false);
         assert_eq!(events.len(), 1);
         match events.into_iter().next().unwrap().unwrap() {
             Event::ToolEnd(t) => {
@@ -397,7 +494,7 @@ mod tests {
     #[test]
     fn parse_user_tool_result_error() {
         let line = r#"{"type":"user","message":{"role":"user","content":[{"type":"tool_result","tool_use_id":"tu-2","is_error":true,"content":"command not found"}]}}"#;
-        let events = parse_claude_line(line);
+        let events = parse_claude_line(line, false);
         assert_eq!(events.len(), 1);
         match events.into_iter().next().unwrap().unwrap() {
             Event::ToolEnd(t) => {
@@ -411,7 +508,7 @@ mod tests {
     #[test]
     fn parse_stream_delta() {
         let line = r#"{"type":"stream_event","event":{"delta":{"type":"text_delta","text":"Hi"}}}"#;
-        let events = parse_claude_line(line);
+        let events = parse_claude_line(line, false);
         assert_eq!(events.len(), 1);
         let event = events.into_iter().next().unwrap().unwrap();
         match event {
@@ -423,7 +520,7 @@ mod tests {
     #[test]
     fn parse_result_success() {
         let line = r#"{"type":"result","subtype":"success","result":"Done","session_id":"s1","duration_ms":1234,"total_cost_usd":0.05}"#;
-        let events = parse_claude_line(line);
+        let events = parse_claude_line(line, false);
         assert_eq!(events.len(), 1);
         let event = events.into_iter().next().unwrap().unwrap();
         match event {
@@ -438,11 +535,47 @@ mod tests {
         }
     }
 
+    #[test]
+    fn parse_result_exit_code() {
+        let line = r#"{"type":"result","subtype":"success","result":"needs review","session_id":"s1","exit_code":3}"#;
+        match parse_claude_line(line, false)
+            .into_iter()
+            .next()
+            .unwrap()
+            .unwrap()
+        {
+            Event::Result(r) => assert_eq!(r.exit_code, Some(3)),
+            other => panic!("expected Result, got {other:?}"),
+        }
+    }
+
+    #[test]
+    fn parse_result_partial() {
+        let flagged = r#"{"type":"result","subtype":"success","result":"halfway","session_id":"s1","partial":true}"#;
+        let checkpoint =
+            r#"{"type":"result","subtype":"checkpoint","result":"halfway","session_id":"s1"}"#;
+        let last = r#"{"type":"result","subtype":"success","result":"Done","session_id":"s1"}"#;
+        for (line, partial) in [(flagged, true), (checkpoint, true), (last, false)] {
+            match parse_claude_line(line, false)
+                .into_iter()
+                .next()
+                .unwrap()
+                .unwrap()
+            {
+                Event::Result(r) => {
+                    assert!(r.success, "{line}");
+                    assert_eq!(r.partial, partial, "{line}");
+                }
+                other => panic!("expected Result, got {other:?}"),
+            }
+        }
+    }
+
     #[test]
     fn parse_result_error() {
         let line =
             r#"{"type":"result","subtype":"error_max_turns","result":"","session_id":"s1"}"#;
-        let events = parse_claude_line(line);
+        let events = parse_claude_line(line, false);
         assert_eq!(events.len(), 1);
         match events.into_iter().next().unwrap().unwrap() {
             Event::Result(r) => assert!(!r.success),
@@ -474,4 +607,36 @@ mod tests {
         assert!(args.contains(&"--max-turns".to_string()));
         assert!(args.contains(&"10".to_string()));
     }
+
+    #[test]
+    fn build_args_resume_session() {
+        let mut config = TaskConfig::new("continue", crate::config::AgentKind::Claude);
+        config.resume_session_id = Some("sess-abc".into());
+        let args = ClaudeRunner.build_args(&config);
+        assert!(args.windows(2).any(|w| w == ["--resume", "sess-abc"]));
+    }
+
+    #[test]
+    fn images_are_rejected() {
+        let mut config = TaskConfig::new("describe", crate::config::AgentKind::Claude);
+        config.images = vec!["shot.png".into()];
+        assert!(crate::runner::ensure_images_supported(&ClaudeRunner, &config).is_err());
+        config.images.clear();
+        assert!(crate::runner::ensure_images_supported(&ClaudeRunner, &config).is_ok());
+    }
+
+    #[test]
+    fn capabilities_streams_text_deltas() {
+        let caps = ClaudeRunner.capabilities();
+        assert!(caps.supports_streaming);
+    }
+
+    #[test]
+    fn permission_modes_are_native() {
+        assert!(ClaudeRunner.supports_permission_mode(PermissionMode::FullAccess));
+        assert!(ClaudeRunner.supports_permission_mode(PermissionMode::ReadOnly));
+        let mut config = TaskConfig::new("look", crate::config::AgentKind::Claude);
+        config.permission_mode = PermissionMode::ReadOnly;
+        assert!(ClaudeRunner.validate_config(&config).is_empty());
+    }
 }
@@ -42,7 +42,7 @@ impl AgentRunner for CodexRunner {
 
         if let Some(ref model) = config.model {
             args.push("--model".to_string());
-            args.push(model.clone());
+            args.push(self.normalize_model(model));
         }
 
         // Map permission mode to Codex's --sandbox + approval flags.
@@ -58,10 +58,19 @@ impl AgentRunner for CodexRunner {
             }
         }
 
+        for image in &config.images {
+            args.push("--image".to_string());
+            args.push(image.display().to_string());
+        }
+
         args.extend(config.extra_args.iter().cloned());
 
-        // Prompt must come last.
-        args.push(config.prompt.clone());
+        // Prompt must come last. `--image` takes several values, so end the
+        // options first or the prompt is read as another image.
+        if !config.images.is_empty() {
+            args.push("--".to_string());
+        }
+        self.prompt_passing().apply(&mut args, &config.prompt);
         args
     }
 
@@ -75,7 +84,14 @@ impl AgentRunner for CodexRunner {
         config: &TaskConfig,
         cancel_token: Option<tokio_util::sync::CancellationToken>,
     ) -> Result<StreamHandle> {
-        spawn_and_stream(self, config, parse_codex_line, cancel_token).await
+        let include_reasoning = config.include_reasoning;
+        spawn_and_stream(
+            self,
+            config,
+            move |line| parse_codex_line(line, include_reasoning),
+            cancel_token,
+        )
+        .await
     }
 
     fn capabilities(&self) -> crate::runner::AgentCapabilities {
@@ -85,11 +101,21 @@ impl AgentRunner for CodexRunner {
             supports_model: true,
             supports_max_turns: false,
             supports_append_system_prompt: false,
+            supports_images: true,
+            supports_streaming: false,
+            supports_resume: false,
+            supports_tool_allowlist: false,
+            supports_reasoning: true,
         }
     }
+
+    fn supports_permission_mode(&self, _mode: PermissionMode) -> bool {
+        // `--sandbox read-only` is enforced by Codex itself.
+        true
+    }
 }
 
-fn parse_codex_line(line: &str) -> Vec<Result<Event>> {
+fn parse_codex_line(line: &str, include_reasoning: bool) -> Vec<Result<Event>> {
     let value: serde_json::Value = match serde_json::from_str(line) {
         Ok(v) => v,
         Err(e) => return vec![Err(Error::ParseError(format!("invalid JSON: {e}: {line}")))],
@@ -100,7 +126,7 @@ fn parse_codex_line(line: &str) -> Vec<Result<Event>> {
         None => return vec![],
     };
 
-    match event_type {
+    let events = match event_type {
         "thread.started" => {
             let thread_id = value
                 .get("thread_id")
@@ -115,7 +141,10 @@ fn parse_codex_line(line: &str) -> Vec<Result<Event>> {
                     .and_then(|v| v.as_str())
                     .map(|s| s.to_string()),
                 cwd: None,
+                permission_mode: None,
+                agent_version: None,
                 timestamp_ms: 0,
+                raw_type: None,
             }))]
         }
 
@@ -146,6 +175,7 @@ fn parse_codex_line(line: &str) -> Vec<Result<Event>> {
                         tool_name: "shell".to_string(),
                         input: Some(serde_json::json!({ "command": command })),
                         timestamp_ms: 0,
+                        raw_type: None,
                     }))]
                 }
                 _ => vec![],
@@ -206,6 +236,7 @@ fn parse_codex_line(line: &str) -> Vec<Result<Event>> {
                         text,
                         usage: None,
                         timestamp_ms: 0,
+                        raw_type: None,
                     }))]
                 }
 
@@ -237,6 +268,7 @@ fn parse_codex_line(line: &str) -> Vec<Result<Event>> {
                         output: output.or_else(|| Some(serde_json::json!({ "command": command }).to_string())),
                         usage: None,
                         timestamp_ms: 0,
+                        raw_type: None,
                     }))]
                 }
 
@@ -252,13 +284,19 @@ fn parse_codex_line(line: &str) -> Vec<Result<Event>> {
                         .unwrap_or("")
                         .to_string();
 
+                    let mut input = serde_json::json!({ "path": path });
+                    if let Some(changes) = item.get("changes") {
+                        input["changes"] = changes.clone();
+                    }
+
                     // Emit both ToolStart and ToolEnd for file_change items.
                     vec![
                         Ok(Event::ToolStart(ToolStartEvent {
                             call_id: call_id.clone(),
                             tool_name: "file_change".to_string(),
-                            input: Some(serde_json::json!({ "path": path })),
+                            input: Some(input),
                             timestamp_ms: 0,
+                            raw_type: None,
                         })),
                         Ok(Event::ToolEnd(ToolEndEvent {
                             call_id,
@@ -267,11 +305,24 @@ fn parse_codex_line(line: &str) -> Vec<Result<Event>> {
                             output: None,
                             usage: None,
                             timestamp_ms: 0,
+                            raw_type: None,
                         })),
                     ]
                 }
 
-                // "reasoning" items — skip (internal thinking).
+                // Internal thinking — dropped unless asked for.
+                "reasoning" if include_reasoning => {
+                    let text = item.get("text").and_then(|v| v.as_str()).unwrap_or("");
+                    if text.is_empty() {
+                        return vec![];
+                    }
+                    vec![Ok(Event::Reasoning(ReasoningEvent {
+                        text: text.to_string(),
+                        timestamp_ms: 0,
+                        raw_type: None,
+                    }))]
+                }
+
                 _ => vec![],
             }
         }
@@ -281,9 +332,14 @@ fn parse_codex_line(line: &str) -> Vec<Result<Event>> {
             // Delegate to item.completed logic since structure is the same.
             let mut patched = value.clone();
             patched["type"] = serde_json::json!("item.completed");
-            parse_codex_line(&patched.to_string())
+            parse_codex_line(&patched.to_string(), include_reasoning)
         }
 
+        "turn.started" => vec![Ok(Event::TurnStart(TurnStartEvent {
+            timestamp_ms: 0,
+            raw_type: None,
+        }))],
+
         "turn.completed" => {
             // { type: "turn.completed", usage: { input_tokens, cached_input_tokens, output_tokens } }
             let usage = value.get("usage").map(|u| UsageData {
@@ -300,8 +356,14 @@ fn parse_codex_line(line: &str) -> Vec<Result<Event>> {
                 events.push(Ok(Event::UsageDelta(UsageDeltaEvent {
                     usage: u.clone(),
                     timestamp_ms: 0,
+                    raw_type: None,
                 })));
             }
+            events.push(Ok(Event::TurnEnd(TurnEndEvent {
+                usage: usage.clone(),
+                timestamp_ms: 0,
+                raw_type: None,
+            })));
 
             // turn.completed is typically the last event from Codex (no thread.completed),
             // so emit a Result event.
@@ -312,7 +374,10 @@ fn parse_codex_line(line: &str) -> Vec<Result<Event>> {
                 duration_ms: None,
                 total_cost_usd: None,
                 usage,
+                exit_code: None,
+                partial: false,
                 timestamp_ms: 0,
+                raw_type: None,
             })));
 
             events
@@ -329,6 +394,7 @@ fn parse_codex_line(line: &str) -> Vec<Result<Event>> {
                 message: error_msg,
                 code: Some("turn_failed".into()),
                 timestamp_ms: 0,
+                raw_type: None,
             }))]
         }
 
@@ -352,7 +418,10 @@ fn parse_codex_line(line: &str) -> Vec<Result<Event>> {
                 duration_ms: value.get("duration_ms").and_then(|v| v.as_u64()),
                 total_cost_usd: None,
                 usage: None,
+                exit_code: None,
+                partial: false,
                 timestamp_ms: 0,
+                raw_type: None,
             }))]
         }
 
@@ -370,12 +439,13 @@ fn parse_codex_line(line: &str) -> Vec<Result<Event>> {
                 message: msg,
                 code,
                 timestamp_ms: 0,
+                raw_type: None,
             }))]
         }
 
-        // turn.started — skip (no useful data).
         _ => vec![],
-    }
+    };
+    super::tag_raw_type(events, event_type)
 }
 
 #[cfg(test)]
@@ -384,10 +454,21 @@ mod tests {
 
     // ── Current format tests ────────────────────────────────────
 
+    #[test]
+    fn parsed_events_record_raw_type() {
+        let events = parse_codex_line(r#"{"type":"thread.started","thread_id":"th-1"}"#, false);
+        assert_eq!(events[0].as_ref().unwrap().raw_type(), Some("thread.started"));
+
+        // Legacy item.created is delegated, but keeps its own raw type.
+        let line = r#"{"type":"item.created","item":{"id":"i1","type":"agent_message","text":"Hi"}}"#;
+        let events = parse_codex_line(line, false);
+        assert_eq!(events[0].as_ref().unwrap().raw_type(), Some("item.created"));
+    }
+
     #[test]
     fn parse_thread_started() {
         let line = r#"{"type":"thread.started","thread_id":"th-123"}"#;
-        let events = parse_codex_line(line);
+        let events = parse_codex_line(line, false);
         assert_eq!(events.len(), 1);
         match &events[0] {
             Ok(Event::SessionStart(s)) => {
@@ -401,7 +482,7 @@ mod tests {
     #[test]
     fn parse_agent_message() {
         let line = r#"{"type":"item.completed","item":{"id":"item_2","type":"agent_message","text":"Hello!"}}"#;
-        let events = parse_codex_line(line);
+        let events = parse_codex_line(line, false);
         assert_eq!(events.len(), 1);
         match &events[0] {
             Ok(Event::Message(m)) => {
@@ -415,7 +496,7 @@ mod tests {
     #[test]
     fn parse_command_started() {
         let line = r#"{"type":"item.started","item":{"id":"item_1","type":"command_execution","command":"/bin/bash -lc 'ls'","aggregated_output":"","exit_code":null,"status":"in_progress"}}"#;
-        let events = parse_codex_line(line);
+        let events = parse_codex_line(line, false);
         assert_eq!(events.len(), 1);
         assert!(matches!(&events[0], Ok(Event::ToolStart(t)) if t.tool_name == "shell" && t.call_id == "item_1"));
     }
@@ -423,7 +504,7 @@ mod tests {
     #[test]
     fn parse_command_completed() {
         let line = r#"{"type":"item.completed","item":{"id":"item_1","type":"command_execution","command":"ls","aggregated_output":"file.txt\n","exit_code":0,"status":"completed"}}"#;
-        let events = parse_codex_line(line);
+        let events = parse_codex_line(line, false);
         assert_eq!(events.len(), 1);
         match &events[0] {
             Ok(Event::ToolEnd(t)) => {
@@ -439,7 +520,7 @@ mod tests {
     #[test]
     fn parse_command_failed() {
         let line = r#"{"type":"item.completed","item":{"id":"item_1","type":"command_execution","command":"false","aggregated_output":"","exit_code":1,"status":"completed"}}"#;
-        let events = parse_codex_line(line);
+        let events = parse_codex_line(line, false);
         assert_eq!(events.len(), 1);
         match &events[0] {
             Ok(Event::ToolEnd(t)) => {
@@ -452,25 +533,66 @@ mod tests {
     #[test]
     fn parse_turn_completed() {
         let line = r#"{"type":"turn.completed","usage":{"input_tokens":8587,"cached_input_tokens":7808,"output_tokens":24}}"#;
-        let events = parse_codex_line(line);
+        let events = parse_codex_line(line, false);
         assert!(events.len() >= 2);
         assert!(events.iter().any(|e| matches!(e, Ok(Event::UsageDelta(u)) if u.usage.input_tokens == Some(8587))));
         assert!(events.iter().any(|e| matches!(e, Ok(Event::Result(r)) if r.success)));
     }
 
+    #[test]
+    fn parse_turn_boundaries() {
+        let events = parse_codex_line(r#"{"type":"turn.started"}"#, false);
+        assert_eq!(events.len(), 1);
+        assert!(
+            matches!(&events[0], Ok(Event::TurnStart(t)) if t.raw_type.as_deref() == Some("turn.started"))
+        );
+
+        let line = r#"{"type":"turn.completed","usage":{"input_tokens":10,"output_tokens":5}}"#;
+        let events: Vec<Event> = parse_codex_line(line, false)
+            .into_iter()
+            .map(|r| r.unwrap())
+            .collect();
+        // The turn closes before the run's Result.
+        let end = events
+            .iter()
+            .position(|e| matches!(e, Event::TurnEnd(_)))
+            .expect("TurnEnd");
+        let result = events
+            .iter()
+            .position(|e| matches!(e, Event::Result(_)))
+            .unwrap();
+        assert!(end < result);
+        match &events[end] {
+            Event::TurnEnd(t) => assert_eq!(t.usage.as_ref().unwrap().output_tokens, Some(5)),
+            _ => unreachable!(),
+        }
+    }
+
     #[test]
     fn parse_file_change() {
         let line = r#"{"type":"item.completed","item":{"type":"file_change","id":"fc-1","path":"src/main.rs"}}"#;
-        let events = parse_codex_line(line);
+        let events = parse_codex_line(line, false);
         assert_eq!(events.len(), 2, "expected ToolStart + ToolEnd");
         assert!(matches!(&events[0], Ok(Event::ToolStart(t)) if t.tool_name == "file_change"));
         assert!(matches!(&events[1], Ok(Event::ToolEnd(t)) if t.tool_name == "file_change" && t.success));
     }
 
+    #[test]
+    fn parse_file_change_keeps_change_list() {
+        let line = r#"{"type":"item.completed","item":{"type":"file_change","id":"fc-2","changes":[{"path":"src/a.rs","kind":"add"}],"status":"completed"}}"#;
+        match &parse_codex_line(line, false)[0] {
+            Ok(Event::ToolStart(t)) => assert_eq!(
+                t.input.as_ref().unwrap()["changes"][0]["path"],
+                serde_json::json!("src/a.rs")
+            ),
+            other => panic!("expected ToolStart, got {other:?}"),
+        }
+    }
+
     #[test]
     fn parse_turn_failed() {
         let line = r#"{"type":"turn.failed","error":"rate limit exceeded"}"#;
-        let events = parse_codex_line(line);
+        let events = parse_codex_line(line, false);
         assert_eq!(events.len(), 1);
         match &events[0] {
             Ok(Event::Error(e)) => {
@@ -483,7 +605,7 @@ mod tests {
     #[test]
     fn parse_error_event() {
         let line = r#"{"type":"error","message":"rate limit exceeded","code":"rate_limit"}"#;
-        let events = parse_codex_line(line);
+        let events = parse_codex_line(line, false);
         assert_eq!(events.len(), 1);
         match &events[0] {
             Ok(Event::Error(e)) => {
@@ -497,16 +619,30 @@ mod tests {
     #[test]
     fn parse_reasoning_item_skipped() {
         let line = r#"{"type":"item.completed","item":{"id":"item_0","type":"reasoning","text":"thinking..."}}"#;
-        let events = parse_codex_line(line);
+        let events = parse_codex_line(line, false);
         assert!(events.is_empty(), "reasoning items should be skipped");
     }
 
+    #[test]
+    fn parse_reasoning_item_when_included() {
+        let line = r#"{"type":"item.completed","item":{"id":"item_0","type":"reasoning","text":"thinking..."}}"#;
+        let events = parse_codex_line(line, true);
+        assert_eq!(events.len(), 1);
+        match &events[0] {
+            Ok(Event::Reasoning(r)) => {
+                assert_eq!(r.text, "thinking...");
+                assert_eq!(r.raw_type.as_deref(), Some("item.completed"));
+            }
+            other => panic!("expected Reasoning, got {other:?}"),
+        }
+    }
+
     // ── Legacy format tests ─────────────────────────────────────
 
     #[test]
     fn parse_legacy_item_created_message() {
         let line = r#"{"type":"item.created","item":{"type":"message","role":"assistant","content":[{"text":"Hello"}]}}"#;
-        let events = parse_codex_line(line);
+        let events = parse_codex_line(line, false);
         assert_eq!(events.len(), 1);
         match &events[0] {
             Ok(Event::Message(m)) => {
@@ -520,7 +656,7 @@ mod tests {
     #[test]
     fn parse_legacy_thread_completed() {
         let line = r#"{"type":"thread.completed","thread_id":"th-123","summary":"All done","duration_ms":5000}"#;
-        let events = parse_codex_line(line);
+        let events = parse_codex_line(line, false);
         assert_eq!(events.len(), 1);
         match &events[0] {
             Ok(Event::Result(r)) => {
@@ -568,4 +704,36 @@ mod tests {
         assert!(args.contains(&"--model".to_string()));
         assert!(args.contains(&"gpt-5-codex".to_string()));
     }
+
+    #[test]
+    fn build_args_with_images() {
+        let config = TaskConfig::builder("describe", crate::config::AgentKind::Codex)
+            .image("/tmp/a.png")
+            .image("/tmp/b.jpg")
+            .build();
+        let args = CodexRunner.build_args(&config);
+        let images: Vec<_> = args
+            .windows(2)
+            .filter(|w| w[0] == "--image")
+            .map(|w| w[1].as_str())
+            .collect();
+        assert_eq!(images, ["/tmp/a.png", "/tmp/b.jpg"]);
+        assert_eq!(args[args.len() - 2..], ["--", "describe"]);
+        assert!(crate::runner::ensure_images_supported(&CodexRunner, &config).is_ok());
+    }
+
+    #[test]
+    fn capabilities_emits_whole_messages_only() {
+        let caps = CodexRunner.capabilities();
+        assert!(!caps.supports_streaming);
+    }
+
+    #[test]
+    fn permission_modes_are_native() {
+        assert!(CodexRunner.supports_permission_mode(PermissionMode::FullAccess));
+        assert!(CodexRunner.supports_permission_mode(PermissionMode::ReadOnly));
+        let mut config = TaskConfig::new("look", crate::config::AgentKind::Codex);
+        config.permission_mode = PermissionMode::ReadOnly;
+        assert!(CodexRunner.validate_config(&config).is_empty());
+    }
 }
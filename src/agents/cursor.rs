@@ -44,7 +44,7 @@ impl AgentRunner for CursorRunner {
 
         if let Some(ref model) = config.model {
             args.push("--model".to_string());
-            args.push(model.clone());
+            args.push(self.normalize_model(model));
         }
 
         match config.permission_mode {
@@ -57,10 +57,16 @@ impl AgentRunner for CursorRunner {
             }
         }
 
+        if let Some(ref id) = config.resume_session_id {
+            args.push("--resume".to_string());
+            args.push(id.clone());
+        }
+
         args.extend(config.extra_args.iter().cloned());
 
-        // Prompt must be the last positional argument.
-        args.push(config.prompt.clone());
+        // Prompt must come last (`-p` above is the print-mode switch, not
+        // the prompt flag).
+        self.prompt_passing().apply(&mut args, &config.prompt);
         args
     }
 
@@ -84,8 +90,18 @@ impl AgentRunner for CursorRunner {
             supports_model: true,
             supports_max_turns: false,
             supports_append_system_prompt: false,
+            supports_images: false,
+            supports_streaming: false,
+            supports_resume: true,
+            supports_tool_allowlist: false,
+            supports_reasoning: false,
         }
     }
+
+    fn supports_permission_mode(&self, _mode: PermissionMode) -> bool {
+        // `--mode plan` is Cursor's own read-only mode.
+        true
+    }
 }
 
 fn parse_cursor_line(line: &str) -> Vec<Result<Event>> {
@@ -118,7 +134,10 @@ fn parse_cursor_line(line: &str) -> Vec<Result<Event>> {
                         .get("cwd")
                         .and_then(|v| v.as_str())
                         .map(|s| s.to_string()),
+                    permission_mode: None,
+                    agent_version: None,
                     timestamp_ms: 0,
+                    raw_type: None,
                 }))]
             } else {
                 vec![]
@@ -135,6 +154,7 @@ fn parse_cursor_line(line: &str) -> Vec<Result<Event>> {
                 text,
                 usage: None,
                 timestamp_ms: 0,
+                raw_type: None,
             }))]
         }
 
@@ -149,6 +169,7 @@ fn parse_cursor_line(line: &str) -> Vec<Result<Event>> {
                 text,
                 usage: None,
                 timestamp_ms: 0,
+                raw_type: None,
             }))]
         }
 
@@ -169,6 +190,7 @@ fn parse_cursor_line(line: &str) -> Vec<Result<Event>> {
                     tool_name,
                     input: input_or_output,
                     timestamp_ms: 0,
+                    raw_type: None,
                 }))],
                 "completed" => vec![Ok(Event::ToolEnd(ToolEndEvent {
                     call_id,
@@ -177,6 +199,7 @@ fn parse_cursor_line(line: &str) -> Vec<Result<Event>> {
                     output: input_or_output.map(|v| v.to_string()),
                     usage: None,
                     timestamp_ms: 0,
+                    raw_type: None,
                 }))],
                 _ => vec![],
             }
@@ -191,7 +214,7 @@ fn parse_cursor_line(line: &str) -> Vec<Result<Event>> {
                 .get("is_error")
                 .and_then(|v| v.as_bool())
                 .unwrap_or(false);
-            let success = subtype == "success" && !is_error;
+            let success = matches!(subtype, "success" | "checkpoint") && !is_error;
 
             vec![Ok(Event::Result(ResultEvent {
                 success,
@@ -208,7 +231,10 @@ fn parse_cursor_line(line: &str) -> Vec<Result<Event>> {
                 duration_ms: value.get("duration_ms").and_then(|v| v.as_u64()),
                 total_cost_usd: None,
                 usage: None,
+                exit_code: super::result_exit_code(&value),
+                partial: super::result_is_partial(&value),
                 timestamp_ms: 0,
+                raw_type: None,
             }))]
         }
 
@@ -365,4 +391,36 @@ mod tests {
         assert!(args.contains(&"sonnet-4.5-thinking".to_string()));
         assert_eq!(args.last().unwrap(), "fix it");
     }
+
+    #[test]
+    fn build_args_resume_session() {
+        let mut config = TaskConfig::new("continue", crate::config::AgentKind::Cursor);
+        config.resume_session_id = Some("chat-7".into());
+        let args = CursorRunner.build_args(&config);
+        assert!(args.windows(2).any(|w| w == ["--resume", "chat-7"]));
+        assert_eq!(args.last().unwrap(), "continue");
+    }
+
+    #[test]
+    fn images_are_rejected() {
+        let mut config = TaskConfig::new("describe", crate::config::AgentKind::Cursor);
+        config.images = vec!["shot.png".into()];
+        let err = crate::runner::ensure_images_supported(&CursorRunner, &config).unwrap_err();
+        assert!(err.to_string().contains("does not support image inputs"));
+    }
+
+    #[test]
+    fn capabilities_emits_whole_messages_only() {
+        let caps = CursorRunner.capabilities();
+        assert!(!caps.supports_streaming);
+    }
+
+    #[test]
+    fn permission_modes_are_native() {
+        assert!(CursorRunner.supports_permission_mode(PermissionMode::FullAccess));
+        assert!(CursorRunner.supports_permission_mode(PermissionMode::ReadOnly));
+        let mut config = TaskConfig::new("look", crate::config::AgentKind::Cursor);
+        config.permission_mode = PermissionMode::ReadOnly;
+        assert!(CursorRunner.validate_config(&config).is_empty());
+    }
 }
@@ -0,0 +1,385 @@
+use std::path::PathBuf;
+
+use async_trait::async_trait;
+
+use crate::config::{PermissionMode, TaskConfig};
+use crate::error::{Error, Result};
+use crate::event::*;
+use crate::process::{spawn_and_stream, StreamHandle};
+use crate::runner::{AgentRunner, PromptPassing};
+
+/// Adapter for Google's Gemini CLI (`gemini` binary).
+///
+/// Headless invocation:
+///   gemini --prompt "<prompt>" --output-format stream-json
+///
+/// Stream format: NDJSON with event types:
+///   - { type: "init", session_id, model }
+///   - { type: "message", role: "user"|"assistant", content, delta? }
+///   - { type: "tool_use", tool_name, tool_id, parameters }
+///   - { type: "tool_result", tool_id, status: "success"|"error", output?, error? }
+///   - { type: "error", severity, message }
+///   - { type: "result", status: "success"|"error", stats: { input_tokens, output_tokens, duration_ms, ... }, error? }
+pub struct GeminiRunner;
+
+#[async_trait]
+impl AgentRunner for GeminiRunner {
+    fn name(&self) -> &str {
+        "gemini"
+    }
+
+    fn is_available(&self) -> bool {
+        crate::runner::is_any_binary_available(crate::config::AgentKind::Gemini)
+    }
+
+    fn binary_path(&self, config: &TaskConfig) -> Result<PathBuf> {
+        crate::runner::resolve_binary(crate::config::AgentKind::Gemini, config)
+    }
+
+    fn build_args(&self, config: &TaskConfig) -> Vec<String> {
+        let mut args = vec!["--output-format".to_string(), "stream-json".to_string()];
+
+        if let Some(ref model) = config.model {
+            args.push("--model".to_string());
+            args.push(self.normalize_model(model));
+        }
+
+        // Headless Gemini denies any tool that would need approval, so the
+        // default approval mode is effectively read-only.
+        args.push("--approval-mode".to_string());
+        args.push(
+            match config.permission_mode {
+                PermissionMode::FullAccess => "yolo",
+                PermissionMode::ReadOnly => "default",
+            }
+            .to_string(),
+        );
+
+        args.extend(config.extra_args.iter().cloned());
+        self.prompt_passing().apply(&mut args, &config.prompt);
+        args
+    }
+
+    fn prompt_passing(&self) -> PromptPassing {
+        PromptPassing::Flag("--prompt")
+    }
+
+    fn build_env(&self, _config: &TaskConfig) -> Vec<(String, String)> {
+        // Gemini reads GEMINI_API_KEY or GOOGLE_API_KEY from the environment.
+        vec![]
+    }
+
+    async fn run(
+        &self,
+        config: &TaskConfig,
+        cancel_token: Option<tokio_util::sync::CancellationToken>,
+    ) -> Result<StreamHandle> {
+        spawn_and_stream(self, config, parse_gemini_line, cancel_token).await
+    }
+
+    fn capabilities(&self) -> crate::runner::AgentCapabilities {
+        crate::runner::AgentCapabilities {
+            supports_system_prompt: false,
+            supports_budget: false,
+            supports_model: true,
+            supports_max_turns: false,
+            supports_append_system_prompt: false,
+            supports_images: false,
+            supports_streaming: true,
+            supports_resume: false,
+            supports_tool_allowlist: false,
+            supports_reasoning: false,
+        }
+    }
+
+    fn supports_permission_mode(&self, mode: PermissionMode) -> bool {
+        // Read-only relies on headless Gemini denying tools that need approval.
+        mode == PermissionMode::FullAccess
+    }
+}
+
+fn parse_gemini_line(line: &str) -> Vec<Result<Event>> {
+    let value: serde_json::Value = match serde_json::from_str(line) {
+        Ok(v) => v,
+        Err(e) => return vec![Err(Error::ParseError(format!("invalid JSON: {e}: {line}")))],
+    };
+
+    let event_type = match value.get("type").and_then(|v| v.as_str()) {
+        Some(t) => t,
+        None => return vec![],
+    };
+    let str_field = |key: &str| value.get(key).and_then(|v| v.as_str());
+
+    let events = match event_type {
+        "init" => vec![Ok(Event::SessionStart(SessionStartEvent {
+            session_id: str_field("session_id").unwrap_or("").to_string(),
+            agent: "gemini".to_string(),
+            model: str_field("model").map(|s| s.to_string()),
+            cwd: None,
+            permission_mode: None,
+            agent_version: None,
+            timestamp_ms: 0,
+            raw_type: None,
+        }))],
+
+        "message" => {
+            let text = str_field("content").unwrap_or("").to_string();
+            if text.is_empty() {
+                return vec![];
+            }
+            let role = match str_field("role") {
+                Some("user") => Role::User,
+                _ => Role::Assistant,
+            };
+            let delta = value
+                .get("delta")
+                .and_then(|v| v.as_bool())
+                .unwrap_or(false);
+            if delta && role == Role::Assistant {
+                vec![Ok(Event::TextDelta(TextDeltaEvent {
+                    text,
+                    timestamp_ms: 0,
+                    raw_type: None,
+                }))]
+            } else {
+                vec![Ok(Event::Message(MessageEvent {
+                    role,
+                    text,
+                    usage: None,
+                    timestamp_ms: 0,
+                    raw_type: None,
+                }))]
+            }
+        }
+
+        "tool_use" => vec![Ok(Event::ToolStart(ToolStartEvent {
+            call_id: str_field("tool_id").unwrap_or("").to_string(),
+            tool_name: str_field("tool_name").unwrap_or("unknown").to_string(),
+            input: value.get("parameters").cloned(),
+            timestamp_ms: 0,
+            raw_type: None,
+        }))],
+
+        "tool_result" => {
+            let success = str_field("status") != Some("error");
+            let output = match str_field("output") {
+                Some(o) => Some(o.to_string()),
+                None => value
+                    .pointer("/error/message")
+                    .and_then(|v| v.as_str())
+                    .map(|s| s.to_string()),
+            };
+            // Gemini only names the tool on `tool_use`; results carry the id alone.
+            vec![Ok(Event::ToolEnd(ToolEndEvent {
+                call_id: str_field("tool_id").unwrap_or("").to_string(),
+                tool_name: str_field("tool_name").unwrap_or("").to_string(),
+                success,
+                output,
+                usage: None,
+                timestamp_ms: 0,
+                raw_type: None,
+            }))]
+        }
+
+        // Warnings don't stop the run; surface them as system messages so
+        // fail-fast and retry logic only see real errors.
+        "error" if matches!(str_field("severity"), Some("warning" | "info")) => {
+            vec![Ok(Event::Message(MessageEvent {
+                role: Role::System,
+                text: str_field("message").unwrap_or("").to_string(),
+                usage: None,
+                timestamp_ms: 0,
+                raw_type: None,
+            }))]
+        }
+
+        "error" => vec![Ok(Event::Error(ErrorEvent {
+            message: str_field("message").unwrap_or("unknown error").to_string(),
+            code: str_field("severity").map(|s| s.to_string()),
+            timestamp_ms: 0,
+            raw_type: None,
+        }))],
+
+        "result" => {
+            let stats = value.get("stats");
+            let stat = |key: &str| stats.and_then(|s| s.get(key)).and_then(|v| v.as_u64());
+            let usage = stats.map(|_| UsageData {
+                input_tokens: stat("input_tokens"),
+                output_tokens: stat("output_tokens"),
+                cache_read_tokens: stat("cached"),
+                cache_creation_tokens: None,
+                cost_usd: None,
+            });
+            vec![Ok(Event::Result(ResultEvent {
+                success: str_field("status") == Some("success"),
+                text: value
+                    .pointer("/error/message")
+                    .and_then(|v| v.as_str())
+                    .unwrap_or("")
+                    .to_string(),
+                session_id: str_field("session_id").unwrap_or("").to_string(),
+                duration_ms: stat("duration_ms"),
+                total_cost_usd: None,
+                usage,
+                exit_code: super::result_exit_code(&value),
+                partial: false,
+                timestamp_ms: 0,
+                raw_type: None,
+            }))]
+        }
+
+        _ => vec![],
+    };
+
+    super::tag_raw_type(events, event_type)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_init_event() {
+        let line = r#"{"type":"init","timestamp":"2025-10-10T12:00:00.000Z","session_id":"g-1","model":"gemini-2.5-pro"}"#;
+        let events = parse_gemini_line(line);
+        assert_eq!(events.len(), 1);
+        match events.into_iter().next().unwrap().unwrap() {
+            Event::SessionStart(s) => {
+                assert_eq!(s.session_id, "g-1");
+                assert_eq!(s.agent, "gemini");
+                assert_eq!(s.model, Some("gemini-2.5-pro".into()));
+                assert_eq!(s.raw_type.as_deref(), Some("init"));
+            }
+            other => panic!("expected SessionStart, got {other:?}"),
+        }
+    }
+
+    #[test]
+    fn parse_messages_and_deltas() {
+        let line = r#"{"type":"message","role":"user","content":"fix the bug"}"#;
+        match parse_gemini_line(line).into_iter().next().unwrap().unwrap() {
+            Event::Message(m) => {
+                assert_eq!(m.role, Role::User);
+                assert_eq!(m.text, "fix the bug");
+            }
+            other => panic!("expected Message, got {other:?}"),
+        }
+
+        let line = r#"{"type":"message","role":"assistant","content":"Looking","delta":true}"#;
+        match parse_gemini_line(line).into_iter().next().unwrap().unwrap() {
+            Event::TextDelta(d) => assert_eq!(d.text, "Looking"),
+            other => panic!("expected TextDelta, got {other:?}"),
+        }
+
+        let line = r#"{"type":"message","role":"assistant","content":"Done."}"#;
+        match parse_gemini_line(line).into_iter().next().unwrap().unwrap() {
+            Event::Message(m) => assert_eq!(m.role, Role::Assistant),
+            other => panic!("expected Message, got {other:?}"),
+        }
+    }
+
+    #[test]
+    fn parse_tool_use_and_result() {
+        let line = r#"{"type":"tool_use","tool_name":"read_file","tool_id":"t-1","parameters":{"absolute_path":"/src/main.rs"}}"#;
+        match parse_gemini_line(line).into_iter().next().unwrap().unwrap() {
+            Event::ToolStart(t) => {
+                assert_eq!(t.call_id, "t-1");
+                assert_eq!(t.tool_name, "read_file");
+                assert_eq!(
+                    t.input,
+                    Some(serde_json::json!({"absolute_path": "/src/main.rs"}))
+                );
+            }
+            other => panic!("expected ToolStart, got {other:?}"),
+        }
+
+        let line = r#"{"type":"tool_result","tool_id":"t-1","status":"error","error":{"type":"not_found","message":"no such file"}}"#;
+        match parse_gemini_line(line).into_iter().next().unwrap().unwrap() {
+            Event::ToolEnd(t) => {
+                assert_eq!(t.call_id, "t-1");
+                assert!(!t.success);
+                assert_eq!(t.output.as_deref(), Some("no such file"));
+            }
+            other => panic!("expected ToolEnd, got {other:?}"),
+        }
+    }
+
+    #[test]
+    fn parse_result_with_stats() {
+        let line = r#"{"type":"result","status":"success","stats":{"total_tokens":150,"input_tokens":100,"output_tokens":50,"duration_ms":1200,"tool_calls":1}}"#;
+        match parse_gemini_line(line).into_iter().next().unwrap().unwrap() {
+            Event::Result(r) => {
+                assert!(r.success);
+                assert_eq!(r.duration_ms, Some(1200));
+                let usage = r.usage.unwrap();
+                assert_eq!(usage.input_tokens, Some(100));
+                assert_eq!(usage.output_tokens, Some(50));
+            }
+            other => panic!("expected Result, got {other:?}"),
+        }
+    }
+
+    #[test]
+    fn parse_error_and_invalid_json() {
+        let line = r#"{"type":"error","severity":"error","message":"quota exceeded"}"#;
+        match parse_gemini_line(line).into_iter().next().unwrap().unwrap() {
+            Event::Error(e) => {
+                assert_eq!(e.message, "quota exceeded");
+                assert_eq!(e.code.as_deref(), Some("error"));
+            }
+            other => panic!("expected Error, got {other:?}"),
+        }
+        assert!(parse_gemini_line("not json")[0].is_err());
+    }
+
+    #[test]
+    fn parse_warning_is_not_an_error() {
+        let line = r#"{"type":"error","severity":"warning","message":"loop detected, continuing"}"#;
+        match parse_gemini_line(line).into_iter().next().unwrap().unwrap() {
+            Event::Message(m) => {
+                assert_eq!(m.role, Role::System);
+                assert_eq!(m.text, "loop detected, continuing");
+            }
+            other => panic!("expected Message, got {other:?}"),
+        }
+    }
+
+    #[test]
+    fn build_args_maps_permissions_and_model() {
+        let mut config = TaskConfig::new("fix it", crate::config::AgentKind::Gemini);
+        config.model = Some("gemini-2.5-flash".into());
+        let args = GeminiRunner.build_args(&config);
+        assert_eq!(&args[..2], ["--prompt", "fix it"]);
+        assert!(args.windows(2).any(|w| w == ["--approval-mode", "yolo"]));
+        assert!(args
+            .windows(2)
+            .any(|w| w == ["--model", "gemini-2.5-flash"]));
+
+        config.permission_mode = PermissionMode::ReadOnly;
+        let args = GeminiRunner.build_args(&config);
+        assert!(args.windows(2).any(|w| w == ["--approval-mode", "default"]));
+    }
+
+    #[test]
+    fn capabilities_support_model_but_not_budget() {
+        let caps = GeminiRunner.capabilities();
+        assert!(caps.supports_model);
+        assert!(!caps.supports_budget);
+        assert!(caps.supports_streaming);
+    }
+
+    #[test]
+    fn read_only_mode_is_emulated() {
+        assert!(GeminiRunner.supports_permission_mode(PermissionMode::FullAccess));
+        assert!(!GeminiRunner.supports_permission_mode(PermissionMode::ReadOnly));
+        let mut config = TaskConfig::new("look", crate::config::AgentKind::Gemini);
+        assert!(GeminiRunner.validate_config(&config).is_empty());
+        config.permission_mode = PermissionMode::ReadOnly;
+        let warnings = GeminiRunner.validate_config(&config);
+        assert_eq!(warnings.len(), 1);
+        assert_eq!(
+            warnings[0].message,
+            "gemini has no native read-only mode; harness emulates it"
+        );
+    }
+}
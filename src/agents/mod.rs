@@ -1,9 +1,12 @@
 pub mod claude;
 pub mod codex;
 pub mod cursor;
+pub mod gemini;
 pub mod opencode;
 
 use crate::config::AgentKind;
+use crate::error::Result;
+use crate::event::Event;
 use crate::runner::AgentRunner;
 
 /// Create the appropriate runner for the given agent kind.
@@ -13,5 +16,34 @@ pub fn create_runner(kind: AgentKind) -> Box<dyn AgentRunner> {
         AgentKind::OpenCode => Box::new(opencode::OpenCodeRunner),
         AgentKind::Codex => Box::new(codex::CodexRunner),
         AgentKind::Cursor => Box::new(cursor::CursorRunner),
+        AgentKind::Gemini => Box::new(gemini::GeminiRunner),
     }
 }
+
+/// Tag every parsed event with the native event type it came from.
+pub(crate) fn tag_raw_type(events: Vec<Result<Event>>, raw_type: &str) -> Vec<Result<Event>> {
+    events
+        .into_iter()
+        .map(|item| {
+            item.map(|mut event| {
+                event.set_raw_type(Some(raw_type.to_string()));
+                event
+            })
+        })
+        .collect()
+}
+
+/// Whether a native result payload is an intermediate checkpoint: flagged
+/// `partial: true`, or of subtype `checkpoint`.
+pub(crate) fn result_is_partial(value: &serde_json::Value) -> bool {
+    value.get("partial").and_then(|v| v.as_bool()) == Some(true)
+        || value.get("subtype").and_then(|v| v.as_str()) == Some("checkpoint")
+}
+
+/// An integer `exit_code` field on a native result payload, if the agent set one.
+pub(crate) fn result_exit_code(value: &serde_json::Value) -> Option<i32> {
+    value
+        .get("exit_code")
+        .and_then(|v| v.as_i64())
+        .and_then(|c| i32::try_from(c).ok())
+}
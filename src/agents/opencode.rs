@@ -46,26 +46,59 @@ impl AgentRunner for OpenCodeRunner {
 
         if let Some(ref model) = config.model {
             args.push("--model".to_string());
-            args.push(model.clone());
+            args.push(self.normalize_model(model));
         }
 
         // OpenCode `run` auto-approves all permissions by default.
-        // For read-only, use a plan agent.
-        match config.permission_mode {
-            PermissionMode::FullAccess => {}
-            PermissionMode::ReadOnly => {
-                args.push("--agent".to_string());
-                args.push("plan".to_string());
-            }
+        // For read-only, use the plan agent; a named profile could write, so
+        // it only applies under full access (validate_config warns otherwise).
+        let profile = match config.permission_mode {
+            PermissionMode::ReadOnly => Some("plan"),
+            PermissionMode::FullAccess => config.agent_profile.as_deref(),
+        };
+        if let Some(profile) = profile {
+            args.push("--agent".to_string());
+            args.push(profile.to_string());
+        }
+
+        for image in &config.images {
+            args.push("--file".to_string());
+            args.push(image.display().to_string());
         }
 
         args.extend(config.extra_args.iter().cloned());
 
-        // Prompt is the final positional argument(s).
-        args.push(config.prompt.clone());
+        // Prompt must come last. `--file` takes several values, so end the
+        // options first or the prompt is read as another file.
+        if !config.images.is_empty() {
+            args.push("--".to_string());
+        }
+        self.prompt_passing().apply(&mut args, &config.prompt);
         args
     }
 
+    /// OpenCode wants `provider/model` ids; add the provider to bare ids
+    /// from well-known model families.
+    fn normalize_model(&self, raw: &str) -> String {
+        if raw.contains('/') {
+            return raw.to_string();
+        }
+        // OpenAI reasoning models: o1, o3-mini, o4-mini, ...
+        let is_o_series = raw
+            .strip_prefix('o')
+            .is_some_and(|rest| rest.starts_with(|c: char| c.is_ascii_digit()));
+        let provider = if raw.starts_with("claude") {
+            "anthropic"
+        } else if raw.starts_with("gpt") || raw.starts_with("codex") || is_o_series {
+            "openai"
+        } else if raw.starts_with("gemini") {
+            "google"
+        } else {
+            return raw.to_string();
+        };
+        format!("{provider}/{raw}")
+    }
+
     fn build_env(&self, _config: &TaskConfig) -> Vec<(String, String)> {
         // OpenCode reads provider API keys from environment (ANTHROPIC_API_KEY,
         // OPENAI_API_KEY, etc.) or from its config files.
@@ -87,8 +120,18 @@ impl AgentRunner for OpenCodeRunner {
             supports_model: true,
             supports_max_turns: false,
             supports_append_system_prompt: false,
+            supports_images: true,
+            supports_streaming: false,
+            supports_resume: false,
+            supports_tool_allowlist: false,
+            supports_reasoning: false,
         }
     }
+
+    fn supports_permission_mode(&self, _mode: PermissionMode) -> bool {
+        // The built-in `plan` agent has edit and bash tools denied.
+        true
+    }
 }
 
 fn parse_opencode_line(line: &str) -> Vec<Result<Event>> {
@@ -99,6 +142,7 @@ fn parse_opencode_line(line: &str) -> Vec<Result<Event>> {
             return vec![Ok(Event::TextDelta(TextDeltaEvent {
                 text: line.to_string(),
                 timestamp_ms: 0,
+                raw_type: None,
             }))];
         }
     };
@@ -148,7 +192,10 @@ fn parse_typed_event(event_type: &str, value: &serde_json::Value) -> Vec<Result<
                 agent: "opencode".to_string(),
                 model: None,
                 cwd: None,
+                permission_mode: None,
+                agent_version: None,
                 timestamp_ms: 0,
+                raw_type: None,
             }))]
         }
 
@@ -167,6 +214,7 @@ fn parse_typed_event(event_type: &str, value: &serde_json::Value) -> Vec<Result<
                 text,
                 usage: None,
                 timestamp_ms: 0,
+                raw_type: None,
             }))]
         }
 
@@ -206,6 +254,7 @@ fn parse_typed_event(event_type: &str, value: &serde_json::Value) -> Vec<Result<
                     tool_name: tool_name.clone(),
                     input,
                     timestamp_ms: 0,
+                    raw_type: None,
                 })),
                 Ok(Event::ToolEnd(ToolEndEvent {
                     call_id,
@@ -214,6 +263,7 @@ fn parse_typed_event(event_type: &str, value: &serde_json::Value) -> Vec<Result<
                     output,
                     usage: None,
                     timestamp_ms: 0,
+                    raw_type: None,
                 })),
             ]
         }
@@ -241,6 +291,7 @@ fn parse_typed_event(event_type: &str, value: &serde_json::Value) -> Vec<Result<
                 events.push(Ok(Event::UsageDelta(UsageDeltaEvent {
                     usage,
                     timestamp_ms: 0,
+                    raw_type: None,
                 })));
             }
 
@@ -253,7 +304,10 @@ fn parse_typed_event(event_type: &str, value: &serde_json::Value) -> Vec<Result<
                     duration_ms: None,
                     total_cost_usd: part.get("cost").and_then(|v| v.as_f64()),
                     usage: extract_opencode_usage(part),
+                    exit_code: None,
+                    partial: false,
                     timestamp_ms: 0,
+                    raw_type: None,
                 })));
             }
             // reason=tool-calls means more steps will follow — no Result yet.
@@ -281,7 +335,10 @@ fn parse_typed_event(event_type: &str, value: &serde_json::Value) -> Vec<Result<
                     .get("cwd")
                     .and_then(|v| v.as_str())
                     .map(|s| s.to_string()),
+                permission_mode: None,
+                agent_version: None,
                 timestamp_ms: 0,
+                raw_type: None,
             }))]
         }
 
@@ -300,6 +357,7 @@ fn parse_typed_event(event_type: &str, value: &serde_json::Value) -> Vec<Result<
                 text,
                 usage: None,
                 timestamp_ms: 0,
+                raw_type: None,
             }))]
         }
 
@@ -317,6 +375,7 @@ fn parse_typed_event(event_type: &str, value: &serde_json::Value) -> Vec<Result<
                     .and_then(|v| v.as_str())
                     .map(|s| s.to_string()),
                 timestamp_ms: 0,
+                raw_type: None,
             }))]
         }
 
@@ -337,14 +396,20 @@ fn parse_typed_event(event_type: &str, value: &serde_json::Value) -> Vec<Result<
                 .get("success")
                 .and_then(|v| v.as_bool())
                 .unwrap_or(true);
+            // Some OpenCode versions put `tokens`/`cost` at the top level.
+            let usage = extract_opencode_usage(value);
+            let total_cost_usd = value.get("cost").and_then(|v| v.as_f64());
             vec![Ok(Event::Result(ResultEvent {
                 success,
                 text,
                 session_id,
                 duration_ms: value.get("duration_ms").and_then(|v| v.as_u64()),
-                total_cost_usd: None,
-                usage: None,
+                total_cost_usd,
+                usage,
+                exit_code: None,
+                partial: false,
                 timestamp_ms: 0,
+                raw_type: None,
             }))]
         }
 
@@ -443,6 +508,37 @@ mod tests {
         }
     }
 
+    #[test]
+    fn parse_legacy_result_with_usage() {
+        let line = r#"{"type":"result","result":"Done","session_id":"oc-1","cost":0.02,"tokens":{"input":120,"output":45,"cache":{"read":10,"write":0}}}"#;
+        let events = parse_opencode_line(line);
+        assert_eq!(events.len(), 1);
+        match &events[0] {
+            Ok(Event::Result(r)) => {
+                assert_eq!(r.text, "Done");
+                assert_eq!(r.total_cost_usd, Some(0.02));
+                let usage = r.usage.as_ref().expect("usage should be populated");
+                assert_eq!(usage.input_tokens, Some(120));
+                assert_eq!(usage.output_tokens, Some(45));
+                assert_eq!(usage.cache_read_tokens, Some(10));
+            }
+            other => panic!("expected Result, got {other:?}"),
+        }
+    }
+
+    #[test]
+    fn parse_legacy_result_without_usage() {
+        let line = r#"{"type":"done","result":"ok"}"#;
+        let events = parse_opencode_line(line);
+        match &events[0] {
+            Ok(Event::Result(r)) => {
+                assert!(r.usage.is_none());
+                assert!(r.total_cost_usd.is_none());
+            }
+            other => panic!("expected Result, got {other:?}"),
+        }
+    }
+
     #[test]
     fn parse_non_json_as_text_delta() {
         let line = "Processing your request...";
@@ -489,6 +585,62 @@ mod tests {
         assert!(args.contains(&"plan".to_string()));
     }
 
+    #[test]
+    fn build_args_passes_custom_agent_profile() {
+        let mut config = TaskConfig::new("review", crate::config::AgentKind::OpenCode);
+        config.agent_profile = Some("reviewer".into());
+        let args = OpenCodeRunner.build_args(&config);
+        let idx = args.iter().position(|a| a == "--agent").unwrap();
+        assert_eq!(args[idx + 1], "reviewer");
+    }
+
+    #[test]
+    fn build_args_read_only_keeps_plan_over_custom_profile() {
+        let mut config = TaskConfig::new("review", crate::config::AgentKind::OpenCode);
+        config.permission_mode = PermissionMode::ReadOnly;
+        config.agent_profile = Some("reviewer".into());
+        let args = OpenCodeRunner.build_args(&config);
+        let idx = args.iter().position(|a| a == "--agent").unwrap();
+        assert_eq!(args[idx + 1], "plan");
+        assert!(!args.contains(&"reviewer".to_string()));
+    }
+
+    #[test]
+    fn build_args_prefixes_bare_model_with_provider() {
+        let mut config = TaskConfig::new("task", crate::config::AgentKind::OpenCode);
+        config.model = Some("claude-sonnet-4-5".into());
+        let args = OpenCodeRunner.build_args(&config);
+        let idx = args.iter().position(|a| a == "--model").unwrap();
+        assert_eq!(args[idx + 1], "anthropic/claude-sonnet-4-5");
+    }
+
+    #[test]
+    fn build_args_attaches_images_as_files() {
+        let mut config = TaskConfig::new("describe", crate::config::AgentKind::OpenCode);
+        config.images = vec!["shot.png".into()];
+        let args = OpenCodeRunner.build_args(&config);
+        let idx = args.iter().position(|a| a == "--file").unwrap();
+        assert_eq!(args[idx + 1], "shot.png");
+        assert_eq!(args[args.len() - 2..], ["--", "describe"]);
+
+        // Without images the prompt follows the options directly.
+        config.images.clear();
+        assert!(!OpenCodeRunner
+            .build_args(&config)
+            .contains(&"--".to_string()));
+    }
+
+    #[test]
+    fn normalize_model_provider_prefixes() {
+        let runner = OpenCodeRunner;
+        assert_eq!(runner.normalize_model("gpt-5"), "openai/gpt-5");
+        assert_eq!(runner.normalize_model("o3-mini"), "openai/o3-mini");
+        assert_eq!(runner.normalize_model("gemini-2.5-pro"), "google/gemini-2.5-pro");
+        assert_eq!(runner.normalize_model("anthropic/claude-opus-4-6"), "anthropic/claude-opus-4-6");
+        assert_eq!(runner.normalize_model("llama3"), "llama3");
+        assert_eq!(runner.normalize_model("opus"), "opus");
+    }
+
     #[test]
     fn build_args_full_access_no_agent_flag() {
         let config = TaskConfig::new("task", crate::config::AgentKind::OpenCode);
@@ -496,4 +648,19 @@ mod tests {
         let args = runner.build_args(&config);
         assert!(!args.contains(&"--agent".to_string()));
     }
+
+    #[test]
+    fn capabilities_emits_whole_messages_only() {
+        let caps = OpenCodeRunner.capabilities();
+        assert!(!caps.supports_streaming);
+    }
+
+    #[test]
+    fn permission_modes_are_native() {
+        assert!(OpenCodeRunner.supports_permission_mode(PermissionMode::FullAccess));
+        assert!(OpenCodeRunner.supports_permission_mode(PermissionMode::ReadOnly));
+        let mut config = TaskConfig::new("look", crate::config::AgentKind::OpenCode);
+        config.permission_mode = PermissionMode::ReadOnly;
+        assert!(OpenCodeRunner.validate_config(&config).is_empty());
+    }
 }
@@ -11,9 +11,19 @@ pub enum AgentKind {
     OpenCode,
     Codex,
     Cursor,
+    Gemini,
 }
 
 impl AgentKind {
+    /// Every supported agent, in display order.
+    pub const ALL: [AgentKind; 5] = [
+        AgentKind::Claude,
+        AgentKind::OpenCode,
+        AgentKind::Codex,
+        AgentKind::Cursor,
+        AgentKind::Gemini,
+    ];
+
     /// Default binary name for this agent (first in the candidates list).
     pub fn default_binary(&self) -> &'static str {
         self.binary_candidates()[0]
@@ -28,29 +38,99 @@ impl AgentKind {
             AgentKind::Codex => &["codex"],
             // Cursor ships as "agent" on some installs, "cursor-agent" on others
             AgentKind::Cursor => &["cursor-agent", "agent"],
+            AgentKind::Gemini => &["gemini"],
         }
     }
 
-    /// Environment variable names for API keys relevant to this agent.
+    /// Environment variable names for API keys relevant to this agent, in
+    /// precedence order: the agent reads the first one that is set.
     pub fn api_key_env_vars(&self) -> &'static [&'static str] {
         match self {
             AgentKind::Claude => &["ANTHROPIC_API_KEY"],
-            AgentKind::Codex => &["OPENAI_API_KEY"],
+            AgentKind::Codex => &["CODEX_API_KEY", "OPENAI_API_KEY"],
             AgentKind::OpenCode => &["ANTHROPIC_API_KEY", "OPENAI_API_KEY"],
             AgentKind::Cursor => &["CURSOR_API_KEY"],
+            AgentKind::Gemini => &["GEMINI_API_KEY", "GOOGLE_API_KEY"],
         }
     }
 
+    /// The first of [`api_key_env_vars`](Self::api_key_env_vars) set to a
+    /// non-empty value, with that value masked (e.g. `****wxyz`).
+    pub fn resolved_api_key(&self) -> Option<(String, String)> {
+        self.resolve_api_key_with(|key| std::env::var(key).ok())
+    }
+
+    /// [`resolved_api_key`](Self::resolved_api_key) against an arbitrary
+    /// variable lookup instead of the process environment.
+    pub fn resolve_api_key_with(
+        &self,
+        lookup: impl Fn(&str) -> Option<String>,
+    ) -> Option<(String, String)> {
+        self.api_key_env_vars().iter().find_map(|key| {
+            let value = lookup(key).filter(|v| !v.is_empty())?;
+            Some((key.to_string(), mask_secret(&value)))
+        })
+    }
+
     pub fn display_name(&self) -> &'static str {
         match self {
             AgentKind::Claude => "Claude Code",
             AgentKind::OpenCode => "OpenCode",
             AgentKind::Codex => "Codex",
             AgentKind::Cursor => "Cursor",
+            AgentKind::Gemini => "Gemini CLI",
+        }
+    }
+
+    /// Recommended task timeout in seconds, used when none is configured.
+    ///
+    /// Legacy builds (below the agent's known-slow threshold) start and
+    /// stream noticeably slower, so they get double the base timeout.
+    /// An unknown or unparseable version gets the base timeout.
+    pub fn recommended_timeout_secs(&self, version: Option<&str>) -> u64 {
+        let (base, legacy_below) = match self {
+            AgentKind::Claude => (600, (1, 0, 0)),
+            AgentKind::OpenCode => (600, (0, 5, 0)),
+            AgentKind::Codex => (900, (0, 20, 0)),
+            AgentKind::Cursor => (600, (2025, 1, 0)),
+            AgentKind::Gemini => (600, (0, 1, 0)),
+        };
+        match version.and_then(parse_version) {
+            Some(v) if v < legacy_below => base * 2,
+            _ => base,
         }
     }
 }
 
+/// Parse a `major.minor.patch` triple out of an agent's `--version` output.
+///
+/// Agents decorate their version differently (`1.0.43 (Claude Code)`,
+/// `codex-cli 0.46.0`, `v0.3.1`), so this takes the first whitespace-separated
+/// token that starts with a digit (after an optional `v`). Missing minor/patch
+/// components default to 0.
+pub fn parse_version(raw: &str) -> Option<(u64, u64, u64)> {
+    let token = raw
+        .split_whitespace()
+        .map(|t| t.trim_start_matches('v'))
+        .find(|t| t.starts_with(|c: char| c.is_ascii_digit()))?;
+    let mut parts = token.split(['.', '-', '+']).map(|p| p.parse::<u64>().ok());
+    let major = parts.next()??;
+    let minor = parts.next().flatten().unwrap_or(0);
+    let patch = parts.next().flatten().unwrap_or(0);
+    Some((major, minor, patch))
+}
+
+/// Mask all but the last four characters of a secret; short secrets are
+/// masked entirely.
+fn mask_secret(secret: &str) -> String {
+    let chars: Vec<char> = secret.chars().collect();
+    if chars.len() < 12 {
+        return "****".to_string();
+    }
+    let tail: String = chars[chars.len() - 4..].iter().collect();
+    format!("****{tail}")
+}
+
 impl std::fmt::Display for AgentKind {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         f.write_str(self.display_name())
@@ -66,8 +146,9 @@ impl std::str::FromStr for AgentKind {
             "opencode" | "open-code" | "open_code" => Ok(AgentKind::OpenCode),
             "codex" | "openai-codex" | "openai_codex" => Ok(AgentKind::Codex),
             "cursor" | "cursor-agent" | "cursor_agent" => Ok(AgentKind::Cursor),
+            "gemini" | "gemini-cli" | "gemini_cli" => Ok(AgentKind::Gemini),
             _ => Err(format!(
-                "unknown agent: `{s}` (expected: claude, opencode, codex, cursor)"
+                "unknown agent: `{s}` (expected: claude, opencode, codex, cursor, gemini)"
             )),
         }
     }
@@ -86,6 +167,33 @@ pub enum PermissionMode {
     ReadOnly,
 }
 
+impl PermissionMode {
+    /// Every mode, in the order `check --capabilities` lists them.
+    pub const ALL: [PermissionMode; 2] = [PermissionMode::FullAccess, PermissionMode::ReadOnly];
+
+    /// The name `--permissions` accepts for this mode.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            PermissionMode::FullAccess => "full-access",
+            PermissionMode::ReadOnly => "read-only",
+        }
+    }
+}
+
+impl std::str::FromStr for PermissionMode {
+    type Err = String;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        match s {
+            "full-access" | "full" | "yolo" | "default" => Ok(PermissionMode::FullAccess),
+            "read-only" | "readonly" | "plan" => Ok(PermissionMode::ReadOnly),
+            _ => Err(format!(
+                "unknown permission mode: `{s}` (expected: full-access, read-only)"
+            )),
+        }
+    }
+}
+
 /// Desired output format for the final result.
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Default)]
 #[serde(rename_all = "snake_case")]
@@ -99,6 +207,25 @@ pub enum OutputFormat {
     StreamJson,
     /// Markdown — human-readable transcript with headings and code blocks.
     Markdown,
+    /// HTML — a self-contained, styled transcript written when the run ends.
+    Html,
+}
+
+impl std::str::FromStr for OutputFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "text" => Ok(OutputFormat::Text),
+            "json" => Ok(OutputFormat::Json),
+            "stream-json" | "stream_json" | "ndjson" => Ok(OutputFormat::StreamJson),
+            "markdown" | "md" => Ok(OutputFormat::Markdown),
+            "html" => Ok(OutputFormat::Html),
+            _ => Err(format!(
+                "unknown output format: `{s}` (expected: text, json, stream-json, markdown, html)"
+            )),
+        }
+    }
 }
 
 /// Unified task configuration — everything needed to run a task on any agent.
@@ -138,6 +265,11 @@ pub struct TaskConfig {
     #[serde(default)]
     pub timeout_secs: Option<u64>,
 
+    /// Cancel the run if the agent produces no event for this many seconds
+    /// (reported as an `Error` with code `stall_timeout`).
+    #[serde(default)]
+    pub stall_timeout_secs: Option<u64>,
+
     /// Custom system prompt (replaces default).
     #[serde(default)]
     pub system_prompt: Option<String>,
@@ -157,6 +289,58 @@ pub struct TaskConfig {
     /// Extra agent-specific flags passed through verbatim.
     #[serde(default)]
     pub extra_args: Vec<String>,
+
+    /// Image files to attach to the prompt (agents with `supports_images` only).
+    #[serde(default)]
+    pub images: Vec<PathBuf>,
+
+    /// Wrapper command the agent is launched through (e.g. `["nice", "-n10"]`);
+    /// the agent binary becomes the wrapper's first argument.
+    #[serde(default)]
+    pub command_prefix: Vec<String>,
+
+    /// Forward the agent's stderr to ours line by line as it arrives
+    /// (prefixed with the agent name), not only on failure.
+    #[serde(default)]
+    pub stream_stderr: bool,
+
+    /// Named agent profile to run (OpenCode `--agent <profile>`). Only used
+    /// under full access; read-only runs keep the `plan` profile.
+    #[serde(default)]
+    pub agent_profile: Option<String>,
+
+    /// Agent session ID to resume instead of starting a new session.
+    #[serde(default)]
+    pub resume_session_id: Option<String>,
+
+    /// Re-run the task when it fails with a retryable error (rate limits,
+    /// timeouts, …) before producing a successful result.
+    #[serde(default)]
+    pub retry: Option<crate::retry::RetryPolicy>,
+
+    /// Extra regexes whose matches are masked as `***` in the event stream
+    /// and session log, on top of the builtin token shapes.
+    #[serde(default)]
+    pub redact_patterns: Vec<String>,
+
+    /// Emit `Reasoning` events for the agent's thinking instead of dropping
+    /// them.
+    #[serde(default)]
+    pub include_reasoning: bool,
+
+    /// Emit `TurnStart`/`TurnEnd` events around each model turn.
+    #[serde(default)]
+    pub include_turns: bool,
+
+    /// Write each tool call's output to `<dir>/<call_id>.txt` and put a
+    /// `file://` reference in the `ToolEnd` event instead of the text.
+    #[serde(default)]
+    pub tool_output_dir: Option<PathBuf>,
+
+    /// Pass agent-reported checkpoint `Result`s through instead of turning
+    /// them into assistant messages.
+    #[serde(default)]
+    pub keep_partial_results: bool,
 }
 
 impl TaskConfig {
@@ -171,11 +355,23 @@ impl TaskConfig {
             max_turns: None,
             max_budget_usd: None,
             timeout_secs: None,
+            stall_timeout_secs: None,
             system_prompt: None,
             append_system_prompt: None,
             binary_path: None,
             env: HashMap::new(),
             extra_args: Vec::new(),
+            images: Vec::new(),
+            command_prefix: Vec::new(),
+            stream_stderr: false,
+            agent_profile: None,
+            resume_session_id: None,
+            retry: None,
+            redact_patterns: Vec::new(),
+            include_reasoning: false,
+            include_turns: false,
+            tool_output_dir: None,
+            keep_partial_results: false,
         }
     }
 
@@ -183,6 +379,32 @@ impl TaskConfig {
     pub fn builder(prompt: impl Into<String>, agent: AgentKind) -> TaskConfigBuilder {
         TaskConfigBuilder::new(prompt, agent)
     }
+
+    /// Check for mistakes no agent could run with: a blank prompt, a
+    /// budget that isn't a positive number, or a zero turn limit or timeout.
+    ///
+    /// Capability mismatches are not errors here; see
+    /// [`AgentRunner::validate_config`](crate::runner::AgentRunner::validate_config).
+    pub fn validate(&self) -> crate::Result<()> {
+        let invalid = |msg: String| Err(crate::Error::Other(msg));
+        if self.prompt.trim().is_empty() {
+            return invalid("prompt is empty".into());
+        }
+        if let Some(budget) = self.max_budget_usd {
+            if !budget.is_finite() || budget <= 0.0 {
+                return invalid(format!(
+                    "max_budget_usd must be a positive number, got {budget}"
+                ));
+            }
+        }
+        if self.max_turns == Some(0) {
+            return invalid("max_turns must be at least 1".into());
+        }
+        if self.timeout_secs == Some(0) || self.stall_timeout_secs == Some(0) {
+            return invalid("timeouts must be at least 1 second".into());
+        }
+        Ok(())
+    }
 }
 
 /// Fluent builder for `TaskConfig`.
@@ -194,6 +416,33 @@ impl TaskConfig {
 ///     .timeout_secs(60)
 ///     .build();
 /// ```
+///
+/// Every field has a setter. `env`, `extra_arg`/`extra_args`, and `image`
+/// accumulate across calls; the rest overwrite.
+///
+/// ```rust
+/// use harness::config::{AgentKind, OutputFormat, PermissionMode, TaskConfig};
+/// let config = TaskConfig::builder("refactor the parser", AgentKind::Codex)
+///     .cwd("/work/project")
+///     .model("gpt-5-codex")
+///     .permission_mode(PermissionMode::ReadOnly)
+///     .output_format(OutputFormat::Json)
+///     .max_turns(20)
+///     .max_budget_usd(2.0)
+///     .timeout_secs(600)
+///     .system_prompt("You are a careful reviewer.")
+///     .append_system_prompt("Keep diffs small.")
+///     .binary_path("/usr/local/bin/codex")
+///     .env("RUST_LOG", "info")
+///     .env("CI", "1")
+///     .extra_arg("--skip-git-repo-check")
+///     .extra_arg("--json")
+///     .build();
+///
+/// assert_eq!(config.max_turns, Some(20));
+/// assert_eq!(config.env.len(), 2);
+/// assert_eq!(config.extra_args, ["--skip-git-repo-check", "--json"]);
+/// ```
 pub struct TaskConfigBuilder {
     config: TaskConfig,
 }
@@ -245,6 +494,11 @@ impl TaskConfigBuilder {
         self
     }
 
+    pub fn stall_timeout_secs(mut self, secs: u64) -> Self {
+        self.config.stall_timeout_secs = Some(secs);
+        self
+    }
+
     pub fn system_prompt(mut self, prompt: impl Into<String>) -> Self {
         self.config.system_prompt = Some(prompt.into());
         self
@@ -275,7 +529,68 @@ impl TaskConfigBuilder {
         self
     }
 
+    pub fn image(mut self, path: impl Into<PathBuf>) -> Self {
+        self.config.images.push(path.into());
+        self
+    }
+
+    pub fn command_prefix(mut self, prefix: Vec<String>) -> Self {
+        self.config.command_prefix = prefix;
+        self
+    }
+
+    pub fn stream_stderr(mut self, enabled: bool) -> Self {
+        self.config.stream_stderr = enabled;
+        self
+    }
+
+    pub fn agent_profile(mut self, profile: impl Into<String>) -> Self {
+        self.config.agent_profile = Some(profile.into());
+        self
+    }
+
+    pub fn resume_session_id(mut self, id: impl Into<String>) -> Self {
+        self.config.resume_session_id = Some(id.into());
+        self
+    }
+
+    pub fn retry(mut self, policy: crate::retry::RetryPolicy) -> Self {
+        self.config.retry = Some(policy);
+        self
+    }
+
+    pub fn redact_pattern(mut self, pattern: impl Into<String>) -> Self {
+        self.config.redact_patterns.push(pattern.into());
+        self
+    }
+
+    pub fn include_reasoning(mut self, include: bool) -> Self {
+        self.config.include_reasoning = include;
+        self
+    }
+
+    pub fn include_turns(mut self, include: bool) -> Self {
+        self.config.include_turns = include;
+        self
+    }
+
+    pub fn tool_output_dir(mut self, dir: impl Into<PathBuf>) -> Self {
+        self.config.tool_output_dir = Some(dir.into());
+        self
+    }
+
+    pub fn keep_partial_results(mut self, keep: bool) -> Self {
+        self.config.keep_partial_results = keep;
+        self
+    }
+
     pub fn build(self) -> TaskConfig {
         self.config
     }
+
+    /// Like [`build`](Self::build), but run [`TaskConfig::validate`] first.
+    pub fn build_validated(self) -> crate::Result<TaskConfig> {
+        self.config.validate()?;
+        Ok(self.config)
+    }
 }
@@ -0,0 +1,167 @@
+//! Minimal UTC date/time helpers (RFC 3339 parsing and civil-date math),
+//! so the harness doesn't need a full date/time dependency.
+
+/// Days since 1970-01-01 for a proleptic Gregorian civil date.
+///
+/// Howard Hinnant's `days_from_civil` algorithm.
+pub fn days_from_civil(year: i64, month: u32, day: u32) -> i64 {
+    let y = if month <= 2 { year - 1 } else { year };
+    let era = if y >= 0 { y } else { y - 399 } / 400;
+    let yoe = y - era * 400;
+    let m = month as i64;
+    let doy = (153 * (if m > 2 { m - 3 } else { m + 9 }) + 2) / 5 + day as i64 - 1;
+    let doe = yoe * 365 + yoe / 4 - yoe / 100 + doy;
+    era * 146_097 + doe - 719_468
+}
+
+/// Civil `(year, month, day)` for a count of days since 1970-01-01.
+///
+/// Inverse of [`days_from_civil`].
+pub fn civil_from_days(days: i64) -> (i64, u32, u32) {
+    let z = days + 719_468;
+    let era = if z >= 0 { z } else { z - 146_096 } / 146_097;
+    let doe = z - era * 146_097;
+    let yoe = (doe - doe / 1460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = (doy - (153 * mp + 2) / 5 + 1) as u32;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 } as u32;
+    let year = yoe + era * 400 + if month <= 2 { 1 } else { 0 };
+    (year, month, day)
+}
+
+/// Format epoch milliseconds as an RFC 3339 UTC timestamp with
+/// millisecond precision (e.g. `2026-03-01T09:30:00.000Z`).
+pub fn format_rfc3339(epoch_ms: u64) -> String {
+    let secs = epoch_ms / 1000;
+    let (year, month, day) = civil_from_days((secs / 86_400) as i64);
+    let rem = secs % 86_400;
+    format!(
+        "{year:04}-{month:02}-{day:02}T{:02}:{:02}:{:02}.{:03}Z",
+        rem / 3600,
+        (rem % 3600) / 60,
+        rem % 60,
+        epoch_ms % 1000
+    )
+}
+
+/// Parse an RFC 3339 timestamp (e.g. `2026-03-01T09:30:00Z`,
+/// `2026-03-01T09:30:00.250+02:00`) into epoch milliseconds.
+///
+/// Accepts `T`, `t`, or a space as the date/time separator and `Z`/`z` or a
+/// `±HH:MM` offset. Returns `None` for anything malformed or before 1970.
+pub fn parse_rfc3339(s: &str) -> Option<u64> {
+    let s = s.trim();
+    let bytes = s.as_bytes();
+    if bytes.len() < 20 || !matches!(bytes[10], b'T' | b't' | b' ') {
+        return None;
+    }
+    let num = |range: std::ops::Range<usize>| -> Option<u32> {
+        let part = s.get(range)?;
+        if !part.bytes().all(|b| b.is_ascii_digit()) {
+            return None;
+        }
+        part.parse().ok()
+    };
+    if bytes[4] != b'-' || bytes[7] != b'-' || bytes[13] != b':' || bytes[16] != b':' {
+        return None;
+    }
+    let year = num(0..4)?;
+    let month = num(5..7)?;
+    let day = num(8..10)?;
+    let hour = num(11..13)?;
+    let minute = num(14..16)?;
+    let second = num(17..19)?;
+    if !(1..=12).contains(&month) || !(1..=31).contains(&day) || hour > 23 || minute > 59 || second > 60 {
+        return None;
+    }
+
+    // Optional fractional seconds.
+    let mut rest = &s[19..];
+    let mut millis = 0u64;
+    if let Some(frac) = rest.strip_prefix('.') {
+        let digits = frac.bytes().take_while(|b| b.is_ascii_digit()).count();
+        if digits == 0 {
+            return None;
+        }
+        let padded = format!("{:0<3}", &frac[..digits.min(3)]);
+        millis = padded.parse().ok()?;
+        rest = &frac[digits..];
+    }
+
+    // Offset: Z or ±HH:MM.
+    let offset_secs: i64 = match rest {
+        "Z" | "z" => 0,
+        _ => {
+            let sign = match rest.as_bytes().first()? {
+                b'+' => 1,
+                b'-' => -1,
+                _ => return None,
+            };
+            let off = &rest[1..];
+            if off.len() != 5 || off.as_bytes()[2] != b':' {
+                return None;
+            }
+            let oh: i64 = off[..2].parse().ok()?;
+            let om: i64 = off[3..].parse().ok()?;
+            sign * (oh * 3600 + om * 60)
+        }
+    };
+
+    let days = days_from_civil(year as i64, month, day);
+    let secs = days * 86_400 + hour as i64 * 3600 + minute as i64 * 60 + second as i64 - offset_secs;
+    if secs < 0 {
+        return None;
+    }
+    Some(secs as u64 * 1000 + millis)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn days_from_civil_known_dates() {
+        assert_eq!(days_from_civil(1970, 1, 1), 0);
+        assert_eq!(days_from_civil(2000, 3, 1), 11_017);
+        assert_eq!(days_from_civil(2024, 2, 29), 19_782);
+    }
+
+    #[test]
+    fn civil_from_days_round_trips() {
+        for days in [-1, 0, 59, 11_017, 19_782, 30_000] {
+            let (y, m, d) = civil_from_days(days);
+            assert_eq!(days_from_civil(y, m, d), days);
+        }
+        assert_eq!(civil_from_days(19_782), (2024, 2, 29));
+    }
+
+    #[test]
+    fn format_rfc3339_round_trips() {
+        let ms = 1_709_208_000_123;
+        let s = format_rfc3339(ms);
+        assert_eq!(s, "2024-02-29T12:00:00.123Z");
+        assert_eq!(parse_rfc3339(&s), Some(ms));
+    }
+
+    #[test]
+    fn parse_rfc3339_utc() {
+        assert_eq!(parse_rfc3339("1970-01-01T00:00:00Z"), Some(0));
+        assert_eq!(parse_rfc3339("2024-02-29T12:00:00Z"), Some(1_709_208_000_000));
+    }
+
+    #[test]
+    fn parse_rfc3339_fraction_and_offset() {
+        assert_eq!(parse_rfc3339("1970-01-01T00:00:01.5Z"), Some(1_500));
+        assert_eq!(parse_rfc3339("1970-01-01T02:00:00+02:00"), Some(0));
+        assert_eq!(parse_rfc3339("1970-01-01T00:00:00-01:00"), Some(3_600_000));
+    }
+
+    #[test]
+    fn parse_rfc3339_rejects_malformed() {
+        assert_eq!(parse_rfc3339("2024-02-29"), None);
+        assert_eq!(parse_rfc3339("2024-13-01T00:00:00Z"), None);
+        assert_eq!(parse_rfc3339("2024-01-01T00:00:00"), None);
+        assert_eq!(parse_rfc3339("not a timestamp at all"), None);
+    }
+}
@@ -18,7 +18,7 @@ pub enum Error {
     #[error("agent timed out after {0} seconds")]
     Timeout(u64),
 
-    #[error("working directory does not exist: {0}")]
+    #[error("working directory does not exist or is not a directory: {0}")]
     InvalidWorkDir(PathBuf),
 
     #[error("I/O error: {0}")]
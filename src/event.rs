@@ -40,23 +40,111 @@ pub enum Event {
     /// A complete assistant message.
     Message(MessageEvent),
 
+    /// The agent's internal reasoning (Codex `reasoning` items, Claude
+    /// `thinking` blocks). Only emitted when `include_reasoning` is set.
+    Reasoning(ReasoningEvent),
+
     /// The agent is invoking a tool.
     ToolStart(ToolStartEvent),
 
     /// A tool invocation has completed.
     ToolEnd(ToolEndEvent),
 
+    /// The agent began a model turn. Only emitted when `include_turns` is set.
+    TurnStart(TurnStartEvent),
+
+    /// The agent finished a model turn. Only emitted when `include_turns` is set.
+    TurnEnd(TurnEndEvent),
+
     /// Incremental usage/cost update.
     UsageDelta(UsageDeltaEvent),
 
     /// The agent run has finished.
     Result(ResultEvent),
 
+    /// Run totals, emitted by the normalizer right after the `Result`.
+    Summary(SummaryEvent),
+
     /// An error occurred during the run.
     Error(ErrorEvent),
 }
 
 impl Event {
+    /// The native agent event type this event was translated from, when the
+    /// parser recorded one (e.g. `"assistant"`, `"item.completed"`).
+    pub fn raw_type(&self) -> Option<&str> {
+        match self {
+            Event::SessionStart(e) => e.raw_type.as_deref(),
+            Event::TextDelta(e) => e.raw_type.as_deref(),
+            Event::Message(e) => e.raw_type.as_deref(),
+            Event::Reasoning(e) => e.raw_type.as_deref(),
+            Event::ToolStart(e) => e.raw_type.as_deref(),
+            Event::ToolEnd(e) => e.raw_type.as_deref(),
+            Event::TurnStart(e) => e.raw_type.as_deref(),
+            Event::TurnEnd(e) => e.raw_type.as_deref(),
+            Event::UsageDelta(e) => e.raw_type.as_deref(),
+            Event::Result(e) => e.raw_type.as_deref(),
+            Event::Summary(e) => e.raw_type.as_deref(),
+            Event::Error(e) => e.raw_type.as_deref(),
+        }
+    }
+
+    /// Set (or clear) the native agent event type.
+    pub fn set_raw_type(&mut self, raw_type: Option<String>) {
+        let slot = match self {
+            Event::SessionStart(e) => &mut e.raw_type,
+            Event::TextDelta(e) => &mut e.raw_type,
+            Event::Message(e) => &mut e.raw_type,
+            Event::Reasoning(e) => &mut e.raw_type,
+            Event::ToolStart(e) => &mut e.raw_type,
+            Event::ToolEnd(e) => &mut e.raw_type,
+            Event::TurnStart(e) => &mut e.raw_type,
+            Event::TurnEnd(e) => &mut e.raw_type,
+            Event::UsageDelta(e) => &mut e.raw_type,
+            Event::Result(e) => &mut e.raw_type,
+            Event::Summary(e) => &mut e.raw_type,
+            Event::Error(e) => &mut e.raw_type,
+        };
+        *slot = raw_type;
+    }
+
+    /// When the event happened, in epoch ms (0 if not yet stamped).
+    pub fn timestamp_ms(&self) -> u64 {
+        match self {
+            Event::SessionStart(e) => e.timestamp_ms,
+            Event::TextDelta(e) => e.timestamp_ms,
+            Event::Message(e) => e.timestamp_ms,
+            Event::Reasoning(e) => e.timestamp_ms,
+            Event::ToolStart(e) => e.timestamp_ms,
+            Event::ToolEnd(e) => e.timestamp_ms,
+            Event::TurnStart(e) => e.timestamp_ms,
+            Event::TurnEnd(e) => e.timestamp_ms,
+            Event::UsageDelta(e) => e.timestamp_ms,
+            Event::Result(e) => e.timestamp_ms,
+            Event::Summary(e) => e.timestamp_ms,
+            Event::Error(e) => e.timestamp_ms,
+        }
+    }
+
+    /// Set the event's timestamp (epoch ms).
+    pub fn set_timestamp_ms(&mut self, timestamp_ms: u64) {
+        let slot = match self {
+            Event::SessionStart(e) => &mut e.timestamp_ms,
+            Event::TextDelta(e) => &mut e.timestamp_ms,
+            Event::Message(e) => &mut e.timestamp_ms,
+            Event::Reasoning(e) => &mut e.timestamp_ms,
+            Event::ToolStart(e) => &mut e.timestamp_ms,
+            Event::ToolEnd(e) => &mut e.timestamp_ms,
+            Event::TurnStart(e) => &mut e.timestamp_ms,
+            Event::TurnEnd(e) => &mut e.timestamp_ms,
+            Event::UsageDelta(e) => &mut e.timestamp_ms,
+            Event::Result(e) => &mut e.timestamp_ms,
+            Event::Summary(e) => &mut e.timestamp_ms,
+            Event::Error(e) => &mut e.timestamp_ms,
+        };
+        *slot = timestamp_ms;
+    }
+
     /// Stamp the event with the current wall-clock time (epoch ms).
     pub fn stamp(self) -> Self {
         let ts = now_ms();
@@ -64,10 +152,14 @@ impl Event {
             Event::SessionStart(mut e) => { e.timestamp_ms = ts; Event::SessionStart(e) }
             Event::TextDelta(mut e) => { e.timestamp_ms = ts; Event::TextDelta(e) }
             Event::Message(mut e) => { e.timestamp_ms = ts; Event::Message(e) }
+            Event::Reasoning(mut e) => { e.timestamp_ms = ts; Event::Reasoning(e) }
             Event::ToolStart(mut e) => { e.timestamp_ms = ts; Event::ToolStart(e) }
             Event::ToolEnd(mut e) => { e.timestamp_ms = ts; Event::ToolEnd(e) }
+            Event::TurnStart(mut e) => { e.timestamp_ms = ts; Event::TurnStart(e) }
+            Event::TurnEnd(mut e) => { e.timestamp_ms = ts; Event::TurnEnd(e) }
             Event::UsageDelta(mut e) => { e.timestamp_ms = ts; Event::UsageDelta(e) }
             Event::Result(mut e) => { e.timestamp_ms = ts; Event::Result(e) }
+            Event::Summary(mut e) => { e.timestamp_ms = ts; Event::Summary(e) }
             Event::Error(mut e) => { e.timestamp_ms = ts; Event::Error(e) }
         }
     }
@@ -81,8 +173,16 @@ pub struct SessionStartEvent {
     pub model: Option<String>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub cwd: Option<String>,
+    /// Permission mode the run was started with.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub permission_mode: Option<crate::config::PermissionMode>,
+    /// The agent CLI's `--version` output, when known.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub agent_version: Option<String>,
     #[serde(default)]
     pub timestamp_ms: u64,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub raw_type: Option<String>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
@@ -90,6 +190,17 @@ pub struct TextDeltaEvent {
     pub text: String,
     #[serde(default)]
     pub timestamp_ms: u64,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub raw_type: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct ReasoningEvent {
+    pub text: String,
+    #[serde(default)]
+    pub timestamp_ms: u64,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub raw_type: Option<String>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
@@ -100,6 +211,8 @@ pub struct MessageEvent {
     pub usage: Option<UsageData>,
     #[serde(default)]
     pub timestamp_ms: u64,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub raw_type: Option<String>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
@@ -128,6 +241,8 @@ pub struct ToolStartEvent {
     pub input: Option<serde_json::Value>,
     #[serde(default)]
     pub timestamp_ms: u64,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub raw_type: Option<String>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
@@ -141,6 +256,29 @@ pub struct ToolEndEvent {
     pub usage: Option<UsageData>,
     #[serde(default)]
     pub timestamp_ms: u64,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub raw_type: Option<String>,
+}
+
+/// Start of one model turn (a request/response round with the model).
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct TurnStartEvent {
+    #[serde(default)]
+    pub timestamp_ms: u64,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub raw_type: Option<String>,
+}
+
+/// End of one model turn.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct TurnEndEvent {
+    /// Usage for the turn, when the agent reports it per turn.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub usage: Option<UsageData>,
+    #[serde(default)]
+    pub timestamp_ms: u64,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub raw_type: Option<String>,
 }
 
 /// Incremental usage report emitted during streaming.
@@ -149,9 +287,16 @@ pub struct UsageDeltaEvent {
     pub usage: UsageData,
     #[serde(default)]
     pub timestamp_ms: u64,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub raw_type: Option<String>,
 }
 
+/// End of a run (or a checkpoint within one, when `partial`).
+///
+/// New fields may be added, so build one with [`ResultEvent::new`] and the
+/// setters rather than a struct literal.
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[non_exhaustive]
 pub struct ResultEvent {
     pub success: bool,
     pub text: String,
@@ -162,8 +307,98 @@ pub struct ResultEvent {
     pub total_cost_usd: Option<f64>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub usage: Option<UsageData>,
+    /// Exit code the agent asked for in its result payload, if any.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub exit_code: Option<i32>,
+    /// An intermediate checkpoint rather than the end of the run. The
+    /// normalizer turns these into assistant messages unless told to keep them.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub partial: bool,
     #[serde(default)]
     pub timestamp_ms: u64,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub raw_type: Option<String>,
+}
+
+impl ResultEvent {
+    /// A final result with no duration, cost, usage, or exit code.
+    pub fn new(success: bool, text: impl Into<String>, session_id: impl Into<String>) -> Self {
+        Self {
+            success,
+            text: text.into(),
+            session_id: session_id.into(),
+            duration_ms: None,
+            total_cost_usd: None,
+            usage: None,
+            exit_code: None,
+            partial: false,
+            timestamp_ms: 0,
+            raw_type: None,
+        }
+    }
+
+    pub fn duration_ms(mut self, duration_ms: u64) -> Self {
+        self.duration_ms = Some(duration_ms);
+        self
+    }
+
+    pub fn total_cost_usd(mut self, cost: f64) -> Self {
+        self.total_cost_usd = Some(cost);
+        self
+    }
+
+    pub fn usage(mut self, usage: UsageData) -> Self {
+        self.usage = Some(usage);
+        self
+    }
+
+    pub fn exit_code(mut self, code: i32) -> Self {
+        self.exit_code = Some(code);
+        self
+    }
+
+    pub fn partial(mut self, partial: bool) -> Self {
+        self.partial = partial;
+        self
+    }
+
+    pub fn timestamp_ms(mut self, timestamp_ms: u64) -> Self {
+        self.timestamp_ms = timestamp_ms;
+        self
+    }
+
+    pub fn raw_type(mut self, raw_type: impl Into<String>) -> Self {
+        self.raw_type = Some(raw_type.into());
+        self
+    }
+}
+
+/// Totals for a whole run, so consumers don't have to aggregate events
+/// themselves. Token counts and cost match the `Result`'s final usage.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Default)]
+pub struct SummaryEvent {
+    #[serde(default)]
+    pub input_tokens: u64,
+    #[serde(default)]
+    pub output_tokens: u64,
+    #[serde(default)]
+    pub cache_read_tokens: u64,
+    #[serde(default)]
+    pub cache_creation_tokens: u64,
+    #[serde(default)]
+    pub total_cost_usd: f64,
+    /// Number of `ToolStart` events.
+    #[serde(default)]
+    pub tool_calls: u64,
+    /// Number of `Message` events, user and assistant.
+    #[serde(default)]
+    pub messages: u64,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub duration_ms: Option<u64>,
+    #[serde(default)]
+    pub timestamp_ms: u64,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub raw_type: Option<String>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
@@ -173,6 +408,8 @@ pub struct ErrorEvent {
     pub code: Option<String>,
     #[serde(default)]
     pub timestamp_ms: u64,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub raw_type: Option<String>,
 }
 
 // ─── Aggregation helpers ────────────────────────────────────────
@@ -236,17 +473,543 @@ pub fn extract_tool_calls(events: &[Event]) -> Vec<(&ToolStartEvent, Option<&Too
     starts
 }
 
+/// Map an agent-specific tool name onto a cross-agent canonical name.
+///
+/// Each backend names the same tools differently (Claude's `Bash`, Codex's
+/// `shell`, OpenCode's `bash`, ...). Known tools map to one of `shell`,
+/// `read`, `write`, `edit`, `search`, or `web`; anything else is returned
+/// unchanged.
+pub fn canonical_tool_name(name: &str) -> &str {
+    match name.to_ascii_lowercase().as_str() {
+        "bash" | "shell" | "command_execution" | "run_terminal_cmd" | "terminal" | "exec" => {
+            "shell"
+        }
+        "read" | "read_file" | "view" => "read",
+        "write" | "write_file" | "create_file" => "write",
+        "edit" | "multiedit" | "str_replace" | "apply_patch" | "patch" | "file_change" => "edit",
+        "grep" | "glob" | "search" | "find" | "ls" | "list" | "codebase_search" => "search",
+        "webfetch" | "web_fetch" | "websearch" | "web_search" => "web",
+        _ => name,
+    }
+}
+
+/// How a tool call changed a file.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum FileChangeKind {
+    Created,
+    Modified,
+    Deleted,
+}
+
+/// A file touched by a run, as reported by [`extract_file_changes`].
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct FileChange {
+    pub path: String,
+    pub kind: FileChangeKind,
+}
+
+/// Derive the files a run changed from its file-editing tool calls.
+///
+/// Recognizes each backend's write/edit/delete tools (Claude's `Write` and
+/// `Edit`, Codex's `file_change` and `apply_patch`, Cursor's `write`/`delete`,
+/// OpenCode's `edit`/`patch`, Gemini's `write_file`/`replace`). Calls whose
+/// `ToolEnd` reports failure are ignored. Each path appears once, in the order
+/// it was first touched, with its net change: a file created and then edited
+/// is `Created`, and a file created and then deleted is dropped.
+///
+/// A whole-file write is `Modified` when the tool's result says it replaced
+/// an existing file (Claude's "has been updated", Gemini's "overwrote").
+/// OpenCode and Cursor don't say, so their writes count as `Created` unless
+/// the run touched the path before.
+pub fn extract_file_changes(events: &[Event]) -> Vec<FileChange> {
+    let mut changes = Vec::new();
+    for (start, end) in extract_tool_calls(events) {
+        if end.is_some_and(|e| !e.success) {
+            continue;
+        }
+        let overwrote = end
+            .and_then(|e| e.output.as_deref())
+            .is_some_and(reports_overwrite);
+        let write_kind = if overwrote {
+            FileChangeKind::Modified
+        } else {
+            FileChangeKind::Created
+        };
+        for (path, kind) in tool_file_changes(&start.tool_name, start.input.as_ref(), write_kind) {
+            record_file_change(&mut changes, path, kind);
+        }
+    }
+    changes
+}
+
+/// Whether a write tool's result says it replaced an existing file.
+fn reports_overwrite(output: &str) -> bool {
+    // Claude: "The file <path> has been updated. Here's the result of ..."
+    // Gemini: "Successfully overwrote file: <path>."
+    output.contains("has been updated") || output.contains("Successfully overwrote file")
+}
+
+fn tool_file_changes(
+    tool_name: &str,
+    input: Option<&serde_json::Value>,
+    write_kind: FileChangeKind,
+) -> Vec<(String, FileChangeKind)> {
+    let Some(input) = input else {
+        return vec![];
+    };
+    let path = [
+        "file_path",
+        "path",
+        "filePath",
+        "notebook_path",
+        "target_file",
+    ]
+    .iter()
+    .find_map(|k| input.get(*k)?.as_str())
+    .filter(|p| !p.is_empty())
+    .map(|p| p.to_string());
+    let single = |kind| path.clone().map(|p| vec![(p, kind)]).unwrap_or_default();
+
+    match tool_name.to_ascii_lowercase().as_str() {
+        "write" | "write_file" | "create_file" => single(write_kind),
+        "edit" | "multiedit" | "notebookedit" | "edit_file" | "str_replace" | "replace"
+        | "search_replace" => single(FileChangeKind::Modified),
+        "delete" | "delete_file" | "remove_file" => single(FileChangeKind::Deleted),
+        // Codex lists every file in one item: `changes: [{ path, kind }]`.
+        "file_change" => match input.get("changes").and_then(|v| v.as_array()) {
+            Some(entries) => entries
+                .iter()
+                .filter_map(|c| {
+                    let path = c.get("path")?.as_str()?;
+                    let kind = c
+                        .get("kind")
+                        .and_then(|k| k.as_str().or_else(|| k.get("type")?.as_str()));
+                    let kind = match kind {
+                        Some("add") => FileChangeKind::Created,
+                        Some("delete") => FileChangeKind::Deleted,
+                        _ => FileChangeKind::Modified,
+                    };
+                    Some((path.to_string(), kind))
+                })
+                .collect(),
+            None => single(FileChangeKind::Modified),
+        },
+        "apply_patch" | "patch" => ["input", "patch", "patchText"]
+            .iter()
+            .find_map(|k| input.get(*k)?.as_str())
+            .or_else(|| input.as_str())
+            .map(patch_file_changes)
+            .unwrap_or_default(),
+        _ => vec![],
+    }
+}
+
+/// Read the file headers of an `apply_patch`-style patch.
+fn patch_file_changes(patch: &str) -> Vec<(String, FileChangeKind)> {
+    patch
+        .lines()
+        .filter_map(|line| {
+            let (kind, path) = if let Some(p) = line.strip_prefix("*** Add File: ") {
+                (FileChangeKind::Created, p)
+            } else if let Some(p) = line.strip_prefix("*** Update File: ") {
+                (FileChangeKind::Modified, p)
+            } else if let Some(p) = line.strip_prefix("*** Delete File: ") {
+                (FileChangeKind::Deleted, p)
+            } else {
+                return None;
+            };
+            Some((path.trim().to_string(), kind))
+        })
+        .collect()
+}
+
+fn record_file_change(changes: &mut Vec<FileChange>, path: String, kind: FileChangeKind) {
+    use FileChangeKind::*;
+    let Some(idx) = changes.iter().position(|c| c.path == path) else {
+        changes.push(FileChange { path, kind });
+        return;
+    };
+    match (changes[idx].kind, kind) {
+        (Created, Deleted) => {
+            changes.remove(idx);
+        }
+        (Created, _) => {}
+        (Deleted, Created) | (Modified, Created) => changes[idx].kind = Modified,
+        (_, kind) => changes[idx].kind = kind,
+    }
+}
+
+// ─── Filtering ──────────────────────────────────────────────────
+
+/// A coarse kind of event, for [`EventFilter`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EventCategory {
+    Session,
+    TextDelta,
+    Message,
+    Reasoning,
+    /// `ToolStart` and `ToolEnd`.
+    Tool,
+    /// `TurnStart` and `TurnEnd`.
+    Turn,
+    Usage,
+    Result,
+    Summary,
+    Error,
+}
+
+impl EventCategory {
+    pub const ALL: [EventCategory; 10] = [
+        EventCategory::Session,
+        EventCategory::TextDelta,
+        EventCategory::Message,
+        EventCategory::Reasoning,
+        EventCategory::Tool,
+        EventCategory::Turn,
+        EventCategory::Usage,
+        EventCategory::Result,
+        EventCategory::Summary,
+        EventCategory::Error,
+    ];
+
+    pub fn of(event: &Event) -> Self {
+        match event {
+            Event::SessionStart(_) => EventCategory::Session,
+            Event::TextDelta(_) => EventCategory::TextDelta,
+            Event::Message(_) => EventCategory::Message,
+            Event::Reasoning(_) => EventCategory::Reasoning,
+            Event::ToolStart(_) | Event::ToolEnd(_) => EventCategory::Tool,
+            Event::TurnStart(_) | Event::TurnEnd(_) => EventCategory::Turn,
+            Event::UsageDelta(_) => EventCategory::Usage,
+            Event::Result(_) => EventCategory::Result,
+            Event::Summary(_) => EventCategory::Summary,
+            Event::Error(_) => EventCategory::Error,
+        }
+    }
+}
+
+impl std::str::FromStr for EventCategory {
+    type Err = String;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "session" | "session_start" => Ok(EventCategory::Session),
+            "text" | "text_delta" => Ok(EventCategory::TextDelta),
+            "message" | "messages" => Ok(EventCategory::Message),
+            "reasoning" => Ok(EventCategory::Reasoning),
+            "tool" | "tools" => Ok(EventCategory::Tool),
+            "turn" | "turns" => Ok(EventCategory::Turn),
+            "usage" | "usage_delta" => Ok(EventCategory::Usage),
+            "result" => Ok(EventCategory::Result),
+            "summary" => Ok(EventCategory::Summary),
+            "error" | "errors" => Ok(EventCategory::Error),
+            _ => Err(format!(
+                "unknown event category: {s} (expected session, text, messages, reasoning, \
+                 tools, turns, usage, result, summary, or errors)"
+            )),
+        }
+    }
+}
+
+/// Which events [`filter_events`] lets through.
+///
+/// Events are dropped by category and, for tool events, by tool name.
+/// Names match exactly or by [`canonical_tool_name`], so `shell` covers
+/// Claude's `Bash` and Codex's `command_execution`. A `ToolEnd` follows
+/// its `ToolStart`'s fate, since some agents only name the tool on the start.
+#[derive(Debug, Clone, Default)]
+pub struct EventFilter {
+    dropped: Vec<EventCategory>,
+    allow_tools: Option<Vec<String>>,
+    deny_tools: Vec<String>,
+    dropped_calls: std::collections::HashSet<String>,
+}
+
+impl EventFilter {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Keep only events in `categories`.
+    pub fn only(categories: &[EventCategory]) -> Self {
+        let mut filter = Self::new();
+        for category in EventCategory::ALL {
+            if !categories.contains(&category) {
+                filter = filter.drop(category);
+            }
+        }
+        filter
+    }
+
+    pub fn drop(mut self, category: EventCategory) -> Self {
+        if !self.dropped.contains(&category) {
+            self.dropped.push(category);
+        }
+        self
+    }
+
+    /// Keep only tool calls with this name (repeatable).
+    pub fn allow_tool(mut self, name: impl Into<String>) -> Self {
+        self.allow_tools
+            .get_or_insert_with(Vec::new)
+            .push(name.into());
+        self
+    }
+
+    pub fn deny_tool(mut self, name: impl Into<String>) -> Self {
+        self.deny_tools.push(name.into());
+        self
+    }
+
+    /// Whether `event` passes the filter. Takes `&mut self` to remember
+    /// which tool calls were dropped, so call it on every event in order.
+    pub fn admit(&mut self, event: &Event) -> bool {
+        if self.dropped.contains(&EventCategory::of(event)) {
+            return false;
+        }
+        match event {
+            Event::ToolStart(t) => {
+                if self.tool_allowed(&t.tool_name) {
+                    true
+                } else {
+                    self.dropped_calls.insert(t.call_id.clone());
+                    false
+                }
+            }
+            Event::ToolEnd(t) => {
+                !self.dropped_calls.remove(&t.call_id)
+                    && (t.tool_name.is_empty() || self.tool_allowed(&t.tool_name))
+            }
+            _ => true,
+        }
+    }
+
+    fn tool_allowed(&self, name: &str) -> bool {
+        let matches = |pattern: &String| {
+            pattern.eq_ignore_ascii_case(name)
+                || canonical_tool_name(pattern) == canonical_tool_name(name)
+        };
+        if self.deny_tools.iter().any(matches) {
+            return false;
+        }
+        self.allow_tools
+            .as_ref()
+            .map_or(true, |allowed| allowed.iter().any(matches))
+    }
+}
+
+/// Drop the events `filter` rejects from a (normalized) stream, keeping
+/// everything else, errors included, in order.
+pub fn filter_events(
+    stream: crate::runner::EventStream,
+    mut filter: EventFilter,
+) -> crate::runner::EventStream {
+    use futures::StreamExt;
+    Box::pin(stream.filter(move |item| {
+        let keep = match item {
+            Ok(event) => filter.admit(event),
+            Err(_) => true,
+        };
+        std::future::ready(keep)
+    }))
+}
+
+// ─── Validation ─────────────────────────────────────────────────
+
+/// JSON shape expected of one event field.
+#[derive(Clone, Copy)]
+enum FieldKind {
+    String,
+    Bool,
+    Unsigned,
+    Integer,
+    Number,
+    Role,
+    PermissionMode,
+    Usage,
+    Any,
+}
+
+impl FieldKind {
+    fn matches(self, value: &serde_json::Value) -> bool {
+        match self {
+            FieldKind::String => value.is_string(),
+            FieldKind::Bool => value.is_boolean(),
+            FieldKind::Unsigned => value.is_u64(),
+            FieldKind::Integer => value.is_i64(),
+            FieldKind::Number => value.is_number(),
+            FieldKind::Role => matches!(value.as_str(), Some("assistant" | "user" | "system")),
+            FieldKind::PermissionMode => {
+                matches!(value.as_str(), Some("full_access" | "read_only"))
+            }
+            FieldKind::Usage => validate_usage(value).is_ok(),
+            FieldKind::Any => true,
+        }
+    }
+}
+
+/// `(field, kind, required)` for every field an event type may carry,
+/// besides `type` itself.
+fn event_fields(event_type: &str) -> Option<&'static [(&'static str, FieldKind, bool)]> {
+    use FieldKind::*;
+    Some(match event_type {
+        "session_start" => &[
+            ("session_id", String, true),
+            ("agent", String, true),
+            ("model", String, false),
+            ("cwd", String, false),
+            ("permission_mode", PermissionMode, false),
+            ("agent_version", String, false),
+            ("timestamp_ms", Unsigned, false),
+            ("raw_type", String, false),
+        ],
+        "text_delta" => &[
+            ("text", String, true),
+            ("timestamp_ms", Unsigned, false),
+            ("raw_type", String, false),
+        ],
+        "message" => &[
+            ("role", Role, true),
+            ("text", String, true),
+            ("usage", Usage, false),
+            ("timestamp_ms", Unsigned, false),
+            ("raw_type", String, false),
+        ],
+        "reasoning" => &[
+            ("text", String, true),
+            ("timestamp_ms", Unsigned, false),
+            ("raw_type", String, false),
+        ],
+        "tool_start" => &[
+            ("call_id", String, true),
+            ("tool_name", String, true),
+            ("input", Any, false),
+            ("timestamp_ms", Unsigned, false),
+            ("raw_type", String, false),
+        ],
+        "tool_end" => &[
+            ("call_id", String, true),
+            ("tool_name", String, true),
+            ("success", Bool, true),
+            ("output", String, false),
+            ("usage", Usage, false),
+            ("timestamp_ms", Unsigned, false),
+            ("raw_type", String, false),
+        ],
+        "turn_start" => &[
+            ("timestamp_ms", Unsigned, false),
+            ("raw_type", String, false),
+        ],
+        "turn_end" => &[
+            ("usage", Usage, false),
+            ("timestamp_ms", Unsigned, false),
+            ("raw_type", String, false),
+        ],
+        "usage_delta" => &[
+            ("usage", Usage, true),
+            ("timestamp_ms", Unsigned, false),
+            ("raw_type", String, false),
+        ],
+        "result" => &[
+            ("success", Bool, true),
+            ("text", String, true),
+            ("session_id", String, true),
+            ("duration_ms", Unsigned, false),
+            ("total_cost_usd", Number, false),
+            ("usage", Usage, false),
+            ("exit_code", Integer, false),
+            ("partial", Bool, false),
+            ("timestamp_ms", Unsigned, false),
+            ("raw_type", String, false),
+        ],
+        "summary" => &[
+            ("input_tokens", Unsigned, false),
+            ("output_tokens", Unsigned, false),
+            ("cache_read_tokens", Unsigned, false),
+            ("cache_creation_tokens", Unsigned, false),
+            ("total_cost_usd", Number, false),
+            ("tool_calls", Unsigned, false),
+            ("messages", Unsigned, false),
+            ("duration_ms", Unsigned, false),
+            ("timestamp_ms", Unsigned, false),
+            ("raw_type", String, false),
+        ],
+        "error" => &[
+            ("message", String, true),
+            ("code", String, false),
+            ("timestamp_ms", Unsigned, false),
+            ("raw_type", String, false),
+        ],
+        _ => return None,
+    })
+}
+
+fn validate_usage(value: &serde_json::Value) -> std::result::Result<(), String> {
+    let obj = value.as_object().ok_or("usage is not an object")?;
+    for (key, field) in obj {
+        let ok = match key.as_str() {
+            "input_tokens" | "output_tokens" | "cache_read_tokens" | "cache_creation_tokens" => {
+                field.is_u64()
+            }
+            "cost_usd" => field.is_number(),
+            _ => return Err(format!("usage has unknown field `{key}`")),
+        };
+        if !ok {
+            return Err(format!("usage field `{key}` has the wrong type"));
+        }
+    }
+    Ok(())
+}
+
+/// Check a serialized event against the unified event schema.
+///
+/// Verifies the `type` tag, that required fields are present, that every
+/// field has the expected JSON type, and that no unknown fields appear.
+/// Used by `--validate-events` to catch adapter bugs before output.
+pub fn validate_event_json(value: &serde_json::Value) -> std::result::Result<(), String> {
+    let obj = value.as_object().ok_or("event is not a JSON object")?;
+    let event_type = obj
+        .get("type")
+        .and_then(|t| t.as_str())
+        .ok_or("event has no string `type`")?;
+    let fields =
+        event_fields(event_type).ok_or_else(|| format!("unknown event type `{event_type}`"))?;
+
+    for (name, kind, required) in fields {
+        match obj.get(*name) {
+            Some(v) if !kind.matches(v) => {
+                return Err(format!("{event_type}: field `{name}` has the wrong type"));
+            }
+            None if *required => {
+                return Err(format!("{event_type}: missing required field `{name}`"));
+            }
+            _ => {}
+        }
+    }
+    if let Some(key) = obj
+        .keys()
+        .find(|k| *k != "type" && !fields.iter().any(|(name, _, _)| name == k))
+    {
+        return Err(format!("{event_type}: unknown field `{key}`"));
+    }
+    Ok(())
+}
+
 impl std::fmt::Display for Event {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
             Event::SessionStart(e) => write!(f, "[session:{}] agent={}", e.session_id, e.agent),
             Event::TextDelta(e) => write!(f, "{}", e.text),
             Event::Message(e) => write!(f, "[{}] {}", e.role, e.text),
+            Event::Reasoning(e) => write!(f, "[reasoning] {}", e.text),
             Event::ToolStart(e) => write!(f, "[tool:start] {}({})", e.tool_name, e.call_id),
             Event::ToolEnd(e) => {
                 let status = if e.success { "ok" } else { "fail" };
                 write!(f, "[tool:{}] {}({})", status, e.tool_name, e.call_id)
             }
+            Event::TurnStart(_) => write!(f, "[turn:start]"),
+            Event::TurnEnd(_) => write!(f, "[turn:end]"),
             Event::UsageDelta(e) => {
                 let input = e.usage.input_tokens.unwrap_or(0);
                 let output = e.usage.output_tokens.unwrap_or(0);
@@ -256,6 +1019,11 @@ impl std::fmt::Display for Event {
                 let status = if e.success { "success" } else { "error" };
                 write!(f, "[result:{}] {}", status, e.text)
             }
+            Event::Summary(e) => write!(
+                f,
+                "[summary] {} in / {} out, ${:.4}, {} tool calls, {} messages",
+                e.input_tokens, e.output_tokens, e.total_cost_usd, e.tool_calls, e.messages
+            ),
             Event::Error(e) => write!(f, "[error] {}", e.message),
         }
     }
@@ -0,0 +1,128 @@
+use std::future::Future;
+use std::sync::Arc;
+
+use futures::StreamExt;
+use tokio::sync::Semaphore;
+
+use crate::config::TaskConfig;
+use crate::error::Error;
+use crate::event::Event;
+
+/// Outcome of one task in a fanout run.
+#[derive(Debug)]
+pub struct FanoutOutcome {
+    /// The config this task ran with.
+    pub config: TaskConfig,
+    /// Every event the task produced, in order.
+    pub events: Vec<Event>,
+    /// The error that failed to start or interrupted the task, if any.
+    pub error: Option<Error>,
+}
+
+impl FanoutOutcome {
+    /// The task's final `Result` event, if it produced one.
+    pub fn result(&self) -> Option<&crate::event::ResultEvent> {
+        self.events.iter().rev().find_map(|e| match e {
+            Event::Result(r) => Some(r),
+            _ => None,
+        })
+    }
+}
+
+/// Run `f` over every item with at most `concurrency` futures in flight,
+/// returning outputs in input order. A `concurrency` of 0 is treated as 1.
+pub async fn run_bounded<T, F, Fut>(items: Vec<T>, concurrency: usize, f: F) -> Vec<Fut::Output>
+where
+    F: Fn(T) -> Fut,
+    Fut: Future,
+{
+    let semaphore = Arc::new(Semaphore::new(concurrency.max(1)));
+    let futures = items.into_iter().map(|item| {
+        let semaphore = semaphore.clone();
+        let fut = f(item);
+        async move {
+            // The semaphore is never closed, so acquire cannot fail.
+            let _permit = semaphore.acquire().await;
+            fut.await
+        }
+    });
+    futures::future::join_all(futures).await
+}
+
+/// Run several tasks concurrently (at most `concurrency` at a time), collecting
+/// each task's events. Outcomes are returned in the same order as `configs`.
+pub async fn run_all(configs: Vec<TaskConfig>, concurrency: usize) -> Vec<FanoutOutcome> {
+    run_bounded(configs, concurrency, |config| async move {
+        let mut events = Vec::new();
+        let mut error = None;
+        match crate::run_task(&config).await {
+            Ok(mut stream) => {
+                while let Some(item) = stream.next().await {
+                    match item {
+                        Ok(event) => events.push(event),
+                        Err(e) => {
+                            error = Some(e);
+                            break;
+                        }
+                    }
+                }
+            }
+            Err(e) => error = Some(e),
+        }
+        FanoutOutcome {
+            config,
+            events,
+            error,
+        }
+    })
+    .await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    #[tokio::test]
+    async fn run_bounded_caps_in_flight() {
+        let in_flight = Arc::new(AtomicUsize::new(0));
+        let max_seen = Arc::new(AtomicUsize::new(0));
+
+        let outputs = run_bounded((0..12).collect(), 3, |i: usize| {
+            let in_flight = in_flight.clone();
+            let max_seen = max_seen.clone();
+            async move {
+                let now = in_flight.fetch_add(1, Ordering::SeqCst) + 1;
+                max_seen.fetch_max(now, Ordering::SeqCst);
+                tokio::time::sleep(std::time::Duration::from_millis(10)).await;
+                in_flight.fetch_sub(1, Ordering::SeqCst);
+                i * 2
+            }
+        })
+        .await;
+
+        assert!(max_seen.load(Ordering::SeqCst) <= 3);
+        assert_eq!(max_seen.load(Ordering::SeqCst), 3);
+        assert_eq!(outputs, (0..12).map(|i| i * 2).collect::<Vec<_>>());
+    }
+
+    #[tokio::test]
+    async fn run_bounded_zero_concurrency_runs_serially() {
+        let in_flight = Arc::new(AtomicUsize::new(0));
+        let max_seen = Arc::new(AtomicUsize::new(0));
+
+        run_bounded(vec![(); 4], 0, |()| {
+            let in_flight = in_flight.clone();
+            let max_seen = max_seen.clone();
+            async move {
+                let now = in_flight.fetch_add(1, Ordering::SeqCst) + 1;
+                max_seen.fetch_max(now, Ordering::SeqCst);
+                tokio::task::yield_now().await;
+                in_flight.fetch_sub(1, Ordering::SeqCst);
+            }
+        })
+        .await;
+
+        assert_eq!(max_seen.load(Ordering::SeqCst), 1);
+    }
+}
@@ -1,5 +1,5 @@
-//! Unified coding agent harness — run Claude Code, OpenCode, Codex, or Cursor
-//! through a single Rust API.
+//! Unified coding agent harness — run Claude Code, OpenCode, Codex, Cursor, or
+//! Gemini CLI through a single Rust API.
 //!
 //! # Quick Start
 //!
@@ -16,15 +16,21 @@
 
 pub mod agents;
 pub mod config;
+pub mod datetime;
 pub mod error;
 pub mod event;
+pub mod fanout;
 pub mod logger;
 pub mod models;
 pub mod normalize;
+#[cfg(feature = "otel")]
+pub mod otel;
 pub mod process;
 pub mod registry;
+pub mod retry;
 pub mod runner;
 pub mod settings;
+pub mod stop;
 
 pub use config::{AgentKind, OutputFormat, PermissionMode, TaskConfig, TaskConfigBuilder};
 pub use error::{Error, Result};
@@ -32,7 +38,8 @@ pub use event::{Event, UsageData};
 pub use models::{ModelEntry, ModelRegistry, ModelResolution};
 pub use normalize::NormalizeConfig;
 pub use process::StreamHandle;
-pub use runner::{AgentCapabilities, AgentRunner, EventStream};
+pub use retry::RetryPolicy;
+pub use runner::{AgentCapabilities, AgentRunner, EventStream, PromptPassing};
 
 /// Re-export the cancel token type for convenience.
 pub use tokio_util::sync::CancellationToken;
@@ -49,11 +56,62 @@ pub async fn run_task(config: &TaskConfig) -> Result<EventStream> {
 /// Run a task with an optional cancellation token.
 ///
 /// Returns a `StreamHandle` containing the event stream and the cancel token.
-/// If no token is provided, a new one is created internally.
+/// If no token is provided, a new one is created internally. A task with a
+/// [`TaskConfig::retry`] policy is re-run on retryable failures.
 pub async fn run_task_with_cancel(
     config: &TaskConfig,
     cancel_token: Option<tokio_util::sync::CancellationToken>,
 ) -> Result<StreamHandle> {
+    match config.retry {
+        Some(ref policy) if policy.max_retries > 0 => {
+            retry::run_task_with_retry(config, policy, cancel_token).await
+        }
+        _ => run_task_once(config, cancel_token).await,
+    }
+}
+
+/// Run a task without normalization, yielding the adapter's events as parsed.
+///
+/// Nothing is enriched: no synthetic user message or usage deltas and no
+/// session metadata; timestamps are just the time each line was read.
+/// Secrets are still redacted.
+/// Useful for profiling the normalizer or for
+/// consumers that want the agent's own view of the run. Stop conditions are
+/// still enforced, but a [`TaskConfig::retry`] policy is ignored.
+pub async fn run_task_raw(
+    config: &TaskConfig,
+    cancel_token: Option<tokio_util::sync::CancellationToken>,
+) -> Result<StreamHandle> {
+    start_task(config, cancel_token, false).await
+}
+
+/// A single attempt of [`run_task_with_cancel`], ignoring any retry policy.
+pub(crate) async fn run_task_once(
+    config: &TaskConfig,
+    cancel_token: Option<tokio_util::sync::CancellationToken>,
+) -> Result<StreamHandle> {
+    start_task(config, cancel_token, true).await
+}
+
+async fn start_task(
+    config: &TaskConfig,
+    cancel_token: Option<tokio_util::sync::CancellationToken>,
+    normalize: bool,
+) -> Result<StreamHandle> {
+    // Fail on a bad working directory before anything is spawned, and pin
+    // a relative one down for the normalizer's `cwd` fallback.
+    let resolved;
+    let config = match config.cwd {
+        Some(ref cwd) => {
+            resolved = TaskConfig {
+                cwd: Some(process::resolve_cwd(cwd)?),
+                ..config.clone()
+            };
+            &resolved
+        }
+        None => config,
+    };
+
     let runner = agents::create_runner(config.agent);
 
     // If the user provided a custom binary path, skip the availability check
@@ -65,26 +123,82 @@ pub async fn run_task_with_cancel(
         });
     }
 
+    runner::ensure_images_supported(runner.as_ref(), config)?;
+    let redact_patterns = normalize::compile_redact_patterns(&config.redact_patterns)?;
+    let caps = runner.capabilities();
+
     let mut handle = runner.run(config, cancel_token).await?;
 
-    let norm_config = NormalizeConfig {
-        cwd: config
-            .cwd
-            .as_ref()
-            .map(|p| p.display().to_string())
-            .or_else(|| std::env::current_dir().ok().map(|p| p.display().to_string())),
-        model: config.model.clone(),
-        prompt: Some(config.prompt.clone()),
+    if normalize {
+        // The version probe spawns the binary and the registry is read from
+        // disk; keep both off the async worker threads.
+        let probe_config = config.clone();
+        let (agent_version, registry) = tokio::task::spawn_blocking(move || {
+            // Only probe binaries found on PATH; an explicit `binary_path` may
+            // be a wrapper script where `--version` isn't meaningful.
+            let version = probe_config
+                .binary_path
+                .is_none()
+                .then(|| agents::create_runner(probe_config.agent).version(&probe_config))
+                .flatten();
+            (version, registry::load_cached())
+        })
+        .await
+        .unwrap_or_else(|_| (None, models::ModelRegistry::builtin()));
+        let norm_config = NormalizeConfig {
+            cwd: config
+                .cwd
+                .as_ref()
+                .map(|p| p.display().to_string())
+                .or_else(|| {
+                    std::env::current_dir()
+                        .ok()
+                        .map(|p| p.display().to_string())
+                }),
+            model: config.model.clone(),
+            prompt: Some(config.prompt.clone()),
+            permission_mode: Some(config.permission_mode),
+            agent_version,
+            redact_patterns,
+            include_raw_type: false,
+            reconcile_usage: false,
+            registry: Some(registry),
+            agent: Some(config.agent),
+            include_turns: config.include_turns,
+            keep_partial_results: config.keep_partial_results,
+            tool_output_dir: config.tool_output_dir.clone(),
+            transform: None,
+        };
+        handle.stream = normalize::normalize_stream(handle.stream, norm_config);
+    } else {
+        handle.stream = normalize::redact_stream(handle.stream, redact_patterns);
+    }
+
+    // Limits the agent can't enforce itself are enforced on its event stream.
+    let limits = stop::StopConditions {
+        max_cost_usd: config.max_budget_usd.filter(|_| !caps.supports_budget),
+        max_turns: config.max_turns.filter(|_| !caps.supports_max_turns),
+        stall_timeout: config
+            .stall_timeout_secs
+            .map(std::time::Duration::from_secs),
+        ..Default::default()
     };
-    handle.stream = normalize::normalize_stream(handle.stream, norm_config);
+    if !limits.is_empty() {
+        handle.stream =
+            stop::enforce_stop_conditions(handle.stream, limits, handle.cancel_token.clone());
+    }
+
+    #[cfg(feature = "otel")]
+    {
+        handle.stream = otel::trace_stream(handle.stream, config);
+    }
 
     Ok(handle)
 }
 
 /// List which agents are currently available on this system.
 pub fn available_agents() -> Vec<AgentKind> {
-    use AgentKind::*;
-    [Claude, OpenCode, Codex, Cursor]
+    AgentKind::ALL
         .into_iter()
         .filter(|kind| {
             let runner = agents::create_runner(*kind);
@@ -2,7 +2,7 @@ use std::io::IsTerminal;
 use std::path::PathBuf;
 use std::process::ExitCode;
 
-use clap::{Parser, Subcommand};
+use clap::{CommandFactory, Parser, Subcommand};
 use futures::StreamExt;
 use harness::{
     config::{AgentKind, OutputFormat, PermissionMode, TaskConfig},
@@ -10,20 +10,29 @@ use harness::{
     logger::SessionLogger,
     models::{ModelRegistry, ModelResolution},
     run_task_with_cancel,
-    settings::{ProjectConfig, Settings},
+    settings::{PolicyAction, ProjectConfig, Settings, ToolPolicy},
+    stop::{StopConditions, StopTracker},
 };
 
 #[derive(Parser)]
 #[command(
     name = "harness",
     about = "Unified coding agent harness",
-    long_about = "Run Claude Code, OpenCode, Codex, or Cursor from a single CLI interface.\n\n\
+    long_about = "Run Claude Code, OpenCode, Codex, Cursor, or Gemini CLI from a single CLI interface.\n\n\
                   Outputs NDJSON/text/JSON/markdown to stdout.",
     version
 )]
 struct Cli {
     #[command(subcommand)]
     command: Commands,
+
+    /// Re-probe agent binaries and versions on every lookup instead of memoizing them
+    #[arg(long, global = true)]
+    no_cache: bool,
+
+    /// Never fetch the model registry over the network; use the cached or builtin copy
+    #[arg(long, global = true)]
+    offline: bool,
 }
 
 #[derive(Subcommand)]
@@ -31,7 +40,7 @@ struct Cli {
 enum Commands {
     /// Run a task on a coding agent.
     Run {
-        /// Which agent to use: claude, opencode, codex, cursor (optional — auto-detects)
+        /// Which agent to use: claude, opencode, codex, cursor, gemini (optional — auto-detects)
         #[arg(short, long)]
         agent: Option<String>,
 
@@ -43,6 +52,34 @@ enum Commands {
         #[arg(long)]
         prompt_file: Option<PathBuf>,
 
+        /// Append a file's raw text to the prompt (after a blank line)
+        #[arg(long, value_name = "FILE")]
+        prompt_append_file: Option<PathBuf>,
+
+        /// Text encoding of --prompt-file and --prompt-append-file (e.g. latin1, shift_jis)
+        #[arg(long, value_name = "ENC", default_value = "utf-8", value_parser = parse_encoding)]
+        prompt_encoding: &'static encoding_rs::Encoding,
+
+        /// Refuse a prompt larger than N bytes, including --prompt-append-file
+        #[arg(long, value_name = "N")]
+        prompt_max_bytes: Option<usize>,
+
+        /// Substitute `{{KEY}}` in the prompt with VALUE (repeatable; undefined placeholders are an error)
+        #[arg(long = "var", value_name = "KEY=VALUE", value_parser = parse_template_var)]
+        vars: Vec<(String, String)>,
+
+        /// Prefix the prompt with the final result of a previously logged session
+        #[arg(long, value_name = "SESSION_ID")]
+        since: Option<String>,
+
+        /// Resume a previous agent session by its ID (Claude Code and Cursor)
+        #[arg(long, value_name = "SESSION_ID")]
+        resume: Option<String>,
+
+        /// Human-readable label for the session, shown by `sessions list`
+        #[arg(long)]
+        name: Option<String>,
+
         /// Working directory for the agent
         #[arg(short = 'd', long)]
         cwd: Option<PathBuf>,
@@ -55,9 +92,9 @@ enum Commands {
         #[arg(long)]
         permissions: Option<String>,
 
-        /// Output format: text, json, stream-json, markdown
-        #[arg(short, long, default_value = "stream-json")]
-        output: String,
+        /// Output format: text, json, stream-json (default), markdown, html
+        #[arg(short, long)]
+        output: Option<String>,
 
         /// Maximum agentic turns
         #[arg(long)]
@@ -71,6 +108,22 @@ enum Commands {
         #[arg(long)]
         timeout: Option<u64>,
 
+        /// Absolute deadline (RFC 3339, e.g. 2026-03-01T09:30:00Z) instead of --timeout
+        #[arg(long, value_name = "TIMESTAMP", conflicts_with = "timeout")]
+        deadline: Option<String>,
+
+        /// Stop the run once the agent starts more than this many tool calls
+        #[arg(long, value_name = "N")]
+        max_tool_calls: Option<u32>,
+
+        /// Stop the run when the agent emits no events for this many seconds
+        #[arg(long, value_name = "SECS")]
+        idle_timeout: Option<u64>,
+
+        /// When no timeout is configured, derive one from the agent's detected version
+        #[arg(long)]
+        agent_timeout_from_version: bool,
+
         /// Custom system prompt (replaces default)
         #[arg(long)]
         system_prompt: Option<String>,
@@ -95,6 +148,157 @@ enum Commands {
         #[arg(long)]
         output_file: Option<PathBuf>,
 
+        /// Append to --output-file, skipping lines a prior partial run already wrote (timestamps ignored)
+        #[arg(long, requires = "output_file")]
+        dedupe_output: bool,
+
+        /// Word-wrap messages in text output at COLS columns ("auto" = terminal width, 0 = off)
+        #[arg(long, value_name = "COLS")]
+        wrap: Option<String>,
+
+        /// Retry a failed run up to N times
+        #[arg(long, value_name = "N")]
+        retry: Option<u32>,
+
+        /// Failure codes to retry on, comma-separated (e.g. rate_limit,timeout; default: any)
+        #[arg(long, value_delimiter = ',', requires = "retry")]
+        retry_on: Vec<String>,
+
+        /// Seconds to wait before the first retry (doubling after each one)
+        #[arg(long, value_name = "SECS", default_value_t = 2.0, requires = "retry")]
+        retry_delay: f64,
+
+        /// Run the prompt on several agents (e.g. claude,codex) and print a markdown comparison
+        #[arg(
+            long,
+            value_name = "AGENTS",
+            value_delimiter = ',',
+            conflicts_with_all = ["agent", "binary", "dry_run", "output", "output_file"]
+        )]
+        compare: Vec<AgentKind>,
+
+        /// Order of the agents' columns and sections in the --compare report (default: the --compare order); unlisted agents follow
+        #[arg(
+            long,
+            value_name = "AGENTS",
+            value_delimiter = ',',
+            requires = "compare"
+        )]
+        agent_order: Vec<AgentKind>,
+
+        /// Cancel the run and exit non-zero on the first error event, even one the agent recovers from
+        #[arg(long)]
+        fail_fast_on_error_event: bool,
+
+        /// Print only the final result and cost line, not the event transcript
+        #[arg(long)]
+        summary_only: bool,
+
+        /// Print only these event kinds, comma-separated (e.g. messages,result; also text, tools, usage, …)
+        #[arg(
+            long,
+            value_name = "KINDS",
+            value_delimiter = ',',
+            conflicts_with = "summary_only"
+        )]
+        only: Vec<harness::event::EventCategory>,
+
+        /// Check every stream-json event against the event schema and fail on the first mismatch (debug aid)
+        #[arg(long)]
+        validate_events: bool,
+
+        /// Pretty-print each stream-json event as indented JSON, separated by blank lines (debug aid)
+        #[arg(long)]
+        pretty: bool,
+
+        /// On completion, print how many events of each type were seen to stderr (debug aid)
+        #[arg(long)]
+        print_events_count: bool,
+
+        /// Skip event normalization and print the adapter's events as parsed (no retries; debug aid)
+        #[arg(long)]
+        no_normalize: bool,
+
+        /// Show a live status line on stderr (elapsed time, cost, tool calls) in text mode; on by default when stderr is a terminal
+        #[arg(long, conflicts_with = "no_progress")]
+        progress: bool,
+
+        /// Never show the text-mode status line
+        #[arg(long)]
+        no_progress: bool,
+
+        /// Run `pre-run`, `post-run`, and `on-tool` scripts from DIR; a failing pre-run aborts the run
+        #[arg(long, value_name = "DIR")]
+        hooks_dir: Option<PathBuf>,
+
+        /// Save the run's changes as a unified diff to PATH (printed after the run if omitted; git repos only)
+        #[arg(long, value_name = "PATH", num_args = 0..=1, default_missing_value = "-")]
+        capture_patch: Option<PathBuf>,
+
+        /// Exit 0 whenever the agent produced text, even if its result reported failure
+        #[arg(long)]
+        allow_empty_result: bool,
+
+        /// How the exit code is derived from the result: success (0/1) or exit-code-from-agent
+        #[arg(long, value_enum, value_name = "MODE", default_value = "success")]
+        on_result: OnResult,
+
+        /// Launch the agent through a wrapper command, e.g. "nice -n10" or "firejail"
+        #[arg(long, value_name = "CMD")]
+        wrap_command: Option<String>,
+
+        /// Set an environment variable for the agent process (repeatable)
+        #[arg(long = "env", value_name = "KEY=VALUE", value_parser = parse_env_pair)]
+        env_vars: Vec<(String, String)>,
+
+        /// Load environment variables for the agent from a dotenv-style file (`--env` wins on conflicts)
+        #[arg(long, value_name = "PATH")]
+        env_file: Option<PathBuf>,
+
+        /// Forward the agent's stderr live (prefixed with the agent name) instead of only on failure
+        #[arg(long)]
+        stream_stderr: bool,
+
+        /// Show the agent's reasoning/thinking (dimmed in text output, quoted in markdown)
+        #[arg(long)]
+        include_reasoning: bool,
+
+        /// Emit turn_start/turn_end events around each model turn
+        #[arg(long)]
+        include_turns: bool,
+
+        /// Write each tool's output to DIR/<call_id>.txt and reference the file from the tool_end event instead of inlining it
+        #[arg(long, value_name = "DIR")]
+        tool_output_to_dir: Option<PathBuf>,
+
+        /// Emit checkpoint results the agent marks partial as result events instead of assistant messages
+        #[arg(long)]
+        keep_partial_results: bool,
+
+        /// OpenCode agent profile to run (`--agent <NAME>`); ignored with a warning in read-only mode
+        #[arg(long, value_name = "NAME")]
+        agent_profile: Option<String>,
+
+        /// Attach an image to the prompt (repeatable; Codex and OpenCode only)
+        #[arg(long, value_name = "PATH")]
+        input_image: Vec<PathBuf>,
+
+        /// Print the N most recent sessions for this agent to stderr before running (default 5)
+        #[arg(long, value_name = "N", num_args = 0..=1, default_missing_value = "5")]
+        show_recent: Option<usize>,
+
+        /// Fail if the model doesn't resolve to a known ID for the agent instead of passing it through
+        #[arg(long)]
+        model_required: bool,
+
+        /// Fetch the latest model registry before resolving the model (skipped with --offline)
+        #[arg(long)]
+        refresh_registry: bool,
+
+        /// Read more passthrough args from PATH (whitespace-separated, shell-style quoting, `#` comments)
+        #[arg(long, value_name = "PATH")]
+        extra_args_file: Option<PathBuf>,
+
         /// Extra flags passed through to the agent verbatim
         #[arg(last = true)]
         extra: Vec<String>,
@@ -109,8 +313,14 @@ enum Commands {
 
     /// Check if a specific agent is available.
     Check {
-        /// Agent to check: claude, opencode, codex, cursor
-        agent: String,
+        /// Agent to check: claude, opencode, codex, cursor, gemini
+        #[arg(required_unless_present = "all")]
+        agent: Option<String>,
+
+        /// Check every agent and print one table; exits non-zero if none is available
+        /// (always includes capabilities and diagnostics)
+        #[arg(long, conflicts_with_all = ["agent", "capabilities", "diagnose"])]
+        all: bool,
 
         /// Output as JSON
         #[arg(long)]
@@ -136,6 +346,26 @@ enum Commands {
         #[command(subcommand)]
         action: ModelsAction,
     },
+
+    /// Inspect logged sessions.
+    Sessions {
+        #[command(subcommand)]
+        action: SessionsAction,
+    },
+
+    /// Print harness, agent, and registry versions.
+    Version {
+        /// Output as JSON
+        #[arg(long)]
+        json: bool,
+    },
+
+    /// Print a shell completion script to stdout.
+    Completions {
+        /// Shell to generate completions for: bash, zsh, fish, powershell, elvish
+        #[arg(value_enum)]
+        shell: clap_complete::Shell,
+    },
 }
 
 #[derive(Subcommand)]
@@ -146,6 +376,12 @@ enum ConfigAction {
     Init,
     /// Print the config file path.
     Path,
+    /// Strictly check harness.toml: unknown keys, invalid values, and models
+    /// without an agent mapping. Exits non-zero if any problem is found.
+    Validate {
+        /// File to check (default: the nearest harness.toml).
+        path: Option<PathBuf>,
+    },
 }
 
 #[derive(Subcommand)]
@@ -160,8 +396,13 @@ enum ModelsAction {
         #[arg(long)]
         agent: Option<String>,
     },
-    /// Force-fetch the latest registry from GitHub.
-    Update,
+    /// Force-fetch the latest registry from GitHub or a mirror.
+    Update {
+        /// URL to fetch models.toml from instead of GitHub (`https://` or
+        /// `file://`). Defaults to `registry_url` in harness.toml.
+        #[arg(long)]
+        source: Option<String>,
+    },
     /// Resolve a model name for a specific agent.
     Resolve {
         /// The model name to resolve.
@@ -173,6 +414,106 @@ enum ModelsAction {
     },
     /// Print the cached registry file path.
     Path,
+    /// Write the effective registry (builtin, cache, and harness.toml merged) to a TOML file.
+    Export {
+        /// File to write.
+        path: PathBuf,
+    },
+    /// Merge the models in a TOML registry file into the project or cached registry.
+    Import {
+        /// Registry file to read, in the `models.toml` layout.
+        path: PathBuf,
+
+        /// Which registry to merge into.
+        #[arg(long, value_enum, default_value_t)]
+        into: ImportTarget,
+    },
+}
+
+/// Where `models import` merges models.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, clap::ValueEnum)]
+enum ImportTarget {
+    /// The `[models]` section of the nearest harness.toml (created in the
+    /// current directory if there is none).
+    #[default]
+    Project,
+    /// The cached registry at `~/.harness/models.toml`.
+    Canonical,
+}
+
+/// How `run` turns the agent's result into the process exit code.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, clap::ValueEnum)]
+enum OnResult {
+    /// 0 on a successful result, 1 otherwise.
+    #[default]
+    Success,
+    /// The `exit_code` the agent set in its result payload, falling back to 0/1.
+    ExitCodeFromAgent,
+}
+
+/// How `sessions show` renders a logged session.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, clap::ValueEnum)]
+enum ShowFormat {
+    /// Assistant text only, as `run --output text` prints it.
+    #[default]
+    Text,
+    /// The full transcript, as `run --output markdown` prints it.
+    Markdown,
+}
+
+/// What `sessions export` writes.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, clap::ValueEnum)]
+enum ExportFormat {
+    /// The session's shell commands as a runnable script.
+    #[default]
+    Shell,
+}
+
+#[derive(Subcommand)]
+enum SessionsAction {
+    /// List logged sessions, newest first.
+    List {
+        /// Output as JSON.
+        #[arg(long)]
+        json: bool,
+
+        /// Only list sessions with this tag (repeatable; all must match).
+        #[arg(long = "tag", value_name = "TAG")]
+        tags: Vec<String>,
+    },
+    /// Add tags to a logged session.
+    Tag {
+        /// Session ID to tag.
+        id: String,
+
+        /// Tags to add.
+        #[arg(required = true)]
+        tags: Vec<String>,
+    },
+    /// Replay a logged session's transcript.
+    Show {
+        /// Session ID to show.
+        id: String,
+
+        /// Render as: text, markdown
+        #[arg(long, value_enum, default_value = "text")]
+        format: ShowFormat,
+    },
+    /// Export a logged session in another format.
+    Export {
+        /// Session ID to export.
+        id: String,
+
+        /// Export format
+        #[arg(long = "as", value_name = "FORMAT", value_enum, default_value_t)]
+        format: ExportFormat,
+    },
+    /// Aggregate spend, tokens, and outcomes across all stored sessions.
+    Stats {
+        /// Output as JSON.
+        #[arg(long)]
+        json: bool,
+    },
 }
 
 #[tokio::main]
@@ -230,13 +571,27 @@ async fn main() -> ExitCode {
         .with_writer(std::io::stderr)
         .init();
 
+    // Flushes exported spans when main returns.
+    #[cfg(feature = "otel")]
+    let _otel = harness::otel::init_otlp_from_env();
+
     let cli = Cli::parse();
+    // One CLI invocation is short-lived, so memoizing probes is safe here.
+    harness::runner::set_probe_cache_enabled(!cli.no_cache);
+    harness::registry::set_offline(cli.offline);
 
     match cli.command {
         Commands::Run {
             agent,
             prompt,
             prompt_file,
+            prompt_append_file,
+            prompt_encoding,
+            prompt_max_bytes,
+            vars,
+            since,
+            resume,
+            name,
             cwd,
             model,
             permissions,
@@ -244,16 +599,81 @@ async fn main() -> ExitCode {
             max_turns,
             max_budget,
             timeout,
+            deadline,
+            max_tool_calls,
+            idle_timeout,
+            agent_timeout_from_version,
             system_prompt,
             append_system_prompt,
             binary,
             dry_run,
             verbose: _,
             output_file,
+            dedupe_output,
+            wrap,
+            retry,
+            retry_on,
+            retry_delay,
+            compare,
+            agent_order,
+            fail_fast_on_error_event,
+            summary_only,
+            only,
+            validate_events,
+            pretty,
+            print_events_count,
+            no_normalize,
+            progress,
+            no_progress,
+            hooks_dir,
+            capture_patch,
+            allow_empty_result,
+            on_result,
+            show_recent,
+            model_required,
+            refresh_registry,
+            input_image,
+            wrap_command,
+            env_vars,
+            env_file,
+            stream_stderr,
+            include_reasoning,
+            include_turns,
+            tool_output_to_dir,
+            keep_partial_results,
+            agent_profile,
+            extra_args_file,
             extra,
         } => {
-            // Resolve agent: CLI flag > project config > legacy config > auto-detect.
-            let agent_kind = match resolve_agent(agent.as_deref(), project_config.as_ref(), &settings) {
+            if compare.len() == 1 {
+                eprintln!("error: --compare needs at least two agents");
+                return ExitCode::from(2);
+            }
+            if let Some(dup) = compare
+                .iter()
+                .enumerate()
+                .find_map(|(i, kind)| compare[..i].contains(kind).then_some(kind))
+            {
+                eprintln!(
+                    "error: --compare lists {} more than once",
+                    dup.display_name()
+                );
+                return ExitCode::from(2);
+            }
+            let compare = match order_agents(&compare, &agent_order) {
+                Ok(compare) => compare,
+                Err(e) => {
+                    eprintln!("error: {e}");
+                    return ExitCode::from(2);
+                }
+            };
+
+            // Resolve agent: --compare > CLI flag > project config > legacy config > auto-detect.
+            let agent_kind = match compare.first() {
+                Some(&kind) => Ok(kind),
+                None => resolve_agent(agent.as_deref(), project_config.as_ref(), &settings),
+            };
+            let agent_kind = match agent_kind {
                 Ok(k) => k,
                 Err(e) => {
                     eprintln!("error: {e}");
@@ -261,8 +681,18 @@ async fn main() -> ExitCode {
                 }
             };
 
-            // Resolve prompt: --prompt > --prompt-file > stdin.
-            let resolved_prompt = match resolve_prompt(prompt, prompt_file) {
+            // Resolve prompt: --prompt > --prompt-file > stdin, then
+            // --prompt-append-file.
+            let prompt_max_bytes = prompt_max_bytes
+                .or_else(|| project_config.as_ref().and_then(|c| c.prompt_max_bytes))
+                .or(settings.prompt_max_bytes);
+            let mut resolved_prompt = match resolve_prompt(
+                prompt,
+                prompt_file,
+                prompt_append_file,
+                prompt_encoding,
+                &vars,
+            ) {
                 Ok(p) => p,
                 Err(e) => {
                     eprintln!("error: {e}");
@@ -270,6 +700,29 @@ async fn main() -> ExitCode {
                 }
             };
 
+            // Carry over the previous session's result as context.
+            if let Some(ref prior_id) = since {
+                let prior = harness::logger::load_session_events(prior_id)
+                    .map_err(|e| e.to_string())
+                    .and_then(|events| {
+                        final_result_text(&events).ok_or_else(|| {
+                            format!("session {prior_id} has no result to continue from")
+                        })
+                    });
+                match prior {
+                    Ok(text) => resolved_prompt = prompt_with_context(&text, &resolved_prompt),
+                    Err(e) => {
+                        eprintln!("error: {e}");
+                        return ExitCode::from(2);
+                    }
+                }
+            }
+            // The limit applies to what the agent receives, context included.
+            if let Err(e) = check_prompt_size(&resolved_prompt, prompt_max_bytes) {
+                eprintln!("error: {e}");
+                return ExitCode::from(2);
+            }
+
             // Resolve permissions: CLI flag > project config > legacy settings > full-access.
             let perm_str = permissions
                 .or_else(|| {
@@ -279,28 +732,97 @@ async fn main() -> ExitCode {
                 })
                 .or_else(|| settings.default_permissions.clone())
                 .unwrap_or_else(|| "full-access".to_string());
-            let permission_mode = match perm_str.as_str() {
-                "full-access" | "full" | "yolo" | "default" => PermissionMode::FullAccess,
-                "read-only" | "readonly" | "plan" => PermissionMode::ReadOnly,
-                other => {
-                    eprintln!("error: unknown permission mode: `{other}` (expected: full-access, read-only)");
+            let permission_mode = match perm_str.parse::<PermissionMode>() {
+                Ok(mode) => mode,
+                Err(e) => {
+                    eprintln!("error: {e}");
+                    return ExitCode::from(2);
+                }
+            };
+
+            // Resolve output format: CLI flag > project config > legacy settings > stream-json.
+            let output_format =
+                match resolve_output_format(output.as_deref(), project_config.as_ref(), &settings) {
+                    Ok(f) => f,
+                    Err(e) => {
+                        eprintln!("error: {e}");
+                        return ExitCode::from(2);
+                    }
+                };
+            if pretty && output_format != OutputFormat::StreamJson {
+                eprintln!("error: --pretty only applies to --output stream-json");
+                return ExitCode::from(2);
+            }
+
+            let wrap = match wrap.as_deref().map(parse_wrap) {
+                Some(Ok(cols)) => cols,
+                Some(Err(e)) => {
+                    eprintln!("error: {e}");
+                    return ExitCode::from(2);
+                }
+                None => None,
+            };
+
+            // An absolute deadline becomes the remaining duration from now.
+            let deadline = match deadline.as_deref().map(remaining_until) {
+                Some(Ok(d)) => Some(d),
+                Some(Err(e)) => {
+                    eprintln!("error: {e}");
                     return ExitCode::from(2);
                 }
+                None => None,
             };
 
-            let output_format = match output.as_str() {
-                "text" => OutputFormat::Text,
-                "json" => OutputFormat::Json,
-                "stream-json" | "stream_json" | "ndjson" => OutputFormat::StreamJson,
-                "markdown" | "md" => OutputFormat::Markdown,
-                other => {
-                    eprintln!("error: unknown output format: `{other}`");
+            // File args follow any trailing `-- ARGS`.
+            let mut extra = extra;
+            if let Some(ref path) = extra_args_file {
+                match read_extra_args_file(path) {
+                    Ok(args) => extra.extend(args),
+                    Err(e) => {
+                        eprintln!("error: {e}");
+                        return ExitCode::from(2);
+                    }
+                }
+            }
+
+            // The wrapper is split like a shell would, so quoted args survive.
+            let command_prefix = match wrap_command.as_deref().map(split_shell_words) {
+                Some(Ok(words)) => words,
+                Some(Err(e)) => {
+                    eprintln!("error: --wrap-command: {e}");
                     return ExitCode::from(2);
                 }
+                None => Vec::new(),
             };
 
+            // `--env` entries come last so they override the file's.
+            let mut env_vars = env_vars;
+            if let Some(ref path) = env_file {
+                match read_env_file(path) {
+                    Ok(mut vars) => {
+                        vars.append(&mut env_vars);
+                        env_vars = vars;
+                    }
+                    Err(e) => {
+                        eprintln!("error: {e}");
+                        return ExitCode::from(2);
+                    }
+                }
+            }
+
+            // Refresh before resolution so this run sees the latest mappings;
+            // a failed refresh falls back to the cached registry.
+            if refresh_registry {
+                match harness::registry::force_update_from(registry_source(project_config.as_ref()))
+                {
+                    Ok(msg) => tracing::info!("{msg}"),
+                    Err(e) => eprintln!("warning: registry refresh skipped: {e}"),
+                }
+            }
+
             // Merge settings: CLI flags > project config > legacy settings.
             let raw_model = model
+                .clone()
                 .or_else(|| {
                     project_config
                         .as_ref()
@@ -309,9 +831,16 @@ async fn main() -> ExitCode {
                 .or_else(|| settings.agent_model(agent_kind));
 
             // Resolve model through the registry.
-            let resolved_model = raw_model.map(|m| {
-                resolve_model(&m, agent_kind, project_config.as_ref())
-            });
+            let resolved_model = match raw_model
+                .map(|m| resolve_model(&m, agent_kind, project_config.as_ref(), model_required))
+                .transpose()
+            {
+                Ok(m) => m,
+                Err(e) => {
+                    eprintln!("error: {e}");
+                    return ExitCode::from(2);
+                }
+            };
 
             let resolved_binary = binary
                 .or_else(|| {
@@ -333,9 +862,9 @@ async fn main() -> ExitCode {
                 .as_ref()
                 .map(|c| c.agent_extra_args(agent_kind))
                 .unwrap_or_else(|| settings.agent_extra_args(agent_kind));
-            resolved_extra.extend(extra);
+            resolved_extra.extend(extra.iter().cloned());
 
-            let config = TaskConfig {
+            let mut config = TaskConfig {
                 prompt: resolved_prompt,
                 agent: agent_kind,
                 cwd,
@@ -345,25 +874,139 @@ async fn main() -> ExitCode {
                 max_turns,
                 max_budget_usd: max_budget,
                 timeout_secs: resolved_timeout,
+                // `--idle-timeout` covers stalls in the CLI.
+                stall_timeout_secs: None,
                 system_prompt,
                 append_system_prompt,
                 binary_path: resolved_binary,
-                env: std::collections::HashMap::new(),
+                env: env_vars.into_iter().collect(),
                 extra_args: resolved_extra,
+                images: input_image,
+                command_prefix,
+                stream_stderr,
+                agent_profile,
+                resume_session_id: resume,
+                retry: retry.map(|max_retries| harness::RetryPolicy {
+                    max_retries,
+                    retry_on,
+                    delay: std::time::Duration::from_secs_f64(retry_delay.max(0.0)),
+                    ..Default::default()
+                }),
+                redact_patterns: Vec::new(),
+                include_reasoning,
+                include_turns,
+                tool_output_dir: tool_output_to_dir,
+                keep_partial_results,
             };
 
+            // Compare: run the same task on every requested agent.
+            if !compare.is_empty() {
+                let configs = compare
+                    .iter()
+                    .map(|&kind| {
+                        compare_config(
+                            &config,
+                            kind,
+                            model.as_deref(),
+                            model_required,
+                            &extra,
+                            project_config.as_ref(),
+                            &settings,
+                        )
+                    })
+                    .collect::<std::result::Result<Vec<_>, _>>();
+                let limits = time_limits(config.timeout_secs, deadline);
+                return match configs {
+                    Ok(configs) => run_compare(configs, limits, name).await,
+                    Err(e) => {
+                        eprintln!("error: {e}");
+                        ExitCode::from(2)
+                    }
+                };
+            }
+
+            let runner = harness::agents::create_runner(agent_kind);
+            if let Err(e) = harness::runner::ensure_images_supported(runner.as_ref(), &config) {
+                eprintln!("error: {e}");
+                return ExitCode::from(2);
+            }
+
+            if let Some(limit) = show_recent {
+                match harness::logger::list_sessions() {
+                    Ok(sessions) => {
+                        eprint!("{}", render_recent_sessions(agent_kind, &sessions, limit))
+                    }
+                    Err(e) => eprintln!("warning: cannot list recent sessions: {e}"),
+                }
+            }
+
             // Dry-run: show the resolved command and exit.
             if dry_run {
                 return run_dry_run(&config);
             }
 
+            // Fall back to the agent's recommended timeout for its version.
+            if agent_timeout_from_version && config.timeout_secs.is_none() && deadline.is_none() {
+                // The probe spawns the agent and waits on it; keep it off the
+                // async runtime.
+                let probe_config = config.clone();
+                let version = tokio::task::spawn_blocking(move || {
+                    harness::agents::create_runner(probe_config.agent).version(&probe_config)
+                })
+                .await
+                .ok()
+                .flatten();
+                let secs = agent_kind.recommended_timeout_secs(version.as_deref());
+                tracing::debug!(
+                    "using recommended timeout of {secs}s for {} {}",
+                    agent_kind.display_name(),
+                    version.as_deref().unwrap_or("(unknown version)")
+                );
+                config.timeout_secs = Some(secs);
+            }
+
             // Print config validation warnings.
-            let runner = harness::agents::create_runner(agent_kind);
             for warning in runner.validate_config(&config) {
                 eprintln!("warning: {warning}");
             }
 
-            run_headless(config, output_file).await
+            let options = HeadlessOptions {
+                output_file,
+                dedupe_output,
+                stop: StopConditions {
+                    max_tool_calls,
+                    idle_timeout: idle_timeout.map(std::time::Duration::from_secs),
+                    // Budget and turn limits are enforced by the agent or the
+                    // library stream.
+                    ..time_limits(config.timeout_secs, deadline)
+                },
+                wrap,
+                fail_fast_on_error_event,
+                summary_only,
+                only,
+                validate_events,
+                pretty,
+                print_events_count,
+                no_normalize,
+                progress: progress_enabled(
+                    config.output_format,
+                    progress.then_some(true).or(no_progress.then_some(false)),
+                    std::io::stderr().is_terminal(),
+                    std::env::var_os("NO_COLOR").is_some_and(|v| !v.is_empty()),
+                ),
+                name,
+                hooks_dir,
+                capture_patch,
+                allow_empty_result,
+                on_result,
+                aggregate_log: project_config
+                    .as_ref()
+                    .and_then(|c| c.aggregate_log)
+                    .or(settings.aggregate_log)
+                    .unwrap_or(false),
+                policy: ToolPolicy::load(&policy_dir(config.cwd.as_deref())),
+            };
+            run_headless(config, options).await
         }
 
         Commands::List { json } => {
@@ -390,7 +1033,7 @@ async fn main() -> ExitCode {
                     }
                 }
             } else if available.is_empty() {
-                println!("No agents found. Install one of: claude, opencode, codex, cursor-agent");
+                println!("No agents found. Install one of: claude, opencode, codex, cursor-agent, gemini");
             } else {
                 println!("Available agents:");
                 for agent in &available {
@@ -410,13 +1053,55 @@ async fn main() -> ExitCode {
             ExitCode::SUCCESS
         }
 
+        Commands::Version { json } => {
+            let harness_version = env!("CARGO_PKG_VERSION");
+            let agents: Vec<(String, Option<String>)> = harness::available_agents()
+                .into_iter()
+                .map(|a| {
+                    let runner = harness::agents::create_runner(a);
+                    let version = runner.version(&TaskConfig::new("", a));
+                    (runner.name().to_string(), version)
+                })
+                .collect();
+
+            if json {
+                let agents: serde_json::Map<String, serde_json::Value> = agents
+                    .into_iter()
+                    .map(|(name, version)| (name, serde_json::json!(version)))
+                    .collect();
+                let info = serde_json::json!({
+                    "harness": harness_version,
+                    "registry_schema_version": harness::models::SCHEMA_VERSION,
+                    "agents": agents,
+                });
+                match serde_json::to_string_pretty(&info) {
+                    Ok(json) => println!("{json}"),
+                    Err(e) => {
+                        eprintln!("error: failed to serialize output: {e}");
+                        return ExitCode::FAILURE;
+                    }
+                }
+            } else {
+                println!("harness {harness_version}");
+                println!("registry schema {}", harness::models::SCHEMA_VERSION);
+                for (name, version) in &agents {
+                    println!("{name} {}", version.as_deref().unwrap_or("unknown"));
+                }
+            }
+            ExitCode::SUCCESS
+        }
+
         Commands::Check {
             agent,
+            all,
             json,
             capabilities,
             diagnose,
         } => {
-            let agent_kind = match agent.parse::<AgentKind>() {
+            if all {
+                return check_all_agents(json);
+            }
+            let agent_kind = match agent.unwrap_or_default().parse::<AgentKind>() {
                 Ok(k) => k,
                 Err(e) => {
                     eprintln!("error: {e}");
@@ -441,6 +1126,7 @@ async fn main() -> ExitCode {
                     if let Ok(caps_val) = serde_json::to_value(&caps) {
                         obj["capabilities"] = caps_val;
                     }
+                    obj["permission_modes"] = permission_modes(&*runner);
                 }
                 if diagnose {
                     let diag = diagnose_agent(agent_kind, &dummy_config, &*runner);
@@ -471,6 +1157,21 @@ async fn main() -> ExitCode {
                     println!("  budget:               {}", caps.supports_budget);
                     println!("  model:                {}", caps.supports_model);
                     println!("  max_turns:            {}", caps.supports_max_turns);
+                    println!("  images:               {}", caps.supports_images);
+                    println!("  streaming:            {}", caps.supports_streaming);
+                    println!("  resume:               {}", caps.supports_resume);
+                    println!("  tool_allowlist:       {}", caps.supports_tool_allowlist);
+                    println!("  reasoning:            {}", caps.supports_reasoning);
+                    let modes = permission_modes(&*runner);
+                    let modes: Vec<String> = modes
+                        .as_object()
+                        .into_iter()
+                        .flatten()
+                        .map(|(mode, support)| {
+                            format!("{mode} ({})", support.as_str().unwrap_or_default())
+                        })
+                        .collect();
+                    println!("  permission_modes:     {}", modes.join(", "));
                 }
                 if diagnose {
                     println!("Diagnostics:");
@@ -493,6 +1194,10 @@ async fn main() -> ExitCode {
                         };
                         println!("  {key}: {status}");
                     }
+                    match agent_kind.resolved_api_key() {
+                        Some((key, masked)) => println!("  api_key:     {key} ({masked})"),
+                        None => println!("  api_key:     none"),
+                    }
                 }
             }
 
@@ -562,6 +1267,33 @@ async fn main() -> ExitCode {
                 }
                 ExitCode::SUCCESS
             }
+            ConfigAction::Validate { path } => {
+                let path = path.or_else(|| {
+                    std::env::current_dir()
+                        .ok()
+                        .and_then(|d| ProjectConfig::find(&d))
+                });
+                let Some(path) = path else {
+                    eprintln!("error: no harness.toml found");
+                    return ExitCode::FAILURE;
+                };
+                let content = match std::fs::read_to_string(&path) {
+                    Ok(c) => c,
+                    Err(e) => {
+                        eprintln!("error: failed to read {}: {e}", path.display());
+                        return ExitCode::FAILURE;
+                    }
+                };
+                let problems = ProjectConfig::validate(&content);
+                if problems.is_empty() {
+                    println!("{}: ok", path.display());
+                    return ExitCode::SUCCESS;
+                }
+                for problem in &problems {
+                    eprintln!("{}: {problem}", path.display());
+                }
+                ExitCode::FAILURE
+            }
         },
 
         Commands::Models { action } => match action {
@@ -608,6 +1340,15 @@ async fn main() -> ExitCode {
                             if let Some(ref v) = entry.cursor {
                                 obj["cursor"] = serde_json::json!(v);
                             }
+                            if let Some(ref v) = entry.gemini {
+                                obj["gemini"] = serde_json::json!(v);
+                            }
+                            if entry.deprecated {
+                                obj["deprecated"] = serde_json::json!(true);
+                            }
+                            if let Some(ref v) = entry.replacement {
+                                obj["replacement"] = serde_json::json!(v);
+                            }
                             Some(obj)
                         })
                         .collect();
@@ -631,7 +1372,12 @@ async fn main() -> ExitCode {
                                     continue;
                                 }
                             }
-                            println!("  {} — {}", name, entry.description);
+                            let marker = if entry.deprecated {
+                                " (deprecated)"
+                            } else {
+                                ""
+                            };
+                            println!("  {}{} — {}", name, marker, entry.description);
                             let agents = entry.supported_agents();
                             let mappings: Vec<String> = agents
                                 .iter()
@@ -647,8 +1393,11 @@ async fn main() -> ExitCode {
                 ExitCode::SUCCESS
             }
 
-            ModelsAction::Update => {
-                match harness::registry::force_update() {
+            ModelsAction::Update { source } => {
+                let source = source
+                    .as_deref()
+                    .or_else(|| registry_source(project_config.as_ref()));
+                match harness::registry::force_update_from(source) {
                     Ok(msg) => {
                         println!("{msg}");
                         ExitCode::SUCCESS
@@ -671,6 +1420,7 @@ async fn main() -> ExitCode {
 
                 let registry = build_registry(project_config.as_ref());
                 let resolution = registry.resolve(&name, agent_kind);
+                warn_if_deprecated(&registry, &name, agent_kind);
                 match &resolution {
                     ModelResolution::Resolved {
                         canonical_name,
@@ -691,30 +1441,340 @@ async fn main() -> ExitCode {
                     }
                     ModelResolution::Passthrough { raw } => {
                         println!("{raw}");
-                        eprintln!("Model `{raw}` not found in registry — passing through");
+                        match registry.reverse_resolve(raw, agent_kind) {
+                            ModelResolution::Resolved { canonical_name, .. } => eprintln!(
+                                "Model `{raw}` is the {} ID for `{canonical_name}`",
+                                agent_kind.display_name()
+                            ),
+                            ModelResolution::Ambiguous { candidates, .. } => eprintln!(
+                                "Model `{raw}` is the {} ID for several entries: {}",
+                                agent_kind.display_name(),
+                                candidates.join(", ")
+                            ),
+                            _ => eprintln!("Model `{raw}` not found in registry — passing through"),
+                        }
+                    }
+                    ModelResolution::Ambiguous { raw, candidates } => {
+                        println!("{raw}");
+                        eprintln!(
+                            "Model `{raw}` is ambiguous: {}",
+                            candidates.join(", ")
+                        );
                     }
                 }
                 ExitCode::SUCCESS
             }
 
-            ModelsAction::Path => {
-                match harness::registry::canonical_path() {
-                    Some(p) => println!("{}", p.display()),
-                    None => {
-                        eprintln!("error: cannot determine home directory");
-                        return ExitCode::FAILURE;
+            ModelsAction::Export { path } => {
+                let registry = build_registry(project_config.as_ref());
+                let written = registry
+                    .to_toml_string()
+                    .and_then(|s| std::fs::write(&path, s).map_err(|e| e.to_string()));
+                match written {
+                    Ok(()) => {
+                        println!(
+                            "Exported {} models to {}",
+                            registry.models.len(),
+                            path.display()
+                        );
+                        ExitCode::SUCCESS
+                    }
+                    Err(e) => {
+                        eprintln!("error: failed to export to {}: {e}", path.display());
+                        ExitCode::FAILURE
                     }
                 }
-                ExitCode::SUCCESS
             }
-        },
-    }
-}
 
-fn format_token_count(count: u64) -> String {
-    if count >= 1_000_000 {
-        format!("{:.1}M", count as f64 / 1_000_000.0)
-    } else if count >= 1_000 {
+            ModelsAction::Import { path, into } => {
+                let imported = match std::fs::read_to_string(&path)
+                    .map_err(|e| e.to_string())
+                    .and_then(|s| ModelRegistry::from_toml(&s))
+                {
+                    Ok(reg) if reg.models.is_empty() => {
+                        eprintln!("error: {} defines no models", path.display());
+                        return ExitCode::FAILURE;
+                    }
+                    Ok(reg) => reg,
+                    Err(e) => {
+                        eprintln!("error: failed to read {}: {e}", path.display());
+                        return ExitCode::FAILURE;
+                    }
+                };
+                let target = match into {
+                    ImportTarget::Project => {
+                        let target = project_config_path
+                            .clone()
+                            .or_else(|| cwd.as_ref().map(|d| d.join("harness.toml")))
+                            .ok_or_else(|| "cannot determine current directory".to_string());
+                        target.and_then(|p| ProjectConfig::import_models(&p, &imported).map(|()| p))
+                    }
+                    ImportTarget::Canonical => harness::registry::import_into(
+                        registry_source(project_config.as_ref()),
+                        &imported,
+                    ),
+                };
+                match target {
+                    Ok(p) => {
+                        println!(
+                            "Imported {} models into {}",
+                            imported.models.len(),
+                            p.display()
+                        );
+                        ExitCode::SUCCESS
+                    }
+                    Err(e) => {
+                        eprintln!("error: {e}");
+                        ExitCode::FAILURE
+                    }
+                }
+            }
+
+            ModelsAction::Path => {
+                match harness::registry::cache_path(registry_source(project_config.as_ref())) {
+                    Some(p) => println!("{}", p.display()),
+                    None => {
+                        eprintln!("error: cannot determine home directory");
+                        return ExitCode::FAILURE;
+                    }
+                }
+                ExitCode::SUCCESS
+            }
+        },
+
+        Commands::Sessions { action } => match action {
+            SessionsAction::List { json, tags } => match harness::logger::list_sessions() {
+                Ok(mut sessions) => {
+                    sessions.retain(|meta| {
+                        let have = meta.tags.as_deref().unwrap_or_default();
+                        tags.iter().all(|t| have.contains(t))
+                    });
+                    if json {
+                        match serde_json::to_string_pretty(&sessions) {
+                            Ok(json) => println!("{json}"),
+                            Err(e) => {
+                                eprintln!("error: failed to serialize sessions: {e}");
+                                return ExitCode::FAILURE;
+                            }
+                        }
+                    } else {
+                        print!("{}", render_session_list(&sessions));
+                    }
+                    ExitCode::SUCCESS
+                }
+                Err(e) => {
+                    eprintln!("error: {e}");
+                    ExitCode::FAILURE
+                }
+            },
+            SessionsAction::Tag { id, tags } => match harness::logger::tag_session(&id, &tags) {
+                Ok(meta) => {
+                    println!("{id}: {}", meta.tags.unwrap_or_default().join(", "));
+                    ExitCode::SUCCESS
+                }
+                Err(e) => {
+                    eprintln!("error: {e}");
+                    ExitCode::FAILURE
+                }
+            },
+            SessionsAction::Show { id, format } => {
+                match harness::logger::load_session_events(&id) {
+                    Ok(events) => {
+                        show_session(&events, format);
+                        ExitCode::SUCCESS
+                    }
+                    Err(e) => {
+                        eprintln!("error: {e}");
+                        ExitCode::FAILURE
+                    }
+                }
+            }
+            SessionsAction::Export { id, format } => {
+                match harness::logger::load_session_events(&id) {
+                    Ok(events) => {
+                        match format {
+                            ExportFormat::Shell => {
+                                print!("{}", shell_script_from_events(&id, &events))
+                            }
+                        }
+                        ExitCode::SUCCESS
+                    }
+                    Err(e) => {
+                        eprintln!("error: {e}");
+                        ExitCode::FAILURE
+                    }
+                }
+            }
+            SessionsAction::Stats { json } => match harness::logger::session_stats() {
+                Ok(stats) if json => match serde_json::to_string_pretty(&stats) {
+                    Ok(json) => {
+                        println!("{json}");
+                        ExitCode::SUCCESS
+                    }
+                    Err(e) => {
+                        eprintln!("error: failed to serialize stats: {e}");
+                        ExitCode::FAILURE
+                    }
+                },
+                Ok(stats) => {
+                    print!("{}", render_session_stats(&stats));
+                    ExitCode::SUCCESS
+                }
+                Err(e) => {
+                    eprintln!("error: {e}");
+                    ExitCode::FAILURE
+                }
+            },
+        },
+
+        Commands::Completions { shell } => {
+            let mut cmd = Cli::command();
+            clap_complete::generate(shell, &mut cmd, "harness", &mut std::io::stdout());
+            ExitCode::SUCCESS
+        }
+    }
+}
+
+/// The `limit` most recent of `sessions` (already newest first) run with
+/// `agent`, for `run --show-recent`.
+fn render_recent_sessions(
+    agent: AgentKind,
+    sessions: &[harness::logger::SessionMeta],
+    limit: usize,
+) -> String {
+    let recent: Vec<_> = sessions
+        .iter()
+        .filter(|m| m.agent == agent.display_name())
+        .take(limit)
+        .collect();
+    if recent.is_empty() {
+        return format!("No recent {} sessions.\n", agent.display_name());
+    }
+
+    let mut out = format!("Recent {} sessions:\n", agent.display_name());
+    for meta in recent {
+        let started = meta
+            .start_time
+            .parse::<u64>()
+            .map(|secs| harness::datetime::format_rfc3339(secs * 1000))
+            .unwrap_or_else(|_| meta.start_time.clone());
+        let status = if meta.success { "ok" } else { "failed" };
+        let prompt = prompt_preview(&meta.prompt);
+        // The agent's own id is what `--resume` takes; fall back to ours.
+        let id = meta.agent_session_id.as_deref().unwrap_or(&meta.session_id);
+        out.push_str(&format!("  {id}  {started}  [{status}]  {prompt}\n"));
+    }
+    out
+}
+
+/// The first line of `prompt`, cut to 50 characters.
+fn prompt_preview(prompt: &str) -> String {
+    let mut preview: String = prompt
+        .lines()
+        .next()
+        .unwrap_or("")
+        .chars()
+        .take(50)
+        .collect();
+    if preview.len() < prompt.len() {
+        preview.push('…');
+    }
+    preview
+}
+
+/// One line per session for `harness sessions list`.
+fn render_session_list(sessions: &[harness::logger::SessionMeta]) -> String {
+    if sessions.is_empty() {
+        return "No sessions.\n".to_string();
+    }
+    let mut out = String::new();
+    for meta in sessions {
+        let status = if meta.success { "ok" } else { "failed" };
+        let duration = meta
+            .duration_ms
+            .map(|ms| format!("{:.1}s", ms as f64 / 1000.0))
+            .unwrap_or_else(|| "-".to_string());
+        let tags = match meta.tags.as_deref() {
+            Some(tags) if !tags.is_empty() => format!("  [{}]", tags.join(", ")),
+            _ => String::new(),
+        };
+        out.push_str(&format!(
+            "{}  {:<12}  {:<6}  {:>7}  {}{}\n",
+            meta.session_id,
+            meta.agent,
+            status,
+            duration,
+            meta.name
+                .clone()
+                .unwrap_or_else(|| prompt_preview(&meta.prompt)),
+            tags
+        ));
+    }
+    out
+}
+
+/// Replay logged events with the same rendering `run` uses for `format`.
+fn show_session(events: &[Event], format: ShowFormat) {
+    let mut tee = TeeWriter::new(None);
+    match format {
+        ShowFormat::Text => {
+            for event in events {
+                render_text_event(&mut tee, event, None);
+            }
+        }
+        ShowFormat::Markdown => {
+            let (agent, model) = events
+                .iter()
+                .find_map(|e| match e {
+                    Event::SessionStart(s) => Some((s.agent.clone(), s.model.clone())),
+                    _ => None,
+                })
+                .unwrap_or_default();
+            render_markdown_header(&mut tee, &agent, model.as_deref().unwrap_or(""));
+            for event in events {
+                render_markdown_event(&mut tee, event);
+            }
+        }
+    }
+}
+
+/// Human-readable report for `harness sessions stats`.
+fn render_session_stats(stats: &harness::logger::SessionStats) -> String {
+    let mut out = String::new();
+    out.push_str(&format!("Sessions:     {}\n", stats.sessions));
+    if stats.sessions == 0 {
+        return out;
+    }
+    out.push_str(&format!(
+        "Success rate: {:.0}% ({}/{})\n",
+        stats.success_rate * 100.0,
+        stats.succeeded,
+        stats.sessions
+    ));
+    out.push_str(&format!("Total spend:  ${:.4}\n", stats.total_cost_usd));
+    out.push_str(&format!(
+        "Tokens:       {} in / {} out\n",
+        format_token_count(stats.total_input_tokens),
+        format_token_count(stats.total_output_tokens)
+    ));
+    if let Some(ms) = stats.avg_duration_ms {
+        out.push_str(&format!("Avg duration: {:.1}s\n", ms as f64 / 1000.0));
+    }
+    out.push_str("\nBy agent:\n");
+    for (agent, count) in &stats.by_agent {
+        out.push_str(&format!("  {agent:<16} {count}\n"));
+    }
+    out.push_str("\nBy model:\n");
+    for (model, count) in &stats.by_model {
+        out.push_str(&format!("  {model:<16} {count}\n"));
+    }
+    out
+}
+
+fn format_token_count(count: u64) -> String {
+    if count >= 1_000_000 {
+        format!("{:.1}M", count as f64 / 1_000_000.0)
+    } else if count >= 1_000 {
         format!("{:.1}k", count as f64 / 1_000.0)
     } else {
         count.to_string()
@@ -744,7 +1804,7 @@ fn resolve_agent(
     // 4. Auto-detect: if exactly one agent is installed, use it.
     let available = harness::available_agents();
     match available.len() {
-        0 => Err("no agent specified and none found in PATH. Install one of: claude, opencode, codex, cursor-agent".to_string()),
+        0 => Err("no agent specified and none found in PATH. Install one of: claude, opencode, codex, cursor-agent, gemini".to_string()),
         1 => Ok(available[0]),
         _ => {
             let names: Vec<_> = available.iter().map(|a| a.default_binary()).collect();
@@ -756,9 +1816,254 @@ fn resolve_agent(
     }
 }
 
+fn resolve_output_format(
+    output_arg: Option<&str>,
+    project_config: Option<&ProjectConfig>,
+    settings: &Settings,
+) -> std::result::Result<OutputFormat, String> {
+    output_arg
+        .or_else(|| project_config.and_then(|c| c.default_output.as_deref()))
+        .or(settings.default_output.as_deref())
+        .map_or(Ok(OutputFormat::StreamJson), str::parse)
+}
+
 fn resolve_prompt(
     prompt_arg: Option<String>,
     prompt_file: Option<PathBuf>,
+    append_file: Option<PathBuf>,
+    encoding: &'static encoding_rs::Encoding,
+    vars: &[(String, String)],
+) -> std::result::Result<String, String> {
+    let mut prompt = resolve_base_prompt(prompt_arg, prompt_file, encoding)?;
+    // Templating is opt-in: without `--var`, `{{…}}` passes through untouched,
+    // so prompts quoting GitHub Actions or Jinja syntax keep working.
+    if !vars.is_empty() {
+        prompt = render_prompt_template(&prompt, vars)?;
+    }
+    if let Some(path) = append_file {
+        // Appended verbatim — no trimming or fencing.
+        let appendix = read_prompt_file(&path, encoding)
+            .map_err(|e| format!("failed to read prompt append file {}: {e}", path.display()))?;
+        prompt = format!("{prompt}\n\n{appendix}");
+    }
+    Ok(prompt)
+}
+
+/// Enforce `--prompt-max-bytes` on the fully assembled prompt.
+fn check_prompt_size(prompt: &str, max_bytes: Option<usize>) -> std::result::Result<(), String> {
+    match max_bytes {
+        Some(max) if prompt.len() > max => Err(format!(
+            "prompt is {} bytes, over the {max}-byte limit (--prompt-max-bytes)",
+            prompt.len()
+        )),
+        _ => Ok(()),
+    }
+}
+
+/// Parse a `--var` value: `KEY=VALUE`, where KEY starts with a letter or
+/// `_` and continues with letters, digits, `_`, `-`, or `.`.
+fn parse_template_var(value: &str) -> std::result::Result<(String, String), String> {
+    let Some((key, val)) = value.split_once('=') else {
+        return Err(format!("expected KEY=VALUE, got `{value}`"));
+    };
+    if !is_template_key(key) {
+        return Err(format!("invalid template variable name: `{key}`"));
+    }
+    Ok((key.to_string(), val.to_string()))
+}
+
+fn is_template_key(key: &str) -> bool {
+    key.starts_with(|c: char| c.is_ascii_alphabetic() || c == '_')
+        && key
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || matches!(c, '_' | '-' | '.'))
+}
+
+/// Replace each `{{key}}` (spaces inside the braces allowed) with its value
+/// from `vars`; the last definition of a key wins. `\{{` is a literal `{{`,
+/// and braces around anything that isn't a valid key are left alone. Any
+/// placeholder without a value is an error naming all of them.
+fn render_prompt_template(
+    template: &str,
+    vars: &[(String, String)],
+) -> std::result::Result<String, String> {
+    let mut out = String::with_capacity(template.len());
+    let mut undefined: Vec<&str> = Vec::new();
+    let mut rest = template;
+    while let Some(at) = rest.find("{{") {
+        if rest[..at].ends_with('\\') {
+            out.push_str(&rest[..at - 1]);
+            out.push_str("{{");
+            rest = &rest[at + 2..];
+            continue;
+        }
+        out.push_str(&rest[..at]);
+        let inner = &rest[at + 2..];
+        let Some(end) = inner
+            .find("}}")
+            .filter(|&end| is_template_key(inner[..end].trim()))
+        else {
+            out.push_str("{{");
+            rest = inner;
+            continue;
+        };
+        let key = inner[..end].trim();
+        match vars.iter().rev().find(|(k, _)| k == key) {
+            Some((_, value)) => out.push_str(value),
+            None if !undefined.contains(&key) => undefined.push(key),
+            None => {}
+        }
+        rest = &inner[end + 2..];
+    }
+    out.push_str(rest);
+    if !undefined.is_empty() {
+        return Err(format!(
+            "undefined prompt variable(s): {} (pass --var KEY=VALUE)",
+            undefined.join(", ")
+        ));
+    }
+    Ok(out)
+}
+
+/// The agent's working directory as an absolute path, so a relative `--cwd`
+/// walks up its real ancestors when looking for `harness.policy.toml`.
+fn policy_dir(cwd: Option<&std::path::Path>) -> PathBuf {
+    let dir = match cwd {
+        Some(dir) => dir.to_path_buf(),
+        None => std::env::current_dir().unwrap_or_default(),
+    };
+    dir.canonicalize().unwrap_or(dir)
+}
+
+/// Read `--extra-args-file`: shell-style words, one or more per line.
+fn read_extra_args_file(path: &std::path::Path) -> std::result::Result<Vec<String>, String> {
+    let content = std::fs::read_to_string(path)
+        .map_err(|e| format!("failed to read extra args file {}: {e}", path.display()))?;
+    split_shell_words(&content).map_err(|e| format!("{}: {e}", path.display()))
+}
+
+/// Read `--env-file`: `KEY=VALUE` lines in dotenv style.
+fn read_env_file(path: &std::path::Path) -> std::result::Result<Vec<(String, String)>, String> {
+    let content = std::fs::read_to_string(path)
+        .map_err(|e| format!("failed to read env file {}: {e}", path.display()))?;
+    parse_env_file(&content).map_err(|e| format!("{}: {e}", path.display()))
+}
+
+/// Parse dotenv-style `KEY=VALUE` lines. Blank lines and `#` comments are
+/// skipped and an `export ` prefix is allowed. Values split at the first
+/// `=`; single-quoted values are literal, double-quoted ones honor `\"`,
+/// `\\`, and `\n`, and unquoted ones are trimmed and end at ` #`.
+fn parse_env_file(content: &str) -> std::result::Result<Vec<(String, String)>, String> {
+    let mut vars = Vec::new();
+    for (i, line) in content.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let line = line.strip_prefix("export ").unwrap_or(line);
+        let Some((key, raw)) = line.split_once('=') else {
+            return Err(format!("line {}: expected KEY=VALUE", i + 1));
+        };
+        let (key, _) = parse_env_pair(&format!("{}=", key.trim()))
+            .map_err(|e| format!("line {}: {e}", i + 1))?;
+        let raw = raw.trim();
+        let value = if let Some(rest) = raw.strip_prefix('\'') {
+            rest.strip_suffix('\'')
+                .ok_or_else(|| format!("line {}: unterminated single quote", i + 1))?
+                .to_string()
+        } else if let Some(rest) = raw.strip_prefix('"') {
+            let mut value = String::new();
+            let mut chars = rest.chars();
+            loop {
+                match chars.next() {
+                    Some('"') => break,
+                    Some('\\') => match chars.next() {
+                        Some('n') => value.push('\n'),
+                        Some(c) => value.push(c),
+                        None => value.push('\\'),
+                    },
+                    Some(c) => value.push(c),
+                    None => return Err(format!("line {}: unterminated double quote", i + 1)),
+                }
+            }
+            value
+        } else {
+            match raw.find(" #") {
+                Some(at) => raw[..at].trim_end().to_string(),
+                None => raw.to_string(),
+            }
+        };
+        vars.push((key, value));
+    }
+    Ok(vars)
+}
+
+/// Split `input` into words the way a POSIX shell would, minus expansion:
+/// single quotes are literal, double quotes honor `\"` and `\\`, a
+/// backslash outside quotes escapes the next character, and `#` at the start
+/// of a word comments out the rest of the line.
+fn split_shell_words(input: &str) -> std::result::Result<Vec<String>, String> {
+    let mut words = Vec::new();
+    let mut word: Option<String> = None;
+    let mut chars = input.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            c if c.is_whitespace() => {
+                words.extend(word.take());
+            }
+            '#' if word.is_none() => {
+                for c in chars.by_ref() {
+                    if c == '\n' {
+                        break;
+                    }
+                }
+            }
+            '\'' => {
+                let w = word.get_or_insert_with(String::new);
+                loop {
+                    match chars.next() {
+                        Some('\'') => break,
+                        Some(c) => w.push(c),
+                        None => return Err("unterminated single quote".to_string()),
+                    }
+                }
+            }
+            '"' => {
+                let w = word.get_or_insert_with(String::new);
+                loop {
+                    match chars.next() {
+                        Some('"') => break,
+                        Some('\\') => match chars.next() {
+                            Some(c @ ('"' | '\\' | '$' | '`')) => w.push(c),
+                            Some('\n') => {}
+                            Some(c) => {
+                                w.push('\\');
+                                w.push(c);
+                            }
+                            None => return Err("unterminated double quote".to_string()),
+                        },
+                        Some(c) => w.push(c),
+                        None => return Err("unterminated double quote".to_string()),
+                    }
+                }
+            }
+            '\\' => match chars.next() {
+                // A backslash-newline continues the line.
+                Some('\n') => {}
+                Some(c) => word.get_or_insert_with(String::new).push(c),
+                None => return Err("trailing backslash".to_string()),
+            },
+            c => word.get_or_insert_with(String::new).push(c),
+        }
+    }
+    words.extend(word);
+    Ok(words)
+}
+
+fn resolve_base_prompt(
+    prompt_arg: Option<String>,
+    prompt_file: Option<PathBuf>,
+    encoding: &'static encoding_rs::Encoding,
 ) -> std::result::Result<String, String> {
     // 1. --prompt flag.
     if let Some(p) = prompt_arg {
@@ -767,7 +2072,7 @@ fn resolve_prompt(
 
     // 2. --prompt-file flag.
     if let Some(path) = prompt_file {
-        return std::fs::read_to_string(&path)
+        return read_prompt_file(&path, encoding)
             .map(|s| s.trim().to_string())
             .map_err(|e| format!("failed to read prompt file {}: {e}", path.display()));
     }
@@ -787,6 +2092,52 @@ fn resolve_prompt(
     Err("no prompt provided. Use --prompt, --prompt-file, or pipe to stdin".to_string())
 }
 
+/// Read a prompt file and decode it from `encoding` (`--prompt-encoding`).
+/// Malformed input is an error rather than being replaced with U+FFFD.
+fn read_prompt_file(
+    path: &std::path::Path,
+    encoding: &'static encoding_rs::Encoding,
+) -> std::result::Result<String, String> {
+    let bytes = std::fs::read(path).map_err(|e| e.to_string())?;
+    encoding
+        .decode_without_bom_handling_and_without_replacement(&bytes)
+        .map(|text| text.into_owned())
+        .ok_or_else(|| format!("not valid {} (see --prompt-encoding)", encoding.name()))
+}
+
+/// Parse a `--prompt-encoding` label (e.g. `utf-8`, `latin1`, `shift_jis`).
+fn parse_encoding(label: &str) -> std::result::Result<&'static encoding_rs::Encoding, String> {
+    encoding_rs::Encoding::for_label(label.as_bytes())
+        .ok_or_else(|| format!("unknown encoding: `{label}`"))
+}
+
+/// The final output of a logged session: its last non-empty `Result` text,
+/// falling back to the last assistant message.
+fn final_result_text(events: &[Event]) -> Option<String> {
+    events
+        .iter()
+        .rev()
+        .find_map(|e| match e {
+            Event::Result(r) if !r.text.is_empty() => Some(r.text.clone()),
+            _ => None,
+        })
+        .or_else(|| {
+            events.iter().rev().find_map(|e| match e {
+                Event::Message(m)
+                    if m.role == harness::event::Role::Assistant && !m.text.is_empty() =>
+                {
+                    Some(m.text.clone())
+                }
+                _ => None,
+            })
+        })
+}
+
+/// Prefix `prompt` with the result of a previous run (`--since`).
+fn prompt_with_context(previous: &str, prompt: &str) -> String {
+    format!("Previously:\n{previous}\n\nNow:\n{prompt}")
+}
+
 /// Resolve a model name through the registry chain:
 /// 1. Project harness.toml [models] section
 /// 2. Canonical ~/.harness/models.toml
@@ -796,38 +2147,341 @@ fn resolve_model(
     raw_name: &str,
     agent: AgentKind,
     project_config: Option<&ProjectConfig>,
-) -> String {
+    required: bool,
+) -> std::result::Result<String, String> {
     // 1. Check project config models.
     if let Some(pc) = project_config {
         let project_reg = pc.model_registry();
         let res = project_reg.resolve(raw_name, agent);
         if let ModelResolution::Resolved { agent_id, .. } = res {
-            return agent_id;
+            warn_if_deprecated(&project_reg, raw_name, agent);
+            return Ok(agent_id);
         }
     }
 
     // 2. Load canonical registry (cached/fetched/builtin).
-    let canonical = harness::registry::load_canonical();
+    let canonical = load_canonical_registry(project_config);
 
     // 3. If project config has a partial entry (found but no agent mapping),
     //    also try canonical before giving up.
     let res = canonical.resolve(raw_name, agent);
+    let registry = match project_config {
+        Some(pc) => canonical.merge(&pc.model_registry()),
+        None => canonical,
+    };
+    warn_if_deprecated(&registry, raw_name, agent);
+    model_id_for(res, raw_name, agent, &registry, required)
+}
+
+/// Print the deprecation warning for the model `raw_name` names, once per
+/// name as typed: `--compare` resolves the same name for every agent.
+fn warn_if_deprecated(registry: &ModelRegistry, raw_name: &str, agent: AgentKind) {
+    static WARNED: std::sync::Mutex<std::collections::BTreeSet<String>> =
+        std::sync::Mutex::new(std::collections::BTreeSet::new());
+    let mut warned = WARNED.lock().unwrap_or_else(|e| e.into_inner());
+    if let Some(warning) = deprecation_notice(registry, raw_name, agent, &mut warned) {
+        eprintln!("warning: {warning}");
+    }
+}
+
+/// The deprecation warning for `raw_name` — a canonical name or `agent`'s own
+/// model ID — unless that name is already in `warned`.
+fn deprecation_notice(
+    registry: &ModelRegistry,
+    raw_name: &str,
+    agent: AgentKind,
+    warned: &mut std::collections::BTreeSet<String>,
+) -> Option<String> {
+    let warning = match registry.reverse_resolve(raw_name, agent) {
+        ModelResolution::Resolved { canonical_name, .. }
+            if !registry.models.contains_key(raw_name) =>
+        {
+            registry.deprecation_warning(&canonical_name)
+        }
+        _ => registry.deprecation_warning(raw_name),
+    }?;
+    warned.insert(raw_name.to_string()).then_some(warning)
+}
+
+/// Turn a registry resolution into the model ID passed to the agent.
+///
+/// Unmapped names are passed through as-is, unless `required` is set: then
+/// only a resolved alias or an ID the registry knows for `agent` is accepted.
+fn model_id_for(
+    res: ModelResolution,
+    raw_name: &str,
+    agent: AgentKind,
+    registry: &ModelRegistry,
+    required: bool,
+) -> std::result::Result<String, String> {
     match res {
-        ModelResolution::Resolved { agent_id, .. } => agent_id,
+        ModelResolution::Resolved { agent_id, .. } => Ok(agent_id),
         ModelResolution::NoAgentMapping { canonical_name } => {
+            if required {
+                return Err(format!(
+                    "model `{canonical_name}` has no mapping for {} (--model-required)",
+                    agent.display_name()
+                ));
+            }
             eprintln!(
                 "warning: model `{canonical_name}` has no mapping for {} — passing through as-is",
                 agent.display_name()
             );
-            raw_name.to_string()
+            Ok(raw_name.to_string())
+        }
+        ModelResolution::Passthrough { raw } | ModelResolution::Ambiguous { raw, .. } => {
+            let known = !matches!(
+                registry.reverse_resolve(&raw, agent),
+                ModelResolution::Passthrough { .. }
+            );
+            if required && !known {
+                return Err(format!(
+                    "model `{raw}` is not a known model for {} (--model-required)",
+                    agent.display_name()
+                ));
+            }
+            Ok(raw)
         }
-        ModelResolution::Passthrough { raw } => raw,
     }
 }
 
+/// Re-target a resolved task at another agent for `--compare`, resolving
+/// that agent's model, binary, and extra args from config.
+fn compare_config(
+    base: &TaskConfig,
+    kind: AgentKind,
+    model_arg: Option<&str>,
+    model_required: bool,
+    cli_extra: &[String],
+    project_config: Option<&ProjectConfig>,
+    settings: &Settings,
+) -> std::result::Result<TaskConfig, String> {
+    let raw_model = model_arg
+        .map(str::to_string)
+        .or_else(|| project_config.and_then(|c| c.agent_model(kind)))
+        .or_else(|| settings.agent_model(kind));
+    let mut extra_args = project_config
+        .map(|c| c.agent_extra_args(kind))
+        .unwrap_or_else(|| settings.agent_extra_args(kind));
+    extra_args.extend(cli_extra.iter().cloned());
+
+    Ok(TaskConfig {
+        agent: kind,
+        model: raw_model
+            .map(|m| resolve_model(&m, kind, project_config, model_required))
+            .transpose()?,
+        binary_path: project_config
+            .and_then(|c| c.agent_binary(kind))
+            .or_else(|| settings.agent_binary(kind)),
+        extra_args,
+        ..base.clone()
+    })
+}
+
+/// The `--compare` agents rearranged by `--agent-order`: listed agents first,
+/// in that order, then the rest in their `--compare` order.
+fn order_agents(
+    compare: &[AgentKind],
+    order: &[AgentKind],
+) -> std::result::Result<Vec<AgentKind>, String> {
+    if let Some(extra) = order.iter().find(|kind| !compare.contains(kind)) {
+        return Err(format!(
+            "--agent-order lists {}, which is not in --compare",
+            extra.display_name()
+        ));
+    }
+    let mut ordered: Vec<AgentKind> = Vec::with_capacity(compare.len());
+    for &kind in order.iter().chain(compare) {
+        if !ordered.contains(&kind) {
+            ordered.push(kind);
+        }
+    }
+    Ok(ordered)
+}
+
+/// The run's time limit: the remaining time to `--deadline` when one was
+/// given (reported as "deadline reached"), otherwise `--timeout`.
+fn time_limits(timeout_secs: Option<u64>, deadline: Option<std::time::Duration>) -> StopConditions {
+    match deadline {
+        Some(remaining) => StopConditions {
+            deadline: Some(std::time::Instant::now() + remaining),
+            ..Default::default()
+        },
+        None => StopConditions {
+            timeout: timeout_secs.map(std::time::Duration::from_secs),
+            ..Default::default()
+        },
+    }
+}
+
+/// Run every config concurrently and print a markdown comparison report.
+/// Outcomes are buffered, so the report follows the configs' order however
+/// the runs finish. Each run is stopped by `limits` and logged as its own
+/// session, `<session id>-<agent>`.
+async fn run_compare(
+    configs: Vec<TaskConfig>,
+    limits: StopConditions,
+    name: Option<String>,
+) -> ExitCode {
+    let session_id = new_session_id();
+    let concurrency = configs.len();
+    let outcomes = harness::fanout::run_bounded(configs, concurrency, |config| {
+        let session_id = format!("{session_id}-{}", config.agent.default_binary());
+        run_compared(config, limits.clone(), session_id, name.clone())
+    })
+    .await;
+    print!("{}", render_comparison(&outcomes));
+
+    let all_ok = outcomes
+        .iter()
+        .all(|o| o.error.is_none() && o.result().is_some_and(|r| r.success));
+    if all_ok {
+        ExitCode::SUCCESS
+    } else {
+        ExitCode::FAILURE
+    }
+}
+
+/// One `--compare` run: the task under `limits`, with its events collected
+/// and logged to the session `session_id`.
+async fn run_compared(
+    config: TaskConfig,
+    limits: StopConditions,
+    session_id: String,
+    name: Option<String>,
+) -> harness::fanout::FanoutOutcome {
+    let mut logger = SessionLogger::new_with_name(&session_id, &config, name)
+        .map_err(|e| tracing::warn!("session log disabled: {e}"))
+        .ok();
+    let mut events = Vec::new();
+    let mut error = None;
+    let cancel = harness::CancellationToken::new();
+    match run_task_with_cancel(&config, Some(cancel.clone())).await {
+        Ok(handle) => {
+            let mut stream = harness::stop::enforce_stop_conditions(handle.stream, limits, cancel);
+            while let Some(item) = stream.next().await {
+                match item {
+                    Ok(event) => {
+                        if let Some(ref mut log) = logger {
+                            log.log_event(&event);
+                        }
+                        events.push(event);
+                    }
+                    Err(e) => {
+                        error = Some(e);
+                        break;
+                    }
+                }
+            }
+        }
+        Err(e) => error = Some(e),
+    }
+    let outcome = harness::fanout::FanoutOutcome {
+        config,
+        events,
+        error,
+    };
+    if let Some(ref mut log) = logger {
+        let result = outcome.result();
+        let success = outcome.error.is_none() && result.is_some_and(|r| r.success);
+        log.finalize(success, result.and_then(|r| r.duration_ms));
+    }
+    outcome
+}
+
+/// Render fanout outcomes as a side-by-side markdown report: a stats table
+/// followed by each agent's final result.
+fn render_comparison(outcomes: &[harness::fanout::FanoutOutcome]) -> String {
+    use std::fmt::Write;
+
+    let mut rows: Vec<(&str, Vec<String>)> = vec![
+        ("Status", Vec::new()),
+        ("Model", Vec::new()),
+        ("Duration", Vec::new()),
+        ("Cost", Vec::new()),
+        ("Tool calls", Vec::new()),
+    ];
+    for o in outcomes {
+        let result = o.result();
+        let status = match (&o.error, result) {
+            (None, Some(r)) if r.success => "success",
+            _ => "failed",
+        };
+        let model = o
+            .events
+            .iter()
+            .find_map(|e| match e {
+                Event::SessionStart(s) => s.model.clone(),
+                _ => None,
+            })
+            .or_else(|| o.config.model.clone());
+        let cost = result
+            .and_then(|r| r.total_cost_usd)
+            .unwrap_or_else(|| harness::event::sum_costs(&o.events));
+        let cells = [
+            status.to_string(),
+            model.unwrap_or_else(|| "—".into()),
+            result
+                .and_then(|r| r.duration_ms)
+                .map(|ms| format!("{:.1}s", ms as f64 / 1000.0))
+                .unwrap_or_else(|| "—".into()),
+            if cost > 0.0 {
+                format!("${cost:.3}")
+            } else {
+                "—".into()
+            },
+            harness::event::extract_tool_calls(&o.events).len().to_string(),
+        ];
+        for ((_, row), cell) in rows.iter_mut().zip(cells) {
+            row.push(cell);
+        }
+    }
+
+    let mut out = String::from("# harness compare\n\n|");
+    for o in outcomes {
+        let _ = write!(out, " | {}", o.config.agent.display_name());
+    }
+    out.push_str(" |\n|---|");
+    out.push_str(&"---|".repeat(outcomes.len()));
+    out.push('\n');
+    for (label, cells) in &rows {
+        let _ = writeln!(out, "| {label} | {} |", cells.join(" | "));
+    }
+
+    for o in outcomes {
+        let _ = write!(out, "\n## {}\n\n", o.config.agent.display_name());
+        if let Some(ref e) = o.error {
+            let _ = writeln!(out, "**Error:** {e}");
+            continue;
+        }
+        match final_result_text(&o.events) {
+            Some(t) => {
+                let _ = writeln!(out, "{}", t.trim_end());
+            }
+            None => out.push_str("_(no result)_\n"),
+        }
+    }
+    out
+}
+
+/// The registry mirror configured in harness.toml, if any.
+fn registry_source(project_config: Option<&ProjectConfig>) -> Option<&str> {
+    project_config.and_then(|c| c.registry_url.as_deref())
+}
+
+/// The canonical registry, fetched from harness.toml's mirror and TTL if set.
+fn load_canonical_registry(project_config: Option<&ProjectConfig>) -> ModelRegistry {
+    harness::registry::load_canonical_with(&harness::registry::RegistryOptions {
+        source: registry_source(project_config).map(str::to_string),
+        ttl: project_config
+            .and_then(|c| c.registry_ttl_secs)
+            .map(std::time::Duration::from_secs),
+    })
+}
+
 /// Build the effective model registry by merging all sources.
 fn build_registry(project_config: Option<&ProjectConfig>) -> ModelRegistry {
-    let canonical = harness::registry::load_canonical();
+    let canonical = load_canonical_registry(project_config);
     if let Some(pc) = project_config {
         let project_reg = pc.model_registry();
         canonical.merge(&project_reg)
@@ -836,6 +2490,128 @@ fn build_registry(project_config: Option<&ProjectConfig>) -> ModelRegistry {
     }
 }
 
+/// `check --all`: availability, version, capabilities, and diagnostics for
+/// every agent, as a table or a JSON array. The agents are probed in
+/// parallel, so one slow `--version` doesn't hold up the rest.
+fn check_all_agents(json: bool) -> ExitCode {
+    let reports: Vec<serde_json::Value> = std::thread::scope(|scope| {
+        let probes: Vec<_> = AgentKind::ALL
+            .into_iter()
+            .map(|kind| scope.spawn(move || check_report(kind)))
+            .collect();
+        probes
+            .into_iter()
+            .map(|probe| probe.join().unwrap_or_default())
+            .collect()
+    });
+
+    if json {
+        match serde_json::to_string_pretty(&reports) {
+            Ok(json) => println!("{json}"),
+            Err(e) => {
+                eprintln!("error: failed to serialize output: {e}");
+                return ExitCode::FAILURE;
+            }
+        }
+    } else {
+        print!("{}", render_check_table(&reports));
+    }
+
+    if reports.iter().any(|r| r["available"] == true) {
+        ExitCode::SUCCESS
+    } else {
+        ExitCode::FAILURE
+    }
+}
+
+/// One agent's `check --all` entry.
+fn check_report(kind: AgentKind) -> serde_json::Value {
+    let runner = harness::agents::create_runner(kind);
+    let config = TaskConfig::new("", kind);
+    serde_json::json!({
+        "agent": runner.name(),
+        "display_name": kind.display_name(),
+        "available": runner.is_available(),
+        "version": runner.version(&config),
+        "capabilities": serde_json::to_value(runner.capabilities()).unwrap_or_default(),
+        "permission_modes": permission_modes(&*runner),
+        "diagnostics": diagnose_agent(kind, &config, &*runner),
+    })
+}
+
+/// Render `check --all` reports as an aligned table, one row per agent.
+fn render_check_table(reports: &[serde_json::Value]) -> String {
+    let text = |v: &serde_json::Value| v.as_str().unwrap_or("-").to_string();
+    let mut rows = vec![[
+        "AGENT".to_string(),
+        "AVAILABLE".to_string(),
+        "VERSION".to_string(),
+        "BINARY".to_string(),
+        "API KEY".to_string(),
+        "CAPABILITIES".to_string(),
+    ]];
+    for report in reports {
+        let capabilities: Vec<&str> = report["capabilities"]
+            .as_object()
+            .into_iter()
+            .flatten()
+            .filter(|(_, supported)| **supported == true)
+            .map(|(name, _)| name.strip_prefix("supports_").unwrap_or(name))
+            .collect();
+        let diagnostics = &report["diagnostics"];
+        rows.push([
+            text(&report["agent"]),
+            if report["available"] == true {
+                "yes"
+            } else {
+                "no"
+            }
+            .to_string(),
+            text(&report["version"]),
+            text(&diagnostics["binary_path"]),
+            diagnostics["api_key"]["var"]
+                .as_str()
+                .unwrap_or("none")
+                .to_string(),
+            capabilities.join(","),
+        ]);
+    }
+
+    let mut widths = [0; 6];
+    for row in &rows {
+        for (width, cell) in widths.iter_mut().zip(row) {
+            *width = (*width).max(cell.chars().count());
+        }
+    }
+    let mut out = String::new();
+    for row in &rows {
+        let cells: Vec<String> = row
+            .iter()
+            .zip(widths)
+            .map(|(cell, width)| format!("{cell:<width$}"))
+            .collect();
+        out.push_str(cells.join("  ").trim_end());
+        out.push('\n');
+    }
+    out
+}
+
+/// How `runner` enforces each permission mode: `native` or `emulated`.
+fn permission_modes(runner: &dyn harness::runner::AgentRunner) -> serde_json::Value {
+    PermissionMode::ALL
+        .into_iter()
+        .map(|mode| {
+            let support = if runner.supports_permission_mode(mode) {
+                "native"
+            } else {
+                "emulated"
+            };
+            (mode.as_str().to_string(), serde_json::json!(support))
+        })
+        .collect::<serde_json::Map<_, _>>()
+        .into()
+}
+
 fn diagnose_agent(
     agent_kind: AgentKind,
     config: &TaskConfig,
@@ -855,13 +2631,120 @@ fn diagnose_agent(
         })
         .collect();
 
+    let api_key = agent_kind
+        .resolved_api_key()
+        .map(|(var, masked)| serde_json::json!({ "var": var, "value": masked }));
+
     serde_json::json!({
         "binary_path": binary_path,
         "candidates": agent_kind.binary_candidates(),
         "env": env_obj,
+        "api_key": api_key,
     })
 }
 
+/// Time remaining until an RFC 3339 `deadline`, or an error if it is
+/// malformed or already past.
+fn remaining_until(deadline: &str) -> std::result::Result<std::time::Duration, String> {
+    let deadline_ms = harness::datetime::parse_rfc3339(deadline)
+        .ok_or_else(|| format!("invalid --deadline `{deadline}` (expected RFC 3339, e.g. 2026-03-01T09:30:00Z)"))?;
+    let now = harness::event::now_ms();
+    if deadline_ms <= now {
+        return Err(format!("deadline `{deadline}` is already in the past"));
+    }
+    Ok(std::time::Duration::from_millis(deadline_ms - now))
+}
+
+/// Parse a `--env` value: `KEY=VALUE`, where `KEY` is a valid environment
+/// variable name. The value may be empty or contain further `=`.
+fn parse_env_pair(value: &str) -> std::result::Result<(String, String), String> {
+    let Some((key, val)) = value.split_once('=') else {
+        return Err(format!("expected KEY=VALUE, got `{value}`"));
+    };
+    let valid_key = key.starts_with(|c: char| c.is_ascii_alphabetic() || c == '_')
+        && key.chars().all(|c| c.is_ascii_alphanumeric() || c == '_');
+    if !valid_key {
+        return Err(format!("invalid environment variable name: `{key}`"));
+    }
+    Ok((key.to_string(), val.to_string()))
+}
+
+/// Parse a `--wrap` value: a column count, or `auto` for the terminal width
+/// (from `$COLUMNS`, only when stdout is a terminal). `0` disables wrapping.
+fn parse_wrap(value: &str) -> std::result::Result<Option<usize>, String> {
+    if value == "auto" {
+        if !std::io::stdout().is_terminal() {
+            return Ok(None);
+        }
+        return Ok(std::env::var("COLUMNS")
+            .ok()
+            .and_then(|c| c.parse::<usize>().ok())
+            .filter(|&c| c > 0)
+            .or(Some(80)));
+    }
+    match value.parse::<usize>() {
+        Ok(0) => Ok(None),
+        Ok(cols) => Ok(Some(cols)),
+        Err(_) => Err(format!("invalid --wrap value: `{value}` (expected a column count or `auto`)")),
+    }
+}
+
+/// Word-wrap `text` at `width` columns, preserving existing line breaks.
+/// Words longer than `width` are kept whole on their own line.
+///
+/// Only prose is wrapped: fenced code blocks, indented lines and table rows
+/// pass through untouched, and list items keep their marker with
+/// continuation lines indented under the item text.
+fn wrap_text(text: &str, width: usize) -> String {
+    let mut out = String::with_capacity(text.len());
+    let mut in_fence = false;
+    for (i, line) in text.split('\n').enumerate() {
+        if i > 0 {
+            out.push('\n');
+        }
+        let trimmed = line.trim_start();
+        if trimmed.starts_with("```") || trimmed.starts_with("~~~") {
+            in_fence = !in_fence;
+            out.push_str(line);
+            continue;
+        }
+        if in_fence || line.starts_with([' ', '\t']) || trimmed.starts_with('|') {
+            out.push_str(line);
+            continue;
+        }
+        let marker = list_marker(line);
+        out.push_str(marker);
+        let indent = marker.chars().count();
+        let mut col = indent;
+        for word in line[marker.len()..].split_whitespace() {
+            let len = word.chars().count();
+            if col > indent && col + 1 + len > width {
+                out.push('\n');
+                out.extend(std::iter::repeat(' ').take(indent));
+                col = indent;
+            } else if col > indent {
+                out.push(' ');
+                col += 1;
+            }
+            out.push_str(word);
+            col += len;
+        }
+    }
+    out
+}
+
+/// The leading `- `, `* `, `+ ` or `1. ` of a markdown list item, if any.
+fn list_marker(line: &str) -> &str {
+    if ["- ", "* ", "+ "].iter().any(|m| line.starts_with(m)) {
+        return &line[..2];
+    }
+    let digits = line.bytes().take_while(u8::is_ascii_digit).count();
+    if digits > 0 && line[digits..].starts_with(". ") {
+        return &line[..digits + 2];
+    }
+    ""
+}
+
 /// Shell-quote a string if it contains characters that need escaping.
 fn shell_quote(s: &str) -> String {
     if s.is_empty() {
@@ -877,6 +2760,49 @@ fn shell_quote(s: &str) -> String {
     format!("'{}'", s.replace('\'', "'\\''"))
 }
 
+/// Render the shell commands a session executed, in order, as a runnable script.
+///
+/// Every tool call whose canonical name is `shell` contributes its `command`
+/// input, preceded by a comment with its call id.
+fn shell_script_from_events(session_id: &str, events: &[Event]) -> String {
+    let mut script = String::from("#!/bin/sh\n");
+    script.push_str(&format!("# Shell commands from harness session {session_id}\n"));
+
+    let cwd = events.iter().find_map(|e| match e {
+        Event::SessionStart(s) => s.cwd.clone(),
+        _ => None,
+    });
+    if let Some(cwd) = cwd {
+        script.push_str(&format!("cd {} || exit 1\n", shell_quote(&cwd)));
+    }
+
+    for (start, end) in harness::event::extract_tool_calls(events) {
+        if harness::event::canonical_tool_name(&start.tool_name) != "shell" {
+            continue;
+        }
+        let Some(input) = start.input.as_ref() else {
+            continue;
+        };
+        let command = match input.get("command").or_else(|| input.get("cmd")) {
+            Some(serde_json::Value::String(c)) => c.clone(),
+            Some(serde_json::Value::Array(parts)) => parts
+                .iter()
+                .filter_map(|p| p.as_str())
+                .map(shell_quote)
+                .collect::<Vec<_>>()
+                .join(" "),
+            _ => continue,
+        };
+        let status = match end {
+            Some(e) if !e.success => " (failed)",
+            _ => "",
+        };
+        script.push_str(&format!("\n# call {}{status}\n{command}\n", start.call_id));
+    }
+
+    script
+}
+
 fn run_dry_run(config: &TaskConfig) -> ExitCode {
     let runner = harness::agents::create_runner(config.agent);
 
@@ -896,6 +2822,10 @@ fn run_dry_run(config: &TaskConfig) -> ExitCode {
         .map(|p| p.display().to_string())
         .unwrap_or_else(|| ".".to_string());
 
+    if !config.command_prefix.is_empty() {
+        let quoted: Vec<String> = config.command_prefix.iter().map(|a| shell_quote(a)).collect();
+        println!("Wrap:   {}", quoted.join(" "));
+    }
     println!("Binary: {binary}");
     let quoted: Vec<String> = args.iter().map(|a| shell_quote(a)).collect();
     println!("Args:   {}", quoted.join(" "));
@@ -917,8 +2847,17 @@ fn run_dry_run(config: &TaskConfig) -> ExitCode {
 }
 
 /// Helper that writes to stdout and optionally tees to a file.
+///
+/// If the stdout consumer goes away (`harness run | head`), writes stop
+/// with `BrokenPipe`; the writer records that instead of panicking so the
+/// caller can wind the run down cleanly.
 struct TeeWriter {
+    stdout: Box<dyn std::io::Write + Send>,
     file: Option<std::fs::File>,
+    stdout_closed: bool,
+    /// Lines already in the file from a prior run, keyed by [`dedupe_key`],
+    /// with how many more times each may still be skipped.
+    existing: std::collections::HashMap<String, usize>,
 }
 
 impl TeeWriter {
@@ -931,33 +2870,709 @@ impl TeeWriter {
                 })
                 .ok()
         });
-        Self { file }
+        Self {
+            stdout: Box::new(std::io::stdout()),
+            file,
+            stdout_closed: false,
+            existing: std::collections::HashMap::new(),
+        }
+    }
+
+    /// Like [`TeeWriter::new`], but append to the file and skip writing any
+    /// line a prior (partial) run already wrote there.
+    fn resuming(path: &PathBuf) -> Self {
+        let mut existing = std::collections::HashMap::new();
+        if let Ok(content) = std::fs::read_to_string(path) {
+            for line in content.lines() {
+                *existing.entry(dedupe_key(line)).or_insert(0) += 1;
+            }
+        }
+        let file = std::fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(path)
+            .map_err(|e| {
+                eprintln!(
+                    "warning: could not open output file {}: {e}",
+                    path.display()
+                )
+            })
+            .ok();
+        Self {
+            stdout: Box::new(std::io::stdout()),
+            file,
+            stdout_closed: false,
+            existing,
+        }
+    }
+
+    /// Whether stdout's reader has closed the pipe.
+    fn stdout_closed(&self) -> bool {
+        self.stdout_closed
+    }
+
+    fn write_stdout(&mut self, bytes: &[u8]) {
+        if self.stdout_closed {
+            return;
+        }
+        let written = self.stdout.write_all(bytes);
+        self.note_stdout_error(written);
+    }
+
+    /// Push buffered stdout to the reader now. Stdout is line-buffered, so
+    /// streamed text without a newline would otherwise sit in the buffer.
+    fn flush(&mut self) {
+        if self.stdout_closed {
+            return;
+        }
+        let flushed = self.stdout.flush();
+        self.note_stdout_error(flushed);
+    }
+
+    fn note_stdout_error(&mut self, result: std::io::Result<()>) {
+        if let Err(e) = result {
+            if e.kind() == std::io::ErrorKind::BrokenPipe {
+                self.stdout_closed = true;
+            } else {
+                tracing::debug!("failed to write to stdout: {e}");
+            }
+        }
+    }
+
+    fn print(&mut self, text: &str) {
+        self.write_stdout(text.as_bytes());
+        if let Some(ref mut f) = self.file {
+            if let Err(e) = std::io::Write::write_all(f, text.as_bytes()) {
+                tracing::debug!("failed to write to output file: {e}");
+            }
+        }
+    }
+
+    fn println(&mut self, text: &str) {
+        self.write_stdout(format!("{text}\n").as_bytes());
+        self.write_file_line(text);
+    }
+
+    /// Like [`TeeWriter::println`], dimmed on a terminal. The output file
+    /// always gets the plain text.
+    fn println_dim(&mut self, text: &str) {
+        self.write_stdout(format!("{}\n", dim(text)).as_bytes());
+        self.write_file_line(text);
+    }
+
+    fn write_file_line(&mut self, text: &str) {
+        if !self.existing.is_empty() {
+            if let Some(count) = self.existing.get_mut(&dedupe_key(text)) {
+                if *count > 0 {
+                    *count -= 1;
+                    return;
+                }
+            }
+        }
+        if let Some(ref mut f) = self.file {
+            if let Err(e) = std::io::Write::write_all(f, text.as_bytes())
+                .and_then(|()| std::io::Write::write_all(f, b"\n"))
+            {
+                tracing::debug!("failed to write to output file: {e}");
+            }
+        }
+    }
+}
+
+/// Identity of an output line for `--dedupe-output`: JSON lines compare
+/// without their `timestamp_ms`, which differs on every run.
+fn dedupe_key(line: &str) -> String {
+    match serde_json::from_str::<serde_json::Value>(line) {
+        Ok(serde_json::Value::Object(mut obj)) => {
+            obj.remove("timestamp_ms");
+            serde_json::Value::Object(obj).to_string()
+        }
+        _ => line.to_string(),
+    }
+}
+
+/// Output and execution options for `run_headless` that aren't part of the task itself.
+#[derive(Default)]
+struct HeadlessOptions {
+    output_file: Option<PathBuf>,
+    /// Resume the output file instead of truncating it (`--dedupe-output`).
+    dedupe_output: bool,
+    /// Limits that cancel the run early (budget, turns, tool calls, idle, timeout).
+    stop: StopConditions,
+    /// Column to word-wrap text-mode messages at.
+    wrap: Option<usize>,
+    /// Stop on the first `Error` event instead of letting the agent recover.
+    fail_fast_on_error_event: bool,
+    /// Skip per-event rendering; print only the final result and stats.
+    summary_only: bool,
+    /// Event kinds to print (`--only`); empty prints everything.
+    only: Vec<harness::event::EventCategory>,
+    /// Validate each stream-json event against the event schema before printing it.
+    validate_events: bool,
+    /// Print stream-json events as indented JSON instead of one per line.
+    pretty: bool,
+    /// Print a per-type event count to stderr when the run ends.
+    print_events_count: bool,
+    /// Stream the adapter's events without normalization (`--no-normalize`).
+    no_normalize: bool,
+    /// Keep a status line on stderr while a text-mode run is in progress.
+    progress: bool,
+    /// Label recorded in the session's metadata (`--name`).
+    name: Option<String>,
+    /// Directory of lifecycle hook scripts (`--hooks-dir`).
+    hooks_dir: Option<PathBuf>,
+    /// Where to write the post-run `git diff` (`-` prints it).
+    capture_patch: Option<PathBuf>,
+    /// Exit 0 on any run that produced text, regardless of `Result.success`.
+    allow_empty_result: bool,
+    /// Where the exit code comes from.
+    on_result: OnResult,
+    /// Also append events to the dated cross-session log.
+    aggregate_log: bool,
+    /// Tool confinement policy from `harness.policy.toml`.
+    policy: Option<ToolPolicy>,
+}
+
+/// `event` as stream-json output: one compact line, or with `--pretty`
+/// indented JSON followed by a blank line.
+fn stream_json(event: &Event, pretty: bool) -> Option<String> {
+    if pretty {
+        serde_json::to_string_pretty(event)
+            .ok()
+            .map(|json| json + "\n")
+    } else {
+        serde_json::to_string(event).ok()
+    }
+}
+
+/// The variant name of `event`, as `--print-events-count` reports it.
+fn event_kind(event: &Event) -> &'static str {
+    match event {
+        Event::SessionStart(_) => "SessionStart",
+        Event::TextDelta(_) => "TextDelta",
+        Event::Message(_) => "Message",
+        Event::Reasoning(_) => "Reasoning",
+        Event::ToolStart(_) => "ToolStart",
+        Event::ToolEnd(_) => "ToolEnd",
+        Event::TurnStart(_) => "TurnStart",
+        Event::TurnEnd(_) => "TurnEnd",
+        Event::UsageDelta(_) => "UsageDelta",
+        Event::Result(_) => "Result",
+        Event::Summary(_) => "Summary",
+        Event::Error(_) => "Error",
+    }
+}
+
+/// Text-mode rendering of one event: assistant text only, errors to stderr.
+fn render_text_event(tee: &mut TeeWriter, event: &Event, wrap: Option<usize>) {
+    match event {
+        Event::TextDelta(d) => {
+            tee.print(&d.text);
+            tee.flush();
+        }
+        Event::Message(m) => {
+            if matches!(m.role, harness::event::Role::Assistant) {
+                match wrap {
+                    Some(cols) => tee.println(&wrap_text(&m.text, cols)),
+                    None => tee.println(&m.text),
+                }
+                tee.flush();
+            }
+        }
+        Event::Reasoning(r) => tee.println_dim(&r.text),
+        Event::Error(e) => eprintln!("error: {}", e.message),
+        _ => {}
+    }
+}
+
+/// Whether to show the `--progress` status line: text mode only, forced by
+/// `--progress`/`--no-progress`, otherwise only on a terminal without
+/// `NO_COLOR`.
+fn progress_enabled(
+    format: OutputFormat,
+    flag: Option<bool>,
+    stderr_is_tty: bool,
+    no_color: bool,
+) -> bool {
+    format == OutputFormat::Text && flag.unwrap_or(stderr_is_tty && !no_color)
+}
+
+/// The `--progress` status line: one stderr line, redrawn as events arrive
+/// and once a second while the agent is quiet, and erased before anything
+/// else is printed.
+struct ProgressLine {
+    out: Box<dyn std::io::Write + Send>,
+    agent: String,
+    started: std::time::Instant,
+    last_drawn: std::time::Instant,
+    cost_usd: f64,
+    tool_calls: usize,
+    drawn: bool,
+    /// Whether stdout ends at a line break; mid-line, drawing would split
+    /// the agent's text.
+    at_line_start: bool,
+}
+
+impl ProgressLine {
+    /// Redraw interval while no events arrive.
+    const TICK: std::time::Duration = std::time::Duration::from_secs(1);
+
+    fn new(agent: &str, now: std::time::Instant) -> Self {
+        Self {
+            out: Box::new(std::io::stderr()),
+            agent: agent.to_string(),
+            started: now,
+            last_drawn: now,
+            cost_usd: 0.0,
+            tool_calls: 0,
+            drawn: false,
+            at_line_start: true,
+        }
+    }
+
+    /// When the line is next due for a redraw.
+    fn next_tick(&self) -> std::time::Instant {
+        self.last_drawn + Self::TICK
+    }
+
+    /// Account for an event that was just rendered.
+    fn observe(&mut self, event: &Event) {
+        match event {
+            Event::ToolStart(_) => self.tool_calls += 1,
+            Event::UsageDelta(u) => self.cost_usd += u.usage.cost_usd.unwrap_or(0.0),
+            Event::Summary(s) => self.cost_usd = s.total_cost_usd,
+            Event::TextDelta(d) if !d.text.is_empty() => {
+                self.at_line_start = d.text.ends_with('\n');
+            }
+            Event::Message(m) if m.role == harness::event::Role::Assistant => {
+                self.at_line_start = true;
+            }
+            _ => {}
+        }
+    }
+
+    fn status(&self, now: std::time::Instant) -> String {
+        let tools = match self.tool_calls {
+            1 => "1 tool".to_string(),
+            n => format!("{n} tools"),
+        };
+        format!(
+            "running {}... {}s, ${:.2}, {tools}",
+            self.agent,
+            now.duration_since(self.started).as_secs(),
+            self.cost_usd
+        )
+    }
+
+    fn draw(&mut self, now: std::time::Instant) {
+        self.last_drawn = now;
+        if !self.at_line_start {
+            return;
+        }
+        let line = format!("\r\x1b[2K{}", self.status(now));
+        let _ = self.out.write_all(line.as_bytes());
+        let _ = self.out.flush();
+        self.drawn = true;
+    }
+
+    fn clear(&mut self) {
+        if self.drawn {
+            let _ = self.out.write_all(b"\r\x1b[2K");
+            let _ = self.out.flush();
+            self.drawn = false;
+        }
+    }
+}
+
+/// `text` in ANSI dim (faint) style when stdout is a terminal.
+fn dim(text: &str) -> String {
+    if std::io::stdout().is_terminal() {
+        format!("\x1b[2m{text}\x1b[0m")
+    } else {
+        text.to_string()
+    }
+}
+
+/// The header printed before the first markdown-mode event.
+fn render_markdown_header(tee: &mut TeeWriter, agent_name: &str, model_name: &str) {
+    tee.println(&format!("# harness session — {agent_name}\n"));
+    if !model_name.is_empty() {
+        tee.println(&format!("**Model:** {model_name}\n"));
+    }
+    tee.println("---\n");
+}
+
+/// Markdown-mode rendering of one event.
+fn render_markdown_event(tee: &mut TeeWriter, event: &Event) {
+    match event {
+        Event::TextDelta(d) => tee.print(&d.text),
+        Event::Message(m) => {
+            let role = match m.role {
+                harness::event::Role::Assistant => "Assistant",
+                harness::event::Role::User => "User",
+                harness::event::Role::System => "System",
+            };
+            tee.println(&format!("\n### {role}\n"));
+            tee.println(&m.text);
+            tee.println("");
+        }
+        Event::Reasoning(r) => {
+            tee.println("");
+            for line in r.text.lines() {
+                if line.trim().is_empty() {
+                    tee.println(">");
+                } else {
+                    tee.println(&format!("> _{line}_"));
+                }
+            }
+            tee.println("");
+        }
+        Event::ToolStart(t) => {
+            tee.println(&format!("\n> **Tool:** `{}` ({})", t.tool_name, t.call_id));
+            if let Some(ref input) = t.input {
+                tee.println("```json");
+                tee.println(&serde_json::to_string_pretty(input).unwrap_or_default());
+                tee.println("```");
+            }
+        }
+        Event::ToolEnd(t) => {
+            let status = if t.success { "ok" } else { "fail" };
+            tee.println(&format!("> **Tool done:** `{}` [{}]", t.tool_name, status));
+        }
+        Event::Result(r) => {
+            let status = if r.success { "Success" } else { "Error" };
+            tee.println(&format!("\n---\n\n**Result:** {status}"));
+            if !r.text.is_empty() {
+                tee.println("");
+                tee.println(&r.text);
+            }
+        }
+        Event::Error(e) => {
+            tee.println(&format!("\n> **Error:** {}", e.message));
+        }
+        Event::SessionStart(s) => {
+            if !s.session_id.is_empty() {
+                tee.println(&format!("**Session:** {}\n", s.session_id));
+            }
+        }
+        Event::TurnStart(_) | Event::TurnEnd(_) | Event::UsageDelta(_) | Event::Summary(_) => {}
+    }
+}
+
+/// Run totals shown in the `--output html` header.
+struct HtmlSummary<'a> {
+    agent: &'a str,
+    model: &'a str,
+    cost_usd: f64,
+    input_tokens: u64,
+    output_tokens: u64,
+    duration_ms: Option<u64>,
+}
+
+const HTML_STYLE: &str = "body{font-family:-apple-system,BlinkMacSystemFont,\"Segoe UI\",sans-serif;\
+max-width:860px;margin:2rem auto;padding:0 1rem;color:#1f2328;background:#f6f8fa}\
+header{border-bottom:1px solid #d0d7de;margin-bottom:1.5rem}header h1{font-size:1.4rem}\
+.meta{color:#57606a}.msg{border-radius:12px;padding:.75rem 1rem;margin:.75rem 0;white-space:pre-wrap}\
+.user{background:#ddf4ff;margin-left:20%}.assistant{background:#fff;border:1px solid #d0d7de;margin-right:20%}\
+.system{background:#eaeef2;font-size:.9rem}.role{font-size:.75rem;font-weight:600;color:#57606a;display:block}\
+details.tool{background:#fff;border:1px solid #d0d7de;border-radius:6px;margin:.5rem 0;padding:.5rem .75rem}\
+details.tool summary{cursor:pointer;font-family:monospace}.status-ok{color:#1a7f37}.status-fail{color:#cf222e}\
+pre{background:#f6f8fa;padding:.5rem;overflow-x:auto;font-size:.85rem}\
+.error{background:#ffebe9;border:1px solid #ff8182;border-radius:6px;padding:.5rem .75rem;margin:.5rem 0}\
+.result{border-radius:6px;padding:1rem;margin-top:1.5rem;white-space:pre-wrap}\
+.result.ok{background:#dafbe1;border:1px solid #4ac26b}.result.fail{background:#ffebe9;border:1px solid #ff8182}";
+
+fn html_escape(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => out.push_str("&amp;"),
+            '<' => out.push_str("&lt;"),
+            '>' => out.push_str("&gt;"),
+            '"' => out.push_str("&quot;"),
+            '\'' => out.push_str("&#39;"),
+            _ => out.push(c),
+        }
+    }
+    out
+}
+
+/// A self-contained HTML transcript of a finished run: a header with the
+/// run's totals, one bubble per message, a collapsible block per tool call,
+/// and a closing result banner.
+fn render_html_document(events: &[Event], summary: &HtmlSummary) -> String {
+    use std::fmt::Write as _;
+
+    let ends: std::collections::HashMap<&str, &harness::event::ToolEndEvent> = events
+        .iter()
+        .filter_map(|e| match e {
+            Event::ToolEnd(t) => Some((t.call_id.as_str(), t)),
+            _ => None,
+        })
+        .collect();
+
+    let title = format!("harness session — {}", html_escape(summary.agent));
+    let mut meta = Vec::new();
+    if !summary.model.is_empty() {
+        meta.push(format!("Model: {}", html_escape(summary.model)));
+    }
+    meta.push(format!("Cost: ${:.3}", summary.cost_usd));
+    meta.push(format!(
+        "Tokens: {} in / {} out",
+        format_token_count(summary.input_tokens),
+        format_token_count(summary.output_tokens)
+    ));
+    if let Some(ms) = summary.duration_ms {
+        meta.push(format!("Duration: {:.1}s", ms as f64 / 1000.0));
+    }
+
+    let mut body = String::new();
+    let mut pending_text = String::new();
+    let flush_text = |body: &mut String, pending: &mut String| {
+        if !pending.is_empty() {
+            let _ = writeln!(
+                body,
+                "<div class=\"msg assistant\"><span class=\"role\">Assistant</span>{}</div>",
+                html_escape(&std::mem::take(pending))
+            );
+        }
+    };
+    for event in events {
+        match event {
+            // Deltas only stand in for a message the agent never completes.
+            Event::TextDelta(d) => pending_text.push_str(&d.text),
+            Event::Message(m) => {
+                let (class, role) = match m.role {
+                    harness::event::Role::Assistant => {
+                        pending_text.clear();
+                        ("assistant", "Assistant")
+                    }
+                    harness::event::Role::User => ("user", "User"),
+                    harness::event::Role::System => ("system", "System"),
+                };
+                let _ = writeln!(
+                    body,
+                    "<div class=\"msg {class}\"><span class=\"role\">{role}</span>{}</div>",
+                    html_escape(&m.text)
+                );
+            }
+            Event::ToolStart(t) => {
+                flush_text(&mut body, &mut pending_text);
+                let end = ends.get(t.call_id.as_str());
+                let status = match end {
+                    Some(e) if e.success => "<span class=\"status-ok\">ok</span>",
+                    Some(_) => "<span class=\"status-fail\">fail</span>",
+                    None => "",
+                };
+                let _ = writeln!(
+                    body,
+                    "<details class=\"tool\"><summary>{} <small>{}</small> {status}</summary>",
+                    html_escape(&t.tool_name),
+                    html_escape(&t.call_id)
+                );
+                if let Some(ref input) = t.input {
+                    let json = serde_json::to_string_pretty(input).unwrap_or_default();
+                    let _ = writeln!(body, "<pre class=\"input\">{}</pre>", html_escape(&json));
+                }
+                if let Some(output) = end.and_then(|e| e.output.as_deref()) {
+                    let _ = writeln!(body, "<pre class=\"output\">{}</pre>", html_escape(output));
+                }
+                body.push_str("</details>\n");
+            }
+            Event::Error(e) => {
+                flush_text(&mut body, &mut pending_text);
+                let _ = writeln!(
+                    body,
+                    "<div class=\"error\"><strong>Error:</strong> {}</div>",
+                    html_escape(&e.message)
+                );
+            }
+            Event::Result(r) => {
+                flush_text(&mut body, &mut pending_text);
+                let (class, label) = if r.success {
+                    ("ok", "Success")
+                } else {
+                    ("fail", "Error")
+                };
+                let _ = write!(
+                    body,
+                    "<div class=\"result {class}\"><strong>Result: {label}</strong>"
+                );
+                if !r.text.is_empty() {
+                    let _ = write!(body, "\n{}", html_escape(&r.text));
+                }
+                body.push_str("</div>\n");
+            }
+            Event::SessionStart(_)
+            | Event::Reasoning(_)
+            | Event::ToolEnd(_)
+            | Event::TurnStart(_)
+            | Event::TurnEnd(_)
+            | Event::UsageDelta(_)
+            | Event::Summary(_) => {}
+        }
+    }
+    flush_text(&mut body, &mut pending_text);
+
+    format!(
+        "<!DOCTYPE html>\n<html lang=\"en\">\n<head>\n<meta charset=\"utf-8\">\n\
+         <title>{title}</title>\n<style>{HTML_STYLE}</style>\n</head>\n<body>\n\
+         <header>\n<h1>{title}</h1>\n<p class=\"meta\">{}</p>\n</header>\n<main>\n{body}</main>\n\
+         </body>\n</html>",
+        meta.join(" · ")
+    )
+}
+
+/// A git work tree's full contents (tracked and untracked, minus ignored
+/// files) recorded as a tree object, so a later snapshot can be diffed
+/// against it.
+struct WorktreeSnapshot {
+    root: PathBuf,
+    tree: String,
+}
+
+fn git_output(
+    cwd: &std::path::Path,
+    index: Option<&std::path::Path>,
+    args: &[&str],
+) -> Option<std::process::Output> {
+    let mut cmd = std::process::Command::new("git");
+    cmd.arg("-C").arg(cwd).args(args);
+    if let Some(index) = index {
+        cmd.env("GIT_INDEX_FILE", index);
+    }
+    cmd.stderr(std::process::Stdio::null())
+        .output()
+        .ok()
+        .filter(|o| o.status.success())
+}
+
+/// Snapshot the work tree containing `dir` without touching the real index:
+/// everything is staged into a scratch copy of it and written out as a tree.
+/// `None` when `dir` isn't in a git work tree.
+fn snapshot_worktree(dir: &std::path::Path) -> Option<WorktreeSnapshot> {
+    let toplevel = git_output(dir, None, &["rev-parse", "--show-toplevel"])?;
+    let root = PathBuf::from(String::from_utf8_lossy(&toplevel.stdout).trim());
+    let index = git_output(&root, None, &["rev-parse", "--git-path", "index"])?;
+    let index = root.join(String::from_utf8_lossy(&index.stdout).trim());
+
+    // Starting from the real index keeps its stat cache, so unchanged files
+    // aren't rehashed. A repo without one simply starts empty.
+    let scratch = index.with_extension(format!("harness-{}", std::process::id()));
+    let _ = std::fs::copy(&index, &scratch);
+    let tree = git_output(&root, Some(&scratch), &["add", "--all"])
+        .and_then(|_| git_output(&root, Some(&scratch), &["write-tree"]));
+    let _ = std::fs::remove_file(&scratch);
+    let tree = String::from_utf8_lossy(&tree?.stdout).trim().to_string();
+    Some(WorktreeSnapshot { root, tree })
+}
+
+/// The work tree's changes since `before` as a unified diff, leaving out
+/// edits that were already there when the snapshot was taken.
+fn capture_git_patch(before: &WorktreeSnapshot) -> Option<String> {
+    let after = snapshot_worktree(&before.root)?;
+    let diff = git_output(
+        &before.root,
+        None,
+        &["diff", &before.tree, &after.tree, "--"],
+    )?;
+    Some(String::from_utf8_lossy(&diff.stdout).into_owned())
+}
+
+/// Lifecycle scripts from `--hooks-dir`, each optional: `pre-run` before the
+/// agent starts, `on-tool` at every tool call, and `post-run` at the end.
+struct Hooks {
+    dir: PathBuf,
+    cwd: PathBuf,
+    /// Context every hook receives as `HARNESS_*` environment variables.
+    env: Vec<(&'static str, String)>,
+}
+
+impl Hooks {
+    fn new(
+        dir: &std::path::Path,
+        config: &TaskConfig,
+        session_id: &str,
+        cwd: &std::path::Path,
+    ) -> Self {
+        let agent = harness::agents::create_runner(config.agent)
+            .name()
+            .to_string();
+        let mut env = vec![
+            ("HARNESS_SESSION_ID", session_id.to_string()),
+            ("HARNESS_AGENT", agent),
+            ("HARNESS_CWD", cwd.display().to_string()),
+        ];
+        if let Some(ref model) = config.model {
+            env.push(("HARNESS_MODEL", model.clone()));
+        }
+        Self {
+            dir: dir.to_path_buf(),
+            cwd: cwd.to_path_buf(),
+            env,
+        }
     }
 
-    fn print(&mut self, text: &str) {
-        print!("{text}");
-        if let Some(ref mut f) = self.file {
-            if let Err(e) = std::io::Write::write_all(f, text.as_bytes()) {
-                tracing::debug!("failed to write to output file: {e}");
-            }
+    /// Run hook `name` with `extra` variables on top of the shared context,
+    /// forwarding its output to stderr. `None` when the directory has no such
+    /// hook.
+    async fn run(
+        &self,
+        name: &str,
+        extra: &[(&str, String)],
+    ) -> Option<std::io::Result<std::process::ExitStatus>> {
+        let path = self.dir.join(name);
+        if !path.is_file() {
+            return None;
         }
+        let output = tokio::process::Command::new(&path)
+            .current_dir(&self.cwd)
+            .envs(self.env.iter().map(|(k, v)| (*k, v)))
+            .envs(extra.iter().map(|(k, v)| (*k, v)))
+            .env("HARNESS_HOOK", name)
+            .stdin(std::process::Stdio::null())
+            .output()
+            .await;
+        Some(output.map(|out| {
+            // Keep stdout for the event stream.
+            eprint!("{}", String::from_utf8_lossy(&out.stdout));
+            eprint!("{}", String::from_utf8_lossy(&out.stderr));
+            out.status
+        }))
     }
 
-    fn println(&mut self, text: &str) {
-        println!("{text}");
-        if let Some(ref mut f) = self.file {
-            if let Err(e) = std::io::Write::write_all(f, text.as_bytes())
-                .and_then(|()| std::io::Write::write_all(f, b"\n"))
-            {
-                tracing::debug!("failed to write to output file: {e}");
+    /// Run a hook whose failure doesn't affect the run, warning if it fails.
+    async fn run_reporting(&self, name: &str, extra: &[(&str, String)]) {
+        match self.run(name, extra).await {
+            Some(Ok(status)) if !status.success() => {
+                eprintln!("warning: {name} hook failed ({status})");
             }
+            Some(Err(e)) => eprintln!("warning: cannot run {name} hook: {e}"),
+            _ => {}
         }
     }
 }
 
-async fn run_headless(config: TaskConfig, output_file: Option<PathBuf>) -> ExitCode {
+/// ID for a new session log. `HARNESS_SESSION_ID` pins it (for
+/// reproducible tests); otherwise it is generated from the timestamp.
+fn new_session_id() -> String {
+    std::env::var("HARNESS_SESSION_ID")
+        .ok()
+        .filter(|id| !id.is_empty())
+        .unwrap_or_else(|| {
+            format!(
+                "session-{}-{}",
+                std::time::SystemTime::now()
+                    .duration_since(std::time::UNIX_EPOCH)
+                    .unwrap_or_default()
+                    .as_millis(),
+                std::process::id()
+            )
+        })
+}
+
+async fn run_headless(config: TaskConfig, options: HeadlessOptions) -> ExitCode {
     let output_format = config.output_format;
-    let timeout_secs = config.timeout_secs;
 
     // Create a cancellation token for graceful shutdown.
     let cancel_token = tokio_util::sync::CancellationToken::new();
@@ -969,18 +3584,69 @@ async fn run_headless(config: TaskConfig, output_file: Option<PathBuf>) -> ExitC
         cancel_for_signal.cancel();
     });
 
-    let handle = match run_task_with_cancel(&config, Some(cancel_token.clone())).await {
+    let session_id = new_session_id();
+    let policy_cwd = policy_dir(config.cwd.as_deref());
+
+    let hooks = options
+        .hooks_dir
+        .as_ref()
+        .map(|dir| Hooks::new(dir, &config, &session_id, &policy_cwd));
+    if let Some(ref hooks) = hooks {
+        match hooks.run("pre-run", &[]).await {
+            Some(Ok(status)) if !status.success() => {
+                eprintln!("error: pre-run hook failed ({status}); not starting the agent");
+                return ExitCode::FAILURE;
+            }
+            Some(Err(e)) => {
+                eprintln!("error: cannot run pre-run hook: {e}");
+                return ExitCode::FAILURE;
+            }
+            _ => {}
+        }
+    }
+
+    let patch_base = match options.capture_patch {
+        Some(_) => {
+            let dir = policy_cwd.clone();
+            let snapshot = tokio::task::spawn_blocking(move || snapshot_worktree(&dir))
+                .await
+                .ok()
+                .flatten();
+            if snapshot.is_none() {
+                eprintln!(
+                    "warning: --capture-patch needs a git repository; {} is not one",
+                    policy_cwd.display()
+                );
+            }
+            snapshot
+        }
+        None => None,
+    };
+
+    let started = if options.no_normalize {
+        harness::run_task_raw(&config, Some(cancel_token.clone())).await
+    } else {
+        run_task_with_cancel(&config, Some(cancel_token.clone())).await
+    };
+    let handle = match started {
         Ok(h) => h,
         Err(e) => {
             match output_format {
                 OutputFormat::Json | OutputFormat::StreamJson => {
                     let err = serde_json::json!({
                         "type": "error",
+                        "code": e.code(),
                         "message": e.to_string(),
                     });
-                    println!("{}", err);
+                    // Stream-json stays one object per line.
+                    let text = if output_format == OutputFormat::Json {
+                        serde_json::to_string_pretty(&err).unwrap_or_default()
+                    } else {
+                        err.to_string()
+                    };
+                    TeeWriter::new(None).println(&text);
                 }
-                OutputFormat::Text | OutputFormat::Markdown => {
+                OutputFormat::Text | OutputFormat::Markdown | OutputFormat::Html => {
                     eprintln!("error: {e}");
                 }
             }
@@ -990,19 +3656,22 @@ async fn run_headless(config: TaskConfig, output_file: Option<PathBuf>) -> ExitC
 
     let mut stream = handle.stream;
 
-    // Create session logger — generate an ID from timestamp.
-    let session_id = format!(
-        "session-{}-{}",
-        std::time::SystemTime::now()
-            .duration_since(std::time::UNIX_EPOCH)
-            .unwrap_or_default()
-            .as_millis(),
-        std::process::id()
-    );
-    let mut logger = SessionLogger::new(&session_id, &config).ok();
+    let mut logger = SessionLogger::new_with_name(&session_id, &config, options.name.clone())
+        .map_err(|e| tracing::warn!("session log disabled: {e}"))
+        .ok();
+    if options.aggregate_log {
+        match (logger.as_mut(), harness::logger::aggregate_dir()) {
+            (Some(log), Ok(dir)) => log.enable_aggregate_log(dir),
+            (_, Err(e)) => tracing::warn!("aggregate log disabled: {e}"),
+            (None, Ok(_)) => {}
+        }
+    }
 
     // Open output file for tee if requested.
-    let mut tee = TeeWriter::new(output_file.as_ref());
+    let mut tee = match options.output_file {
+        Some(ref path) if options.dedupe_output => TeeWriter::resuming(path),
+        ref path => TeeWriter::new(path.as_ref()),
+    };
 
     let mut final_text = String::new();
     let mut success = false;
@@ -1014,10 +3683,52 @@ async fn run_headless(config: TaskConfig, output_file: Option<PathBuf>) -> ExitC
     let mut agent_name = config.agent.display_name().to_string();
     let mut model_name = config.model.clone().unwrap_or_default();
     let mut md_header_printed = false;
+    let mut failed_fast = false;
+    let mut agent_exit_code = None;
+
+    // `--output html` renders the whole transcript once the run ends.
+    let mut html_events: Vec<Event> = Vec::new();
+
+    // Per-type event counts for `--print-events-count`, in first-seen order.
+    let mut event_counts: Vec<(&'static str, usize)> = Vec::new();
+
+    // `--only` trims what's printed; the session log still gets everything.
+    let mut output_filter =
+        (!options.only.is_empty()).then(|| harness::event::EventFilter::only(&options.only));
+
+    let mut stop = StopTracker::new(options.stop.clone(), std::time::Instant::now());
+    let mut stop_reason = None;
+
+    let mut progress = options
+        .progress
+        .then(|| ProgressLine::new(&agent_name, std::time::Instant::now()));
 
     let cancel_for_timeout = cancel_token.clone();
     let process = async {
-        while let Some(result) = stream.next().await {
+        loop {
+            // Wake up for the time-based stop conditions and the progress
+            // line while waiting on the agent.
+            let tick = progress.as_ref().map(ProgressLine::next_tick);
+            let next = match [stop.next_wakeup(), tick].into_iter().flatten().min() {
+                Some(at) => tokio::select! {
+                    next = stream.next() => next,
+                    () = tokio::time::sleep_until(at.into()) => {
+                        let now = std::time::Instant::now();
+                        stop_reason = stop.check_time(now);
+                        if stop_reason.is_some() {
+                            break;
+                        }
+                        if let Some(ref mut p) = progress {
+                            if now >= p.next_tick() {
+                                p.draw(now);
+                            }
+                        }
+                        continue;
+                    }
+                },
+                None => stream.next().await,
+            };
+            let Some(result) = next else { break };
             match result {
                 Ok(event) => {
                     // Log to session file.
@@ -1025,91 +3736,46 @@ async fn run_headless(config: TaskConfig, output_file: Option<PathBuf>) -> ExitC
                         log.log_event(&event);
                     }
 
+                    let kind = event_kind(&event);
+                    match event_counts.iter_mut().find(|(k, _)| *k == kind) {
+                        Some((_, n)) => *n += 1,
+                        None => event_counts.push((kind, 1)),
+                    }
+
+                    if let Some(ref mut p) = progress {
+                        p.clear();
+                    }
+
                     match output_format {
+                        _ if options.summary_only => {}
+                        _ if output_filter.as_mut().is_some_and(|f| !f.admit(&event)) => {}
                         OutputFormat::StreamJson => {
-                            if let Ok(json) = serde_json::to_string(&event) {
-                                tee.println(&json);
-                            }
-                        }
-                        OutputFormat::Text => {
-                            // In text mode, only print text deltas and messages.
-                            match &event {
-                                Event::TextDelta(d) => tee.print(&d.text),
-                                Event::Message(m) => {
-                                    if matches!(m.role, harness::event::Role::Assistant) {
-                                        tee.println(&m.text);
-                                    }
+                            if options.validate_events {
+                                let checked = serde_json::to_value(&event)
+                                    .map_err(|e| e.to_string())
+                                    .and_then(|v| harness::event::validate_event_json(&v));
+                                if let Err(e) = checked {
+                                    eprintln!("error: invalid event: {e}");
+                                    cancel_for_timeout.cancel();
+                                    failed_fast = true;
+                                    success = false;
+                                    break;
                                 }
-                                Event::Error(e) => eprintln!("error: {}", e.message),
-                                _ => {}
+                            }
+                            if let Some(json) = stream_json(&event, options.pretty) {
+                                tee.println(&json);
                             }
                         }
+                        OutputFormat::Text => render_text_event(&mut tee, &event, options.wrap),
                         OutputFormat::Markdown => {
                             // Emit a markdown header on first event.
                             if !md_header_printed {
-                                tee.println(&format!("# harness session — {agent_name}\n"));
-                                if !model_name.is_empty() {
-                                    tee.println(&format!("**Model:** {model_name}\n"));
-                                }
-                                tee.println("---\n");
+                                render_markdown_header(&mut tee, &agent_name, &model_name);
                                 md_header_printed = true;
                             }
-                            match &event {
-                                Event::TextDelta(d) => tee.print(&d.text),
-                                Event::Message(m) => {
-                                    let role = match m.role {
-                                        harness::event::Role::Assistant => "Assistant",
-                                        harness::event::Role::User => "User",
-                                        harness::event::Role::System => "System",
-                                    };
-                                    tee.println(&format!("\n### {role}\n"));
-                                    tee.println(&m.text);
-                                    tee.println("");
-                                }
-                                Event::ToolStart(t) => {
-                                    tee.println(&format!(
-                                        "\n> **Tool:** `{}` ({})",
-                                        t.tool_name, t.call_id
-                                    ));
-                                    if let Some(ref input) = t.input {
-                                        tee.println("```json");
-                                        tee.println(
-                                            &serde_json::to_string_pretty(input)
-                                                .unwrap_or_default(),
-                                        );
-                                        tee.println("```");
-                                    }
-                                }
-                                Event::ToolEnd(t) => {
-                                    let status = if t.success { "ok" } else { "fail" };
-                                    tee.println(&format!(
-                                        "> **Tool done:** `{}` [{}]",
-                                        t.tool_name, status
-                                    ));
-                                }
-                                Event::Result(r) => {
-                                    let status =
-                                        if r.success { "Success" } else { "Error" };
-                                    tee.println(&format!("\n---\n\n**Result:** {status}"));
-                                    if !r.text.is_empty() {
-                                        tee.println("");
-                                        tee.println(&r.text);
-                                    }
-                                }
-                                Event::Error(e) => {
-                                    tee.println(&format!("\n> **Error:** {}", e.message));
-                                }
-                                Event::SessionStart(s) => {
-                                    if !s.session_id.is_empty() {
-                                        tee.println(&format!(
-                                            "**Session:** {}\n",
-                                            s.session_id
-                                        ));
-                                    }
-                                }
-                                Event::UsageDelta(_) => {}
-                            }
+                            render_markdown_event(&mut tee, &event);
                         }
+                        OutputFormat::Html => html_events.push(event.clone()),
                         OutputFormat::Json => {
                             // Collect for final JSON output.
                         }
@@ -1119,6 +3785,7 @@ async fn run_headless(config: TaskConfig, output_file: Option<PathBuf>) -> ExitC
                     match &event {
                         Event::Result(r) => {
                             success = r.success;
+                            agent_exit_code = r.exit_code;
                             final_text.clone_from(&r.text);
                             real_session_id.clone_from(&r.session_id);
                             duration_ms = r.duration_ms;
@@ -1157,64 +3824,169 @@ async fn run_headless(config: TaskConfig, output_file: Option<PathBuf>) -> ExitC
                                 total_cost += c;
                             }
                         }
+                        // The normalizer's totals supersede the running tally.
+                        Event::Summary(s) => {
+                            total_input_tokens = s.input_tokens;
+                            total_output_tokens = s.output_tokens;
+                            total_cost = s.total_cost_usd;
+                            duration_ms = s.duration_ms.or(duration_ms);
+                        }
                         _ => {}
                     }
+
+                    if let (Some(hooks), Event::ToolStart(t)) = (&hooks, &event) {
+                        let input = t.input.as_ref().map(|v| v.to_string()).unwrap_or_default();
+                        let context = [
+                            ("HARNESS_TOOL_NAME", t.tool_name.clone()),
+                            ("HARNESS_TOOL_CALL_ID", t.call_id.clone()),
+                            ("HARNESS_TOOL_INPUT", input),
+                        ];
+                        hooks.run_reporting("on-tool", &context).await;
+                    }
+
+                    // Strict mode: any error event ends the run.
+                    if options.fail_fast_on_error_event && matches!(event, Event::Error(_)) {
+                        tracing::debug!("error event with --fail-fast-on-error-event, stopping agent");
+                        cancel_for_timeout.cancel();
+                        failed_fast = true;
+                        success = false;
+                        break;
+                    }
+
+                    // Tool confinement: warn, or stop the agent, per the policy.
+                    if let (Some(policy), Event::ToolStart(t)) = (&options.policy, &event) {
+                        if let Some(violation) =
+                            policy.check(&t.tool_name, t.input.as_ref(), &policy_cwd)
+                        {
+                            eprintln!("policy: {violation}");
+                            if policy.action == PolicyAction::Abort {
+                                cancel_for_timeout.cancel();
+                                failed_fast = true;
+                                success = false;
+                                break;
+                            }
+                        }
+                    }
+
+                    // Any configured limit exceeded: stop the agent.
+                    stop_reason = stop.observe(&event, std::time::Instant::now());
+                    if stop_reason.is_some() {
+                        break;
+                    }
+
+                    if let Some(ref mut p) = progress {
+                        p.observe(&event);
+                        p.draw(std::time::Instant::now());
+                    }
+
+                    // Downstream reader is gone: stop the agent and wind down.
+                    if tee.stdout_closed() {
+                        tracing::debug!("stdout closed by reader, stopping agent");
+                        cancel_for_timeout.cancel();
+                        break;
+                    }
                 }
                 Err(e) => {
+                    if let Some(ref mut p) = progress {
+                        p.clear();
+                    }
                     match output_format {
                         OutputFormat::StreamJson => {
                             let err_event = Event::Error(harness::event::ErrorEvent {
                                 message: e.to_string(),
                                 code: None,
                                 timestamp_ms: 0,
+                                raw_type: None,
                             });
-                            if let Ok(json) = serde_json::to_string(&err_event) {
+                            if let Some(json) = stream_json(&err_event, options.pretty) {
                                 tee.println(&json);
                             }
                         }
-                        OutputFormat::Text | OutputFormat::Markdown => {
+                        OutputFormat::Text | OutputFormat::Markdown | OutputFormat::Html => {
                             eprintln!("error: {e}");
                         }
                         OutputFormat::Json => {}
                     }
                     success = false;
+                    agent_exit_code = None;
                     break;
                 }
             }
         }
     };
 
-    if let Some(timeout) = timeout_secs {
-        match tokio::time::timeout(std::time::Duration::from_secs(timeout), process).await {
-            Ok(()) => {}
-            Err(_) => {
-                // Timeout: cancel the subprocess gracefully.
-                cancel_for_timeout.cancel();
-                let msg = format!("timed out after {timeout}s");
-                match output_format {
-                    OutputFormat::StreamJson => {
-                        let err = Event::Error(harness::event::ErrorEvent {
-                            message: msg.clone(),
-                            code: Some("timeout".into()),
-                            timestamp_ms: 0,
-                        });
-                        if let Ok(json) = serde_json::to_string(&err) {
-                            println!("{json}");
-                        }
-                    }
-                    OutputFormat::Text | OutputFormat::Markdown => eprintln!("error: {msg}"),
-                    OutputFormat::Json => {}
+    process.await;
+
+    if let Some(ref mut p) = progress {
+        p.clear();
+    }
+
+    if let Some(reason) = stop_reason {
+        cancel_token.cancel();
+        let err = stop.error_event(reason);
+        match output_format {
+            OutputFormat::StreamJson => {
+                if let Some(json) = stream_json(&err, options.pretty) {
+                    tee.println(&json);
                 }
-                success = false;
             }
+            OutputFormat::Text | OutputFormat::Markdown | OutputFormat::Html => {
+                eprintln!("error: {}", stop.describe(reason));
+            }
+            OutputFormat::Json => {}
+        }
+        success = false;
+        agent_exit_code = None;
+    }
+
+    if let Some(ref hooks) = hooks {
+        let context = [
+            (
+                "HARNESS_SUCCESS",
+                if success { "1" } else { "0" }.to_string(),
+            ),
+            ("HARNESS_AGENT_SESSION_ID", real_session_id.clone()),
+        ];
+        hooks.run_reporting("post-run", &context).await;
+    }
+
+    if options.print_events_count {
+        let counts: Vec<String> = event_counts
+            .iter()
+            .map(|(k, n)| format!("{k}:{n}"))
+            .collect();
+        eprintln!("Events: {}", counts.join(" "));
+    }
+
+    let text_output = matches!(output_format, OutputFormat::Text | OutputFormat::Markdown);
+    if let (Some(dest), Some(base)) = (options.capture_patch.as_ref(), patch_base) {
+        let patch = tokio::task::spawn_blocking(move || capture_git_patch(&base))
+            .await
+            .ok()
+            .flatten();
+        match patch {
+            Some(patch) if dest.as_os_str() == "-" => {
+                // Keep stdout parseable in the JSON formats.
+                if text_output {
+                    tee.print(&patch);
+                } else {
+                    eprint!("{patch}");
+                }
+            }
+            Some(patch) => {
+                if let Err(e) = std::fs::write(dest, patch) {
+                    eprintln!("error: failed to write patch to {}: {e}", dest.display());
+                }
+            }
+            None => eprintln!("warning: --capture-patch could not diff the work tree"),
         }
-    } else {
-        process.await;
+    }
+    if options.summary_only && text_output && !final_text.is_empty() {
+        tee.println(&final_text);
     }
 
-    // Print cost summary to stderr for text/markdown modes.
-    if matches!(output_format, OutputFormat::Text | OutputFormat::Markdown)
-        && (total_cost > 0.0 || total_input_tokens > 0)
+    // Print cost summary to stderr for text/markdown modes (always with --summary-only).
+    if (text_output || options.summary_only) && (total_cost > 0.0 || total_input_tokens > 0)
     {
         let dur_str = duration_ms
             .map(|ms| format!("{:.1}s", ms as f64 / 1000.0))
@@ -1237,13 +4009,35 @@ async fn run_headless(config: TaskConfig, output_file: Option<PathBuf>) -> ExitC
         log.finalize(success, duration_ms);
     }
 
-    // For JSON output mode, emit the collected result.
-    if output_format == OutputFormat::Json {
+    if output_format == OutputFormat::Html {
+        let summary = HtmlSummary {
+            agent: &agent_name,
+            model: &model_name,
+            cost_usd: total_cost,
+            input_tokens: total_input_tokens,
+            output_tokens: total_output_tokens,
+            duration_ms,
+        };
+        tee.println(&render_html_document(&html_events, &summary));
+    }
+
+    // For JSON output mode (or a stream-json summary), emit the collected result.
+    if output_format == OutputFormat::Json
+        || (options.summary_only && output_format == OutputFormat::StreamJson)
+    {
         let result = serde_json::json!({
             "type": "result",
             "success": success,
             "result": final_text,
             "session_id": real_session_id,
+            "agent": agent_name,
+            "model": (!model_name.is_empty()).then_some(&model_name),
+            "duration_ms": duration_ms,
+            "total_cost_usd": total_cost,
+            "usage": {
+                "input_tokens": total_input_tokens,
+                "output_tokens": total_output_tokens,
+            },
         });
         match serde_json::to_string_pretty(&result) {
             Ok(json) => tee.println(&json),
@@ -1254,15 +4048,25 @@ async fn run_headless(config: TaskConfig, output_file: Option<PathBuf>) -> ExitC
         }
     }
 
-    if success {
+    // The exit code follows `Result.success` (or the agent's own code under
+    // `--on-result exit-code-from-agent`, when it fits in 0–255 and nothing
+    // after the result failed the run). A reader that stopped listening
+    // (`| head`) is not a failure, and `--allow-empty-result` also accepts
+    // any run that produced text.
+    if failed_fast {
+        ExitCode::FAILURE
+    } else if let (OnResult::ExitCodeFromAgent, Some(code)) = (
+        options.on_result,
+        agent_exit_code.and_then(|code| u8::try_from(code).ok()),
+    ) {
+        ExitCode::from(code)
+    } else if success
+        || tee.stdout_closed()
+        || (options.allow_empty_result && !final_text.is_empty())
+    {
         ExitCode::SUCCESS
     } else {
-        // Exit 0 if we got text output (no explicit failure), exit 1 otherwise.
-        if !final_text.is_empty() {
-            ExitCode::SUCCESS
-        } else {
-            ExitCode::FAILURE
-        }
+        ExitCode::FAILURE
     }
 }
 
@@ -1302,6 +4106,450 @@ mod tests {
         assert_eq!(shell_quote("$(cmd)"), "'$(cmd)'");
     }
 
+    // ─── split_shell_words ───────────────────────────────────────
+
+    #[test]
+    fn split_shell_words_handles_quotes_and_comments() {
+        let input = "--flag value  # trailing comment\n\
+                     # full-line comment\n\
+                     --msg \"two words\" 'it''s' a\\ b \"q\\\"x\" ''\n";
+        assert_eq!(
+            split_shell_words(input).unwrap(),
+            [
+                "--flag",
+                "value",
+                "--msg",
+                "two words",
+                "its",
+                "a b",
+                "q\"x",
+                ""
+            ]
+        );
+        assert_eq!(split_shell_words("a#b").unwrap(), ["a#b"]);
+        assert!(split_shell_words("'open").is_err());
+        assert!(split_shell_words("\"open").is_err());
+    }
+
+    // ─── parse_env_file ──────────────────────────────────────────
+
+    #[test]
+    fn parse_env_file_handles_comments_quotes_and_empty_values() {
+        let input = "# credentials\n\
+                     \n\
+                     export API_URL=https://example.com/?a=b  # inline comment\n\
+                     EMPTY=\n\
+                     SINGLE='keep \\n # as is'\n\
+                     DOUBLE=\"two words \\\"quoted\\\"\\nnext\"\n\
+                     SPACED = value \n";
+        assert_eq!(
+            parse_env_file(input).unwrap(),
+            [
+                (
+                    "API_URL".to_string(),
+                    "https://example.com/?a=b".to_string()
+                ),
+                ("EMPTY".to_string(), String::new()),
+                ("SINGLE".to_string(), "keep \\n # as is".to_string()),
+                (
+                    "DOUBLE".to_string(),
+                    "two words \"quoted\"\nnext".to_string()
+                ),
+                ("SPACED".to_string(), "value".to_string()),
+            ]
+        );
+        assert!(parse_env_file("NOVALUE\n").unwrap_err().contains("line 1"));
+        assert!(parse_env_file("A=1\nBAD-KEY=x\n")
+            .unwrap_err()
+            .contains("line 2"));
+        assert!(parse_env_file("Q=\"open\n").is_err());
+    }
+
+    // ─── resolve_output_format ───────────────────────────────────
+
+    #[test]
+    fn output_format_defaults_to_stream_json() {
+        let settings = Settings::default();
+        assert_eq!(
+            resolve_output_format(None, None, &settings),
+            Ok(OutputFormat::StreamJson)
+        );
+    }
+
+    #[test]
+    fn output_format_precedence() {
+        let settings = Settings {
+            default_output: Some("markdown".into()),
+            ..Default::default()
+        };
+        let project = ProjectConfig {
+            default_output: Some("text".into()),
+            ..Default::default()
+        };
+        assert_eq!(
+            resolve_output_format(None, None, &settings),
+            Ok(OutputFormat::Markdown)
+        );
+        assert_eq!(
+            resolve_output_format(None, Some(&project), &settings),
+            Ok(OutputFormat::Text)
+        );
+        assert_eq!(
+            resolve_output_format(Some("json"), Some(&project), &settings),
+            Ok(OutputFormat::Json)
+        );
+    }
+
+    #[test]
+    fn output_format_rejects_unknown_config_value() {
+        let settings = Settings {
+            default_output: Some("yaml".into()),
+            ..Default::default()
+        };
+        let err = resolve_output_format(None, None, &settings).unwrap_err();
+        assert!(err.contains("unknown output format"));
+    }
+
+    // ─── resolve_prompt ──────────────────────────────────────────
+
+    #[test]
+    fn prompt_max_bytes_counts_the_appended_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let appendix = dir.path().join("notes.txt");
+        std::fs::write(&appendix, "0123456789").unwrap();
+        let prompt = resolve_prompt(
+            Some("task".into()),
+            None,
+            Some(appendix.clone()),
+            encoding_rs::UTF_8,
+            &[],
+        )
+        .unwrap();
+
+        // "task" + "\n\n" + 10 bytes.
+        assert_eq!(prompt.len(), 16);
+        assert!(check_prompt_size(&prompt, Some(16)).is_ok());
+        assert!(check_prompt_size(&prompt, None).is_ok());
+        let err = check_prompt_size(&prompt, Some(15)).unwrap_err();
+        assert!(err.contains("16 bytes, over the 15-byte limit"), "{err}");
+    }
+
+    #[test]
+    fn resolve_prompt_passes_placeholders_through_without_any_var() {
+        let resolve =
+            |prompt: &str| resolve_prompt(Some(prompt.into()), None, None, encoding_rs::UTF_8, &[]);
+        for prompt in [
+            "use ${{ secrets.NPM_TOKEN }} in the workflow",
+            "render {{ user.name }} and {{#each items}}",
+            r"Literal \{{ticket}}",
+        ] {
+            assert_eq!(resolve(prompt).unwrap(), prompt);
+        }
+    }
+
+    // ─── render_prompt_template ──────────────────────────────────
+
+    fn vars(pairs: &[(&str, &str)]) -> Vec<(String, String)> {
+        pairs
+            .iter()
+            .map(|(k, v)| (k.to_string(), v.to_string()))
+            .collect()
+    }
+
+    #[test]
+    fn render_prompt_template_substitutes_variables() {
+        let vars = vars(&[
+            ("ticket", "ENG-42"),
+            ("branch", "main"),
+            ("ticket", "ENG-43"),
+        ]);
+        assert_eq!(
+            render_prompt_template("Fix {{ticket}} on {{ branch }}; see {{ticket}}.", &vars)
+                .unwrap(),
+            "Fix ENG-43 on main; see ENG-43."
+        );
+        // Not a placeholder: no key, or not a valid key.
+        assert_eq!(
+            render_prompt_template("{{}} {{ .Name }} {{open", &vars).unwrap(),
+            "{{}} {{ .Name }} {{open"
+        );
+    }
+
+    #[test]
+    fn render_prompt_template_escapes_braces() {
+        let vars = vars(&[("ticket", "ENG-42")]);
+        assert_eq!(
+            render_prompt_template(r"Literal \{{ticket}} vs {{ticket}}", &vars).unwrap(),
+            "Literal {{ticket}} vs ENG-42"
+        );
+    }
+
+    #[test]
+    fn render_prompt_template_lists_undefined_variables() {
+        let vars = vars(&[("ticket", "ENG-42")]);
+        let err = render_prompt_template("{{ticket}} {{branch}} {{owner}} {{branch}}", &vars)
+            .unwrap_err();
+        assert!(err.contains("branch, owner"), "{err}");
+        assert!(parse_template_var("novalue").is_err());
+        assert!(parse_template_var("bad key=x").is_err());
+        assert_eq!(
+            parse_template_var("a.b-c=x=y"),
+            Ok(("a.b-c".into(), "x=y".into()))
+        );
+    }
+
+    // ─── model_id_for ────────────────────────────────────────────
+
+    fn registry_with_opus() -> ModelRegistry {
+        ModelRegistry::from_toml(
+            "[models.opus]\ndescription = \"Opus\"\nprovider = \"anthropic\"\nclaude = \"claude-opus-4-6\"\n",
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn model_required_rejects_unresolved_names() {
+        let reg = registry_with_opus();
+        let res = reg.resolve("mystery-model", AgentKind::Claude);
+        let err = model_id_for(res, "mystery-model", AgentKind::Claude, &reg, true).unwrap_err();
+        assert!(err.contains("mystery-model"));
+
+        let res = reg.resolve("opus", AgentKind::Codex);
+        assert!(model_id_for(res, "opus", AgentKind::Codex, &reg, true).is_err());
+    }
+
+    #[test]
+    fn model_required_accepts_aliases_and_known_ids() {
+        let reg = registry_with_opus();
+        let res = reg.resolve("opus", AgentKind::Claude);
+        assert_eq!(
+            model_id_for(res, "opus", AgentKind::Claude, &reg, true),
+            Ok("claude-opus-4-6".to_string())
+        );
+
+        let res = reg.resolve("claude-opus-4-6", AgentKind::Claude);
+        assert_eq!(
+            model_id_for(res, "claude-opus-4-6", AgentKind::Claude, &reg, true),
+            Ok("claude-opus-4-6".to_string())
+        );
+    }
+
+    #[test]
+    fn deprecation_notice_is_once_per_typed_name() {
+        let reg = ModelRegistry::from_toml(concat!(
+            "[models.opus-old]\ndescription = \"Opus (old)\"\nprovider = \"anthropic\"\n",
+            "claude = \"claude-opus-4-1\"\ndeprecated = true\nreplacement = \"opus\"\n",
+        ))
+        .unwrap();
+        let mut warned = std::collections::BTreeSet::new();
+        let expected = "model `opus-old` is deprecated; use `opus` instead";
+
+        let notice =
+            |name: &str, warned: &mut _| deprecation_notice(&reg, name, AgentKind::Claude, warned);
+        assert_eq!(notice("opus-old", &mut warned).as_deref(), Some(expected));
+        assert_eq!(notice("opus-old", &mut warned), None);
+        // Another name for the same model still gets its own warning.
+        assert_eq!(
+            notice("claude-opus-4-1", &mut warned).as_deref(),
+            Some(expected)
+        );
+        assert_eq!(notice("claude-opus-4-1", &mut warned), None);
+        assert_eq!(notice("opus", &mut warned), None);
+    }
+
+    #[test]
+    fn model_passthrough_without_required() {
+        let reg = registry_with_opus();
+        let res = reg.resolve("mystery-model", AgentKind::Claude);
+        assert_eq!(
+            model_id_for(res, "mystery-model", AgentKind::Claude, &reg, false),
+            Ok("mystery-model".to_string())
+        );
+    }
+
+    // ─── render_comparison ───────────────────────────────────────
+
+    #[test]
+    fn comparison_report_has_stats_and_results() {
+        use harness::event::ResultEvent;
+        use harness::fanout::FanoutOutcome;
+
+        let outcomes = vec![
+            FanoutOutcome {
+                config: TaskConfig::new("hi", AgentKind::Claude),
+                events: vec![Event::Result(
+                    ResultEvent::new(true, "Claude says hi", "a")
+                        .duration_ms(1500)
+                        .total_cost_usd(0.02),
+                )],
+                error: None,
+            },
+            FanoutOutcome {
+                config: TaskConfig::new("hi", AgentKind::Codex),
+                events: Vec::new(),
+                error: Some(harness::error::Error::Other("spawn failed".into())),
+            },
+        ];
+
+        let report = render_comparison(&outcomes);
+        assert!(report.contains("| | Claude Code | Codex |"), "{report}");
+        assert!(report.contains("| Status | success | failed |"));
+        assert!(report.contains("| Duration | 1.5s | — |"));
+        assert!(report.contains("| Cost | $0.020 | — |"));
+        assert!(report.contains("Claude says hi"));
+        assert!(report.contains("**Error:** spawn failed"));
+    }
+
+    #[test]
+    fn agent_order_puts_listed_agents_first() {
+        use AgentKind::*;
+        assert_eq!(
+            order_agents(&[Claude, Codex, Gemini], &[Gemini, Claude]),
+            Ok(vec![Gemini, Claude, Codex])
+        );
+        assert_eq!(order_agents(&[Claude, Codex], &[]), Ok(vec![Claude, Codex]));
+        assert!(order_agents(&[Claude, Codex], &[Cursor])
+            .unwrap_err()
+            .contains("not in --compare"));
+    }
+
+    // ─── shell_script_from_events ─────────────────────────────────
+
+    #[test]
+    fn shell_script_contains_commands_in_order() {
+        use harness::event::*;
+        let events = vec![
+            Event::SessionStart(SessionStartEvent {
+                session_id: "s1".into(),
+                agent: "claude".into(),
+                model: None,
+                cwd: Some("/work dir".into()),
+                permission_mode: None,
+                agent_version: None,
+                timestamp_ms: 0,
+                raw_type: None,
+            }),
+            Event::ToolStart(ToolStartEvent {
+                call_id: "c1".into(),
+                tool_name: "Bash".into(),
+                input: Some(serde_json::json!({"command": "cargo build"})),
+                timestamp_ms: 0,
+                raw_type: None,
+            }),
+            Event::ToolStart(ToolStartEvent {
+                call_id: "c2".into(),
+                tool_name: "Read".into(),
+                input: Some(serde_json::json!({"file_path": "src/main.rs"})),
+                timestamp_ms: 0,
+                raw_type: None,
+            }),
+            Event::ToolStart(ToolStartEvent {
+                call_id: "c3".into(),
+                tool_name: "shell".into(),
+                input: Some(serde_json::json!({"command": ["ls", "-la", "my dir"]})),
+                timestamp_ms: 0,
+                raw_type: None,
+            }),
+        ];
+
+        let script = shell_script_from_events("s1", &events);
+        assert!(script.starts_with("#!/bin/sh\n"));
+        assert!(script.contains("cd '/work dir' || exit 1"));
+        assert!(script.contains("# call c1\ncargo build\n"));
+        assert!(script.contains("# call c3\nls -la 'my dir'\n"));
+        assert!(!script.contains("src/main.rs"));
+        assert!(script.find("cargo build").unwrap() < script.find("ls -la").unwrap());
+    }
+
+    // ─── --since context ─────────────────────────────────────────
+
+    #[test]
+    fn final_result_text_prefers_result() {
+        use harness::event::*;
+        let events = vec![
+            Event::Message(MessageEvent {
+                role: Role::Assistant,
+                text: "working on it".into(),
+                usage: None,
+                timestamp_ms: 0,
+                raw_type: None,
+            }),
+            Event::Result(ResultEvent::new(true, "Renamed the module", "s1")),
+        ];
+        assert_eq!(final_result_text(&events).as_deref(), Some("Renamed the module"));
+        assert_eq!(final_result_text(&events[..1]).as_deref(), Some("working on it"));
+        assert_eq!(final_result_text(&[]), None);
+    }
+
+    #[test]
+    fn prompt_with_context_layout() {
+        assert_eq!(
+            prompt_with_context("did X", "now do Y"),
+            "Previously:\ndid X\n\nNow:\nnow do Y"
+        );
+    }
+
+    // ─── wrap_text ───────────────────────────────────────────────
+
+    #[test]
+    fn wrap_text_at_column() {
+        let wrapped = wrap_text("the quick brown fox jumps over the lazy dog", 15);
+        assert_eq!(wrapped, "the quick brown\nfox jumps over\nthe lazy dog");
+        assert!(wrapped.lines().all(|l| l.chars().count() <= 15));
+    }
+
+    #[test]
+    fn wrap_text_preserves_newlines_and_long_words() {
+        assert_eq!(wrap_text("a b\n\nc", 10), "a b\n\nc");
+        assert_eq!(wrap_text("hi supercalifragilistic yo", 5), "hi\nsupercalifragilistic\nyo");
+    }
+
+    #[test]
+    fn wrap_text_leaves_code_and_lists_intact() {
+        let text = "Run this:\n```sh\ncargo test --workspace --all-features -- --nocapture\n```\n    indented code that is long\n- a list item that wraps\n12. numbered";
+        assert_eq!(
+            wrap_text(text, 12),
+            "Run this:\n```sh\ncargo test --workspace --all-features -- --nocapture\n```\n    indented code that is long\n- a list\n  item that\n  wraps\n12. numbered"
+        );
+    }
+
+    #[test]
+    fn parse_wrap_values() {
+        assert_eq!(parse_wrap("0"), Ok(None));
+        assert_eq!(parse_wrap("72"), Ok(Some(72)));
+        assert!(parse_wrap("wide").is_err());
+    }
+
+    #[test]
+    fn parse_env_pair_values() {
+        assert_eq!(parse_env_pair("A_1=x=y"), Ok(("A_1".into(), "x=y".into())));
+        assert_eq!(
+            parse_env_pair("EMPTY="),
+            Ok(("EMPTY".into(), String::new()))
+        );
+        assert!(parse_env_pair("NOVALUE").is_err());
+        assert!(parse_env_pair("=x").is_err());
+        assert!(parse_env_pair("1BAD=x").is_err());
+        assert!(parse_env_pair("MY-KEY=x").is_err());
+    }
+
+    // ─── remaining_until ─────────────────────────────────────────
+
+    #[test]
+    fn remaining_until_future_deadline() {
+        let deadline = harness::datetime::format_rfc3339(harness::event::now_ms() + 60_000);
+        let remaining = remaining_until(&deadline).unwrap();
+        assert!(remaining.as_secs() > 50 && remaining.as_secs() <= 60);
+    }
+
+    #[test]
+    fn remaining_until_rejects_past_and_malformed() {
+        let err = remaining_until("2000-01-01T00:00:00Z").unwrap_err();
+        assert!(err.contains("in the past"), "{err}");
+        let err = remaining_until("tomorrow").unwrap_err();
+        assert!(err.contains("invalid --deadline"), "{err}");
+    }
+
     // ─── format_token_count ──────────────────────────────────────
 
     #[test]
@@ -1323,4 +4571,141 @@ mod tests {
         assert_eq!(format_token_count(1_000_000), "1.0M");
         assert_eq!(format_token_count(2_500_000), "2.5M");
     }
+
+    // ─── text-mode flushing ──────────────────────────────────────
+
+    /// Records what was written and how often it was flushed.
+    #[derive(Clone, Default)]
+    struct FlushCounter {
+        written: std::sync::Arc<std::sync::Mutex<Vec<u8>>>,
+        flushes: std::sync::Arc<std::sync::atomic::AtomicUsize>,
+    }
+
+    impl std::io::Write for FlushCounter {
+        fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+            self.written.lock().unwrap().extend_from_slice(buf);
+            Ok(buf.len())
+        }
+
+        fn flush(&mut self) -> std::io::Result<()> {
+            self.flushes.fetch_add(1, std::sync::atomic::Ordering::SeqCst);
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn text_mode_flushes_after_each_delta_and_message() {
+        let counter = FlushCounter::default();
+        let mut tee = TeeWriter::new(None);
+        tee.stdout = Box::new(counter.clone());
+        let flushes = || counter.flushes.load(std::sync::atomic::Ordering::SeqCst);
+
+        for text in ["Hel", "lo"] {
+            let delta = Event::TextDelta(harness::event::TextDeltaEvent {
+                text: text.into(),
+                timestamp_ms: 0,
+                raw_type: None,
+            });
+            render_text_event(&mut tee, &delta, None);
+        }
+        assert_eq!(flushes(), 2);
+
+        let message = Event::Message(harness::event::MessageEvent {
+            role: harness::event::Role::Assistant,
+            text: "Hello".into(),
+            usage: None,
+            timestamp_ms: 0,
+            raw_type: None,
+        });
+        render_text_event(&mut tee, &message, None);
+        assert_eq!(flushes(), 3);
+        assert_eq!(&*counter.written.lock().unwrap(), b"HelloHello\n");
+    }
+
+    #[test]
+    fn reasoning_reaches_the_output_file_without_ansi_codes() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("out.txt");
+        let mut tee = TeeWriter::new(Some(&path));
+        tee.stdout = Box::new(std::io::sink());
+        let reasoning = Event::Reasoning(harness::event::ReasoningEvent {
+            text: "thinking".into(),
+            timestamp_ms: 0,
+            raw_type: None,
+        });
+        render_text_event(&mut tee, &reasoning, None);
+        render_markdown_event(&mut tee, &reasoning);
+        drop(tee);
+        let written = std::fs::read_to_string(&path).unwrap();
+        assert!(!written.contains('\x1b'), "{written:?}");
+        assert!(written.starts_with("thinking\n"), "{written:?}");
+        assert!(written.contains("> _thinking_"), "{written:?}");
+    }
+
+    // ─── --progress ──────────────────────────────────────────────
+
+    #[test]
+    fn progress_defaults_to_text_mode_on_a_terminal() {
+        assert!(progress_enabled(OutputFormat::Text, None, true, false));
+        assert!(!progress_enabled(OutputFormat::Text, None, false, false));
+        assert!(!progress_enabled(OutputFormat::Text, None, true, true));
+        assert!(!progress_enabled(
+            OutputFormat::StreamJson,
+            None,
+            true,
+            false
+        ));
+        assert!(progress_enabled(
+            OutputFormat::Text,
+            Some(true),
+            false,
+            true
+        ));
+        assert!(!progress_enabled(
+            OutputFormat::Text,
+            Some(false),
+            true,
+            false
+        ));
+        assert!(!progress_enabled(
+            OutputFormat::Json,
+            Some(true),
+            true,
+            false
+        ));
+    }
+
+    #[test]
+    fn progress_line_waits_for_line_breaks() {
+        let start = std::time::Instant::now();
+        let counter = FlushCounter::default();
+        let mut progress = ProgressLine::new("claude", start);
+        progress.out = Box::new(counter.clone());
+        let written = || String::from_utf8(counter.written.lock().unwrap().clone()).unwrap();
+
+        progress.observe(&Event::ToolStart(harness::event::ToolStartEvent {
+            call_id: "t1".into(),
+            tool_name: "Bash".into(),
+            input: None,
+            timestamp_ms: 0,
+            raw_type: None,
+        }));
+        let later = start + std::time::Duration::from_secs(12);
+        progress.draw(later);
+        assert_eq!(written(), "\r\x1b[2Krunning claude... 12s, $0.00, 1 tool");
+        assert_eq!(progress.next_tick(), later + ProgressLine::TICK);
+        progress.clear();
+        assert!(written().ends_with("1 tool\r\x1b[2K"));
+
+        // Mid-line, drawing would split the agent's text.
+        progress.observe(&Event::TextDelta(harness::event::TextDeltaEvent {
+            text: "Hel".into(),
+            timestamp_ms: 0,
+            raw_type: None,
+        }));
+        let before = written();
+        progress.draw(later);
+        progress.clear();
+        assert_eq!(written(), before);
+    }
 }
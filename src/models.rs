@@ -1,4 +1,4 @@
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
 
 use serde::{Deserialize, Serialize};
 
@@ -26,6 +26,26 @@ pub struct ModelEntry {
     /// Model ID for Cursor CLI.
     #[serde(default)]
     pub cursor: Option<String>,
+    /// Model ID for Gemini CLI.
+    #[serde(default)]
+    pub gemini: Option<String>,
+    /// The model is being retired; resolving it prints a warning.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub deprecated: bool,
+    /// Canonical name to suggest instead of a deprecated model.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub replacement: Option<String>,
+    /// USD per million input tokens, for estimating cost when the agent
+    /// reports usage without one.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub input_price_per_mtok: Option<f64>,
+    /// USD per million output tokens.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub output_price_per_mtok: Option<f64>,
+    /// USD per million input tokens read from the prompt cache; the full
+    /// input price applies when unset.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cached_input_price_per_mtok: Option<f64>,
 }
 
 impl ModelEntry {
@@ -36,6 +56,7 @@ impl ModelEntry {
             AgentKind::Codex => self.codex.as_deref(),
             AgentKind::OpenCode => self.opencode.as_deref(),
             AgentKind::Cursor => self.cursor.as_deref(),
+            AgentKind::Gemini => self.gemini.as_deref(),
         }
     }
 
@@ -54,10 +75,22 @@ impl ModelEntry {
         if self.cursor.is_some() {
             agents.push(AgentKind::Cursor);
         }
+        if self.gemini.is_some() {
+            agents.push(AgentKind::Gemini);
+        }
         agents
     }
 }
 
+/// Version of the `models.toml` layout this build reads.
+///
+/// A registry may declare its layout with a top-level `schema_version`
+/// (absent means 1). The version is bumped only for changes an older build
+/// would misread, not for new optional keys, and
+/// [`ModelRegistry::from_toml`] rejects anything newer than this, so a
+/// refreshed registry never replaces a cache this build can still read.
+pub const SCHEMA_VERSION: u32 = 1;
+
 /// The model registry — a map from canonical names to model entries.
 #[derive(Debug, Clone, Serialize, Deserialize, Default, PartialEq)]
 pub struct ModelRegistry {
@@ -77,6 +110,12 @@ pub enum ModelResolution {
     NoAgentMapping { canonical_name: String },
     /// Not found in registry — pass through as-is.
     Passthrough { raw: String },
+    /// A raw agent model ID maps to more than one canonical entry.
+    Ambiguous {
+        raw: String,
+        /// Matching canonical names, sorted.
+        candidates: Vec<String>,
+    },
 }
 
 impl ModelResolution {
@@ -86,26 +125,61 @@ impl ModelResolution {
             ModelResolution::Resolved { agent_id, .. } => agent_id,
             ModelResolution::NoAgentMapping { canonical_name } => canonical_name,
             ModelResolution::Passthrough { raw } => raw,
+            ModelResolution::Ambiguous { raw, .. } => raw,
         }
     }
 }
 
 impl ModelRegistry {
     /// Parse the builtin models.toml compiled into the binary.
+    ///
+    /// Falls back to an empty registry if the embedded data is malformed;
+    /// see [`builtin_checked`](Self::builtin_checked) to surface the error.
     pub fn builtin() -> Self {
-        let content = include_str!("../models.toml");
-        match toml::from_str(content) {
-            Ok(reg) => reg,
-            Err(e) => {
-                tracing::warn!("builtin models.toml is malformed: {e}");
-                Self::default()
-            }
+        Self::builtin_checked().unwrap_or_else(|e| {
+            tracing::warn!("{e}");
+            Self::default()
+        })
+    }
+
+    /// Parse the builtin models.toml, returning the parse error (or an empty
+    /// registry) as an error instead of swallowing it.
+    pub fn builtin_checked() -> Result<Self, String> {
+        let reg = Self::from_toml(include_str!("../models.toml"))
+            .map_err(|e| format!("builtin models.toml is malformed: {e}"))?;
+        if reg.models.is_empty() {
+            return Err("builtin models.toml defines no models".to_string());
         }
+        Ok(reg)
     }
 
     /// Parse a TOML string into a registry.
     pub fn from_toml(content: &str) -> Result<Self, String> {
-        toml::from_str(content).map_err(|e| e.to_string())
+        #[derive(Deserialize)]
+        struct Header {
+            schema_version: Option<u32>,
+        }
+        let header: Header = toml::from_str(content).map_err(|e| e.to_string())?;
+        match header.schema_version {
+            Some(v) if v > SCHEMA_VERSION => Err(format!(
+                "registry schema version {v} is newer than this harness supports \
+                 ({SCHEMA_VERSION}); upgrade harness to use it"
+            )),
+            _ => toml::from_str(content).map_err(|e| e.to_string()),
+        }
+    }
+
+    /// Serialize to TOML in the `models.toml` layout, with models sorted by
+    /// name so the output is stable.
+    pub fn to_toml_string(&self) -> Result<String, String> {
+        #[derive(Serialize)]
+        struct Sorted<'a> {
+            models: BTreeMap<&'a String, &'a ModelEntry>,
+        }
+        let sorted = Sorted {
+            models: self.models.iter().collect(),
+        };
+        toml::to_string_pretty(&sorted).map_err(|e| e.to_string())
     }
 
     /// Merge another registry into this one. `overrides` wins on conflicts.
@@ -137,6 +211,108 @@ impl ModelRegistry {
         }
     }
 
+    /// Warning text for a deprecated `canonical_name`, naming its replacement
+    /// when the entry has one. `None` for unknown or current models.
+    pub fn deprecation_warning(&self, canonical_name: &str) -> Option<String> {
+        let entry = self.models.get(canonical_name).filter(|e| e.deprecated)?;
+        Some(match entry.replacement {
+            Some(ref replacement) => {
+                format!("model `{canonical_name}` is deprecated; use `{replacement}` instead")
+            }
+            None => format!("model `{canonical_name}` is deprecated"),
+        })
+    }
+
+    /// Reverse lookup: find the canonical entry whose `agent` mapping is `agent_id`.
+    ///
+    /// Returns `Resolved` for a unique match, `Ambiguous` when several entries
+    /// share the ID, and `Passthrough` when none do.
+    pub fn reverse_resolve(&self, agent_id: &str, agent: AgentKind) -> ModelResolution {
+        let mut candidates: Vec<String> = self
+            .models
+            .iter()
+            .filter(|(_, entry)| entry.agent_model(agent) == Some(agent_id))
+            .map(|(name, _)| name.clone())
+            .collect();
+        candidates.sort();
+
+        match candidates.len() {
+            0 => ModelResolution::Passthrough {
+                raw: agent_id.to_string(),
+            },
+            1 => ModelResolution::Resolved {
+                canonical_name: candidates.remove(0),
+                agent_id: agent_id.to_string(),
+            },
+            _ => ModelResolution::Ambiguous {
+                raw: agent_id.to_string(),
+                candidates,
+            },
+        }
+    }
+
+    /// Estimate the USD cost of a run from the entry's pricing.
+    ///
+    /// `name` may be a canonical name or `agent`'s own model ID. Returns
+    /// `None` for unknown models and entries without both prices.
+    pub fn estimate_cost(
+        &self,
+        name: &str,
+        agent: AgentKind,
+        input_tokens: u64,
+        output_tokens: u64,
+    ) -> Option<f64> {
+        let entry = self.priced_entry(name, agent)?;
+        let input = entry.input_price_per_mtok?;
+        let output = entry.output_price_per_mtok?;
+        Some((input_tokens as f64 * input + output_tokens as f64 * output) / 1_000_000.0)
+    }
+
+    /// Like [`estimate_cost`](Self::estimate_cost) for an agent's reported
+    /// `usage`, billing cache reads at the cached input price.
+    ///
+    /// Codex and Gemini count cache reads inside `input_tokens`, so they are
+    /// taken out of the full-price count; the other agents report them
+    /// separately.
+    pub fn estimate_usage_cost(
+        &self,
+        name: &str,
+        agent: AgentKind,
+        usage: &crate::event::UsageData,
+    ) -> Option<f64> {
+        let entry = self.priced_entry(name, agent)?;
+        let input_price = entry.input_price_per_mtok?;
+        let output_price = entry.output_price_per_mtok?;
+        let cached_price = entry.cached_input_price_per_mtok.unwrap_or(input_price);
+
+        let cached = usage.cache_read_tokens.unwrap_or(0);
+        let input = usage.input_tokens.unwrap_or(0);
+        let full_price_input = match agent {
+            AgentKind::Codex | AgentKind::Gemini => input.saturating_sub(cached),
+            AgentKind::Claude | AgentKind::OpenCode | AgentKind::Cursor => input,
+        };
+        let output = usage.output_tokens.unwrap_or(0);
+        Some(
+            (full_price_input as f64 * input_price
+                + cached as f64 * cached_price
+                + output as f64 * output_price)
+                / 1_000_000.0,
+        )
+    }
+
+    /// The entry `name` (canonical, or `agent`'s own model ID) refers to.
+    fn priced_entry(&self, name: &str, agent: AgentKind) -> Option<&ModelEntry> {
+        match self.models.get(name) {
+            Some(entry) => Some(entry),
+            None => match self.reverse_resolve(name, agent) {
+                ModelResolution::Resolved { canonical_name, .. } => {
+                    self.models.get(&canonical_name)
+                }
+                _ => None,
+            },
+        }
+    }
+
     /// Return all canonical model names, sorted.
     pub fn names(&self) -> Vec<&str> {
         let mut names: Vec<&str> = self.models.keys().map(|s| s.as_str()).collect();
@@ -171,6 +347,13 @@ mod tests {
         assert!(reg.models.contains_key("opus"));
     }
 
+    #[test]
+    fn builtin_checked_embedded_registry_is_valid() {
+        let reg = ModelRegistry::builtin_checked().unwrap_or_else(|e| panic!("{e}"));
+        assert!(!reg.models.is_empty());
+        assert_eq!(reg.models.len(), ModelRegistry::builtin().models.len());
+    }
+
     #[test]
     fn builtin_opus_has_claude_mapping() {
         let reg = ModelRegistry::builtin();
@@ -250,6 +433,19 @@ claude = "test-id"
         assert!(reg.models.is_empty());
     }
 
+    #[test]
+    fn from_toml_rejects_a_newer_schema_version() {
+        let entry = "[models.m]\nclaude = \"m\"\n";
+        for version in [None, Some(SCHEMA_VERSION)] {
+            let header = version.map(|v| format!("schema_version = {v}\n"));
+            let toml = format!("{}{entry}", header.unwrap_or_default());
+            assert!(ModelRegistry::from_toml(&toml).is_ok(), "{toml}");
+        }
+        let newer = format!("schema_version = {}\n{entry}", SCHEMA_VERSION + 1);
+        let err = ModelRegistry::from_toml(&newer).unwrap_err();
+        assert!(err.contains("newer than this harness supports"), "{err}");
+    }
+
     #[test]
     fn from_toml_invalid() {
         let result = ModelRegistry::from_toml("not valid toml {{{{");
@@ -322,9 +518,10 @@ claude = "custom-id"
         let claude_models = reg.models_for_agent(AgentKind::Claude);
         // Claude should have opus
         assert!(claude_models.iter().any(|(name, _)| *name == "opus"));
-        // Codex should have no models (opus is not mapped for codex)
+        // Codex gets the OpenAI models but not opus
         let codex_models = reg.models_for_agent(AgentKind::Codex);
-        assert!(codex_models.is_empty());
+        assert!(codex_models.iter().any(|(name, _)| *name == "gpt-5"));
+        assert!(!codex_models.iter().any(|(name, _)| *name == "opus"));
     }
 
     #[test]
@@ -336,6 +533,12 @@ claude = "custom-id"
             codex: None,
             opencode: Some("o".into()),
             cursor: None,
+            gemini: None,
+            deprecated: false,
+            replacement: None,
+            input_price_per_mtok: None,
+            output_price_per_mtok: None,
+            cached_input_price_per_mtok: None,
         };
         let agents = entry.supported_agents();
         assert_eq!(agents, vec![AgentKind::Claude, AgentKind::OpenCode]);
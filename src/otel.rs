@@ -0,0 +1,169 @@
+//! OpenTelemetry spans for task runs (the `otel` cargo feature).
+//!
+//! [`trace_stream`] wraps a run's event stream in a root `harness.run` span
+//! with a child span per tool call, opened at its `ToolStart` and ended at
+//! the matching `ToolEnd`. Spans go to the global tracer provider, so the
+//! embedder decides where they are exported; the CLI installs an OTLP
+//! exporter with [`init_otlp_from_env`].
+
+use std::collections::HashMap;
+use std::time::Instant;
+
+use futures::StreamExt;
+use opentelemetry::global::{self, BoxedSpan, BoxedTracer};
+use opentelemetry::trace::{Span, Status, TraceContextExt, Tracer};
+use opentelemetry::{Context, KeyValue};
+use opentelemetry_sdk::trace::SdkTracerProvider;
+
+use crate::config::TaskConfig;
+use crate::event::Event;
+use crate::runner::EventStream;
+
+/// Name of the tracer harness spans are recorded with.
+pub const TRACER_NAME: &str = "harness";
+
+/// Record the run as spans while passing every event through unchanged.
+///
+/// The root span carries the agent, model, and prompt length, plus the
+/// outcome once the `Result` arrives. Tool spans record `success` and
+/// `duration_ms`; any still open when the stream ends are ended unfinished.
+pub fn trace_stream(stream: EventStream, config: &TaskConfig) -> EventStream {
+    let tracer = global::tracer(TRACER_NAME);
+    let mut attributes = vec![
+        KeyValue::new("harness.agent", config.agent.default_binary()),
+        KeyValue::new("harness.prompt_length", config.prompt.len() as i64),
+    ];
+    if let Some(ref model) = config.model {
+        attributes.push(KeyValue::new("harness.model", model.clone()));
+    }
+    let root = tracer
+        .span_builder("harness.run")
+        .with_attributes(attributes)
+        .start(&tracer);
+    let mut spans = RunSpans {
+        tracer,
+        root: Context::current_with_span(root),
+        tools: HashMap::new(),
+    };
+    Box::pin(stream.map(move |item| {
+        if let Ok(ref event) = item {
+            spans.observe(event);
+        }
+        item
+    }))
+}
+
+/// Open spans of one traced run; dropping it ends them.
+struct RunSpans {
+    tracer: BoxedTracer,
+    /// Context holding the root `harness.run` span, parent of tool spans.
+    root: Context,
+    /// Tool spans by call ID, with when the call started.
+    tools: HashMap<String, (BoxedSpan, Instant)>,
+}
+
+impl RunSpans {
+    fn observe(&mut self, event: &Event) {
+        match event {
+            Event::ToolStart(t) => {
+                let span = self
+                    .tracer
+                    .span_builder(format!("tool {}", t.tool_name))
+                    .with_attributes([
+                        KeyValue::new("harness.tool.name", t.tool_name.clone()),
+                        KeyValue::new("harness.tool.call_id", t.call_id.clone()),
+                    ])
+                    .start_with_context(&self.tracer, &self.root);
+                self.tools.insert(t.call_id.clone(), (span, Instant::now()));
+            }
+            Event::ToolEnd(t) => {
+                let Some((mut span, started)) = self.tools.remove(&t.call_id) else {
+                    return;
+                };
+                span.set_attribute(KeyValue::new("harness.tool.success", t.success));
+                span.set_attribute(KeyValue::new(
+                    "harness.tool.duration_ms",
+                    started.elapsed().as_millis() as i64,
+                ));
+                if !t.success {
+                    span.set_status(Status::error("tool call failed"));
+                }
+                span.end();
+            }
+            Event::Error(e) => {
+                self.root.span().add_event(
+                    "harness.error",
+                    vec![KeyValue::new("message", e.message.clone())],
+                );
+            }
+            Event::Result(r) => {
+                let root = self.root.span();
+                root.set_attribute(KeyValue::new("harness.success", r.success));
+                if let Some(cost) = r.total_cost_usd {
+                    root.set_attribute(KeyValue::new("harness.cost_usd", cost));
+                }
+                if !r.success {
+                    root.set_status(Status::error(r.text.clone()));
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+impl Drop for RunSpans {
+    fn drop(&mut self) {
+        for (_, (mut span, _)) in self.tools.drain() {
+            span.end();
+        }
+        self.root.span().end();
+    }
+}
+
+/// Ends the exporter installed by [`init_otlp_from_env`] when dropped,
+/// flushing spans still buffered.
+pub struct OtlpGuard(SdkTracerProvider);
+
+impl Drop for OtlpGuard {
+    fn drop(&mut self) {
+        if let Err(e) = self.0.shutdown() {
+            tracing::warn!("failed to flush OpenTelemetry spans: {e}");
+        }
+    }
+}
+
+/// Install a global OTLP/HTTP span exporter when `OTEL_EXPORTER_OTLP_ENDPOINT`
+/// (or `OTEL_EXPORTER_OTLP_TRACES_ENDPOINT`) is set; the other standard
+/// `OTEL_*` variables apply too. Returns `None` when unset or if the exporter
+/// can't be built, leaving spans unexported.
+pub fn init_otlp_from_env() -> Option<OtlpGuard> {
+    let configured = [
+        "OTEL_EXPORTER_OTLP_ENDPOINT",
+        "OTEL_EXPORTER_OTLP_TRACES_ENDPOINT",
+    ]
+    .iter()
+    .any(|var| std::env::var_os(var).is_some_and(|v| !v.is_empty()));
+    if !configured {
+        return None;
+    }
+    let exporter = match opentelemetry_otlp::SpanExporter::builder()
+        .with_http()
+        .build()
+    {
+        Ok(exporter) => exporter,
+        Err(e) => {
+            tracing::warn!("failed to set up the OTLP exporter: {e}");
+            return None;
+        }
+    };
+    let provider = SdkTracerProvider::builder()
+        .with_batch_exporter(exporter)
+        .with_resource(
+            opentelemetry_sdk::Resource::builder()
+                .with_service_name(TRACER_NAME)
+                .build(),
+        )
+        .build();
+    global::set_tracer_provider(provider.clone());
+    Some(OtlpGuard(provider))
+}
@@ -1,4 +1,5 @@
-use std::path::Path;
+use std::ffi::OsString;
+use std::path::{Path, PathBuf};
 use std::process::Stdio;
 use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::Arc;
@@ -9,7 +10,7 @@ use tokio_util::sync::CancellationToken;
 
 use crate::config::TaskConfig;
 use crate::error::{Error, Result};
-use crate::event::Event;
+use crate::event::{now_ms, Event};
 use crate::runner::{AgentRunner, EventStream};
 
 /// Maximum bytes we'll collect from stderr before truncating.
@@ -99,6 +100,18 @@ pub struct StreamHandle {
     pub cancel_token: CancellationToken,
 }
 
+/// The full argv to spawn: the config's `command_prefix` (if any), then the
+/// agent binary, then its arguments.
+pub fn command_argv(config: &TaskConfig, binary: &Path, args: &[String]) -> Vec<OsString> {
+    config
+        .command_prefix
+        .iter()
+        .map(OsString::from)
+        .chain(std::iter::once(binary.as_os_str().to_owned()))
+        .chain(args.iter().map(OsString::from))
+        .collect()
+}
+
 /// Spawns an agent subprocess and returns a `StreamHandle` containing an
 /// `EventStream` and a `CancellationToken`.
 ///
@@ -122,23 +135,25 @@ where
     let args = runner.build_args(config);
     let env_vars = runner.build_env(config);
 
-    let cwd = config
-        .cwd
-        .clone()
-        .unwrap_or_else(|| std::env::current_dir().unwrap_or_default());
-
-    validate_cwd(&cwd)?;
+    let cwd = resolve_cwd(
+        &config
+            .cwd
+            .clone()
+            .unwrap_or_else(|| std::env::current_dir().unwrap_or_default()),
+    )?;
 
     tracing::debug!(
         agent = runner.name(),
         binary = %binary.display(),
+        prefix = ?config.command_prefix,
         args = ?args,
         cwd = %cwd.display(),
         "spawning agent process"
     );
 
-    let mut cmd = Command::new(&binary);
-    cmd.args(&args)
+    let argv = command_argv(config, &binary, &args);
+    let mut cmd = Command::new(&argv[0]);
+    cmd.args(&argv[1..])
         .current_dir(&cwd)
         .stdin(Stdio::null())
         .stdout(Stdio::piped())
@@ -174,13 +189,24 @@ where
         .take()
         .ok_or_else(|| Error::Other("failed to capture stderr".into()))?;
 
-    // Spawn a task to collect stderr for error reporting (capped at MAX_STDERR_BYTES).
+    // Spawn a task to collect stderr for error reporting (capped at MAX_STDERR_BYTES),
+    // forwarding each line live to our own stderr if requested.
+    let live_prefix = config
+        .stream_stderr
+        .then(|| format!("[{}] ", runner.name()));
     let stderr_handle = tokio::spawn(async move {
         let reader = BufReader::new(stderr);
         let mut lines = reader.lines();
         let mut buf = String::new();
         while let Ok(Some(line)) = lines.next_line().await {
+            if let Some(ref prefix) = live_prefix {
+                eprintln!("{prefix}{line}");
+            }
             if buf.len() >= MAX_STDERR_BYTES {
+                // Keep draining only while forwarding live.
+                if live_prefix.is_some() {
+                    continue;
+                }
                 break;
             }
             if !buf.is_empty() {
@@ -189,7 +215,7 @@ where
             let remaining = MAX_STDERR_BYTES - buf.len();
             if line.len() > remaining {
                 buf.push_str(&line[..remaining]);
-                break;
+                continue;
             }
             buf.push_str(&line);
         }
@@ -214,6 +240,10 @@ where
         // Keep guard alive for the duration of this task.
         let _guard = guard;
 
+        // Receive time of the latest event the parser left unstamped, so a
+        // wall clock stepping back cannot reorder timestamps.
+        let mut receive_clock = 0u64;
+
         loop {
             tokio::select! {
                 _ = token_for_task.cancelled() => {
@@ -227,11 +257,18 @@ where
                             if line.trim().is_empty() {
                                 continue;
                             }
-                            let events = parse_line(&line);
-                            for result in events {
-                                let stamped = result.map(|e| e.stamp());
-                                if tx.send(stamped).await.is_err() {
-                                    return; // receiver dropped
+                            for chunk in split_json_objects(&line) {
+                                for result in parse_line(chunk) {
+                                    let stamped = result.map(|mut e| {
+                                        if e.timestamp_ms() == 0 {
+                                            receive_clock = receive_clock.max(now_ms());
+                                            e.set_timestamp_ms(receive_clock);
+                                        }
+                                        e
+                                    });
+                                    if tx.send(stamped).await.is_err() {
+                                        return; // receiver dropped
+                                    }
                                 }
                             }
                         }
@@ -282,15 +319,83 @@ where
     })
 }
 
-fn validate_cwd(cwd: &Path) -> Result<()> {
-    if !cwd.exists() {
-        return Err(Error::InvalidWorkDir(cwd.to_path_buf()));
+/// Split a line holding several concatenated JSON objects (`{...}{...}`,
+/// as produced by some agents or by output buffering) into one slice per
+/// object.
+///
+/// Anything that isn't purely a sequence of JSON objects — a single object,
+/// plain text, malformed JSON — is returned whole so the parser sees it
+/// unchanged.
+pub(crate) fn split_json_objects(line: &str) -> Vec<&str> {
+    let mut stream = serde_json::Deserializer::from_str(line).into_iter::<serde_json::Value>();
+    let mut chunks = Vec::new();
+    let mut start = 0;
+    while let Some(item) = stream.next() {
+        match item {
+            Ok(value) if value.is_object() => {
+                let end = stream.byte_offset();
+                chunks.push(line[start..end].trim());
+                start = end;
+            }
+            _ => return vec![line],
+        }
     }
+    if chunks.len() > 1 {
+        chunks
+    } else {
+        vec![line]
+    }
+}
+
+/// Check that `cwd` is an existing directory and return its canonical path,
+/// so a relative working directory means the same thing everywhere it is used.
+pub(crate) fn resolve_cwd(cwd: &Path) -> Result<PathBuf> {
     if !cwd.is_dir() {
-        return Err(Error::Other(format!(
-            "working directory is not a directory: {}",
-            cwd.display()
-        )));
+        return Err(Error::InvalidWorkDir(cwd.to_path_buf()));
+    }
+    cwd.canonicalize()
+        .map_err(|_| Error::InvalidWorkDir(cwd.to_path_buf()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn command_argv_without_prefix() {
+        let config = TaskConfig::new("hi", crate::config::AgentKind::Claude);
+        let argv = command_argv(&config, Path::new("/bin/claude"), &["-p".into(), "hi".into()]);
+        assert_eq!(argv, ["/bin/claude", "-p", "hi"]);
+    }
+
+    #[test]
+    fn command_argv_prefix_precedes_binary() {
+        let mut config = TaskConfig::new("hi", crate::config::AgentKind::Claude);
+        config.command_prefix = vec!["nice".into(), "-n10".into()];
+        let argv = command_argv(&config, Path::new("/bin/claude"), &["-p".into(), "hi".into()]);
+        assert_eq!(argv, ["nice", "-n10", "/bin/claude", "-p", "hi"]);
+    }
+
+    #[test]
+    fn split_single_object_unchanged() {
+        let line = r#"{"type":"a"}"#;
+        assert_eq!(split_json_objects(line), vec![line]);
+    }
+
+    #[test]
+    fn split_concatenated_objects() {
+        let line = r#"{"type":"a","n":1}{"type":"b"} {"type":"c","s":"}{"}"#;
+        assert_eq!(
+            split_json_objects(line),
+            vec![r#"{"type":"a","n":1}"#, r#"{"type":"b"}"#, r#"{"type":"c","s":"}{"}"#]
+        );
+    }
+
+    #[test]
+    fn split_leaves_non_json_whole() {
+        assert_eq!(split_json_objects("plain progress text"), vec!["plain progress text"]);
+        let broken = r#"{"type":"a"}{"type":"#;
+        assert_eq!(split_json_objects(broken), vec![broken]);
+        assert_eq!(split_json_objects("[1,2]"), vec!["[1,2]"]);
     }
-    Ok(())
 }
@@ -1,58 +1,150 @@
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
 
 use crate::models::ModelRegistry;
 
-/// URL to fetch the canonical models.toml from GitHub.
-const REGISTRY_URL: &str =
+/// URL to fetch the canonical models.toml from GitHub. A mirror can be
+/// used instead via `registry_url` in `harness.toml` or `--source`.
+pub const REGISTRY_URL: &str =
     "https://raw.githubusercontent.com/ayshptk/harness-cli/main/models.toml";
 
-/// Cache TTL in seconds (24 hours).
-const TTL_SECS: u64 = 86400;
+/// Default cache TTL in seconds (24 hours); `registry_ttl_secs` in
+/// `harness.toml` overrides it.
+pub const DEFAULT_TTL_SECS: u64 = 86400;
+
+/// Where [`load_canonical_with`] fetches the registry from and how long its
+/// cache stays fresh.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct RegistryOptions {
+    /// Registry URL (`https://` or `file://`); GitHub when `None`.
+    pub source: Option<String>,
+    /// Age after which the cache is refetched; [`DEFAULT_TTL_SECS`] when `None`.
+    pub ttl: Option<Duration>,
+}
 
 /// HTTP request timeout in seconds.
 const FETCH_TIMEOUT_SECS: u64 = 5;
 
+/// Set by `--offline`: never fetch the registry over the network.
+static OFFLINE: AtomicBool = AtomicBool::new(false);
+
+/// Turn network fetches of the registry off or on (`--offline`). While
+/// offline, [`load_canonical`] uses the cache or builtin registry and
+/// [`force_update`] fails.
+pub fn set_offline(offline: bool) {
+    OFFLINE.store(offline, Ordering::Relaxed);
+}
+
 /// Path to the cached registry: `~/.harness/models.toml`.
 pub fn canonical_path() -> Option<PathBuf> {
     dirs::home_dir().map(|d| d.join(".harness").join("models.toml"))
 }
 
+/// Path to the cache of the registry fetched from `source`: the
+/// [`canonical_path`] for GitHub, and `~/.harness/models-<hash>.toml` for a
+/// mirror, so switching sources never serves another source's cache.
+pub fn cache_path(source: Option<&str>) -> Option<PathBuf> {
+    let path = canonical_path()?;
+    Some(match source.filter(|s| *s != REGISTRY_URL) {
+        Some(source) => path.with_file_name(format!("models-{:016x}.toml", fnv1a(source))),
+        None => path,
+    })
+}
+
+/// FNV-1a: a hash that, unlike `std`'s, stays the same across Rust releases.
+fn fnv1a(s: &str) -> u64 {
+    s.bytes().fold(0xcbf2_9ce4_8422_2325, |hash, b| {
+        (hash ^ u64::from(b)).wrapping_mul(0x0100_0000_01b3)
+    })
+}
+
+/// Path of the file recording when the cache at `path` was fetched:
+/// `models.toml.fetched-at`, holding Unix seconds.
+fn fetched_at_path(path: &Path) -> PathBuf {
+    path.with_extension("toml.fetched-at")
+}
+
+/// When the cache at `path` was last fetched. Caches written before the
+/// timestamp file existed fall back to the file's modification time.
+fn fetched_at(path: &Path) -> Option<SystemTime> {
+    let stamp = std::fs::read_to_string(fetched_at_path(path))
+        .ok()
+        .and_then(|s| s.trim().parse::<u64>().ok())
+        .map(|secs| UNIX_EPOCH + Duration::from_secs(secs));
+    stamp.or_else(|| std::fs::metadata(path).and_then(|m| m.modified()).ok())
+}
+
 /// Load the canonical model registry.
 ///
 /// Resolution order:
-/// 1. Load from `~/.harness/models.toml` if it exists and is fresh (< 24h old).
-/// 2. If missing or stale, attempt to fetch from GitHub and cache.
+/// 1. Load from `~/.harness/models.toml` if it exists and was fetched less
+///    than [`DEFAULT_TTL_SECS`] ago.
+/// 2. If missing or stale, attempt to fetch from GitHub and cache — unless
+///    offline.
 /// 3. If fetch fails, use cached version (even if stale).
 /// 4. If no cache at all, fall back to the builtin registry.
 ///
 /// This function **never** fails — it always returns a usable registry.
 pub fn load_canonical() -> ModelRegistry {
-    let path = match canonical_path() {
+    load_canonical_with(&RegistryOptions::default())
+}
+
+/// [`load_canonical`] fetching from `source` (GitHub when `None`) into that
+/// source's [`cache_path`].
+pub fn load_canonical_from(source: Option<&str>) -> ModelRegistry {
+    load_canonical_with(&RegistryOptions {
+        source: source.map(str::to_string),
+        ttl: None,
+    })
+}
+
+/// [`load_canonical`] with the source and cache TTL taken from `options`.
+pub fn load_canonical_with(options: &RegistryOptions) -> ModelRegistry {
+    let source = options.source.as_deref();
+    let path = match cache_path(source) {
         Some(p) => p,
         None => {
             tracing::debug!("cannot determine home directory, using builtin registry");
             return ModelRegistry::builtin();
         }
     };
+    let ttl = options.ttl.unwrap_or(Duration::from_secs(DEFAULT_TTL_SECS));
+    let offline = OFFLINE.load(Ordering::Relaxed);
+    load_canonical_at(&path, ttl, SystemTime::now(), offline, || {
+        fetch_registry_content(source.unwrap_or(REGISTRY_URL))
+    })
+}
 
+/// [`load_canonical`] against the cache at `path`, with the clock and the
+/// fetch swapped out.
+fn load_canonical_at(
+    path: &Path,
+    ttl: Duration,
+    now: SystemTime,
+    offline: bool,
+    fetch: impl FnOnce() -> Result<String, String>,
+) -> ModelRegistry {
     // If the file exists and is fresh, use it.
-    if path.exists() && !is_stale(&path) {
-        if let Some(reg) = load_from_disk(&path) {
+    if path.exists() && !is_stale(fetched_at(path), ttl, now) {
+        if let Some(reg) = load_from_disk(path) {
             return reg;
         }
     }
 
     // Try to fetch and cache a fresh copy.
-    match fetch_and_cache(&path) {
-        Ok(reg) => return reg,
-        Err(e) => {
-            tracing::debug!("failed to fetch models registry: {e}");
+    if !offline {
+        match fetch_and_cache_with(path, fetch, now) {
+            Ok(reg) => return reg,
+            Err(e) => {
+                tracing::debug!("failed to fetch models registry: {e}");
+            }
         }
     }
 
     // Fall back to stale cache.
     if path.exists() {
-        if let Some(reg) = load_from_disk(&path) {
+        if let Some(reg) = load_from_disk(path) {
             tracing::debug!("using stale cached registry");
             return reg;
         }
@@ -63,34 +155,67 @@ pub fn load_canonical() -> ModelRegistry {
     ModelRegistry::builtin()
 }
 
+/// Load the cached registry over the builtin one without touching the
+/// network, whatever the cache's age. Used where a fetch would be unwelcome,
+/// e.g. pricing lookups inside the library.
+pub fn load_cached() -> ModelRegistry {
+    let builtin = ModelRegistry::builtin();
+    match canonical_path().and_then(|p| load_from_disk(&p)) {
+        Some(cached) => builtin.merge(&cached),
+        None => builtin,
+    }
+}
+
 /// Force-fetch the registry from GitHub and cache it.
-/// Returns a human-readable status message.
+/// Returns a human-readable status message. On failure the existing cache
+/// is left untouched.
 pub fn force_update() -> Result<String, String> {
-    let path = canonical_path().ok_or("cannot determine home directory")?;
-    match fetch_and_cache(&path) {
-        Ok(_) => Ok(format!("Updated registry at {}", path.display())),
-        Err(e) => Err(format!("failed to fetch: {e}")),
+    force_update_from(None)
+}
+
+/// [`force_update`] from `source` (GitHub when `None`) into that source's
+/// [`cache_path`].
+pub fn force_update_from(source: Option<&str>) -> Result<String, String> {
+    if OFFLINE.load(Ordering::Relaxed) {
+        return Err("offline mode, not fetching the registry".to_string());
+    }
+    let path = cache_path(source).ok_or("cannot determine home directory")?;
+    let url = source.unwrap_or(REGISTRY_URL);
+    match fetch_and_cache(&path, source) {
+        Ok(_) => Ok(format!("Updated registry at {} from {url}", path.display())),
+        Err(e) => Err(format!(
+            "failed to fetch {url}: {e} (kept the cached registry at {})",
+            path.display()
+        )),
     }
 }
 
-/// Check if the cached file is older than `TTL_SECS`.
-fn is_stale(path: &std::path::Path) -> bool {
-    let metadata = match std::fs::metadata(path) {
-        Ok(m) => m,
-        Err(_) => return true,
-    };
-    let modified = match metadata.modified() {
-        Ok(t) => t,
-        Err(_) => return true,
-    };
-    let age = std::time::SystemTime::now()
-        .duration_since(modified)
-        .unwrap_or_default();
-    age.as_secs() > TTL_SECS
+/// Merge `imported` into the cached registry — or into the builtin one when
+/// nothing is cached yet — with `imported` winning on conflicts. Returns the
+/// cache path. A later refresh replaces the cache, imports included.
+pub fn import(imported: &ModelRegistry) -> Result<PathBuf, String> {
+    import_into(None, imported)
+}
+
+/// [`import`] into the cache of the registry fetched from `source`.
+pub fn import_into(source: Option<&str>, imported: &ModelRegistry) -> Result<PathBuf, String> {
+    let path = cache_path(source).ok_or("cannot determine home directory")?;
+    let base = load_from_disk(&path).unwrap_or_else(ModelRegistry::builtin);
+    write_cache(&path, &base.merge(imported).to_toml_string()?)?;
+    Ok(path)
+}
+
+/// Whether a cache fetched at `fetched_at` is older than `ttl` at `now`.
+/// An unknown fetch time counts as stale.
+fn is_stale(fetched_at: Option<SystemTime>, ttl: Duration, now: SystemTime) -> bool {
+    match fetched_at {
+        Some(at) => now.duration_since(at).unwrap_or_default() > ttl,
+        None => true,
+    }
 }
 
 /// Load and parse a registry from disk, returning `None` on any error.
-fn load_from_disk(path: &std::path::Path) -> Option<ModelRegistry> {
+fn load_from_disk(path: &Path) -> Option<ModelRegistry> {
     let content = std::fs::read_to_string(path).ok()?;
     match ModelRegistry::from_toml(&content) {
         Ok(reg) => Some(reg),
@@ -101,11 +226,41 @@ fn load_from_disk(path: &std::path::Path) -> Option<ModelRegistry> {
     }
 }
 
-/// Fetch from GitHub, parse, and atomically write to disk.
-fn fetch_and_cache(path: &std::path::Path) -> Result<ModelRegistry, String> {
-    let body = fetch_registry_content()?;
+/// Fetch from `source` (GitHub when `None`), parse, and atomically write to disk.
+fn fetch_and_cache(path: &Path, source: Option<&str>) -> Result<ModelRegistry, String> {
+    fetch_and_cache_with(
+        path,
+        || fetch_registry_content(source.unwrap_or(REGISTRY_URL)),
+        SystemTime::now(),
+    )
+}
+
+/// [`fetch_and_cache`] with the HTTP fetch swapped out. The cache is only
+/// replaced once the fetched content parses; `now` is recorded as its
+/// fetch time.
+fn fetch_and_cache_with(
+    path: &Path,
+    fetch: impl FnOnce() -> Result<String, String>,
+    now: SystemTime,
+) -> Result<ModelRegistry, String> {
+    let body = fetch()?;
     let reg = ModelRegistry::from_toml(&body)?;
+    if reg.models.is_empty() {
+        return Err("fetched registry defines no models".to_string());
+    }
+
+    write_cache(path, &body)?;
+    let secs = now.duration_since(UNIX_EPOCH).unwrap_or_default().as_secs();
+    if let Err(e) = std::fs::write(fetched_at_path(path), format!("{secs}\n")) {
+        tracing::debug!("failed to record registry fetch time: {e}");
+    }
 
+    tracing::debug!("cached registry at {}", path.display());
+    Ok(reg)
+}
+
+/// Atomically replace the cache at `path` with `body`.
+fn write_cache(path: &Path, body: &str) -> Result<(), String> {
     // Ensure parent directory exists.
     if let Some(parent) = path.parent() {
         std::fs::create_dir_all(parent).map_err(|e| format!("mkdir failed: {e}"))?;
@@ -113,21 +268,22 @@ fn fetch_and_cache(path: &std::path::Path) -> Result<ModelRegistry, String> {
 
     // Atomic write: write to .tmp, then rename.
     let tmp_path = path.with_extension("toml.tmp");
-    std::fs::write(&tmp_path, &body).map_err(|e| format!("write failed: {e}"))?;
-    std::fs::rename(&tmp_path, path).map_err(|e| format!("rename failed: {e}"))?;
-
-    tracing::debug!("cached registry at {}", path.display());
-    Ok(reg)
+    std::fs::write(&tmp_path, body).map_err(|e| format!("write failed: {e}"))?;
+    std::fs::rename(&tmp_path, path).map_err(|e| format!("rename failed: {e}"))
 }
 
-/// HTTP GET the registry content.
-fn fetch_registry_content() -> Result<String, String> {
+/// Read the registry content from `url`: HTTP(S) GET, or a local read for
+/// `file://` URLs.
+fn fetch_registry_content(url: &str) -> Result<String, String> {
+    if let Some(path) = url.strip_prefix("file://") {
+        return std::fs::read_to_string(path).map_err(|e| format!("failed to read {path}: {e}"));
+    }
     let agent = ureq::Agent::config_builder()
         .timeout_global(Some(std::time::Duration::from_secs(FETCH_TIMEOUT_SECS)))
         .build()
         .new_agent();
     let body = agent
-        .get(REGISTRY_URL)
+        .get(url)
         .call()
         .map_err(|e| format!("HTTP request failed: {e}"))?
         .body_mut()
@@ -148,9 +304,92 @@ mod tests {
         }
     }
 
+    #[test]
+    fn cache_path_is_keyed_by_source() {
+        let Some(canonical) = canonical_path() else {
+            return;
+        };
+        assert_eq!(cache_path(None), Some(canonical.clone()));
+        assert_eq!(cache_path(Some(REGISTRY_URL)), Some(canonical.clone()));
+
+        let a = cache_path(Some("https://a.example/models.toml")).unwrap();
+        let b = cache_path(Some("https://b.example/models.toml")).unwrap();
+        assert_ne!(a, canonical);
+        assert_ne!(a, b);
+        assert_eq!(a.parent(), canonical.parent());
+        assert_eq!(cache_path(Some("https://a.example/models.toml")), Some(a));
+    }
+
     #[test]
     fn is_stale_missing_file() {
-        assert!(is_stale(std::path::Path::new("/nonexistent/file")));
+        let fetched = fetched_at(Path::new("/nonexistent/file"));
+        assert!(is_stale(
+            fetched,
+            Duration::from_secs(60),
+            SystemTime::now()
+        ));
+    }
+
+    #[test]
+    fn is_stale_after_ttl_expires() {
+        let fetched = UNIX_EPOCH + Duration::from_secs(1_000_000);
+        let ttl = Duration::from_secs(3600);
+        assert!(!is_stale(Some(fetched), ttl, fetched));
+        assert!(!is_stale(Some(fetched), ttl, fetched + ttl));
+        assert!(is_stale(
+            Some(fetched),
+            ttl,
+            fetched + ttl + Duration::from_secs(1)
+        ));
+        // A fetch time in the future (clock skew) is not stale.
+        assert!(!is_stale(Some(fetched), ttl, fetched - ttl));
+    }
+
+    #[test]
+    fn load_canonical_refetches_only_once_stale() {
+        let tmp = tempfile::tempdir().unwrap();
+        let path = tmp.path().join("models.toml");
+        let t0 = UNIX_EPOCH + Duration::from_secs(1_000_000);
+        let ttl = Duration::from_secs(3600);
+        let body = |id: &str| format!("[models.fast]\nclaude = \"{id}\"\n");
+        let id = |reg: &ModelRegistry| {
+            reg.resolve("fast", crate::config::AgentKind::Claude)
+                .model_id()
+                .to_string()
+        };
+
+        let reg = load_canonical_at(&path, ttl, t0, false, || Ok(body("v1")));
+        assert_eq!(id(&reg), "v1");
+        assert_eq!(fetched_at(&path), Some(t0));
+
+        // Within the TTL the cache is used without fetching.
+        let reg = load_canonical_at(&path, ttl, t0 + ttl, false, || {
+            panic!("fetched a fresh cache")
+        });
+        assert_eq!(id(&reg), "v1");
+
+        let later = t0 + ttl + Duration::from_secs(1);
+        let reg = load_canonical_at(&path, ttl, later, false, || Ok(body("v2")));
+        assert_eq!(id(&reg), "v2");
+        assert_eq!(fetched_at(&path), Some(later));
+    }
+
+    #[test]
+    fn offline_never_fetches() {
+        let tmp = tempfile::tempdir().unwrap();
+        let path = tmp.path().join("models.toml");
+        let ttl = Duration::from_secs(3600);
+        let now = UNIX_EPOCH + Duration::from_secs(1_000_000);
+
+        // No cache: the builtin registry.
+        let reg = load_canonical_at(&path, ttl, now, true, || panic!("fetched while offline"));
+        assert_eq!(reg.models.len(), ModelRegistry::builtin().models.len());
+
+        // A stale cache is used as-is.
+        std::fs::write(&path, "[models.fast]\nclaude = \"cached-id\"\n").unwrap();
+        std::fs::write(fetched_at_path(&path), "0\n").unwrap();
+        let reg = load_canonical_at(&path, ttl, now, true, || panic!("fetched while offline"));
+        assert!(reg.models.contains_key("fast"));
     }
 
     #[test]
@@ -178,6 +417,52 @@ claude = "test-id"
         assert!(load_from_disk(tmp.path()).is_none());
     }
 
+    #[test]
+    fn fetch_and_cache_replaces_stale_mappings() {
+        let tmp = tempfile::tempdir().unwrap();
+        let path = tmp.path().join("models.toml");
+        std::fs::write(&path, "[models.fast]\nclaude = \"old-id\"\n").unwrap();
+
+        let fetched = || Ok("[models.fast]\nclaude = \"new-id\"\n".to_string());
+        fetch_and_cache_with(&path, fetched, SystemTime::now()).unwrap();
+        let reg = load_from_disk(&path).unwrap();
+        assert_eq!(
+            reg.resolve("fast", crate::config::AgentKind::Claude)
+                .model_id(),
+            "new-id"
+        );
+
+        // A failed fetch or unparseable body leaves the cache alone.
+        let now = SystemTime::now();
+        assert!(fetch_and_cache_with(&path, || Err("no network".into()), now).is_err());
+        assert!(fetch_and_cache_with(&path, || Ok("{{ nope".into()), now).is_err());
+        assert!(std::fs::read_to_string(&path).unwrap().contains("new-id"));
+    }
+
+    #[test]
+    fn fetch_and_cache_reads_file_source() {
+        let tmp = tempfile::tempdir().unwrap();
+        let mirror = tmp.path().join("mirror.toml");
+        std::fs::write(&mirror, "[models.fast]\nclaude = \"mirror-id\"\n").unwrap();
+        let path = tmp.path().join("cache").join("models.toml");
+
+        let source = format!("file://{}", mirror.display());
+        let reg = fetch_and_cache(&path, Some(&source)).unwrap();
+        assert!(reg.models.contains_key("fast"));
+        assert!(std::fs::read_to_string(&path)
+            .unwrap()
+            .contains("mirror-id"));
+
+        // A missing mirror or one with no models keeps the cache.
+        let missing = format!("file://{}", tmp.path().join("gone.toml").display());
+        assert!(fetch_and_cache(&path, Some(&missing)).is_err());
+        std::fs::write(&mirror, "").unwrap();
+        assert!(fetch_and_cache(&path, Some(&source)).is_err());
+        assert!(std::fs::read_to_string(&path)
+            .unwrap()
+            .contains("mirror-id"));
+    }
+
     #[test]
     fn load_canonical_returns_something() {
         // This should always succeed, at minimum returning the builtin.
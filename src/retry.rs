@@ -0,0 +1,350 @@
+use std::sync::OnceLock;
+use std::time::Duration;
+
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use tokio_util::sync::CancellationToken;
+
+use crate::config::TaskConfig;
+use crate::error::{Error, Result};
+use crate::event::Event;
+use crate::process::StreamHandle;
+
+/// When and how often to re-run a failed task.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct RetryPolicy {
+    /// Retries after the first attempt (0 = run once).
+    pub max_retries: u32,
+    /// Failure codes that trigger a retry (e.g. `rate_limit`, `timeout`).
+    /// Empty means any failure is retried.
+    pub retry_on: Vec<String>,
+    /// Delay before the first retry.
+    #[serde(rename = "delay_ms", with = "duration_ms")]
+    pub delay: Duration,
+    /// Factor the delay grows by after each retry (1.0 keeps it constant).
+    pub backoff_factor: f64,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            max_retries: 0,
+            retry_on: Vec::new(),
+            delay: Duration::from_secs(2),
+            backoff_factor: 2.0,
+        }
+    }
+}
+
+impl RetryPolicy {
+    /// Whether a failure with the given code should be retried.
+    pub fn should_retry(&self, code: &str) -> bool {
+        self.retry_on.is_empty() || self.retry_on.iter().any(|c| c == code)
+    }
+
+    /// How long to wait before retry number `retry` (1-based):
+    /// `delay * backoff_factor^(retry - 1)`.
+    pub fn delay_for(&self, retry: u32) -> Duration {
+        let exponent = retry.saturating_sub(1).min(i32::MAX as u32) as i32;
+        let factor = self.backoff_factor.max(0.0).powi(exponent);
+        Duration::try_from_secs_f64(self.delay.as_secs_f64() * factor).unwrap_or(Duration::MAX)
+    }
+}
+
+/// Serialize a [`Duration`] as whole milliseconds.
+mod duration_ms {
+    use std::time::Duration;
+
+    use serde::{Deserialize, Deserializer, Serializer};
+
+    pub fn serialize<S: Serializer>(d: &Duration, s: S) -> Result<S::Ok, S::Error> {
+        s.serialize_u64(d.as_millis() as u64)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(d: D) -> Result<Duration, D::Error> {
+        u64::deserialize(d).map(Duration::from_millis)
+    }
+}
+
+/// Setup failures (a missing binary, a bad working directory) that no
+/// amount of retrying will fix.
+fn is_permanent(err: &Error) -> bool {
+    matches!(
+        err,
+        Error::BinaryNotFound { .. } | Error::InvalidWorkDir(_) | Error::SpawnFailed(_)
+    )
+}
+
+/// Classify free-form failure text (an error message, a failed result, or
+/// stderr) into a retry code: `rate_limit`, `overloaded`, `timeout`, or
+/// `network`.
+///
+/// Only API error phrases and status codes following an HTTP/status marker
+/// count, so a failed result that merely mentions "429" or "connection"
+/// isn't mistaken for a transient failure.
+pub fn classify_failure(text: &str) -> Option<&'static str> {
+    static PATTERNS: OnceLock<Vec<(&'static str, Regex)>> = OnceLock::new();
+    let patterns = PATTERNS.get_or_init(|| {
+        [
+            (
+                "rate_limit",
+                r"(?i)\brate[ _]limit(ed\b|_error\b|s? (exceeded|reached)\b)|\btoo many requests\b|\b(http|status|code|error)\W{0,3}429\b",
+            ),
+            (
+                "overloaded",
+                r"(?i)\boverloaded_error\b|\b(api|server|service|model) (is )?(currently )?overloaded\b|\bservice unavailable\b|\b(http|status|code|error)\W{0,3}(503|529)\b",
+            ),
+            (
+                "timeout",
+                r"(?i)\b(request|connection|operation|read) timed out\b|\btimeout error\b|\betimedout\b|\b(http|status|code|error)\W{0,3}(408|504)\b",
+            ),
+            (
+                "network",
+                r"(?i)\bconnection (reset|refused|closed|aborted|error)\b|\bnetwork error\b|\beconn(reset|refused)\b",
+            ),
+        ]
+        .into_iter()
+        .map(|(code, pattern)| (code, Regex::new(pattern).expect("valid failure pattern")))
+        .collect()
+    });
+    patterns
+        .iter()
+        .find(|(_, re)| re.is_match(text))
+        .map(|(code, _)| *code)
+}
+
+/// Failure code for an error event: its explicit code if it has one,
+/// otherwise a classification of its message.
+fn event_failure_code(event: &Event) -> Option<String> {
+    match event {
+        Event::Error(e) => Some(
+            e.code
+                .clone()
+                .or_else(|| classify_failure(&e.message).map(String::from))
+                .unwrap_or_else(|| "error".into()),
+        ),
+        Event::Result(r) if !r.success => {
+            Some(classify_failure(&r.text).unwrap_or("error").to_string())
+        }
+        _ => None,
+    }
+}
+
+fn error_failure_code(err: &Error) -> String {
+    match err {
+        Error::Timeout(_) => "timeout".into(),
+        Error::ProcessFailed { stderr, .. } => {
+            classify_failure(stderr).unwrap_or("process_failed").into()
+        }
+        other => classify_failure(&other.to_string())
+            .unwrap_or("error")
+            .into(),
+    }
+}
+
+/// Run a task, re-running it according to `policy` when an attempt fails
+/// with a retryable code.
+///
+/// While a retry is still possible, an attempt's events are held back until
+/// it ends: if it is retried they are dropped, so the stream carries exactly
+/// one attempt's session, messages, cost, and result; otherwise they are
+/// flushed. The last allowed attempt streams live. Each retry is logged at
+/// warn level.
+///
+/// Setup errors such as [`Error::BinaryNotFound`] are never retried; on the
+/// first attempt they are returned directly.
+pub async fn run_task_with_retry(
+    config: &TaskConfig,
+    policy: &RetryPolicy,
+    cancel_token: Option<CancellationToken>,
+) -> Result<StreamHandle> {
+    let token = cancel_token.unwrap_or_default();
+    let first = match crate::run_task_once(config, Some(token.child_token())).await {
+        Err(e) if is_permanent(&e) => return Err(e),
+        started => started,
+    };
+    let config = config.clone();
+    let policy = policy.clone();
+    let token_for_task = token.clone();
+
+    let (tx, rx) = tokio::sync::mpsc::channel::<Result<Event>>(256);
+
+    tokio::spawn(async move {
+        let mut attempt = 0u32;
+        let mut first = Some(first);
+        loop {
+            let can_retry = attempt < policy.max_retries;
+            let mut held: Vec<Result<Event>> = Vec::new();
+            let mut failure: Option<String> = None;
+            let mut succeeded = false;
+
+            let started = match first.take() {
+                Some(started) => started,
+                None => crate::run_task_once(&config, Some(token_for_task.child_token())).await,
+            };
+            match started {
+                Ok(handle) => {
+                    use futures::StreamExt;
+                    let mut stream = handle.stream;
+                    while let Some(item) = stream.next().await {
+                        match item {
+                            Ok(Event::Result(ref r)) if r.success => succeeded = true,
+                            Ok(ref event) => {
+                                if let Some(code) = event_failure_code(event) {
+                                    failure.get_or_insert(code);
+                                }
+                            }
+                            Err(ref e) => {
+                                failure.get_or_insert_with(|| error_failure_code(e));
+                            }
+                        }
+                        if can_retry {
+                            held.push(item);
+                        } else if tx.send(item).await.is_err() {
+                            return;
+                        }
+                    }
+                }
+                Err(e) => {
+                    if !is_permanent(&e) {
+                        failure = Some(error_failure_code(&e));
+                    }
+                    held.push(Err(e));
+                }
+            }
+
+            let retry_code = match failure {
+                Some(code) if !succeeded && can_retry && !token_for_task.is_cancelled() => {
+                    policy.should_retry(&code).then_some(code)
+                }
+                _ => None,
+            };
+
+            let Some(code) = retry_code else {
+                for item in held {
+                    if tx.send(item).await.is_err() {
+                        return;
+                    }
+                }
+                return;
+            };
+
+            attempt += 1;
+            let delay = policy.delay_for(attempt);
+            tracing::warn!(
+                "attempt {attempt} failed ({code}), retrying in {:.1}s ({attempt}/{})",
+                delay.as_secs_f64(),
+                policy.max_retries
+            );
+            tokio::select! {
+                _ = token_for_task.cancelled() => return,
+                _ = tokio::time::sleep(delay) => {}
+            }
+        }
+    });
+
+    let stream = tokio_stream::wrappers::ReceiverStream::new(rx);
+
+    Ok(StreamHandle {
+        stream: Box::pin(stream),
+        cancel_token: token,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn classify_failure_codes() {
+        assert_eq!(classify_failure("Rate limit exceeded"), Some("rate_limit"));
+        assert_eq!(classify_failure("HTTP 429 Too Many Requests"), Some("rate_limit"));
+        assert_eq!(classify_failure("API is overloaded"), Some("overloaded"));
+        assert_eq!(classify_failure("request timed out"), Some("timeout"));
+        assert_eq!(classify_failure("connection reset"), Some("network"));
+        assert_eq!(classify_failure("syntax error"), None);
+        assert_eq!(classify_failure("Error: status 503"), Some("overloaded"));
+    }
+
+    #[test]
+    fn classify_failure_ignores_ordinary_text() {
+        for text in [
+            "Fixed the 429 handler and the connection pool",
+            "Added a timeout to the network client",
+            "The rate limiter now allows 10 requests per second",
+            "Looked at error 4290 in the log",
+        ] {
+            assert_eq!(classify_failure(text), None, "{text}");
+        }
+    }
+
+    #[test]
+    fn should_retry_respects_codes() {
+        let policy = RetryPolicy {
+            max_retries: 3,
+            retry_on: vec!["rate_limit".into(), "timeout".into()],
+            ..Default::default()
+        };
+        assert!(policy.should_retry("rate_limit"));
+        assert!(policy.should_retry("timeout"));
+        assert!(!policy.should_retry("auth_error"));
+    }
+
+    #[test]
+    fn empty_retry_on_retries_everything() {
+        let policy = RetryPolicy::default();
+        assert!(policy.should_retry("anything"));
+    }
+
+    #[test]
+    fn delay_grows_exponentially() {
+        let policy = RetryPolicy {
+            delay: Duration::from_millis(100),
+            ..Default::default()
+        };
+        assert_eq!(policy.delay_for(1), Duration::from_millis(100));
+        assert_eq!(policy.delay_for(2), Duration::from_millis(200));
+        assert_eq!(policy.delay_for(4), Duration::from_millis(800));
+
+        let constant = RetryPolicy {
+            backoff_factor: 1.0,
+            ..policy
+        };
+        assert_eq!(constant.delay_for(5), Duration::from_millis(100));
+    }
+
+    #[test]
+    fn policy_serializes_delay_as_millis() {
+        let policy = RetryPolicy {
+            max_retries: 2,
+            delay: Duration::from_millis(1500),
+            ..Default::default()
+        };
+        let json = serde_json::to_value(&policy).unwrap();
+        assert_eq!(json["delay_ms"], 1500);
+        let back: RetryPolicy = serde_json::from_value(json).unwrap();
+        assert_eq!(back, policy);
+        let partial: RetryPolicy = serde_json::from_str(r#"{"max_retries":1}"#).unwrap();
+        assert_eq!(partial.delay, Duration::from_secs(2));
+    }
+
+    #[test]
+    fn setup_errors_are_permanent() {
+        assert!(is_permanent(&Error::BinaryNotFound {
+            agent: "Claude Code".into(),
+            binary: "claude".into(),
+        }));
+        assert!(!is_permanent(&Error::Timeout(5)));
+    }
+
+    #[test]
+    fn process_failure_classified_from_stderr() {
+        let err = Error::ProcessFailed {
+            code: 1,
+            stderr: "error: rate limit reached".into(),
+        };
+        assert_eq!(error_failure_code(&err), "rate_limit");
+        assert_eq!(error_failure_code(&Error::Timeout(5)), "timeout");
+    }
+}
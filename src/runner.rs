@@ -1,12 +1,16 @@
-use std::path::PathBuf;
+use std::collections::HashMap;
+use std::hash::Hash;
+use std::path::{Path, PathBuf};
 use std::pin::Pin;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Mutex, OnceLock};
 
 use async_trait::async_trait;
 use futures::Stream;
 use serde::{Deserialize, Serialize};
 use tokio_util::sync::CancellationToken;
 
-use crate::config::{AgentKind, TaskConfig};
+use crate::config::{AgentKind, PermissionMode, TaskConfig};
 use crate::error::{Error, Result};
 use crate::event::Event;
 use crate::process::StreamHandle;
@@ -14,11 +18,120 @@ use crate::process::StreamHandle;
 /// A boxed, pinned event stream returned by agent runners.
 pub type EventStream = Pin<Box<dyn Stream<Item = Result<Event>> + Send>>;
 
+/// Whether [`find_binary`] and the default [`AgentRunner::version`] memoize
+/// their probes for the rest of the process. Off unless a caller opts in, so
+/// long-lived library users see agents installed or upgraded after startup.
+static PROBE_CACHE_ENABLED: AtomicBool = AtomicBool::new(false);
+
+/// Process-level memo of a filesystem probe, keyed by `K`.
+struct ProbeCache<K, V> {
+    entries: OnceLock<Mutex<HashMap<K, V>>>,
+}
+
+impl<K: Eq + Hash, V: Clone> ProbeCache<K, V> {
+    const fn new() -> Self {
+        Self {
+            entries: OnceLock::new(),
+        }
+    }
+
+    fn map(&self) -> std::sync::MutexGuard<'_, HashMap<K, V>> {
+        self.entries
+            .get_or_init(Default::default)
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// Run `probe`, memoizing its result when the probe cache is enabled.
+    fn get_or_probe(&self, key: K, probe: impl FnOnce() -> V) -> V {
+        if PROBE_CACHE_ENABLED.load(Ordering::Relaxed) {
+            self.memoize(key, probe)
+        } else {
+            probe()
+        }
+    }
+
+    /// Return the cached value for `key`, or run `probe` and cache its result.
+    /// The lock is not held while probing.
+    fn memoize(&self, key: K, probe: impl FnOnce() -> V) -> V {
+        if let Some(value) = self.map().get(&key) {
+            return value.clone();
+        }
+        let value = probe();
+        self.map().insert(key, value.clone());
+        value
+    }
+
+    fn clear(&self) {
+        self.map().clear();
+    }
+}
+
+static BINARY_CACHE: ProbeCache<AgentKind, Option<PathBuf>> = ProbeCache::new();
+static VERSION_CACHE: ProbeCache<PathBuf, Option<String>> = ProbeCache::new();
+
+/// How long `AgentRunner::version` waits for `<binary> --version`.
+pub const VERSION_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(3);
+
+/// Run `<binary> --version` and return its trimmed stdout, or `None` if it
+/// fails or is still running after `timeout` (in which case it's killed).
+fn probe_version(binary: &Path, timeout: std::time::Duration) -> Option<String> {
+    let mut child = std::process::Command::new(binary)
+        .arg("--version")
+        .stdin(std::process::Stdio::null())
+        .stdout(std::process::Stdio::piped())
+        .stderr(std::process::Stdio::null())
+        .spawn()
+        .ok()?;
+
+    let deadline = std::time::Instant::now() + timeout;
+    let status = loop {
+        match child.try_wait() {
+            Ok(Some(status)) => break status,
+            Ok(None) if std::time::Instant::now() < deadline => {
+                std::thread::sleep(std::time::Duration::from_millis(10));
+            }
+            _ => {
+                tracing::debug!("{} --version timed out", binary.display());
+                let _ = child.kill();
+                let _ = child.wait();
+                return None;
+            }
+        }
+    };
+    if !status.success() {
+        return None;
+    }
+
+    let mut stdout = String::new();
+    std::io::Read::read_to_string(&mut child.stdout.take()?, &mut stdout).ok()?;
+    Some(stdout.trim().to_string())
+}
+
+/// Turn availability/version memoization on or off. It's off by default; the
+/// CLI turns it on for each invocation unless `--no-cache` is passed. Either
+/// way, anything cached so far is dropped.
+pub fn set_probe_cache_enabled(enabled: bool) {
+    PROBE_CACHE_ENABLED.store(enabled, Ordering::Relaxed);
+    clear_probe_cache();
+}
+
+/// Forget memoized binary lookups and versions, e.g. after installing an agent.
+pub fn clear_probe_cache() {
+    BINARY_CACHE.clear();
+    VERSION_CACHE.clear();
+}
+
 /// Check if any of the binary candidates for the given agent kind exist in PATH.
+///
+/// With [`set_probe_cache_enabled`], the lookup is memoized per agent for the
+/// rest of the process.
 pub fn find_binary(kind: AgentKind) -> Option<PathBuf> {
-    kind.binary_candidates()
-        .iter()
-        .find_map(|name| which::which(name).ok())
+    BINARY_CACHE.get_or_probe(kind, || {
+        kind.binary_candidates()
+            .iter()
+            .find_map(|name| which::which(name).ok())
+    })
 }
 
 /// Check if any binary candidate is available on the system.
@@ -45,6 +158,56 @@ pub struct AgentCapabilities {
     pub supports_model: bool,
     pub supports_max_turns: bool,
     pub supports_append_system_prompt: bool,
+    #[serde(default)]
+    pub supports_images: bool,
+    /// Whether the agent streams `TextDelta` events; otherwise text only
+    /// arrives as whole `Message` events.
+    #[serde(default)]
+    pub supports_streaming: bool,
+    /// Whether the agent can continue a previous session (`--resume`).
+    #[serde(default)]
+    pub supports_resume: bool,
+    /// Whether the agent CLI can restrict itself to an allowlist of tools.
+    #[serde(default)]
+    pub supports_tool_allowlist: bool,
+    /// Whether the agent's stream carries reasoning (thinking) output.
+    #[serde(default)]
+    pub supports_reasoning: bool,
+}
+
+/// How an agent CLI receives the prompt.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PromptPassing {
+    /// As the value of a leading flag, e.g. `claude -p <prompt> ...`.
+    Flag(&'static str),
+    /// As the final positional argument, after every flag and extra arg.
+    Positional,
+}
+
+impl PromptPassing {
+    /// Place `prompt` into `args`, which hold every other argument.
+    pub fn apply(self, args: &mut Vec<String>, prompt: &str) {
+        match self {
+            PromptPassing::Flag(flag) => {
+                args.splice(0..0, [flag.to_string(), prompt.to_string()]);
+            }
+            PromptPassing::Positional => args.push(prompt.to_string()),
+        }
+    }
+}
+
+/// Error if `config` attaches images but the agent can't take image input.
+///
+/// Unlike the [`AgentRunner::validate_config`] warnings this is fatal: the
+/// images are part of the task, so silently dropping them would change it.
+pub fn ensure_images_supported<R: AgentRunner + ?Sized>(runner: &R, config: &TaskConfig) -> Result<()> {
+    if !config.images.is_empty() && !runner.capabilities().supports_images {
+        return Err(Error::Other(format!(
+            "{} does not support image inputs (--input-image)",
+            config.agent.display_name()
+        )));
+    }
+    Ok(())
 }
 
 /// A config validation warning.
@@ -88,22 +251,30 @@ pub trait AgentRunner: Send + Sync {
         cancel_token: Option<CancellationToken>,
     ) -> Result<StreamHandle>;
 
+    /// How this agent's CLI takes the prompt. `build_args` places the prompt
+    /// with [`PromptPassing::apply`].
+    ///
+    /// Default: positional.
+    fn prompt_passing(&self) -> PromptPassing {
+        PromptPassing::Positional
+    }
+
+    /// Massage a raw model string into the form this agent expects
+    /// (e.g. adding a provider prefix). Called from `build_args`.
+    ///
+    /// Default: identity.
+    fn normalize_model(&self, raw: &str) -> String {
+        raw.to_string()
+    }
+
     /// Get the version of the installed agent binary.
+    ///
+    /// Memoized per binary path when [`set_probe_cache_enabled`] is on. A binary that
+    /// doesn't answer `--version` within [`VERSION_TIMEOUT`] is killed and
+    /// reported as `None`.
     fn version(&self, config: &TaskConfig) -> Option<String> {
         let binary = self.binary_path(config).ok()?;
-        let output = std::process::Command::new(&binary)
-            .arg("--version")
-            .stdout(std::process::Stdio::piped())
-            .stderr(std::process::Stdio::piped())
-            .output()
-            .ok()?;
-
-        if output.status.success() {
-            let version_str = String::from_utf8_lossy(&output.stdout);
-            Some(version_str.trim().to_string())
-        } else {
-            None
-        }
+        VERSION_CACHE.get_or_probe(binary.clone(), || probe_version(&binary, VERSION_TIMEOUT))
     }
 
     /// What features this agent supports.
@@ -112,6 +283,18 @@ pub trait AgentRunner: Send + Sync {
         AgentCapabilities::default()
     }
 
+    /// Whether this agent can resume a previous session.
+    fn supports_resume(&self) -> bool {
+        self.capabilities().supports_resume
+    }
+
+    /// Whether the agent enforces `mode` with a native flag. When it does
+    /// not, the adapter approximates the mode as best it can.
+    fn supports_permission_mode(&self, mode: PermissionMode) -> bool {
+        // Default: conservative — only full access is assumed native.
+        mode == PermissionMode::FullAccess
+    }
+
     /// Validate config against this agent's capabilities.
     fn validate_config(&self, config: &TaskConfig) -> Vec<ConfigWarning> {
         let caps = self.capabilities();
@@ -124,7 +307,10 @@ pub trait AgentRunner: Send + Sync {
         }
         if config.max_budget_usd.is_some() && !caps.supports_budget {
             warnings.push(ConfigWarning {
-                message: format!("{} does not support --max-budget", self.name()),
+                message: format!(
+                    "{} does not support --max-budget; harness stops it once reported cost exceeds the budget",
+                    self.name()
+                ),
             });
         }
         if config.model.is_some() && !caps.supports_model {
@@ -134,7 +320,10 @@ pub trait AgentRunner: Send + Sync {
         }
         if config.max_turns.is_some() && !caps.supports_max_turns {
             warnings.push(ConfigWarning {
-                message: format!("{} does not support --max-turns", self.name()),
+                message: format!(
+                    "{} does not support --max-turns; harness stops it after that many assistant messages",
+                    self.name()
+                ),
             });
         }
         if config.append_system_prompt.is_some() && !caps.supports_append_system_prompt {
@@ -142,7 +331,87 @@ pub trait AgentRunner: Send + Sync {
                 message: format!("{} does not support --append-system-prompt", self.name()),
             });
         }
+        if config.resume_session_id.is_some() && !caps.supports_resume {
+            warnings.push(ConfigWarning {
+                message: format!("{} does not support --resume", self.name()),
+            });
+        }
+        if config.include_reasoning && !caps.supports_reasoning {
+            warnings.push(ConfigWarning {
+                message: format!(
+                    "{} does not stream reasoning; --include-reasoning has no effect",
+                    self.name()
+                ),
+            });
+        }
+        if !self.supports_permission_mode(config.permission_mode) {
+            warnings.push(ConfigWarning {
+                message: format!(
+                    "{} has no native {} mode; harness emulates it",
+                    self.name(),
+                    config.permission_mode.as_str()
+                ),
+            });
+        }
+        if let Some(profile) = &config.agent_profile {
+            if config.agent != AgentKind::OpenCode {
+                warnings.push(ConfigWarning {
+                    message: format!("{} does not support --agent-profile", self.name()),
+                });
+            } else if config.permission_mode == PermissionMode::ReadOnly {
+                warnings.push(ConfigWarning {
+                    message: format!(
+                        "--agent-profile {profile} is ignored in read-only mode; \
+                         running the plan agent instead (pass --permissions full-access to use it)"
+                    ),
+                });
+            }
+        }
 
         warnings
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::AtomicUsize;
+
+    #[test]
+    fn probe_cache_probes_once_per_key() {
+        let cache: ProbeCache<AgentKind, Option<PathBuf>> = ProbeCache::new();
+        let probes = AtomicUsize::new(0);
+        let probe = || {
+            probes.fetch_add(1, Ordering::SeqCst);
+            Some(PathBuf::from("/usr/bin/claude"))
+        };
+
+        for _ in 0..3 {
+            assert_eq!(
+                cache.memoize(AgentKind::Claude, probe),
+                Some(PathBuf::from("/usr/bin/claude"))
+            );
+        }
+        assert_eq!(probes.load(Ordering::SeqCst), 1);
+
+        cache.memoize(AgentKind::Codex, probe);
+        assert_eq!(probes.load(Ordering::SeqCst), 2);
+
+        cache.clear();
+        cache.memoize(AgentKind::Claude, probe);
+        assert_eq!(probes.load(Ordering::SeqCst), 3);
+    }
+
+    #[test]
+    fn probe_cache_is_off_unless_enabled() {
+        let cache: ProbeCache<AgentKind, Option<PathBuf>> = ProbeCache::new();
+        let probes = AtomicUsize::new(0);
+        for _ in 0..2 {
+            cache.get_or_probe(AgentKind::Claude, || {
+                probes.fetch_add(1, Ordering::SeqCst);
+                None
+            });
+        }
+        assert_eq!(probes.load(Ordering::SeqCst), 2);
+    }
+}
@@ -3,7 +3,7 @@ use std::path::{Path, PathBuf};
 
 use serde::{Deserialize, Serialize};
 
-use crate::config::AgentKind;
+use crate::config::{AgentKind, PermissionMode};
 use crate::models::{ModelEntry, ModelRegistry};
 
 /// User settings loaded from `~/.config/harness/config.toml` and optionally
@@ -26,10 +26,25 @@ pub struct Settings {
     #[serde(default)]
     pub default_timeout_secs: Option<u64>,
 
+    /// Default output format (`text`, `json`, `stream-json`, `markdown`)
+    /// if `--output` is omitted.
+    #[serde(default)]
+    pub default_output: Option<String>,
+
     /// Log level for tracing output (e.g. "debug", "info", "warn").
     #[serde(default)]
     pub log_level: Option<String>,
 
+    /// Also append every run's events to a dated aggregate log
+    /// (`~/.local/share/harness/harness-YYYY-MM-DD.ndjson`).
+    #[serde(default)]
+    pub aggregate_log: Option<bool>,
+
+    /// Refuse prompts larger than this many bytes if `--prompt-max-bytes`
+    /// is omitted.
+    #[serde(default)]
+    pub prompt_max_bytes: Option<usize>,
+
     /// Per-agent configuration overrides.
     #[serde(default)]
     pub agents: HashMap<String, AgentSettings>,
@@ -131,9 +146,18 @@ impl Settings {
         if other.default_timeout_secs.is_some() {
             merged.default_timeout_secs = other.default_timeout_secs;
         }
+        if other.default_output.is_some() {
+            merged.default_output.clone_from(&other.default_output);
+        }
         if other.log_level.is_some() {
             merged.log_level.clone_from(&other.log_level);
         }
+        if other.aggregate_log.is_some() {
+            merged.aggregate_log = other.aggregate_log;
+        }
+        if other.prompt_max_bytes.is_some() {
+            merged.prompt_max_bytes = other.prompt_max_bytes;
+        }
 
         // Merge per-agent settings.
         for (key, other_agent) in &other.agents {
@@ -177,9 +201,18 @@ impl Settings {
 # Default timeout in seconds.
 # default_timeout_secs = 300
 
+# Default output format: "text", "json", "stream-json", "markdown".
+# default_output = "stream-json"
+
 # Log level: "error", "warn", "info", "debug", "trace".
 # log_level = "warn"
 
+# Append all runs' events to ~/.local/share/harness/harness-YYYY-MM-DD.ndjson.
+# aggregate_log = false
+
+# Refuse prompts larger than this many bytes.
+# prompt_max_bytes = 1000000
+
 # Per-agent settings.
 # [agents.claude]
 # binary = "/opt/claude/bin/claude"
@@ -204,6 +237,7 @@ impl Settings {
             AgentKind::OpenCode => "opencode",
             AgentKind::Codex => "codex",
             AgentKind::Cursor => "cursor",
+            AgentKind::Gemini => "gemini",
         };
         self.agents.get(key)
     }
@@ -250,9 +284,28 @@ pub struct ProjectConfig {
     #[serde(default)]
     pub default_timeout_secs: Option<u64>,
 
+    #[serde(default)]
+    pub default_output: Option<String>,
+
     #[serde(default)]
     pub log_level: Option<String>,
 
+    #[serde(default)]
+    pub aggregate_log: Option<bool>,
+
+    #[serde(default)]
+    pub prompt_max_bytes: Option<usize>,
+
+    /// Where to fetch the model registry from instead of GitHub, e.g. an
+    /// internal mirror or a `file://` path.
+    #[serde(default)]
+    pub registry_url: Option<String>,
+
+    /// How long a fetched model registry stays fresh, in seconds (default
+    /// 24h). Set to 0 to refetch on every run.
+    #[serde(default)]
+    pub registry_ttl_secs: Option<u64>,
+
     /// Per-agent configuration overrides.
     #[serde(default)]
     pub agents: HashMap<String, AgentSettings>,
@@ -272,30 +325,13 @@ impl ProjectConfig {
     /// Load `harness.toml` by walking up from `dir`, returning both the config
     /// and the path where it was found.
     pub fn load_with_path(dir: &Path) -> Option<(Self, PathBuf)> {
-        let mut current = dir.to_path_buf();
-        loop {
-            let path = current.join("harness.toml");
-            if path.exists() {
-                let content = match std::fs::read_to_string(&path) {
-                    Ok(c) => c,
-                    Err(e) => {
-                        tracing::warn!("failed to read {}: {e}", path.display());
-                        return None;
-                    }
-                };
-                return match toml::from_str(&content) {
-                    Ok(c) => Some((c, path)),
-                    Err(e) => {
-                        tracing::warn!("failed to parse {}: {e}", path.display());
-                        None
-                    }
-                };
-            }
-            if !current.pop() {
-                break;
-            }
-        }
-        None
+        load_walking_up(dir, "harness.toml")
+    }
+
+    /// Path of the nearest `harness.toml` in `dir` or an ancestor, whether or
+    /// not it parses.
+    pub fn find(dir: &Path) -> Option<PathBuf> {
+        find_walking_up(dir, "harness.toml")
     }
 
     /// Extract the `[models]` section as a `ModelRegistry`.
@@ -305,6 +341,101 @@ impl ProjectConfig {
         }
     }
 
+    /// Merge `imported` into the `[models]` section of the `harness.toml` at
+    /// `path`, creating the file if needed. Imported entries replace
+    /// same-named ones; the rest of the file is kept, though not its comments.
+    pub fn import_models(path: &Path, imported: &ModelRegistry) -> Result<(), String> {
+        let mut doc = match std::fs::read_to_string(path) {
+            Ok(content) => content
+                .parse::<toml::Table>()
+                .map_err(|e| format!("failed to parse {}: {e}", path.display()))?,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => toml::Table::new(),
+            Err(e) => return Err(format!("failed to read {}: {e}", path.display())),
+        };
+        let models = doc
+            .entry("models")
+            .or_insert_with(|| toml::Value::Table(toml::Table::new()));
+        let toml::Value::Table(models) = models else {
+            return Err(format!("`models` in {} is not a table", path.display()));
+        };
+        for (name, entry) in &imported.models {
+            let value = toml::Value::try_from(entry).map_err(|e| e.to_string())?;
+            models.insert(name.clone(), value);
+        }
+        let content = toml::to_string_pretty(&doc).map_err(|e| e.to_string())?;
+        std::fs::write(path, content)
+            .map_err(|e| format!("failed to write {}: {e}", path.display()))
+    }
+
+    /// Check `harness.toml` content strictly, returning one problem per line
+    /// prefixed with its key path: unknown keys, a `default_agent` or
+    /// `default_permissions` that doesn't parse, and models with no agent
+    /// mapping. Empty when the config is clean.
+    pub fn validate(content: &str) -> Vec<String> {
+        let table = match content.parse::<toml::Table>() {
+            Ok(table) => table,
+            Err(e) => {
+                return vec![format!(
+                    "{}invalid TOML: {}",
+                    at_line(content, &e),
+                    e.message()
+                )]
+            }
+        };
+        let mut problems = Vec::new();
+        unknown_keys(&table, "", PROJECT_KEYS, &mut problems);
+        if let Some(toml::Value::Table(agents)) = table.get("agents") {
+            for (name, value) in agents {
+                let path = format!("agents.{name}");
+                if !AGENT_NAMES.contains(&name.as_str()) {
+                    problems.push(format!(
+                        "{path}: unknown agent (expected: {})",
+                        AGENT_NAMES.join(", ")
+                    ));
+                }
+                if let toml::Value::Table(agent) = value {
+                    unknown_keys(agent, &path, AGENT_KEYS, &mut problems);
+                }
+            }
+        }
+        if let Some(toml::Value::Table(models)) = table.get("models") {
+            for (name, value) in models {
+                if let toml::Value::Table(model) = value {
+                    unknown_keys(model, &format!("models.{name}"), MODEL_KEYS, &mut problems);
+                }
+            }
+        }
+
+        let config: Self = match toml::from_str(content) {
+            Ok(config) => config,
+            Err(e) => {
+                problems.push(format!("{}{}", at_line(content, &e), e.message()));
+                return problems;
+            }
+        };
+        if let Some(Err(e)) = config.default_agent.as_deref().map(str::parse::<AgentKind>) {
+            problems.push(format!("default_agent: {e}"));
+        }
+        if let Some(Err(e)) = config
+            .default_permissions
+            .as_deref()
+            .map(str::parse::<PermissionMode>)
+        {
+            problems.push(format!("default_permissions: {e}"));
+        }
+        let mut models: Vec<_> = config.models.iter().collect();
+        models.sort_by_key(|(name, _)| name.as_str());
+        for (name, entry) in models {
+            if entry.supported_agents().is_empty() {
+                problems.push(format!(
+                    "models.{name}: no agent mapping (set at least one of {})",
+                    AGENT_NAMES.join(", ")
+                ));
+            }
+        }
+        problems
+    }
+
     /// Resolve the default agent from this config.
     pub fn resolve_default_agent(&self) -> Option<AgentKind> {
         self.default_agent.as_ref()?.parse().ok()
@@ -317,6 +448,7 @@ impl ProjectConfig {
             AgentKind::OpenCode => "opencode",
             AgentKind::Codex => "codex",
             AgentKind::Cursor => "cursor",
+            AgentKind::Gemini => "gemini",
         };
         self.agents.get(key)
     }
@@ -360,9 +492,18 @@ impl ProjectConfig {
 # Default timeout in seconds.
 # default_timeout_secs = 300
 
+# Default output format: "text", "json", "stream-json", "markdown".
+# default_output = "stream-json"
+
 # Log level: "error", "warn", "info", "debug", "trace".
 # log_level = "warn"
 
+# Append all runs' events to ~/.local/share/harness/harness-YYYY-MM-DD.ndjson.
+# aggregate_log = false
+
+# Refuse prompts larger than this many bytes.
+# prompt_max_bytes = 1000000
+
 # Per-agent settings.
 # [agents.claude]
 # binary = "/opt/claude/bin/claude"
@@ -379,6 +520,213 @@ impl ProjectConfig {
     }
 }
 
+/// Keys `ProjectConfig` understands at the top level of `harness.toml`.
+const PROJECT_KEYS: &[&str] = &[
+    "default_agent",
+    "default_model",
+    "default_permissions",
+    "default_timeout_secs",
+    "default_output",
+    "log_level",
+    "aggregate_log",
+    "prompt_max_bytes",
+    "registry_url",
+    "registry_ttl_secs",
+    "agents",
+    "models",
+];
+
+/// Keys of an `[agents.<name>]` table.
+const AGENT_KEYS: &[&str] = &["binary", "model", "extra_args"];
+
+/// Keys of a `[models.<name>]` table.
+const MODEL_KEYS: &[&str] = &[
+    "description",
+    "provider",
+    "claude",
+    "codex",
+    "opencode",
+    "cursor",
+    "gemini",
+    "deprecated",
+    "replacement",
+    "input_price_per_mtok",
+    "output_price_per_mtok",
+    "cached_input_price_per_mtok",
+];
+
+/// Names accepted as `[agents.<name>]` tables.
+const AGENT_NAMES: &[&str] = &["claude", "opencode", "codex", "cursor", "gemini"];
+
+/// Report each key of `table` not in `known`, under the key path `prefix`.
+fn unknown_keys(table: &toml::Table, prefix: &str, known: &[&str], problems: &mut Vec<String>) {
+    for key in table.keys() {
+        if !known.contains(&key.as_str()) {
+            let path = match prefix {
+                "" => key.clone(),
+                _ => format!("{prefix}.{key}"),
+            };
+            problems.push(format!("{path}: unknown key"));
+        }
+    }
+}
+
+/// `line N: ` for where a TOML error points, or nothing if it has no span.
+fn at_line(content: &str, error: &toml::de::Error) -> String {
+    match error.span() {
+        Some(span) => format!("line {}: ", content[..span.start].matches('\n').count() + 1),
+        None => String::new(),
+    }
+}
+
+/// The first `file_name` in `dir` or one of its ancestors.
+fn find_walking_up(dir: &Path, file_name: &str) -> Option<PathBuf> {
+    let mut current = dir.to_path_buf();
+    loop {
+        let path = current.join(file_name);
+        if path.exists() {
+            return Some(path);
+        }
+        if !current.pop() {
+            return None;
+        }
+    }
+}
+
+/// Find `file_name` in `dir` or its nearest ancestor and parse it as TOML.
+///
+/// Stops at the first file found; a file that can't be read or parsed is
+/// logged and yields `None` rather than falling back to a further ancestor.
+fn load_walking_up<T: serde::de::DeserializeOwned>(
+    dir: &Path,
+    file_name: &str,
+) -> Option<(T, PathBuf)> {
+    let path = find_walking_up(dir, file_name)?;
+    let content = match std::fs::read_to_string(&path) {
+        Ok(c) => c,
+        Err(e) => {
+            tracing::warn!("failed to read {}: {e}", path.display());
+            return None;
+        }
+    };
+    match toml::from_str(&content) {
+        Ok(c) => Some((c, path)),
+        Err(e) => {
+            tracing::warn!("failed to parse {}: {e}", path.display());
+            None
+        }
+    }
+}
+
+/// What `run` does when a tool call violates the [`ToolPolicy`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum PolicyAction {
+    /// Print a warning and let the agent continue.
+    #[default]
+    Warn,
+    /// Stop the agent and exit non-zero.
+    Abort,
+}
+
+/// Tool confinement policy loaded from `harness.policy.toml`, found by
+/// walking up from the working directory like `harness.toml`.
+///
+/// Tool names are matched case-insensitively against the unified event
+/// names (`shell`, `bash`, `file_change`, `Write`, ...).
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+pub struct ToolPolicy {
+    /// If non-empty, only these tools may be called.
+    #[serde(default)]
+    pub allowed_tools: Vec<String>,
+
+    /// Tools that may never be called.
+    #[serde(default)]
+    pub denied_tools: Vec<String>,
+
+    /// Directories file-writing tools may touch. Empty allows any path.
+    /// Relative roots are resolved against the policy file's directory.
+    #[serde(default)]
+    pub allowed_write_roots: Vec<PathBuf>,
+
+    /// What to do on a violation.
+    #[serde(default)]
+    pub action: PolicyAction,
+}
+
+impl ToolPolicy {
+    /// Load `harness.policy.toml` by walking up from `dir`, with relative
+    /// write roots made absolute.
+    pub fn load(dir: &Path) -> Option<Self> {
+        let (mut policy, path): (Self, PathBuf) = load_walking_up(dir, "harness.policy.toml")?;
+        let base = path.parent().unwrap_or(Path::new("/"));
+        for root in &mut policy.allowed_write_roots {
+            *root = normalize_path(&base.join(&*root));
+        }
+        Some(policy)
+    }
+
+    /// Check a tool call. Returns a description of the violation, if any.
+    ///
+    /// `cwd` resolves relative paths in the call's input.
+    pub fn check(
+        &self,
+        tool_name: &str,
+        input: Option<&serde_json::Value>,
+        cwd: &Path,
+    ) -> Option<String> {
+        let matches = |names: &[String]| names.iter().any(|n| n.eq_ignore_ascii_case(tool_name));
+        if matches(&self.denied_tools) {
+            return Some(format!("tool `{tool_name}` is denied by policy"));
+        }
+        if !self.allowed_tools.is_empty() && !matches(&self.allowed_tools) {
+            return Some(format!(
+                "tool `{tool_name}` is not in the policy's allowed tools"
+            ));
+        }
+        if self.allowed_write_roots.is_empty() || !is_write_tool(tool_name) {
+            return None;
+        }
+        let path = ["file_path", "path", "filePath"]
+            .iter()
+            .find_map(|k| input?.get(*k)?.as_str())?;
+        let target = normalize_path(&cwd.join(path));
+        if self
+            .allowed_write_roots
+            .iter()
+            .any(|root| target.starts_with(root))
+        {
+            None
+        } else {
+            Some(format!(
+                "tool `{tool_name}` writes `{}` outside the policy's allowed write roots",
+                target.display()
+            ))
+        }
+    }
+}
+
+fn is_write_tool(tool_name: &str) -> bool {
+    let name = tool_name.to_ascii_lowercase();
+    name == "file_change" || name.contains("write") || name.contains("edit")
+}
+
+/// Lexically resolve `.` and `..` components (the path may not exist yet).
+fn normalize_path(path: &Path) -> PathBuf {
+    use std::path::Component;
+    let mut out = PathBuf::new();
+    for component in path.components() {
+        match component {
+            Component::CurDir => {}
+            Component::ParentDir => {
+                out.pop();
+            }
+            other => out.push(other),
+        }
+    }
+    out
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -422,7 +770,10 @@ default_agent = "claude"
 default_model = "opus"
 default_permissions = "read-only"
 default_timeout_secs = 300
+default_output = "text"
 log_level = "debug"
+aggregate_log = true
+prompt_max_bytes = 4096
 
 [agents.claude]
 binary = "/usr/bin/claude"
@@ -432,7 +783,10 @@ extra_args = ["--verbose", "--no-color"]
         let settings: Settings = toml::from_str(toml).unwrap();
         assert_eq!(settings.default_permissions, Some("read-only".into()));
         assert_eq!(settings.default_timeout_secs, Some(300));
+        assert_eq!(settings.default_output, Some("text".into()));
         assert_eq!(settings.log_level, Some("debug".into()));
+        assert_eq!(settings.aggregate_log, Some(true));
+        assert_eq!(settings.prompt_max_bytes, Some(4096));
         let claude = settings.agent_settings(AgentKind::Claude).unwrap();
         assert_eq!(claude.extra_args, vec!["--verbose", "--no-color"]);
     }
@@ -609,6 +963,12 @@ claude = "custom-id"
         );
     }
 
+    #[test]
+    fn project_config_parse_default_output() {
+        let config: ProjectConfig = toml::from_str("default_output = \"json\"\n").unwrap();
+        assert_eq!(config.default_output, Some("json".into()));
+    }
+
     #[test]
     fn project_config_model_registry() {
         let toml = r#"
@@ -666,4 +1026,188 @@ claude = "test-id"
             toml::from_str(ProjectConfig::template());
         assert!(result.is_ok());
     }
+
+    #[test]
+    fn validate_accepts_clean_config() {
+        assert!(ProjectConfig::validate(ProjectConfig::template()).is_empty());
+        let content = r#"
+default_agent = "claude-code"
+default_permissions = "read-only"
+registry_ttl_secs = 60
+
+[agents.codex]
+model = "o3"
+
+[models.mine]
+description = "Mine"
+claude = "mine-id"
+"#;
+        assert_eq!(ProjectConfig::validate(content), Vec::<String>::new());
+    }
+
+    #[test]
+    fn validate_reports_each_problem_with_its_key_path() {
+        let content = r#"
+defualt_agent = "claude"
+default_agent = "clade"
+default_permissions = "write"
+
+[agents.claud]
+binary = "/bin/claude"
+
+[agents.codex]
+modle = "o3"
+
+[models.empty]
+description = "No mappings"
+
+[models.typo]
+claude = "id"
+input_price = 1.0
+"#;
+        let mut problems = ProjectConfig::validate(content);
+        problems.sort();
+        assert_eq!(
+            problems,
+            [
+                "agents.claud: unknown agent (expected: claude, opencode, codex, cursor, gemini)",
+                "agents.codex.modle: unknown key",
+                "default_agent: unknown agent: `clade` (expected: claude, opencode, codex, cursor, gemini)",
+                "default_permissions: unknown permission mode: `write` (expected: full-access, read-only)",
+                "defualt_agent: unknown key",
+                "models.empty: no agent mapping (set at least one of claude, opencode, codex, cursor, gemini)",
+                "models.typo.input_price: unknown key",
+            ]
+        );
+
+        let broken = ProjectConfig::validate("default_timeout_secs = \"ten\"");
+        assert_eq!(
+            broken,
+            ["line 1: invalid type: string \"ten\", expected u64"]
+        );
+        let invalid = ProjectConfig::validate("default_agent = \"claude\"\nlog_level = ");
+        assert_eq!(invalid.len(), 1, "{invalid:?}");
+        assert!(
+            invalid[0].starts_with("line 2: invalid TOML"),
+            "{invalid:?}"
+        );
+    }
+
+    #[test]
+    fn validate_knows_every_field() {
+        let entry = ModelEntry {
+            description: "d".into(),
+            provider: "p".into(),
+            claude: Some("id".into()),
+            codex: Some("id".into()),
+            opencode: Some("id".into()),
+            cursor: Some("id".into()),
+            gemini: Some("id".into()),
+            deprecated: true,
+            replacement: Some("other".into()),
+            input_price_per_mtok: Some(1.0),
+            output_price_per_mtok: Some(2.0),
+            cached_input_price_per_mtok: Some(0.5),
+        };
+        let config = ProjectConfig {
+            default_agent: Some("claude".into()),
+            default_model: Some("m".into()),
+            default_permissions: Some("read-only".into()),
+            default_timeout_secs: Some(1),
+            default_output: Some("text".into()),
+            log_level: Some("info".into()),
+            aggregate_log: Some(true),
+            prompt_max_bytes: Some(1),
+            registry_url: Some("file:///r.toml".into()),
+            registry_ttl_secs: Some(1),
+            agents: HashMap::from([(
+                "claude".into(),
+                AgentSettings {
+                    binary: Some("b".into()),
+                    model: Some("m".into()),
+                    extra_args: vec!["-v".into()],
+                },
+            )]),
+            models: HashMap::from([("m".into(), entry)]),
+        };
+        let content = toml::to_string(&config).unwrap();
+        assert_eq!(ProjectConfig::validate(&content), Vec::<String>::new());
+        let table: toml::Table = content.parse().unwrap();
+        assert_eq!(table.len(), PROJECT_KEYS.len());
+        assert_eq!(
+            table["agents"]["claude"].as_table().unwrap().len(),
+            AGENT_KEYS.len()
+        );
+        assert_eq!(
+            table["models"]["m"].as_table().unwrap().len(),
+            MODEL_KEYS.len()
+        );
+    }
+
+    // ─── ToolPolicy ──────────────────────────────────────────────
+
+    #[test]
+    fn tool_policy_load_walks_up_and_resolves_roots() {
+        let tmp = tempfile::tempdir().unwrap();
+        let deep = tmp.path().join("a").join("b");
+        std::fs::create_dir_all(&deep).unwrap();
+        std::fs::write(
+            tmp.path().join("a").join("harness.policy.toml"),
+            "denied_tools = [\"shell\"]\nallowed_write_roots = [\"src\"]\naction = \"abort\"\n",
+        )
+        .unwrap();
+
+        let policy = ToolPolicy::load(&deep).unwrap();
+        assert_eq!(policy.denied_tools, vec!["shell".to_string()]);
+        assert_eq!(policy.action, PolicyAction::Abort);
+        assert_eq!(
+            policy.allowed_write_roots,
+            vec![tmp.path().join("a").join("src")]
+        );
+        assert!(ToolPolicy::load(tmp.path()).is_none());
+    }
+
+    #[test]
+    fn tool_policy_denied_and_allowed_tools() {
+        let policy = ToolPolicy {
+            allowed_tools: vec!["Read".into(), "shell".into()],
+            denied_tools: vec!["shell".into()],
+            ..Default::default()
+        };
+        let cwd = Path::new("/work");
+        assert!(policy.check("SHELL", None, cwd).unwrap().contains("denied"));
+        assert!(policy
+            .check("web_fetch", None, cwd)
+            .unwrap()
+            .contains("not in the policy"));
+        assert!(policy.check("read", None, cwd).is_none());
+    }
+
+    #[test]
+    fn tool_policy_write_roots() {
+        let policy = ToolPolicy {
+            allowed_write_roots: vec!["/work/src".into()],
+            ..Default::default()
+        };
+        let cwd = Path::new("/work");
+        let input = |p: &str| serde_json::json!({ "file_path": p });
+        assert!(policy
+            .check("Write", Some(&input("src/lib.rs")), cwd)
+            .is_none());
+        assert!(policy
+            .check("Edit", Some(&input("src/../Cargo.toml")), cwd)
+            .unwrap()
+            .contains("/work/Cargo.toml"));
+        assert!(policy
+            .check(
+                "file_change",
+                Some(&serde_json::json!({ "path": "/etc/passwd" })),
+                cwd
+            )
+            .is_some());
+        // Non-writing tools aren't confined.
+        assert!(policy
+            .check("Read", Some(&input("/etc/passwd")), cwd)
+            .is_none());
+    }
 }
@@ -0,0 +1,585 @@
+use std::collections::VecDeque;
+use std::time::{Duration, Instant};
+
+use futures::StreamExt;
+use tokio_util::sync::CancellationToken;
+
+use crate::event::{ErrorEvent, Event, Role};
+use crate::runner::EventStream;
+
+/// How long a stopped stream waits for the cancelled agent's `Summary`.
+const SUMMARY_GRACE: Duration = Duration::from_secs(1);
+
+/// Limits that end a run early, checked by the harness as events arrive.
+///
+/// Every limit is optional; the first one exceeded wins and is reported as
+/// the `code` of the final `Error` event (see [`StopReason::code`]).
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct StopConditions {
+    /// Stop once the reported spend exceeds this many USD.
+    pub max_cost_usd: Option<f64>,
+    /// Stop once the agent has produced more than this many assistant messages.
+    pub max_turns: Option<u32>,
+    /// Stop once the agent has started more than this many tool calls.
+    pub max_tool_calls: Option<u32>,
+    /// Stop when no event has arrived for this long.
+    pub idle_timeout: Option<Duration>,
+    /// Like `idle_timeout`, but reported as a stall (`stall_timeout`): the
+    /// library's watchdog for [`TaskConfig::stall_timeout_secs`].
+    ///
+    /// [`TaskConfig::stall_timeout_secs`]: crate::config::TaskConfig::stall_timeout_secs
+    pub stall_timeout: Option<Duration>,
+    /// Stop when the run has lasted this long.
+    pub timeout: Option<Duration>,
+    /// Stop at this instant (an absolute `--deadline`). Reported with the
+    /// same `timeout` code as [`timeout`](Self::timeout).
+    pub deadline: Option<Instant>,
+}
+
+impl StopConditions {
+    /// Whether no limit is set at all.
+    pub fn is_empty(&self) -> bool {
+        *self == Self::default()
+    }
+}
+
+/// Which stop condition tripped.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StopReason {
+    Budget,
+    MaxTurns,
+    ToolCalls,
+    Idle,
+    Stall,
+    Timeout,
+    Deadline,
+}
+
+impl StopReason {
+    /// Stable error code reported in the final `Error` event.
+    pub fn code(&self) -> &'static str {
+        match self {
+            StopReason::Budget => "budget_exceeded",
+            StopReason::MaxTurns => "max_turns_exceeded",
+            StopReason::ToolCalls => "max_tool_calls_exceeded",
+            StopReason::Idle => "idle_timeout",
+            StopReason::Stall => "stall_timeout",
+            StopReason::Timeout | StopReason::Deadline => "timeout",
+        }
+    }
+}
+
+/// Running state for evaluating [`StopConditions`] against an event stream.
+///
+/// Feed every event through [`observe`](Self::observe) and, while waiting
+/// for the next one, wake up at [`next_wakeup`](Self::next_wakeup) to call
+/// [`check_time`](Self::check_time).
+#[derive(Debug)]
+pub struct StopTracker {
+    conditions: StopConditions,
+    started: Instant,
+    last_event: Instant,
+    cost_usd: f64,
+    turns: u32,
+    tool_calls: u32,
+}
+
+impl StopTracker {
+    pub fn new(conditions: StopConditions, now: Instant) -> Self {
+        Self {
+            conditions,
+            started: now,
+            last_event: now,
+            cost_usd: 0.0,
+            turns: 0,
+            tool_calls: 0,
+        }
+    }
+
+    /// Account for one event; returns the condition it trips, if any.
+    ///
+    /// The final `Result` only updates the counters — the run is already
+    /// over, so there is nothing left to stop.
+    pub fn observe(&mut self, event: &Event, now: Instant) -> Option<StopReason> {
+        self.last_event = now;
+        match event {
+            Event::UsageDelta(u) => {
+                self.cost_usd += u.usage.cost_usd.unwrap_or(0.0);
+                let limit = self.conditions.max_cost_usd?;
+                (self.cost_usd > limit).then_some(StopReason::Budget)
+            }
+            Event::Message(m) if m.role == Role::Assistant => {
+                self.turns += 1;
+                let limit = self.conditions.max_turns?;
+                (self.turns > limit).then_some(StopReason::MaxTurns)
+            }
+            Event::ToolStart(_) => {
+                self.tool_calls += 1;
+                let limit = self.conditions.max_tool_calls?;
+                (self.tool_calls > limit).then_some(StopReason::ToolCalls)
+            }
+            Event::Result(r) => {
+                if let Some(c) = r.total_cost_usd {
+                    self.cost_usd = self.cost_usd.max(c);
+                }
+                None
+            }
+            _ => None,
+        }
+    }
+
+    /// Check the time-based conditions at `now`.
+    pub fn check_time(&self, now: Instant) -> Option<StopReason> {
+        if let Some(limit) = self.conditions.timeout {
+            if now.duration_since(self.started) >= limit {
+                return Some(StopReason::Timeout);
+            }
+        }
+        if self.conditions.deadline.is_some_and(|at| now >= at) {
+            return Some(StopReason::Deadline);
+        }
+        if let Some(limit) = self.conditions.idle_timeout {
+            if now.duration_since(self.last_event) >= limit {
+                return Some(StopReason::Idle);
+            }
+        }
+        if let Some(limit) = self.conditions.stall_timeout {
+            if now.duration_since(self.last_event) >= limit {
+                return Some(StopReason::Stall);
+            }
+        }
+        None
+    }
+
+    /// The earliest instant a time-based condition could trip, if any is set.
+    pub fn next_wakeup(&self) -> Option<Instant> {
+        let timeout = self.conditions.timeout.map(|d| self.started + d);
+        let idle = self.conditions.idle_timeout.map(|d| self.last_event + d);
+        let stall = self.conditions.stall_timeout.map(|d| self.last_event + d);
+        [timeout, self.conditions.deadline, idle, stall]
+            .into_iter()
+            .flatten()
+            .min()
+    }
+
+    /// Human-readable description of why the run stopped.
+    pub fn describe(&self, reason: StopReason) -> String {
+        let c = &self.conditions;
+        match reason {
+            StopReason::Budget => format!(
+                "spend ${:.3} exceeded the ${:.3} limit",
+                self.cost_usd,
+                c.max_cost_usd.unwrap_or_default()
+            ),
+            StopReason::MaxTurns => {
+                format!("exceeded {} turns", c.max_turns.unwrap_or_default())
+            }
+            StopReason::ToolCalls => {
+                format!(
+                    "exceeded {} tool calls",
+                    c.max_tool_calls.unwrap_or_default()
+                )
+            }
+            StopReason::Idle => format!(
+                "no events for {}s",
+                c.idle_timeout.unwrap_or_default().as_secs()
+            ),
+            StopReason::Stall => format!(
+                "agent stalled: no events for {}s",
+                c.stall_timeout.unwrap_or_default().as_secs()
+            ),
+            StopReason::Timeout => {
+                format!(
+                    "timed out after {}s",
+                    c.timeout.unwrap_or_default().as_secs()
+                )
+            }
+            StopReason::Deadline => "deadline reached".to_string(),
+        }
+    }
+
+    /// The `Error` event reporting that the run stopped for `reason`.
+    pub fn error_event(&self, reason: StopReason) -> Event {
+        Event::Error(ErrorEvent {
+            message: self.describe(reason),
+            code: Some(reason.code().into()),
+            timestamp_ms: 0,
+            raw_type: None,
+        })
+    }
+}
+
+/// Enforce [`StopConditions`] on a stream.
+///
+/// When a condition trips, the stream cancels `cancel` and emits an `Error`
+/// event carrying the reason's code, then nothing but the run's `Summary`.
+/// The triggering event is passed through first for the budget (the spend
+/// already happened) but dropped for turn and tool-call limits (it is work
+/// past the limit).
+/// Time-based conditions are checked on a timer while waiting for events.
+pub fn enforce_stop_conditions(
+    stream: EventStream,
+    conditions: StopConditions,
+    cancel: CancellationToken,
+) -> EventStream {
+    let state = Enforcer {
+        stream,
+        tracker: StopTracker::new(conditions, Instant::now()),
+        cancel,
+        queue: VecDeque::new(),
+        stopped: None,
+    };
+    let enforced = futures::stream::unfold(state, |mut s| async move {
+        loop {
+            if let Some(item) = s.queue.pop_front() {
+                return Some((item, s));
+            }
+            if let Some(deadline) = s.stopped {
+                // The agent is cancelled, so the inner stream ends shortly;
+                // pass on only the normalizer's closing `Summary`.
+                loop {
+                    let next = tokio::time::timeout_at(deadline.into(), s.stream.next());
+                    match next.await {
+                        Ok(Some(item @ Ok(Event::Summary(_)))) => return Some((item, s)),
+                        Ok(Some(_)) => {}
+                        Ok(None) | Err(_) => return None,
+                    }
+                }
+            }
+            let next = match s.tracker.next_wakeup() {
+                Some(at) => tokio::select! {
+                    next = s.stream.next() => next,
+                    () = tokio::time::sleep_until(at.into()) => {
+                        if let Some(reason) = s.tracker.check_time(Instant::now()) {
+                            s.stop(reason);
+                        }
+                        continue;
+                    }
+                },
+                None => s.stream.next().await,
+            };
+            let item = next?;
+            let reason = match item {
+                Ok(ref event) => s.tracker.observe(event, Instant::now()),
+                Err(_) => None,
+            };
+            if !matches!(reason, Some(StopReason::MaxTurns | StopReason::ToolCalls)) {
+                s.queue.push_back(item);
+            }
+            if let Some(reason) = reason {
+                s.stop(reason);
+            }
+        }
+    });
+    Box::pin(enforced)
+}
+
+/// State of the stream returned by [`enforce_stop_conditions`].
+struct Enforcer {
+    stream: EventStream,
+    tracker: StopTracker,
+    cancel: CancellationToken,
+    /// Items ready to emit, ahead of the inner stream.
+    queue: VecDeque<crate::Result<Event>>,
+    /// Set once stopped: until when to wait for the `Summary`.
+    stopped: Option<Instant>,
+}
+
+impl Enforcer {
+    fn stop(&mut self, reason: StopReason) {
+        self.stopped = Some(Instant::now() + SUMMARY_GRACE);
+        self.cancel.cancel();
+        self.queue
+            .push_back(Ok(self.tracker.error_event(reason).stamp()));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::event::*;
+
+    fn assistant() -> Event {
+        Event::Message(MessageEvent {
+            role: Role::Assistant,
+            text: "ok".into(),
+            usage: None,
+            timestamp_ms: 0,
+            raw_type: None,
+        })
+    }
+
+    fn tool_start(id: &str) -> Event {
+        Event::ToolStart(ToolStartEvent {
+            call_id: id.into(),
+            tool_name: "Bash".into(),
+            input: None,
+            timestamp_ms: 0,
+            raw_type: None,
+        })
+    }
+
+    fn spend(cost: f64) -> Event {
+        Event::UsageDelta(UsageDeltaEvent {
+            usage: UsageData {
+                cost_usd: Some(cost),
+                ..Default::default()
+            },
+            timestamp_ms: 0,
+            raw_type: None,
+        })
+    }
+
+    #[test]
+    fn budget_trips_once_exceeded() {
+        let now = Instant::now();
+        let mut tracker = StopTracker::new(
+            StopConditions {
+                max_cost_usd: Some(0.10),
+                ..Default::default()
+            },
+            now,
+        );
+        assert_eq!(tracker.observe(&spend(0.06), now), None);
+        assert_eq!(tracker.observe(&assistant(), now), None);
+        assert_eq!(tracker.observe(&spend(0.06), now), Some(StopReason::Budget));
+        assert_eq!(StopReason::Budget.code(), "budget_exceeded");
+    }
+
+    #[test]
+    fn max_turns_trips_on_extra_assistant_message() {
+        let now = Instant::now();
+        let mut tracker = StopTracker::new(
+            StopConditions {
+                max_turns: Some(2),
+                ..Default::default()
+            },
+            now,
+        );
+        assert_eq!(tracker.observe(&assistant(), now), None);
+        assert_eq!(tracker.observe(&tool_start("a"), now), None);
+        assert_eq!(tracker.observe(&assistant(), now), None);
+        assert_eq!(
+            tracker.observe(&assistant(), now),
+            Some(StopReason::MaxTurns)
+        );
+    }
+
+    #[test]
+    fn tool_calls_trip_on_extra_tool_start() {
+        let now = Instant::now();
+        let mut tracker = StopTracker::new(
+            StopConditions {
+                max_tool_calls: Some(1),
+                ..Default::default()
+            },
+            now,
+        );
+        assert_eq!(tracker.observe(&tool_start("a"), now), None);
+        assert_eq!(tracker.observe(&assistant(), now), None);
+        assert_eq!(
+            tracker.observe(&tool_start("b"), now),
+            Some(StopReason::ToolCalls)
+        );
+    }
+
+    #[test]
+    fn idle_timeout_resets_on_each_event() {
+        let start = Instant::now();
+        let idle = Duration::from_secs(5);
+        let mut tracker = StopTracker::new(
+            StopConditions {
+                idle_timeout: Some(idle),
+                ..Default::default()
+            },
+            start,
+        );
+        assert_eq!(tracker.next_wakeup(), Some(start + idle));
+        let later = start + Duration::from_secs(4);
+        tracker.observe(&assistant(), later);
+        assert_eq!(tracker.check_time(start + Duration::from_secs(6)), None);
+        assert_eq!(tracker.next_wakeup(), Some(later + idle));
+        assert_eq!(tracker.check_time(later + idle), Some(StopReason::Idle));
+    }
+
+    #[test]
+    fn timeout_trips_regardless_of_activity() {
+        let start = Instant::now();
+        let mut tracker = StopTracker::new(
+            StopConditions {
+                timeout: Some(Duration::from_secs(10)),
+                idle_timeout: Some(Duration::from_secs(30)),
+                ..Default::default()
+            },
+            start,
+        );
+        let late = start + Duration::from_secs(10);
+        tracker.observe(&assistant(), late);
+        assert_eq!(tracker.next_wakeup(), Some(late));
+        assert_eq!(tracker.check_time(late), Some(StopReason::Timeout));
+    }
+
+    #[test]
+    fn deadline_trips_at_its_instant_and_reports_timeout() {
+        let start = Instant::now();
+        let at = start + Duration::from_secs(5);
+        let tracker = StopTracker::new(
+            StopConditions {
+                deadline: Some(at),
+                ..Default::default()
+            },
+            start,
+        );
+        assert_eq!(tracker.next_wakeup(), Some(at));
+        assert_eq!(tracker.check_time(at - Duration::from_secs(1)), None);
+        assert_eq!(tracker.check_time(at), Some(StopReason::Deadline));
+        assert_eq!(StopReason::Deadline.code(), "timeout");
+        assert_eq!(tracker.describe(StopReason::Deadline), "deadline reached");
+    }
+
+    #[test]
+    fn no_conditions_never_trip() {
+        let now = Instant::now();
+        let mut tracker = StopTracker::new(StopConditions::default(), now);
+        for event in [spend(100.0), assistant(), tool_start("a")] {
+            assert_eq!(tracker.observe(&event, now), None);
+        }
+        assert_eq!(tracker.next_wakeup(), None);
+        assert_eq!(tracker.check_time(now + Duration::from_secs(3600)), None);
+    }
+
+    fn make_stream(events: Vec<Event>) -> EventStream {
+        Box::pin(futures::stream::iter(events.into_iter().map(Ok)))
+    }
+
+    #[tokio::test]
+    async fn enforce_stops_stream_past_budget() {
+        let cancel = CancellationToken::new();
+        let events = vec![
+            spend(0.04),
+            assistant(),
+            spend(0.04),
+            spend(0.04),
+            assistant(),
+        ];
+        let conditions = StopConditions {
+            max_cost_usd: Some(0.10),
+            ..Default::default()
+        };
+        let collected: Vec<Event> =
+            enforce_stop_conditions(make_stream(events), conditions, cancel.clone())
+                .map(|r| r.unwrap())
+                .collect()
+                .await;
+
+        // The third delta crosses $0.10; it is passed through, then the error.
+        assert_eq!(collected.len(), 5, "{collected:?}");
+        assert!(matches!(&collected[3], Event::UsageDelta(_)));
+        match &collected[4] {
+            Event::Error(e) => {
+                assert_eq!(e.code.as_deref(), Some("budget_exceeded"));
+                assert!(e.timestamp_ms > 0);
+            }
+            other => panic!("expected Error, got {other:?}"),
+        }
+        assert!(cancel.is_cancelled());
+    }
+
+    #[tokio::test]
+    async fn enforce_reports_stall_when_stream_goes_quiet() {
+        let cancel = CancellationToken::new();
+        let stream: EventStream = Box::pin(
+            futures::stream::iter(vec![Ok(assistant())]).chain(futures::stream::pending()),
+        );
+        let conditions = StopConditions {
+            stall_timeout: Some(Duration::from_millis(50)),
+            ..Default::default()
+        };
+        let collected: Vec<Event> = enforce_stop_conditions(stream, conditions, cancel.clone())
+            .map(|r| r.unwrap())
+            .collect()
+            .await;
+
+        assert_eq!(collected.len(), 2, "{collected:?}");
+        match &collected[1] {
+            Event::Error(e) => assert_eq!(e.code.as_deref(), Some("stall_timeout")),
+            other => panic!("expected Error, got {other:?}"),
+        }
+        assert!(cancel.is_cancelled());
+    }
+
+    #[tokio::test]
+    async fn enforce_passes_stream_under_budget() {
+        let cancel = CancellationToken::new();
+        let events = vec![spend(0.04), assistant(), spend(0.04)];
+        let conditions = StopConditions {
+            max_cost_usd: Some(0.10),
+            ..Default::default()
+        };
+        let collected: Vec<_> =
+            enforce_stop_conditions(make_stream(events), conditions, cancel.clone())
+                .collect()
+                .await;
+        assert_eq!(collected.len(), 3);
+        assert!(!cancel.is_cancelled());
+    }
+
+    #[tokio::test]
+    async fn enforce_stops_after_max_turns() {
+        let cancel = CancellationToken::new();
+        let n = 3;
+        let events = (0..=n).map(|_| assistant()).collect();
+        let conditions = StopConditions {
+            max_turns: Some(n),
+            ..Default::default()
+        };
+        let collected: Vec<Event> =
+            enforce_stop_conditions(make_stream(events), conditions, cancel.clone())
+                .map(|r| r.unwrap())
+                .collect()
+                .await;
+
+        let messages = collected
+            .iter()
+            .filter(|e| matches!(e, Event::Message(_)))
+            .count();
+        assert_eq!(messages, n as usize, "{collected:?}");
+        assert!(matches!(collected.last(), Some(Event::Error(e))
+            if e.code.as_deref() == Some("max_turns_exceeded")));
+        assert!(cancel.is_cancelled());
+    }
+
+    #[tokio::test]
+    async fn enforce_allows_exactly_max_turns() {
+        let cancel = CancellationToken::new();
+        let events = vec![assistant(), tool_start("a"), assistant()];
+        let conditions = StopConditions {
+            max_turns: Some(2),
+            ..Default::default()
+        };
+        let collected: Vec<_> =
+            enforce_stop_conditions(make_stream(events), conditions, cancel.clone())
+                .collect()
+                .await;
+        assert_eq!(collected.len(), 3);
+        assert!(!cancel.is_cancelled());
+    }
+
+    #[test]
+    fn error_event_carries_reason_code() {
+        let tracker = StopTracker::new(
+            StopConditions {
+                max_tool_calls: Some(3),
+                ..Default::default()
+            },
+            Instant::now(),
+        );
+        match tracker.error_event(StopReason::ToolCalls) {
+            Event::Error(e) => {
+                assert_eq!(e.code.as_deref(), Some("max_tool_calls_exceeded"));
+                assert_eq!(e.message, "exceeded 3 tool calls");
+            }
+            other => panic!("expected Error, got {other:?}"),
+        }
+    }
+}
@@ -13,7 +13,7 @@ fn help_flag_shows_usage() {
         .arg("--help")
         .assert()
         .success()
-        .stdout(predicate::str::contains("Run Claude Code, OpenCode, Codex, or Cursor"));
+        .stdout(predicate::str::contains("Run Claude Code, OpenCode, Codex, Cursor, or Gemini CLI"));
 }
 
 #[test]
@@ -42,6 +42,54 @@ fn list_command_json() {
         .stdout(predicate::str::contains("["));
 }
 
+#[test]
+fn list_command_accepts_no_cache() {
+    harness_cmd().args(["list", "--no-cache"]).assert().success();
+}
+
+#[test]
+fn list_completes_when_version_probe_hangs() {
+    let dir = tempfile::tempdir().unwrap();
+    write_script(
+        &dir.path().join("claude"),
+        "#!/bin/bash\nif [ \"$1\" = \"--version\" ]; then exec /bin/sleep 30; fi\n",
+    );
+
+    let start = std::time::Instant::now();
+    harness_cmd()
+        .env("PATH", dir.path())
+        .args(["list", "--json"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("claude"));
+    assert!(start.elapsed() < std::time::Duration::from_secs(15));
+}
+
+// ─── Version command ─────────────────────────────────────────────
+
+#[test]
+fn version_json_includes_harness_and_agents() {
+    let dir = tempfile::tempdir().unwrap();
+    write_script(
+        &dir.path().join("codex"),
+        "#!/bin/bash\necho 'codex-cli 0.30.0'\n",
+    );
+
+    let output = harness_cmd()
+        .env("PATH", dir.path())
+        .args(["version", "--json"])
+        .assert()
+        .success()
+        .get_output()
+        .clone();
+
+    let json: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(json["harness"], env!("CARGO_PKG_VERSION"));
+    assert!(json["registry_schema_version"].is_u64());
+    assert!(json["agents"].is_object());
+    assert_eq!(json["agents"]["codex"], "codex-cli 0.30.0");
+}
+
 // ─── Check command ────────────────────────────────────────────────
 
 #[test]
@@ -74,6 +122,54 @@ fn check_accepts_all_agent_names() {
     }
 }
 
+#[test]
+fn check_capabilities_reports_permission_modes() {
+    let output = harness_cmd()
+        .args(["check", "gemini", "--capabilities", "--json"])
+        .output()
+        .unwrap();
+    let report: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(report["permission_modes"]["full-access"], "native");
+    assert_eq!(report["permission_modes"]["read-only"], "emulated");
+
+    let output = harness_cmd()
+        .args(["check", "codex", "--capabilities"])
+        .output()
+        .unwrap();
+    let text = String::from_utf8_lossy(&output.stdout);
+    assert!(
+        text.contains("permission_modes:     full-access (native), read-only (native)"),
+        "{text}"
+    );
+}
+
+#[test]
+fn check_all_reports_every_agent() {
+    // Exit code depends on what's installed; the report shape does not.
+    let output = harness_cmd().args(["check", "--all", "--json"]).output().unwrap();
+    let reports: Vec<serde_json::Value> = serde_json::from_slice(&output.stdout).unwrap();
+    let agents: Vec<&str> = reports.iter().map(|r| r["agent"].as_str().unwrap()).collect();
+    assert_eq!(agents, ["claude", "opencode", "codex", "cursor", "gemini"]);
+    for report in &reports {
+        assert!(report["available"].is_boolean());
+        assert!(report["capabilities"]["supports_model"].is_boolean());
+        assert!(report["diagnostics"]["candidates"].is_array());
+    }
+
+    let output = harness_cmd().args(["check", "--all"]).output().unwrap();
+    let table = String::from_utf8_lossy(&output.stdout);
+    assert!(table.starts_with("AGENT"), "{table}");
+    assert_eq!(table.lines().count(), 6, "{table}");
+
+    for extra in ["claude", "--capabilities", "--diagnose"] {
+        harness_cmd()
+            .args(["check", "--all", extra])
+            .assert()
+            .failure()
+            .stderr(predicate::str::contains("cannot be used with"));
+    }
+}
+
 // ─── Run command validation ───────────────────────────────────────
 
 #[test]
@@ -141,7 +237,7 @@ fn run_accepts_all_permission_modes() {
 
 #[test]
 fn run_accepts_all_output_formats() {
-    for fmt in &["text", "json", "stream-json", "markdown"] {
+    for fmt in &["text", "json", "stream-json", "markdown", "html"] {
         let result = harness_cmd()
             .args([
                 "run",
@@ -174,6 +270,42 @@ fn run_invalid_cwd_fails() {
         .failure();
 }
 
+#[test]
+fn run_spawn_failure_json_error_includes_code() {
+    // No agent binaries on PATH: the run fails before spawning.
+    let dir = tempfile::tempdir().unwrap();
+
+    let output = harness_cmd()
+        .env("PATH", dir.path())
+        .env("HOME", dir.path())
+        .env("HARNESS_SESSIONS_DIR", dir.path().join("sessions"))
+        .args(["run", "--agent", "claude", "--prompt", "hello", "--output", "json"])
+        .assert()
+        .failure()
+        .get_output()
+        .clone();
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.lines().count() > 1, "json mode should pretty-print: {stdout}");
+    let err: serde_json::Value = serde_json::from_str(&stdout).unwrap();
+    assert_eq!(err["type"], "error");
+    assert_eq!(err["code"], "E001");
+
+    // Stream-json keeps the error on one line.
+    let output = harness_cmd()
+        .env("PATH", dir.path())
+        .env("HOME", dir.path())
+        .env("HARNESS_SESSIONS_DIR", dir.path().join("sessions"))
+        .args(["run", "--agent", "claude", "--prompt", "hello"])
+        .assert()
+        .failure()
+        .get_output()
+        .clone();
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert_eq!(stdout.lines().count(), 1, "{stdout}");
+    let err: serde_json::Value = serde_json::from_str(&stdout).unwrap();
+    assert_eq!(err["code"], "E001");
+}
+
 // ─── Subcommand routing ──────────────────────────────────────────
 
 #[test]
@@ -208,6 +340,54 @@ fn dry_run_prints_command_info() {
     );
 }
 
+#[test]
+fn dry_run_includes_extra_args_file() {
+    let dir = tempfile::tempdir().unwrap();
+    let args_file = dir.path().join("args.txt");
+    std::fs::write(
+        &args_file,
+        "# passthrough flags\n--add-dir /tmp/shared\n--note \"two words\"  # quoted\n",
+    )
+    .unwrap();
+
+    harness_cmd()
+        .args(["run", "--agent", "claude", "--prompt", "hello", "--binary", "/bin/echo", "--dry-run"])
+        .arg("--extra-args-file").arg(&args_file)
+        .args(["--", "--verbose"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("--verbose --add-dir /tmp/shared --note 'two words'"));
+
+    harness_cmd()
+        .args(["run", "--agent", "claude", "--prompt", "hello", "--dry-run"])
+        .arg("--extra-args-file").arg(dir.path().join("missing.txt"))
+        .assert()
+        .code(2)
+        .stderr(predicate::str::contains("failed to read extra args file"));
+}
+
+#[test]
+fn dry_run_shows_env_file_and_env_overrides() {
+    let dir = tempfile::tempdir().unwrap();
+    let env_file = dir.path().join(".env");
+    std::fs::write(
+        &env_file,
+        "# agent env\nexport FROM_FILE=\"a=b c\"\nOVERRIDDEN=file\n",
+    )
+    .unwrap();
+
+    harness_cmd()
+        .args(["run", "--agent", "claude", "--prompt", "hello", "--binary", "/bin/echo", "--dry-run"])
+        .arg("--env-file").arg(&env_file)
+        .args(["--env", "OVERRIDDEN=flag"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("User env:"))
+        .stdout(predicate::str::contains("FROM_FILE=a=b c"))
+        .stdout(predicate::str::contains("OVERRIDDEN=flag"))
+        .stdout(predicate::str::contains("OVERRIDDEN=file").not());
+}
+
 // ─── Verbose flag ────────────────────────────────────────────────
 
 #[test]
@@ -265,6 +445,20 @@ fn check_diagnose_json() {
     );
 }
 
+#[test]
+fn check_diagnose_resolves_openai_key_for_codex() {
+    let output = harness_cmd()
+        .env_remove("CODEX_API_KEY")
+        .env("OPENAI_API_KEY", "sk-test-0123456789")
+        .args(["check", "codex", "--diagnose", "--json"])
+        .output()
+        .unwrap();
+    let json: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    let api_key = &json["diagnostics"]["api_key"];
+    assert_eq!(api_key["var"], "OPENAI_API_KEY");
+    assert_eq!(api_key["value"], "****6789");
+}
+
 // ─── Models subcommand ───────────────────────────────────────────
 
 #[test]
@@ -293,6 +487,60 @@ fn models_path_runs() {
         .stdout(predicate::str::contains("models.toml"));
 }
 
+#[test]
+fn models_update_fetches_from_source_and_keeps_cache_on_failure() {
+    let dir = tempfile::tempdir().unwrap();
+    let mirror = dir.path().join("mirror.toml");
+    std::fs::write(&mirror, "[models.fast]\nclaude = \"mirror-id\"\n").unwrap();
+    let mirror_url = format!("file://{}", mirror.display());
+    let set_registry_url = |url: &str| {
+        std::fs::write(
+            dir.path().join("harness.toml"),
+            format!("registry_url = \"{url}\"\n"),
+        )
+        .unwrap()
+    };
+    let cache_path = || {
+        let out = harness_cmd()
+            .current_dir(dir.path())
+            .env("HOME", dir.path())
+            .args(["models", "path"])
+            .output()
+            .unwrap();
+        std::path::PathBuf::from(String::from_utf8(out.stdout).unwrap().trim())
+    };
+
+    harness_cmd()
+        .current_dir(dir.path())
+        .env("HOME", dir.path())
+        .args(["models", "update", "--source", &mirror_url])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("mirror.toml"));
+
+    // `registry_url` in harness.toml is the default source, with its own cache.
+    set_registry_url(&mirror_url);
+    let cache = cache_path();
+    assert_ne!(cache, dir.path().join(".harness").join("models.toml"));
+    assert!(std::fs::read_to_string(&cache).unwrap().contains("mirror-id"));
+    assert!(!dir.path().join(".harness").join("models.toml").exists());
+
+    let broken = dir.path().join("broken.toml");
+    std::fs::write(&broken, "{{ not toml").unwrap();
+    set_registry_url(&format!("file://{}", broken.display()));
+    assert_ne!(cache_path(), cache);
+    harness_cmd()
+        .current_dir(dir.path())
+        .env("HOME", dir.path())
+        .args(["models", "update"])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("broken.toml"))
+        .stderr(predicate::str::contains("kept the cached registry"));
+    assert!(!cache_path().exists());
+    assert!(std::fs::read_to_string(&cache).unwrap().contains("mirror-id"));
+}
+
 // ─── Output file flag ────────────────────────────────────────────
 
 #[test]
@@ -318,3 +566,1830 @@ fn run_accepts_output_file_flag() {
         "output-file flag produced unexpected output: {combined}"
     );
 }
+
+#[test]
+fn run_dedupe_output_resumes_partial_file() {
+    let dir = tempfile::tempdir().unwrap();
+    let binary = dir.path().join("claude");
+    write_script(
+        &binary,
+        r#"#!/bin/bash
+echo '{"type":"system","subtype":"init","session_id":"s1","model":"sonnet"}'
+echo '{"type":"assistant","message":{"content":[{"type":"text","text":"Hello"}]}}'
+echo '{"type":"result","subtype":"success","result":"Hello","session_id":"s1"}'
+"#,
+    );
+    let out = dir.path().join("out.ndjson");
+    let run = |dedupe: bool| {
+        let mut cmd = harness_cmd();
+        cmd.env("HARNESS_SESSIONS_DIR", dir.path().join("sessions"))
+            .args(["run", "--agent", "claude", "--prompt", "hi", "--binary"])
+            .arg(&binary)
+            .arg("--output-file")
+            .arg(&out);
+        if dedupe {
+            cmd.arg("--dedupe-output");
+        }
+        cmd.assert().success();
+    };
+
+    run(false);
+    let full = std::fs::read_to_string(&out).unwrap();
+    let full_lines: Vec<&str> = full.lines().collect();
+    assert!(full_lines.len() >= 3, "{full}");
+
+    // A crashed run left only the first two lines behind.
+    let partial = format!("{}\n{}\n", full_lines[0], full_lines[1]);
+    std::fs::write(&out, &partial).unwrap();
+
+    run(true);
+    let resumed = std::fs::read_to_string(&out).unwrap();
+    assert!(resumed.starts_with(&partial), "{resumed}");
+    assert_eq!(resumed.lines().count(), full_lines.len(), "{resumed}");
+    assert_eq!(resumed.matches(r#""type":"session_start""#).count(), 1);
+}
+
+#[test]
+fn run_capture_patch_writes_git_diff() {
+    let dir = tempfile::tempdir().unwrap();
+    let repo = dir.path().join("repo");
+    std::fs::create_dir(&repo).unwrap();
+    let git = |args: &[&str]| {
+        let status = std::process::Command::new("git")
+            .arg("-C").arg(&repo)
+            .args(["-c", "user.name=test", "-c", "user.email=test@example.com"])
+            .args(args)
+            .output()
+            .unwrap()
+            .status;
+        assert!(status.success(), "git {args:?} failed");
+    };
+    git(&["init", "-q"]);
+    std::fs::write(repo.join("tracked.txt"), "before\n").unwrap();
+    git(&["add", "tracked.txt"]);
+    git(&["commit", "-q", "-m", "init"]);
+    // Left dirty before the run; not the agent's doing.
+    std::fs::write(repo.join("stale.txt"), "already here\n").unwrap();
+
+    // The mock "agent" edits a tracked file and creates a new one.
+    let binary = dir.path().join("claude");
+    write_script(
+        &binary,
+        r#"#!/bin/bash
+echo after > tracked.txt
+echo fresh > added.txt
+echo '{"type":"system","subtype":"init","session_id":"s1","model":"sonnet"}'
+echo '{"type":"result","subtype":"success","result":"done","session_id":"s1"}'
+"#,
+    );
+    let patch_path = dir.path().join("run.patch");
+    harness_cmd()
+        .env("HARNESS_SESSIONS_DIR", dir.path().join("sessions"))
+        .args(["run", "--agent", "claude", "--prompt", "edit", "--binary"])
+        .arg(&binary)
+        .arg("--cwd").arg(&repo)
+        .arg("--capture-patch").arg(&patch_path)
+        .assert()
+        .success();
+
+    let patch = std::fs::read_to_string(&patch_path).unwrap();
+    assert!(patch.contains("-before") && patch.contains("+after"), "{patch}");
+    assert!(patch.contains("b/added.txt") && patch.contains("+fresh"), "{patch}");
+    assert!(!patch.contains("stale.txt"), "{patch}");
+}
+
+#[test]
+fn run_name_labels_session() {
+    let dir = tempfile::tempdir().unwrap();
+    let binary = dir.path().join("claude");
+    write_script(
+        &binary,
+        r#"#!/bin/bash
+echo '{"type":"system","subtype":"init","session_id":"s1","model":"sonnet"}'
+echo '{"type":"result","subtype":"success","result":"done","session_id":"s1"}'
+"#,
+    );
+    let sessions = dir.path().join("sessions");
+    harness_cmd()
+        .env("HARNESS_SESSIONS_DIR", &sessions)
+        .args(["run", "--agent", "claude", "--prompt", "hi", "--name", "fix auth bug", "--binary"])
+        .arg(&binary)
+        .assert()
+        .success();
+
+    let meta_path = std::fs::read_dir(&sessions)
+        .unwrap()
+        .map(|e| e.unwrap().path())
+        .find(|p| p.to_string_lossy().ends_with(".meta.json"))
+        .expect("no meta.json written");
+    let meta: serde_json::Value =
+        serde_json::from_str(&std::fs::read_to_string(meta_path).unwrap()).unwrap();
+    assert_eq!(meta["name"], "fix auth bug");
+
+    harness_cmd()
+        .env("HARNESS_SESSIONS_DIR", &sessions)
+        .args(["sessions", "list"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("fix auth bug"));
+}
+
+#[test]
+fn aggregate_log_follows_harness_sessions_dir() {
+    let dir = tempfile::tempdir().unwrap();
+    let binary = dir.path().join("claude");
+    write_script(
+        &binary,
+        r#"#!/bin/bash
+echo '{"type":"system","subtype":"init","session_id":"s1","model":"sonnet"}'
+echo '{"type":"result","subtype":"success","result":"done","session_id":"s1"}'
+"#,
+    );
+    std::fs::write(dir.path().join("harness.toml"), "aggregate_log = true\n").unwrap();
+    let sessions = dir.path().join("sessions");
+    harness_cmd()
+        .current_dir(dir.path())
+        .env("HARNESS_SESSIONS_DIR", &sessions)
+        .args(["run", "--agent", "claude", "--prompt", "hi", "--binary"])
+        .arg(&binary)
+        .assert()
+        .success();
+
+    let aggregate = std::fs::read_dir(&sessions)
+        .unwrap()
+        .map(|e| e.unwrap().path())
+        .find(|p| p.file_name().unwrap().to_string_lossy().starts_with("harness-"))
+        .expect("no aggregate log in HARNESS_SESSIONS_DIR");
+    let content = std::fs::read_to_string(aggregate).unwrap();
+    assert!(content.contains(r#""type":"result""#), "{content}");
+}
+
+#[test]
+fn run_honors_harness_session_id() {
+    let dir = tempfile::tempdir().unwrap();
+    let binary = dir.path().join("claude");
+    write_script(
+        &binary,
+        r#"#!/bin/bash
+echo '{"type":"system","subtype":"init","session_id":"s1","model":"sonnet"}'
+echo '{"type":"result","subtype":"success","result":"done","session_id":"s1"}'
+"#,
+    );
+    let sessions = dir.path().join("sessions");
+    harness_cmd()
+        .env("HARNESS_SESSIONS_DIR", &sessions)
+        .env("HARNESS_SESSION_ID", "fixed-session")
+        .args(["run", "--agent", "claude", "--prompt", "hi", "--binary"])
+        .arg(&binary)
+        .assert()
+        .success();
+
+    assert!(sessions.join("fixed-session.ndjson").exists());
+    let meta = std::fs::read_to_string(sessions.join("fixed-session.meta.json")).unwrap();
+    let meta: serde_json::Value = serde_json::from_str(&meta).unwrap();
+    assert_eq!(meta["session_id"], "fixed-session");
+}
+
+#[test]
+fn run_redacts_secrets_from_stream_and_session_log() {
+    let dir = tempfile::tempdir().unwrap();
+    let binary = dir.path().join("claude");
+    write_script(
+        &binary,
+        r#"#!/bin/bash
+echo '{"type":"system","subtype":"init","session_id":"s1","model":"sonnet"}'
+echo '{"type":"assistant","message":{"content":[{"type":"tool_use","id":"t1","name":"Bash","input":{"command":"env"}}]}}'
+echo '{"type":"user","message":{"content":[{"type":"tool_result","tool_use_id":"t1","content":"ANTHROPIC_API_KEY=sk-ant-REDACTED"}]}}'
+echo '{"type":"result","subtype":"success","result":"done","session_id":"s1"}'
+"#,
+    );
+    let sessions = dir.path().join("sessions");
+    let output = harness_cmd()
+        .env("HARNESS_SESSIONS_DIR", &sessions)
+        .args(["run", "--agent", "claude", "--prompt", "hi", "--binary"])
+        .arg(&binary)
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains("ANTHROPIC_API_KEY=***"), "{stdout}");
+
+    let log_path = std::fs::read_dir(&sessions)
+        .unwrap()
+        .map(|e| e.unwrap().path())
+        .find(|p| p.to_string_lossy().ends_with(".ndjson"))
+        .expect("no session log written");
+    let log = std::fs::read_to_string(log_path).unwrap();
+    assert!(log.contains("ANTHROPIC_API_KEY=***"), "{log}");
+    assert!(!log.contains("sk-ant-"), "{log}");
+}
+
+// ─── Retry ───────────────────────────────────────────────────────
+
+fn write_script(path: &std::path::Path, script: &str) {
+    use std::io::Write;
+    let mut f = std::fs::File::create(path).unwrap();
+    f.write_all(script.as_bytes()).unwrap();
+    f.sync_all().unwrap();
+    drop(f);
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        std::fs::set_permissions(path, std::fs::Permissions::from_mode(0o755)).unwrap();
+    }
+}
+
+/// Mock Claude binary that fails with a rate-limit error on its first
+/// invocation and succeeds on every later one.
+fn create_flaky_claude(dir: &std::path::Path) -> std::path::PathBuf {
+    let path = dir.join("claude");
+    let marker = dir.join("attempted");
+    let script = format!(
+        r#"#!/bin/bash
+if [ ! -f "{marker}" ]; then
+  touch "{marker}"
+  echo "error: rate limit exceeded (429)" >&2
+  exit 1
+fi
+echo '{{"type":"system","subtype":"init","session_id":"s1","model":"m"}}'
+echo '{{"type":"result","subtype":"success","result":"recovered","session_id":"s1"}}'
+"#,
+        marker = marker.display()
+    );
+    write_script(&path, &script);
+    path
+}
+
+#[test]
+fn run_retries_on_rate_limit() {
+    let dir = tempfile::tempdir().unwrap();
+    let binary = create_flaky_claude(dir.path());
+    harness_cmd()
+        .args([
+            "run",
+            "--agent", "claude",
+            "--prompt", "hello",
+            "--output", "json",
+            "--retry", "2",
+            "--retry-on", "rate_limit,timeout",
+            "--retry-delay", "0",
+            "--binary",
+        ])
+        .arg(&binary)
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("recovered"))
+        .stderr(predicate::str::contains("retrying"));
+}
+
+#[test]
+fn run_does_not_retry_unlisted_code() {
+    let dir = tempfile::tempdir().unwrap();
+    let binary = create_flaky_claude(dir.path());
+    harness_cmd()
+        .args([
+            "run",
+            "--agent", "claude",
+            "--prompt", "hello",
+            "--output", "text",
+            "--retry", "2",
+            "--retry-on", "timeout",
+            "--retry-delay", "0",
+            "--binary",
+        ])
+        .arg(&binary)
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("rate limit"));
+}
+
+// ─── Completions ─────────────────────────────────────────────────
+
+#[test]
+fn completions_bash_emits_script() {
+    harness_cmd()
+        .args(["completions", "bash"])
+        .assert()
+        .success()
+        .stdout(predicate::str::is_empty().not())
+        .stdout(predicate::str::contains("harness"));
+}
+
+#[test]
+fn completions_rejects_unknown_shell() {
+    harness_cmd()
+        .args(["completions", "tcsh"])
+        .assert()
+        .failure();
+}
+
+// ─── Deadline ────────────────────────────────────────────────────
+
+#[test]
+fn run_deadline_times_out() {
+    let dir = tempfile::tempdir().unwrap();
+    let binary = dir.path().join("claude");
+    write_script(
+        &binary,
+        "#!/bin/bash\necho '{\"type\":\"system\",\"subtype\":\"init\",\"session_id\":\"s1\"}'\nsleep 30\n",
+    );
+    let deadline = harness::datetime::format_rfc3339(harness::event::now_ms() + 1_500);
+    let start = std::time::Instant::now();
+    harness_cmd()
+        .args([
+            "run",
+            "--agent", "claude",
+            "--prompt", "hello",
+            "--deadline", &deadline,
+            "--binary",
+        ])
+        .arg(&binary)
+        .assert()
+        .failure()
+        .stdout(predicate::str::contains("\"code\":\"timeout\""));
+    assert!(start.elapsed() < std::time::Duration::from_secs(20));
+}
+
+#[test]
+fn run_past_deadline_is_rejected() {
+    harness_cmd()
+        .args([
+            "run",
+            "--agent", "claude",
+            "--prompt", "hello",
+            "--deadline", "2000-01-01T00:00:00Z",
+        ])
+        .assert()
+        .code(2)
+        .stderr(predicate::str::contains("already in the past"));
+}
+
+// ─── Sessions ────────────────────────────────────────────────────
+
+#[test]
+fn sessions_export_shell_script() {
+    let dir = tempfile::tempdir().unwrap();
+    std::fs::write(
+        dir.path().join("sess-1.ndjson"),
+        concat!(
+            r#"{"type":"session_start","session_id":"sess-1","agent":"codex"}"#, "\n",
+            r#"{"type":"tool_start","call_id":"item_1","tool_name":"shell","input":{"command":"echo hello"}}"#, "\n",
+            r#"{"type":"tool_end","call_id":"item_1","tool_name":"shell","success":true}"#, "\n",
+            r#"{"type":"tool_start","call_id":"item_2","tool_name":"shell","input":{"command":"cargo test"}}"#, "\n",
+        ),
+    )
+    .unwrap();
+
+    harness_cmd()
+        .env("HARNESS_SESSIONS_DIR", dir.path())
+        .args(["sessions", "export", "sess-1", "--as", "shell"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("# call item_1\necho hello"))
+        .stdout(predicate::str::contains("# call item_2\ncargo test"));
+}
+
+#[test]
+fn sessions_export_unknown_session_fails() {
+    let dir = tempfile::tempdir().unwrap();
+    harness_cmd()
+        .env("HARNESS_SESSIONS_DIR", dir.path())
+        .args(["sessions", "export", "missing"])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("session not found"));
+}
+
+#[test]
+fn sessions_export_rejects_unknown_format() {
+    harness_cmd()
+        .args(["sessions", "export", "sess-1", "--as", "csv"])
+        .assert()
+        .code(2)
+        .stderr(predicate::str::contains("possible values: shell"));
+}
+
+#[test]
+fn sessions_stats_json() {
+    let dir = tempfile::tempdir().unwrap();
+    std::fs::write(
+        dir.path().join("a.meta.json"),
+        r#"{"session_id":"a","agent":"Codex","prompt":"x","model":"gpt-5","start_time":"0","duration_ms":500,"success":true,"total_cost_usd":0.1}"#,
+    )
+    .unwrap();
+
+    let output = harness_cmd()
+        .env("HARNESS_SESSIONS_DIR", dir.path())
+        .args(["sessions", "stats", "--json"])
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+    let stats: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(stats["sessions"], 1);
+    assert_eq!(stats["by_agent"]["Codex"], 1);
+    assert_eq!(stats["avg_duration_ms"], 500);
+}
+
+#[test]
+fn sessions_list_newest_first() {
+    let dir = tempfile::tempdir().unwrap();
+    for (id, start) in [("older", "100"), ("newer", "200")] {
+        std::fs::write(
+            dir.path().join(format!("{id}.meta.json")),
+            format!(r#"{{"session_id":"{id}","agent":"Codex","prompt":"task {id}","start_time":"{start}","duration_ms":1500,"success":true}}"#),
+        )
+        .unwrap();
+    }
+
+    let output = harness_cmd()
+        .env("HARNESS_SESSIONS_DIR", dir.path())
+        .args(["sessions", "list", "--json"])
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+    let sessions: Vec<serde_json::Value> = serde_json::from_slice(&output.stdout).unwrap();
+    let ids: Vec<&str> = sessions.iter().map(|s| s["session_id"].as_str().unwrap()).collect();
+    assert_eq!(ids, ["newer", "older"]);
+
+    harness_cmd()
+        .env("HARNESS_SESSIONS_DIR", dir.path())
+        .args(["sessions", "list"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("newer").and(predicate::str::contains("1.5s")))
+        .stdout(predicate::str::contains("task older"));
+}
+
+#[test]
+fn sessions_tag_then_filter_list() {
+    let dir = tempfile::tempdir().unwrap();
+    for (id, start) in [("fix-auth", "100"), ("docs", "200")] {
+        std::fs::write(
+            dir.path().join(format!("{id}.meta.json")),
+            format!(r#"{{"session_id":"{id}","agent":"Claude","prompt":"task {id}","start_time":"{start}","success":true}}"#),
+        )
+        .unwrap();
+    }
+
+    harness_cmd()
+        .env("HARNESS_SESSIONS_DIR", dir.path())
+        .args(["sessions", "tag", "fix-auth", "bug-fix", "auth"])
+        .assert()
+        .success()
+        .stdout("fix-auth: bug-fix, auth\n");
+
+    let output = harness_cmd()
+        .env("HARNESS_SESSIONS_DIR", dir.path())
+        .args(["sessions", "list", "--json", "--tag", "auth"])
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+    let sessions: Vec<serde_json::Value> = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(sessions.len(), 1);
+    assert_eq!(sessions[0]["session_id"], "fix-auth");
+    assert_eq!(sessions[0]["tags"], serde_json::json!(["bug-fix", "auth"]));
+
+    harness_cmd()
+        .env("HARNESS_SESSIONS_DIR", dir.path())
+        .args(["sessions", "list", "--tag", "bug-fix"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("task fix-auth  [bug-fix, auth]"))
+        .stdout(predicate::str::contains("docs").not());
+
+    harness_cmd()
+        .env("HARNESS_SESSIONS_DIR", dir.path())
+        .args(["sessions", "tag", "nope", "x"])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("session not found: nope"));
+}
+
+#[test]
+fn sessions_show_replays_transcript() {
+    let dir = tempfile::tempdir().unwrap();
+    std::fs::write(
+        dir.path().join("sess-1.ndjson"),
+        concat!(
+            r#"{"type":"session_start","session_id":"sess-1","agent":"codex","model":"gpt-5"}"#, "\n",
+            r#"{"type":"message","role":"assistant","text":"Fixed the bug."}"#, "\n",
+            r#"{"type":"tool_start","call_id":"c1","tool_name":"shell"}"#, "\n",
+            r#"{"type":"result","success":true,"text":"Fixed the bug.","session_id":"sess-1"}"#, "\n",
+        ),
+    )
+    .unwrap();
+
+    harness_cmd()
+        .env("HARNESS_SESSIONS_DIR", dir.path())
+        .args(["sessions", "show", "sess-1"])
+        .assert()
+        .success()
+        .stdout("Fixed the bug.\n");
+
+    harness_cmd()
+        .env("HARNESS_SESSIONS_DIR", dir.path())
+        .args(["sessions", "show", "sess-1", "--format", "markdown"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("# harness session — codex"))
+        .stdout(predicate::str::contains("**Model:** gpt-5"))
+        .stdout(predicate::str::contains("> **Tool:** `shell` (c1)"))
+        .stdout(predicate::str::contains("**Result:** Success"));
+}
+
+#[test]
+fn run_show_recent_lists_agent_sessions_on_stderr() {
+    let dir = tempfile::tempdir().unwrap();
+    let metas = [
+        ("a", "Claude Code", "1700000000", "fix the login bug", Some("claude-sess-a")),
+        ("b", "Codex", "1700000100", "codex task", None),
+        ("c", "Claude Code", "1700000200", "add tests", None),
+    ];
+    for (id, agent, start, prompt, agent_session) in metas {
+        let mut meta = serde_json::json!({
+            "session_id": id, "agent": agent, "prompt": prompt,
+            "start_time": start, "success": true,
+        });
+        if let Some(s) = agent_session {
+            meta["agent_session_id"] = s.into();
+        }
+        std::fs::write(dir.path().join(format!("{id}.meta.json")), meta.to_string()).unwrap();
+    }
+
+    let output = harness_cmd()
+        .env("HARNESS_SESSIONS_DIR", dir.path())
+        .args(["run", "--agent", "claude", "--prompt", "x", "--show-recent", "--binary", "/bin/echo", "--dry-run"])
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("Recent Claude Code sessions:"), "{stderr}");
+    assert!(stderr.contains("claude-sess-a"), "{stderr}");
+    assert!(!stderr.contains("codex task"), "{stderr}");
+    // Newest first.
+    assert!(stderr.find("add tests").unwrap() < stderr.find("fix the login bug").unwrap());
+}
+
+#[test]
+fn run_since_injects_prior_result() {
+    let dir = tempfile::tempdir().unwrap();
+    std::fs::write(
+        dir.path().join("prior.ndjson"),
+        concat!(
+            r#"{"type":"session_start","session_id":"a1","agent":"claude"}"#, "\n",
+            r#"{"type":"result","success":true,"text":"Added the parser module","session_id":"a1"}"#, "\n",
+        ),
+    )
+    .unwrap();
+
+    harness_cmd()
+        .env("HARNESS_SESSIONS_DIR", dir.path())
+        .args([
+            "run",
+            "--agent", "claude",
+            "--prompt", "write tests for it",
+            "--since", "prior",
+            "--binary", "/bin/echo",
+            "--dry-run",
+        ])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("Added the parser module"))
+        .stdout(predicate::str::contains("write tests for it"));
+
+    // The prepended context counts toward --prompt-max-bytes.
+    harness_cmd()
+        .env("HARNESS_SESSIONS_DIR", dir.path())
+        .args([
+            "run",
+            "--agent", "claude",
+            "--prompt", "write tests for it",
+            "--since", "prior",
+            "--prompt-max-bytes", "40",
+            "--binary", "/bin/echo",
+            "--dry-run",
+        ])
+        .assert()
+        .code(2)
+        .stderr(predicate::str::contains("prompt is 60 bytes, over the 40-byte limit"));
+}
+
+#[test]
+fn run_wrap_command_keeps_quoted_arguments() {
+    harness_cmd()
+        .args([
+            "run",
+            "--agent", "claude",
+            "--prompt", "hi",
+            "--wrap-command", "sandbox-exec -p 'my profile'",
+            "--binary", "/bin/echo",
+            "--dry-run",
+        ])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("Wrap:   sandbox-exec -p 'my profile'"));
+
+    harness_cmd()
+        .args([
+            "run",
+            "--agent", "claude",
+            "--prompt", "hi",
+            "--wrap-command", "sandbox-exec -p 'unterminated",
+            "--binary", "/bin/echo",
+            "--dry-run",
+        ])
+        .assert()
+        .code(2)
+        .stderr(predicate::str::contains("error: --wrap-command:"));
+}
+
+#[test]
+fn run_prompt_append_file_appends_raw_text() {
+    let dir = tempfile::tempdir().unwrap();
+    let appendix = dir.path().join("notes.txt");
+    std::fs::write(&appendix, "APPENDIX LINE\n").unwrap();
+
+    harness_cmd()
+        .args([
+            "run",
+            "--agent", "claude",
+            "--prompt", "summarize the notes",
+            "--prompt-append-file", appendix.to_str().unwrap(),
+            "--binary", "/bin/echo",
+            "--dry-run",
+        ])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("summarize the notes\n\nAPPENDIX LINE\n"));
+}
+
+#[test]
+fn run_prompt_max_bytes_rejects_oversized_prompt() {
+    harness_cmd()
+        .args([
+            "run",
+            "--agent", "claude",
+            "--prompt", &"x".repeat(2048),
+            "--prompt-max-bytes", "1024",
+            "--binary", "/bin/echo",
+            "--dry-run",
+        ])
+        .assert()
+        .code(2)
+        .stderr(predicate::str::contains("prompt is 2048 bytes, over the 1024-byte limit"));
+
+    // The limit can also come from harness.toml.
+    let dir = tempfile::tempdir().unwrap();
+    std::fs::write(dir.path().join("harness.toml"), "prompt_max_bytes = 4\n").unwrap();
+    harness_cmd()
+        .current_dir(dir.path())
+        .args(["run", "--agent", "claude", "--prompt", "hello", "--binary", "/bin/echo", "--dry-run"])
+        .assert()
+        .code(2)
+        .stderr(predicate::str::contains("over the 4-byte limit"));
+}
+
+#[test]
+fn run_prompt_encoding_decodes_latin1_file() {
+    let dir = tempfile::tempdir().unwrap();
+    let prompt = dir.path().join("prompt.txt");
+    // "café naïve" in ISO-8859-1.
+    std::fs::write(&prompt, b"caf\xe9 na\xefve").unwrap();
+
+    harness_cmd()
+        .args(["run", "--agent", "claude", "--prompt-file"])
+        .arg(&prompt)
+        .args(["--prompt-encoding", "latin1", "--binary", "/bin/echo", "--dry-run"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("café naïve"));
+
+    // Without the flag the file is read as UTF-8 and rejected.
+    harness_cmd()
+        .args(["run", "--agent", "claude", "--prompt-file"])
+        .arg(&prompt)
+        .args(["--binary", "/bin/echo", "--dry-run"])
+        .assert()
+        .code(2)
+        .stderr(predicate::str::contains("not valid UTF-8"));
+}
+
+#[test]
+fn run_env_flag_shows_in_dry_run() {
+    harness_cmd()
+        .args([
+            "run",
+            "--agent", "codex",
+            "--prompt", "x",
+            "--env", "CODEX_API_KEY=sk-test",
+            "--env", "EXTRA=a=b",
+            "--binary", "/bin/echo",
+            "--dry-run",
+        ])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("CODEX_API_KEY=sk-test"))
+        .stdout(predicate::str::contains("EXTRA=a=b"));
+}
+
+#[test]
+fn run_env_flag_rejects_malformed_pair() {
+    harness_cmd()
+        .args(["run", "--agent", "codex", "--prompt", "x", "--env", "NOVALUE", "--dry-run"])
+        .assert()
+        .code(2)
+        .stderr(predicate::str::contains("expected KEY=VALUE"));
+}
+
+#[test]
+fn run_since_unknown_session_fails() {
+    let dir = tempfile::tempdir().unwrap();
+    harness_cmd()
+        .env("HARNESS_SESSIONS_DIR", dir.path())
+        .args(["run", "--agent", "claude", "--prompt", "x", "--since", "nope", "--dry-run"])
+        .assert()
+        .code(2)
+        .stderr(predicate::str::contains("session not found"));
+}
+
+// ─── Broken pipe ─────────────────────────────────────────────────
+
+#[test]
+fn run_exits_cleanly_when_stdout_reader_closes() {
+    use std::io::BufRead;
+
+    let dir = tempfile::tempdir().unwrap();
+    let binary = dir.path().join("claude");
+    let pid_file = dir.path().join("agent.pid");
+    // Emit far more than a pipe buffer's worth of events, then hang.
+    let script = format!(
+        r#"#!/bin/bash
+echo $$ > "{pid}"
+echo '{{"type":"system","subtype":"init","session_id":"s1"}}'
+for i in $(seq 1 5000); do
+  echo '{{"type":"assistant","message":{{"role":"assistant","content":[{{"type":"text","text":"line of output that keeps the pipe busy"}}]}}}}'
+done
+sleep 60
+"#,
+        pid = pid_file.display()
+    );
+    write_script(&binary, &script);
+
+    let mut child = std::process::Command::new(env!("CARGO_BIN_EXE_harness"))
+        .env("HARNESS_SESSIONS_DIR", dir.path().join("sessions"))
+        .args(["run", "--agent", "claude", "--prompt", "hello", "--binary"])
+        .arg(&binary)
+        .stdout(std::process::Stdio::piped())
+        .stderr(std::process::Stdio::piped())
+        .spawn()
+        .unwrap();
+
+    // Read a single line, then hang up like `| head -1`.
+    {
+        let stdout = child.stdout.take().unwrap();
+        let mut reader = std::io::BufReader::new(stdout);
+        let mut line = String::new();
+        reader.read_line(&mut line).unwrap();
+        assert!(line.contains("session_start"), "unexpected first line: {line}");
+    }
+
+    let start = std::time::Instant::now();
+    let status = loop {
+        if let Some(status) = child.try_wait().unwrap() {
+            break status;
+        }
+        if start.elapsed() > std::time::Duration::from_secs(20) {
+            let _ = child.kill();
+            panic!("harness did not exit after stdout closed");
+        }
+        std::thread::sleep(std::time::Duration::from_millis(50));
+    };
+
+    let mut stderr = String::new();
+    std::io::Read::read_to_string(&mut child.stderr.take().unwrap(), &mut stderr).unwrap();
+    assert!(!stderr.contains("panicked"), "harness panicked: {stderr}");
+    assert!(status.success(), "expected exit 0, got {status:?}; stderr: {stderr}");
+
+    // The agent process should be gone (SIGTERM to its process group).
+    let pid = std::fs::read_to_string(&pid_file).unwrap().trim().to_string();
+    let gone = (0..100).any(|_| {
+        let alive = std::process::Command::new("kill")
+            .args(["-0", &pid])
+            .stderr(std::process::Stdio::null())
+            .status()
+            .map(|s| s.success())
+            .unwrap_or(false);
+        if alive {
+            std::thread::sleep(std::time::Duration::from_millis(50));
+        }
+        !alive
+    });
+    assert!(gone, "agent process {pid} still running");
+}
+
+// ─── JSON output ─────────────────────────────────────────────────
+
+#[test]
+fn run_json_output_includes_usage_and_cost() {
+    let dir = tempfile::tempdir().unwrap();
+    let binary = dir.path().join("claude");
+    write_script(
+        &binary,
+        r#"#!/bin/bash
+echo '{"type":"system","subtype":"init","session_id":"j1","model":"mock-model"}'
+echo '{"type":"assistant","message":{"role":"assistant","content":[{"type":"text","text":"All done."}]}}'
+echo '{"type":"result","subtype":"success","result":"All done.","session_id":"j1","duration_ms":1200,"total_cost_usd":0.05,"usage":{"input_tokens":300,"output_tokens":40}}'
+"#,
+    );
+
+    let output = harness_cmd()
+        .env("HARNESS_SESSIONS_DIR", dir.path().join("sessions"))
+        .args(["run", "--agent", "claude", "--prompt", "hi", "--output", "json", "--binary"])
+        .arg(&binary)
+        .assert()
+        .success()
+        .get_output()
+        .clone();
+
+    let json: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(json["type"], "result");
+    assert_eq!(json["result"], "All done.");
+    assert_eq!(json["model"], "mock-model");
+    assert_eq!(json["duration_ms"], 1200);
+    assert_eq!(json["total_cost_usd"], 0.05);
+    assert_eq!(json["usage"]["input_tokens"], 300);
+    assert_eq!(json["usage"]["output_tokens"], 40);
+    assert!(json["agent"].is_string());
+}
+
+// ─── Compare ─────────────────────────────────────────────────────
+
+#[test]
+fn run_compare_reports_both_agents() {
+    let dir = tempfile::tempdir().unwrap();
+    let claude = dir.path().join("claude");
+    write_script(
+        &claude,
+        r#"#!/bin/bash
+echo '{"type":"system","subtype":"init","session_id":"c1","model":"mock-claude"}'
+echo '{"type":"result","subtype":"success","result":"Claude found the bug.","session_id":"c1","duration_ms":800,"total_cost_usd":0.02}'
+"#,
+    );
+    let codex = dir.path().join("codex");
+    write_script(
+        &codex,
+        r#"#!/bin/bash
+echo '{"type":"thread.started","thread_id":"th-1"}'
+echo '{"type":"item.completed","item":{"id":"item_1","type":"agent_message","text":"Codex fixed it."}}'
+echo '{"type":"turn.completed","usage":{"input_tokens":10,"output_tokens":5}}'
+"#,
+    );
+    std::fs::write(
+        dir.path().join("harness.toml"),
+        format!(
+            "[agents.claude]\nbinary = \"{}\"\n\n[agents.codex]\nbinary = \"{}\"\n",
+            claude.display(),
+            codex.display()
+        ),
+    )
+    .unwrap();
+
+    harness_cmd()
+        .current_dir(dir.path())
+        .env("HARNESS_SESSIONS_DIR", dir.path().join("sessions"))
+        .args(["run", "--prompt", "fix it", "--compare", "claude,codex"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("| | Claude Code | Codex |"))
+        .stdout(predicate::str::contains("## Claude Code"))
+        .stdout(predicate::str::contains("Claude found the bug."))
+        .stdout(predicate::str::contains("## Codex"))
+        .stdout(predicate::str::contains("Codex fixed it."));
+}
+
+#[test]
+fn run_compare_report_follows_agent_order_not_completion_order() {
+    let dir = tempfile::tempdir().unwrap();
+    let claude = dir.path().join("claude");
+    write_script(
+        &claude,
+        r#"#!/bin/bash
+sleep 0.5
+echo '{"type":"result","subtype":"success","result":"Claude was slow.","session_id":"c1"}'
+"#,
+    );
+    let codex = dir.path().join("codex");
+    write_script(
+        &codex,
+        r#"#!/bin/bash
+echo '{"type":"item.completed","item":{"id":"item_1","type":"agent_message","text":"Codex was fast."}}'
+echo '{"type":"turn.completed","usage":{"input_tokens":1,"output_tokens":1}}'
+"#,
+    );
+    std::fs::write(
+        dir.path().join("harness.toml"),
+        format!(
+            "[agents.claude]\nbinary = \"{}\"\n\n[agents.codex]\nbinary = \"{}\"\n",
+            claude.display(),
+            codex.display()
+        ),
+    )
+    .unwrap();
+
+    let report = |args: &[&str]| {
+        let output = harness_cmd()
+            .current_dir(dir.path())
+            .env("HARNESS_SESSIONS_DIR", dir.path().join("sessions"))
+            .args(["run", "--prompt", "go"])
+            .args(args)
+            .output()
+            .unwrap();
+        assert!(output.status.success(), "{output:?}");
+        String::from_utf8(output.stdout).unwrap()
+    };
+    let sections = |report: &str| {
+        report
+            .lines()
+            .filter(|l| l.starts_with("## "))
+            .map(str::to_string)
+            .collect::<Vec<_>>()
+    };
+
+    let claude_first = report(&["--compare", "codex,claude", "--agent-order", "claude"]);
+    assert!(claude_first.contains("| | Claude Code | Codex |"), "{claude_first}");
+    assert_eq!(sections(&claude_first), ["## Claude Code", "## Codex"]);
+
+    let default_order = report(&["--compare", "codex,claude"]);
+    assert_eq!(sections(&default_order), ["## Codex", "## Claude Code"]);
+
+    harness_cmd()
+        .args(["run", "--prompt", "go", "--compare", "codex,claude", "--agent-order", "gemini"])
+        .assert()
+        .code(2)
+        .stderr(predicate::str::contains("not in --compare"));
+}
+
+#[test]
+fn run_compare_rejects_single_agent() {
+    harness_cmd()
+        .args(["run", "--prompt", "hi", "--compare", "claude"])
+        .assert()
+        .code(2)
+        .stderr(predicate::str::contains("at least two agents"));
+}
+
+#[test]
+fn run_compare_rejects_repeated_agent_and_output_flags() {
+    harness_cmd()
+        .args(["run", "--prompt", "hi", "--compare", "claude,codex,claude"])
+        .assert()
+        .code(2)
+        .stderr(predicate::str::contains("Claude Code more than once"));
+    for flag in [["--output", "json"], ["--output-file", "out.txt"]] {
+        harness_cmd()
+            .args(["run", "--prompt", "hi", "--compare", "claude,codex"])
+            .args(flag)
+            .assert()
+            .code(2)
+            .stderr(predicate::str::contains("cannot be used with"));
+    }
+}
+
+#[test]
+fn run_compare_applies_timeout_and_logs_each_run() {
+    let dir = tempfile::tempdir().unwrap();
+    let claude = dir.path().join("claude");
+    write_script(
+        &claude,
+        r#"#!/bin/bash
+echo '{"type":"system","subtype":"init","session_id":"c1","model":"mock-claude"}'
+sleep 30
+"#,
+    );
+    let codex = dir.path().join("codex");
+    write_script(
+        &codex,
+        r#"#!/bin/bash
+echo '{"type":"item.completed","item":{"id":"item_1","type":"agent_message","text":"Codex was fast."}}'
+echo '{"type":"turn.completed","usage":{"input_tokens":1,"output_tokens":1}}'
+"#,
+    );
+    std::fs::write(
+        dir.path().join("harness.toml"),
+        format!(
+            "[agents.claude]\nbinary = \"{}\"\n\n[agents.codex]\nbinary = \"{}\"\n",
+            claude.display(),
+            codex.display()
+        ),
+    )
+    .unwrap();
+    let sessions = dir.path().join("sessions");
+
+    let start = std::time::Instant::now();
+    harness_cmd()
+        .current_dir(dir.path())
+        .env("HARNESS_SESSIONS_DIR", &sessions)
+        .env("HARNESS_SESSION_ID", "cmp")
+        .args(["run", "--prompt", "go", "--compare", "claude,codex", "--timeout", "1"])
+        .assert()
+        .failure()
+        .stdout(predicate::str::contains("Codex was fast."));
+    assert!(start.elapsed() < std::time::Duration::from_secs(15));
+
+    let claude_log = std::fs::read_to_string(sessions.join("cmp-claude.ndjson")).unwrap();
+    assert!(claude_log.contains(r#""code":"timeout""#), "{claude_log}");
+    let codex_log = std::fs::read_to_string(sessions.join("cmp-codex.ndjson")).unwrap();
+    assert!(codex_log.contains("Codex was fast."), "{codex_log}");
+}
+
+// ─── Fail fast on error events ───────────────────────────────────
+
+#[test]
+fn run_fail_fast_on_error_event_stops_early() {
+    let dir = tempfile::tempdir().unwrap();
+    let binary = dir.path().join("codex");
+    write_script(
+        &binary,
+        r#"#!/bin/bash
+echo '{"type":"thread.started","thread_id":"th-1"}'
+echo '{"type":"item.completed","item":{"id":"item_1","type":"agent_message","text":"Before the error."}}'
+echo '{"type":"error","message":"tool crashed","code":"tool_error"}'
+sleep 5
+echo '{"type":"item.completed","item":{"id":"item_2","type":"agent_message","text":"Recovered anyway."}}'
+echo '{"type":"turn.completed","usage":{"input_tokens":10,"output_tokens":5}}'
+"#,
+    );
+
+    let started = std::time::Instant::now();
+    harness_cmd()
+        .env("HARNESS_SESSIONS_DIR", dir.path().join("sessions"))
+        .args(["run", "--agent", "codex", "--prompt", "hi", "--fail-fast-on-error-event", "--binary"])
+        .arg(&binary)
+        .assert()
+        .failure()
+        .stdout(predicate::str::contains("tool crashed"))
+        .stdout(predicate::str::contains("Recovered anyway.").not());
+    assert!(started.elapsed() < std::time::Duration::from_secs(5));
+}
+
+// ─── Stop conditions ─────────────────────────────────────────────
+
+#[test]
+fn run_max_tool_calls_reports_stop_code() {
+    let dir = tempfile::tempdir().unwrap();
+    let binary = dir.path().join("codex");
+    write_script(
+        &binary,
+        r#"#!/bin/bash
+echo '{"type":"thread.started","thread_id":"th-1"}'
+echo '{"type":"item.started","item":{"id":"c1","type":"command_execution","command":"ls"}}'
+echo '{"type":"item.started","item":{"id":"c2","type":"command_execution","command":"ls -la"}}'
+sleep 5
+echo '{"type":"turn.completed","usage":{"input_tokens":10,"output_tokens":5}}'
+"#,
+    );
+
+    let started = std::time::Instant::now();
+    harness_cmd()
+        .env("HARNESS_SESSIONS_DIR", dir.path().join("sessions"))
+        .args(["run", "--agent", "codex", "--prompt", "hi", "--max-tool-calls", "1", "--binary"])
+        .arg(&binary)
+        .assert()
+        .failure()
+        .stdout(predicate::str::contains(r#""code":"max_tool_calls_exceeded""#));
+    assert!(started.elapsed() < std::time::Duration::from_secs(5));
+}
+
+#[test]
+fn run_idle_timeout_reports_stop_code() {
+    let dir = tempfile::tempdir().unwrap();
+    let binary = dir.path().join("codex");
+    write_script(
+        &binary,
+        r#"#!/bin/bash
+echo '{"type":"thread.started","thread_id":"th-1"}'
+sleep 5
+echo '{"type":"turn.completed","usage":{"input_tokens":10,"output_tokens":5}}'
+"#,
+    );
+
+    let started = std::time::Instant::now();
+    harness_cmd()
+        .env("HARNESS_SESSIONS_DIR", dir.path().join("sessions"))
+        .args(["run", "--agent", "codex", "--prompt", "hi", "--idle-timeout", "1", "--binary"])
+        .arg(&binary)
+        .assert()
+        .failure()
+        .stdout(predicate::str::contains(r#""code":"idle_timeout""#));
+    assert!(started.elapsed() < std::time::Duration::from_secs(5));
+}
+
+// ─── Event validation ────────────────────────────────────────────
+
+#[test]
+fn run_validate_events_passes_well_formed_stream() {
+    let dir = tempfile::tempdir().unwrap();
+    let binary = dir.path().join("claude");
+    write_script(
+        &binary,
+        r#"#!/bin/bash
+echo '{"type":"system","subtype":"init","session_id":"s1","model":"sonnet"}'
+echo '{"type":"assistant","message":{"content":[{"type":"text","text":"Hello"}]}}'
+echo '{"type":"result","subtype":"success","result":"Hello","session_id":"s1","total_cost_usd":0.01}'
+"#,
+    );
+
+    harness_cmd()
+        .env("HARNESS_SESSIONS_DIR", dir.path().join("sessions"))
+        .args(["run", "--agent", "claude", "--prompt", "hi", "--validate-events", "--binary"])
+        .arg(&binary)
+        .assert()
+        .success()
+        .stdout(predicate::str::contains(r#""type":"result""#))
+        .stderr(predicate::str::contains("invalid event").not());
+}
+
+#[test]
+fn run_print_events_count_reports_breakdown() {
+    let dir = tempfile::tempdir().unwrap();
+    let binary = dir.path().join("claude");
+    write_script(
+        &binary,
+        r#"#!/bin/bash
+echo '{"type":"system","subtype":"init","session_id":"s1","model":"sonnet"}'
+echo '{"type":"assistant","message":{"content":[{"type":"text","text":"Reading"},{"type":"tool_use","id":"t1","name":"Read","input":{}}]}}'
+echo '{"type":"user","message":{"content":[{"type":"tool_result","tool_use_id":"t1","content":"ok"}]}}'
+echo '{"type":"assistant","message":{"content":[{"type":"text","text":"Done"}]}}'
+echo '{"type":"result","subtype":"success","result":"Done","session_id":"s1","usage":{"input_tokens":10,"output_tokens":5}}'
+"#,
+    );
+
+    harness_cmd()
+        .env("HARNESS_SESSIONS_DIR", dir.path().join("sessions"))
+        .args(["run", "--agent", "claude", "--prompt", "hi", "--print-events-count", "--binary"])
+        .arg(&binary)
+        .assert()
+        .success()
+        .stderr(predicate::str::contains(
+            "Events: SessionStart:1 Message:3 ToolStart:1 ToolEnd:1 UsageDelta:1 Result:1 Summary:1\n",
+        ));
+}
+
+#[test]
+fn run_output_html_renders_transcript() {
+    let dir = tempfile::tempdir().unwrap();
+    let binary = dir.path().join("claude");
+    write_script(
+        &binary,
+        r#"#!/bin/bash
+echo '{"type":"system","subtype":"init","session_id":"s1","model":"sonnet"}'
+echo '{"type":"assistant","message":{"content":[{"type":"text","text":"Reading <main.rs>"},{"type":"tool_use","id":"t1","name":"Read","input":{"file_path":"src/main.rs"}}]}}'
+echo '{"type":"user","message":{"content":[{"type":"tool_result","tool_use_id":"t1","content":"fn main() {}"}]}}'
+echo '{"type":"result","subtype":"success","result":"All done","session_id":"s1","total_cost_usd":0.25}'
+"#,
+    );
+
+    let output = harness_cmd()
+        .env("HARNESS_SESSIONS_DIR", dir.path().join("sessions"))
+        .args(["run", "--agent", "claude", "--prompt", "fix it", "--output", "html", "--binary"])
+        .arg(&binary)
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+    let html = String::from_utf8(output.stdout).unwrap();
+    assert!(html.starts_with("<!DOCTYPE html>"), "{html}");
+    assert!(html.trim_end().ends_with("</html>"));
+    assert!(html.contains("<h1>harness session — claude</h1>"));
+    assert!(html.contains("Model: sonnet"));
+    assert!(html.contains("Cost: $0.250"));
+    assert!(html.contains("<div class=\"msg user\"><span class=\"role\">User</span>fix it</div>"));
+    assert!(html.contains("<div class=\"msg assistant\"><span class=\"role\">Assistant</span>Reading &lt;main.rs&gt;</div>"));
+    assert!(html.contains("<details class=\"tool\"><summary>Read"));
+    assert!(html.contains("&quot;file_path&quot;: &quot;src/main.rs&quot;"));
+    assert!(html.contains("<pre class=\"output\">fn main() {}</pre>"));
+    assert!(html.contains("<div class=\"result ok\"><strong>Result: Success</strong>\nAll done</div>"));
+}
+
+// ─── Hooks ───────────────────────────────────────────────────────
+
+#[test]
+fn run_failing_pre_run_hook_aborts() {
+    let dir = tempfile::tempdir().unwrap();
+    let binary = dir.path().join("claude");
+    let started = dir.path().join("agent-started");
+    write_script(
+        &binary,
+        &format!("#!/bin/bash\ntouch {}\n", started.display()),
+    );
+    let hooks = dir.path().join("hooks");
+    std::fs::create_dir(&hooks).unwrap();
+    write_script(&hooks.join("pre-run"), "#!/bin/bash\necho 'dirty tree' >&2\nexit 3\n");
+
+    harness_cmd()
+        .env("HARNESS_SESSIONS_DIR", dir.path().join("sessions"))
+        .args(["run", "--agent", "claude", "--prompt", "hi", "--binary"])
+        .arg(&binary)
+        .arg("--hooks-dir").arg(&hooks)
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("dirty tree"))
+        .stderr(predicate::str::contains("pre-run hook failed"));
+    assert!(!started.exists(), "agent ran despite the failing pre-run hook");
+}
+
+#[test]
+fn run_hooks_receive_lifecycle_context() {
+    let dir = tempfile::tempdir().unwrap();
+    let binary = dir.path().join("claude");
+    write_script(
+        &binary,
+        r#"#!/bin/bash
+echo '{"type":"system","subtype":"init","session_id":"agent-1","model":"sonnet"}'
+echo '{"type":"assistant","message":{"content":[{"type":"tool_use","id":"t1","name":"Bash","input":{"command":"ls"}}]}}'
+echo '{"type":"result","subtype":"success","result":"done","session_id":"agent-1"}'
+"#,
+    );
+    let hooks = dir.path().join("hooks");
+    std::fs::create_dir(&hooks).unwrap();
+    let log = dir.path().join("hooks.log");
+    for name in ["pre-run", "on-tool", "post-run"] {
+        write_script(
+            &hooks.join(name),
+            &format!(
+                "#!/bin/bash\necho \"$HARNESS_HOOK agent=$HARNESS_AGENT tool=$HARNESS_TOOL_NAME input=$HARNESS_TOOL_INPUT success=$HARNESS_SUCCESS\" >> {}\n",
+                log.display()
+            ),
+        );
+    }
+
+    harness_cmd()
+        .env("HARNESS_SESSIONS_DIR", dir.path().join("sessions"))
+        .args(["run", "--agent", "claude", "--prompt", "hi", "--binary"])
+        .arg(&binary)
+        .arg("--hooks-dir").arg(&hooks)
+        .assert()
+        .success();
+
+    let log = std::fs::read_to_string(&log).unwrap();
+    let lines: Vec<&str> = log.lines().collect();
+    assert_eq!(lines.len(), 3, "{log}");
+    assert_eq!(lines[0], "pre-run agent=claude tool= input= success=");
+    assert_eq!(lines[1], r#"on-tool agent=claude tool=Bash input={"command":"ls"} success="#);
+    assert_eq!(lines[2], "post-run agent=claude tool= input= success=1");
+}
+
+// ─── Stream stderr ───────────────────────────────────────────────
+
+#[test]
+fn run_stream_stderr_forwards_agent_stderr() {
+    let dir = tempfile::tempdir().unwrap();
+    let binary = dir.path().join("claude");
+    write_script(
+        &binary,
+        r#"#!/bin/bash
+echo 'indexing files 1/2' >&2
+echo '{"type":"system","subtype":"init","session_id":"s1"}'
+echo 'indexing files 2/2' >&2
+echo '{"type":"result","subtype":"success","result":"done","session_id":"s1"}'
+"#,
+    );
+
+    harness_cmd()
+        .env("HARNESS_SESSIONS_DIR", dir.path().join("sessions"))
+        .args(["run", "--agent", "claude", "--prompt", "hi", "--stream-stderr", "--binary"])
+        .arg(&binary)
+        .assert()
+        .success()
+        .stderr(predicate::str::contains("[claude] indexing files 1/2"))
+        .stderr(predicate::str::contains("[claude] indexing files 2/2"))
+        .stdout(predicate::str::contains("indexing").not());
+
+    // Without the flag a successful run keeps the agent's stderr to itself.
+    harness_cmd()
+        .env("HARNESS_SESSIONS_DIR", dir.path().join("sessions"))
+        .args(["run", "--agent", "claude", "--prompt", "hi", "--binary"])
+        .arg(&binary)
+        .assert()
+        .success()
+        .stderr(predicate::str::contains("indexing").not());
+}
+
+// ─── Exit code from agent ────────────────────────────────────────
+
+fn write_exit_code_mock_claude(dir: &std::path::Path, result: &str) -> std::path::PathBuf {
+    let binary = dir.join("claude");
+    write_script(
+        &binary,
+        &format!(
+            "#!/bin/bash\necho '{{\"type\":\"system\",\"subtype\":\"init\",\"session_id\":\"s1\"}}'\necho '{result}'\n"
+        ),
+    );
+    binary
+}
+
+#[test]
+fn run_on_result_exit_code_from_agent() {
+    let dir = tempfile::tempdir().unwrap();
+    let binary = write_exit_code_mock_claude(
+        dir.path(),
+        r#"{"type":"result","subtype":"success","result":"needs review","session_id":"s1","exit_code":3}"#,
+    );
+
+    harness_cmd()
+        .env("HARNESS_SESSIONS_DIR", dir.path().join("sessions"))
+        .args(["run", "--agent", "claude", "--prompt", "hi", "--on-result", "exit-code-from-agent", "--binary"])
+        .arg(&binary)
+        .assert()
+        .code(3);
+
+    // Without the flag, the code follows Result.success.
+    harness_cmd()
+        .env("HARNESS_SESSIONS_DIR", dir.path().join("sessions"))
+        .args(["run", "--agent", "claude", "--prompt", "hi", "--binary"])
+        .arg(&binary)
+        .assert()
+        .code(0);
+}
+
+#[test]
+fn run_on_result_exit_code_falls_back_to_success() {
+    let dir = tempfile::tempdir().unwrap();
+    let binary = write_exit_code_mock_claude(
+        dir.path(),
+        r#"{"type":"result","subtype":"error_during_execution","result":"","session_id":"s1"}"#,
+    );
+
+    harness_cmd()
+        .env("HARNESS_SESSIONS_DIR", dir.path().join("sessions"))
+        .args(["run", "--agent", "claude", "--prompt", "hi", "--on-result", "exit-code-from-agent", "--binary"])
+        .arg(&binary)
+        .assert()
+        .code(1);
+}
+
+#[test]
+fn run_on_result_exit_code_out_of_range_falls_back_to_success() {
+    let dir = tempfile::tempdir().unwrap();
+    let binary = write_exit_code_mock_claude(
+        dir.path(),
+        r#"{"type":"result","subtype":"error_during_execution","result":"","session_id":"s1","exit_code":-1}"#,
+    );
+
+    harness_cmd()
+        .env("HARNESS_SESSIONS_DIR", dir.path().join("sessions"))
+        .args(["run", "--agent", "claude", "--prompt", "hi", "--on-result", "exit-code-from-agent", "--binary"])
+        .arg(&binary)
+        .assert()
+        .code(1);
+}
+
+// ─── Tool policy ─────────────────────────────────────────────────
+
+fn write_policy_mock_codex(dir: &std::path::Path) -> std::path::PathBuf {
+    let binary = dir.join("codex");
+    write_script(
+        &binary,
+        r#"#!/bin/bash
+echo '{"type":"thread.started","thread_id":"th-1"}'
+echo '{"type":"item.started","item":{"id":"item_1","type":"command_execution","command":"rm -rf build","status":"in_progress"}}'
+sleep 2
+echo '{"type":"item.completed","item":{"id":"item_2","type":"agent_message","text":"Cleaned up."}}'
+echo '{"type":"turn.completed","usage":{"input_tokens":10,"output_tokens":5}}'
+"#,
+    );
+    binary
+}
+
+#[test]
+fn run_policy_abort_stops_on_denied_tool() {
+    let dir = tempfile::tempdir().unwrap();
+    let binary = write_policy_mock_codex(dir.path());
+    std::fs::write(
+        dir.path().join("harness.policy.toml"),
+        "denied_tools = [\"shell\"]\naction = \"abort\"\n",
+    )
+    .unwrap();
+
+    harness_cmd()
+        .current_dir(dir.path())
+        .env("HARNESS_SESSIONS_DIR", dir.path().join("sessions"))
+        .args(["run", "--agent", "codex", "--prompt", "clean", "--binary"])
+        .arg(&binary)
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("policy: tool `shell` is denied by policy"))
+        .stdout(predicate::str::contains("Cleaned up.").not());
+}
+
+#[test]
+fn run_policy_warn_lets_agent_continue() {
+    let dir = tempfile::tempdir().unwrap();
+    let binary = write_policy_mock_codex(dir.path());
+    std::fs::write(dir.path().join("harness.policy.toml"), "denied_tools = [\"shell\"]\n").unwrap();
+
+    harness_cmd()
+        .current_dir(dir.path())
+        .env("HARNESS_SESSIONS_DIR", dir.path().join("sessions"))
+        .args(["run", "--agent", "codex", "--prompt", "clean", "--binary"])
+        .arg(&binary)
+        .assert()
+        .success()
+        .stderr(predicate::str::contains("policy: tool `shell` is denied by policy"))
+        .stdout(predicate::str::contains("Cleaned up."));
+}
+
+#[test]
+fn run_policy_found_above_a_relative_cwd() {
+    let dir = tempfile::tempdir().unwrap();
+    let binary = write_policy_mock_codex(dir.path());
+    std::fs::write(
+        dir.path().join("harness.policy.toml"),
+        "denied_tools = [\"shell\"]\naction = \"abort\"\n",
+    )
+    .unwrap();
+    std::fs::create_dir_all(dir.path().join("tools").join("app")).unwrap();
+
+    harness_cmd()
+        .current_dir(dir.path().join("tools"))
+        .env("HARNESS_SESSIONS_DIR", dir.path().join("sessions"))
+        .args(["run", "--agent", "codex", "--prompt", "clean", "--cwd", "app", "--binary"])
+        .arg(&binary)
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("policy: tool `shell` is denied by policy"));
+}
+
+// ─── Summary only ────────────────────────────────────────────────
+
+#[test]
+fn run_summary_only_prints_final_result() {
+    let dir = tempfile::tempdir().unwrap();
+    let binary = dir.path().join("claude");
+    write_script(
+        &binary,
+        r#"#!/bin/bash
+echo '{"type":"system","subtype":"init","session_id":"s1","model":"mock-model"}'
+echo '{"type":"assistant","message":{"role":"assistant","content":[{"type":"text","text":"Working on it."}]}}'
+echo '{"type":"assistant","message":{"role":"assistant","content":[{"type":"text","text":"Still going."}]}}'
+echo '{"type":"result","subtype":"success","result":"Final answer.","session_id":"s1","total_cost_usd":0.01,"usage":{"input_tokens":10,"output_tokens":2}}'
+"#,
+    );
+
+    harness_cmd()
+        .env("HARNESS_SESSIONS_DIR", dir.path().join("sessions"))
+        .args(["run", "--agent", "claude", "--prompt", "hi", "--output", "text", "--summary-only", "--binary"])
+        .arg(&binary)
+        .assert()
+        .success()
+        .stdout("Final answer.\n")
+        .stderr(predicate::str::contains("Total:"));
+}
+
+#[test]
+fn run_summary_only_stream_json_prints_single_object() {
+    let dir = tempfile::tempdir().unwrap();
+    let binary = dir.path().join("claude");
+    write_script(
+        &binary,
+        r#"#!/bin/bash
+echo '{"type":"system","subtype":"init","session_id":"s1"}'
+echo '{"type":"assistant","message":{"role":"assistant","content":[{"type":"text","text":"Working on it."}]}}'
+echo '{"type":"result","subtype":"success","result":"Final answer.","session_id":"s1"}'
+"#,
+    );
+
+    let output = harness_cmd()
+        .env("HARNESS_SESSIONS_DIR", dir.path().join("sessions"))
+        .args(["run", "--agent", "claude", "--prompt", "hi", "--summary-only", "--binary"])
+        .arg(&binary)
+        .assert()
+        .success()
+        .get_output()
+        .clone();
+
+    let json: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(json["type"], "result");
+    assert_eq!(json["result"], "Final answer.");
+}
+
+#[test]
+fn run_only_prints_selected_event_kinds() {
+    let dir = tempfile::tempdir().unwrap();
+    let binary = dir.path().join("claude");
+    write_script(
+        &binary,
+        r#"#!/bin/bash
+echo '{"type":"system","subtype":"init","session_id":"s1"}'
+echo '{"type":"assistant","message":{"role":"assistant","content":[{"type":"text","text":"Checking."},{"type":"tool_use","id":"tu-1","name":"Bash","input":{"command":"ls"}}]}}'
+echo '{"type":"user","message":{"role":"user","content":[{"type":"tool_result","tool_use_id":"tu-1","content":"a.txt"}]}}'
+echo '{"type":"result","subtype":"success","result":"Done.","session_id":"s1"}'
+"#,
+    );
+
+    let output = harness_cmd()
+        .env("HARNESS_SESSIONS_DIR", dir.path().join("sessions"))
+        .args(["run", "--agent", "claude", "--prompt", "hi", "--only", "messages,result", "--binary"])
+        .arg(&binary)
+        .assert()
+        .success()
+        .get_output()
+        .clone();
+
+    let types: Vec<String> = String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .map(|l| serde_json::from_str::<serde_json::Value>(l).unwrap()["type"].as_str().unwrap().to_string())
+        .collect();
+    assert_eq!(types, ["message", "message", "result"]);
+
+    // The session log is unfiltered.
+    let log_path = std::fs::read_dir(dir.path().join("sessions"))
+        .unwrap()
+        .map(|e| e.unwrap().path())
+        .find(|p| p.to_string_lossy().ends_with(".ndjson"))
+        .expect("no session log written");
+    let logged = std::fs::read_to_string(log_path).unwrap();
+    assert!(logged.contains("\"tool_start\""));
+}
+
+#[test]
+fn run_tool_output_to_dir_writes_files_and_references_them() {
+    let dir = tempfile::tempdir().unwrap();
+    let binary = dir.path().join("claude");
+    write_script(
+        &binary,
+        r#"#!/bin/bash
+echo '{"type":"system","subtype":"init","session_id":"s1"}'
+echo '{"type":"assistant","message":{"role":"assistant","content":[{"type":"tool_use","id":"tu-1","name":"Bash","input":{"command":"ls"}}]}}'
+echo '{"type":"user","message":{"role":"user","content":[{"type":"tool_result","tool_use_id":"tu-1","content":"a.txt\nb.txt"}]}}'
+echo '{"type":"result","subtype":"success","result":"Done.","session_id":"s1"}'
+"#,
+    );
+    let out_dir = dir.path().join("tool-output");
+
+    let output = harness_cmd()
+        .env("HARNESS_SESSIONS_DIR", dir.path().join("sessions"))
+        .args(["run", "--agent", "claude", "--prompt", "hi", "--tool-output-to-dir"])
+        .arg(&out_dir)
+        .arg("--binary")
+        .arg(&binary)
+        .assert()
+        .success()
+        .get_output()
+        .clone();
+
+    let file = std::fs::canonicalize(out_dir.join("tu-1.txt")).unwrap();
+    assert_eq!(std::fs::read_to_string(&file).unwrap(), "a.txt\nb.txt");
+    let tool_end = String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .map(|l| serde_json::from_str::<serde_json::Value>(l).unwrap())
+        .find(|v| v["type"] == "tool_end")
+        .expect("no tool_end event");
+    assert_eq!(tool_end["output"], format!("file://{}", file.display()));
+
+    // The session log carries the reference, not the output.
+    let log_path = std::fs::read_dir(dir.path().join("sessions"))
+        .unwrap()
+        .map(|e| e.unwrap().path())
+        .find(|p| p.to_string_lossy().ends_with(".ndjson"))
+        .expect("no session log written");
+    let logged = std::fs::read_to_string(log_path).unwrap();
+    assert!(logged.contains("tu-1.txt"));
+    assert!(!logged.contains("b.txt"));
+}
+
+#[test]
+fn run_pretty_prints_stream_json_events() {
+    let dir = tempfile::tempdir().unwrap();
+    let binary = dir.path().join("claude");
+    write_script(
+        &binary,
+        r#"#!/bin/bash
+echo '{"type":"system","subtype":"init","session_id":"s1"}'
+echo '{"type":"result","subtype":"success","result":"Done.","session_id":"s1"}'
+"#,
+    );
+
+    let output = harness_cmd()
+        .env("HARNESS_SESSIONS_DIR", dir.path().join("sessions"))
+        .args(["run", "--agent", "claude", "--prompt", "hi", "--pretty", "--binary"])
+        .arg(&binary)
+        .assert()
+        .success()
+        .get_output()
+        .clone();
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let blocks: Vec<&str> = stdout.split("\n\n").filter(|b| !b.trim().is_empty()).collect();
+    assert!(blocks.len() >= 2, "{stdout}");
+    for block in &blocks {
+        assert!(block.trim().contains('\n'), "event not pretty-printed: {block}");
+        let event: serde_json::Value = serde_json::from_str(block).unwrap();
+        assert!(event["type"].is_string());
+    }
+
+    harness_cmd()
+        .args(["run", "--agent", "claude", "--prompt", "hi", "--pretty", "--output", "text", "--binary"])
+        .arg(&binary)
+        .assert()
+        .code(2)
+        .stderr(predicate::str::contains("--pretty only applies"));
+}
+
+#[test]
+fn run_progress_line_only_when_forced_off_a_tty() {
+    let dir = tempfile::tempdir().unwrap();
+    let binary = dir.path().join("claude");
+    write_script(
+        &binary,
+        r#"#!/bin/bash
+echo '{"type":"system","subtype":"init","session_id":"s1"}'
+echo '{"type":"assistant","message":{"role":"assistant","content":[{"type":"text","text":"Done."}]}}'
+echo '{"type":"result","subtype":"success","result":"Done.","session_id":"s1"}'
+"#,
+    );
+
+    // stderr is a pipe here, so the status line stays off by default.
+    let output = harness_cmd()
+        .env("HARNESS_SESSIONS_DIR", dir.path().join("sessions"))
+        .args(["run", "--agent", "claude", "--prompt", "hi", "--output", "text", "--binary"])
+        .arg(&binary)
+        .assert()
+        .success()
+        .get_output()
+        .clone();
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(!stderr.contains("running"), "{stderr}");
+    assert!(!stderr.contains('\r'), "{stderr}");
+    assert_eq!(String::from_utf8_lossy(&output.stdout), "Done.\n");
+
+    let output = harness_cmd()
+        .env("HARNESS_SESSIONS_DIR", dir.path().join("sessions"))
+        .args(["run", "--agent", "claude", "--prompt", "hi", "--output", "text", "--progress", "--binary"])
+        .arg(&binary)
+        .assert()
+        .success()
+        .get_output()
+        .clone();
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("running Claude Code..."), "{stderr}");
+    // The line is erased before the run ends.
+    assert!(stderr.ends_with("\r\x1b[2K"), "{stderr:?}");
+}
+
+// ─── Exit code from Result.success ───────────────────────────────
+
+fn write_failing_with_text_mock(path: &std::path::Path) {
+    write_script(
+        path,
+        r#"#!/bin/bash
+echo '{"type":"system","subtype":"init","session_id":"f1"}'
+echo '{"type":"assistant","message":{"role":"assistant","content":[{"type":"text","text":"Partial work."}]}}'
+echo '{"type":"result","subtype":"error_max_turns","result":"","session_id":"f1"}'
+"#,
+    );
+}
+
+#[test]
+fn run_failed_result_exits_nonzero_despite_text() {
+    let dir = tempfile::tempdir().unwrap();
+    let binary = dir.path().join("claude");
+    write_failing_with_text_mock(&binary);
+
+    harness_cmd()
+        .env("HARNESS_SESSIONS_DIR", dir.path().join("sessions"))
+        .args(["run", "--agent", "claude", "--prompt", "hi", "--binary"])
+        .arg(&binary)
+        .assert()
+        .failure();
+}
+
+#[test]
+fn run_allow_empty_result_accepts_text() {
+    let dir = tempfile::tempdir().unwrap();
+    let binary = dir.path().join("claude");
+    write_failing_with_text_mock(&binary);
+
+    harness_cmd()
+        .env("HARNESS_SESSIONS_DIR", dir.path().join("sessions"))
+        .args(["run", "--agent", "claude", "--prompt", "hi", "--allow-empty-result", "--binary"])
+        .arg(&binary)
+        .assert()
+        .success();
+}
+
+#[test]
+fn run_successful_result_exits_zero() {
+    let dir = tempfile::tempdir().unwrap();
+    let binary = dir.path().join("claude");
+    write_script(
+        &binary,
+        r#"#!/bin/bash
+echo '{"type":"system","subtype":"init","session_id":"ok1"}'
+echo '{"type":"result","subtype":"success","result":"Done.","session_id":"ok1"}'
+"#,
+    );
+
+    harness_cmd()
+        .env("HARNESS_SESSIONS_DIR", dir.path().join("sessions"))
+        .args(["run", "--agent", "claude", "--prompt", "hi", "--binary"])
+        .arg(&binary)
+        .assert()
+        .success();
+}
+
+// ─── Model required ──────────────────────────────────────────────
+
+#[test]
+fn run_model_required_rejects_unknown_model() {
+    let dir = tempfile::tempdir().unwrap();
+    // A fresh cached registry keeps the lookup offline.
+    std::fs::create_dir_all(dir.path().join(".harness")).unwrap();
+    std::fs::write(
+        dir.path().join(".harness").join("models.toml"),
+        "[models.opus]\ndescription = \"Opus\"\nprovider = \"anthropic\"\nclaude = \"claude-opus-4-6\"\n",
+    )
+    .unwrap();
+
+    harness_cmd()
+        .current_dir(dir.path())
+        .env("HOME", dir.path())
+        .args([
+            "run", "--agent", "claude", "--prompt", "hi", "--model", "mystery-model",
+            "--model-required", "--dry-run", "--binary", "/bin/true",
+        ])
+        .assert()
+        .code(2)
+        .stderr(predicate::str::contains("mystery-model"));
+
+    harness_cmd()
+        .current_dir(dir.path())
+        .env("HOME", dir.path())
+        .args([
+            "run", "--agent", "claude", "--prompt", "hi", "--model", "opus",
+            "--model-required", "--dry-run", "--binary", "/bin/true",
+        ])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("claude-opus-4-6"));
+}
+
+#[test]
+fn run_deprecated_model_warns_with_replacement() {
+    let dir = tempfile::tempdir().unwrap();
+    std::fs::create_dir_all(dir.path().join(".harness")).unwrap();
+    std::fs::write(
+        dir.path().join(".harness").join("models.toml"),
+        concat!(
+            "[models.opus-old]\ndescription = \"Opus (old)\"\nprovider = \"anthropic\"\n",
+            "claude = \"claude-opus-4-1\"\ndeprecated = true\nreplacement = \"opus\"\n",
+            "[models.opus]\ndescription = \"Opus\"\nprovider = \"anthropic\"\nclaude = \"claude-opus-4-6\"\n",
+        ),
+    )
+    .unwrap();
+
+    harness_cmd()
+        .current_dir(dir.path())
+        .env("HOME", dir.path())
+        .args([
+            "run", "--agent", "claude", "--prompt", "hi", "--model", "opus-old",
+            "--dry-run", "--binary", "/bin/true",
+        ])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("claude-opus-4-1"))
+        .stderr(predicate::str::contains(
+            "warning: model `opus-old` is deprecated; use `opus` instead",
+        ));
+
+    // The agent's own ID for a deprecated model warns too.
+    harness_cmd()
+        .current_dir(dir.path())
+        .env("HOME", dir.path())
+        .args([
+            "run",
+            "--agent",
+            "claude",
+            "--prompt",
+            "hi",
+            "--model",
+            "claude-opus-4-1",
+            "--dry-run",
+            "--binary",
+            "/bin/true",
+        ])
+        .assert()
+        .success()
+        .stderr(predicate::str::contains(
+            "warning: model `opus-old` is deprecated; use `opus` instead",
+        ));
+
+    harness_cmd()
+        .current_dir(dir.path())
+        .env("HOME", dir.path())
+        .args([
+            "run", "--agent", "claude", "--prompt", "hi", "--model", "opus",
+            "--dry-run", "--binary", "/bin/true",
+        ])
+        .assert()
+        .success()
+        .stderr(predicate::str::contains("deprecated").not());
+}
@@ -18,6 +18,8 @@ fn parse_agent_kind_all_variants() {
         ("cursor", AgentKind::Cursor),
         ("cursor-agent", AgentKind::Cursor),
         ("cursor_agent", AgentKind::Cursor),
+        ("gemini", AgentKind::Gemini),
+        ("gemini-cli", AgentKind::Gemini),
     ];
 
     for (input, expected) in cases {
@@ -42,6 +44,7 @@ fn agent_kind_default_binary() {
     assert_eq!(AgentKind::OpenCode.default_binary(), "opencode");
     assert_eq!(AgentKind::Codex.default_binary(), "codex");
     assert_eq!(AgentKind::Cursor.default_binary(), "cursor-agent");
+    assert_eq!(AgentKind::Gemini.default_binary(), "gemini");
 }
 
 #[test]
@@ -50,6 +53,7 @@ fn agent_kind_display_name() {
     assert_eq!(AgentKind::OpenCode.display_name(), "OpenCode");
     assert_eq!(AgentKind::Codex.display_name(), "Codex");
     assert_eq!(AgentKind::Cursor.display_name(), "Cursor");
+    assert_eq!(AgentKind::Gemini.display_name(), "Gemini CLI");
 }
 
 #[test]
@@ -58,6 +62,38 @@ fn agent_kind_display_trait() {
     assert_eq!(format!("{}", AgentKind::Cursor), "Cursor");
 }
 
+#[test]
+fn resolve_api_key_follows_precedence() {
+    let env = |vars: &'static [(&'static str, &'static str)]| {
+        move |key: &str| {
+            vars.iter()
+                .find(|(k, _)| *k == key)
+                .map(|(_, v)| v.to_string())
+        }
+    };
+
+    let both = env(&[
+        ("CODEX_API_KEY", "codex-key-0000-1111"),
+        ("OPENAI_API_KEY", "sk-openai-abcd"),
+    ]);
+    assert_eq!(
+        AgentKind::Codex.resolve_api_key_with(both),
+        Some(("CODEX_API_KEY".into(), "****1111".into()))
+    );
+    // An empty value counts as unset.
+    let empty = env(&[("CODEX_API_KEY", ""), ("OPENAI_API_KEY", "sk-openai-abcd")]);
+    assert_eq!(
+        AgentKind::Codex.resolve_api_key_with(empty),
+        Some(("OPENAI_API_KEY".into(), "****abcd".into()))
+    );
+    let short = env(&[("ANTHROPIC_API_KEY", "short")]);
+    assert_eq!(
+        AgentKind::Claude.resolve_api_key_with(short),
+        Some(("ANTHROPIC_API_KEY".into(), "****".into()))
+    );
+    assert_eq!(AgentKind::Cursor.resolve_api_key_with(env(&[])), None);
+}
+
 // ─── TaskConfig ──────────────────────────────────────────────────
 
 #[test]
@@ -196,6 +232,65 @@ fn builder_extra_args() {
     assert_eq!(config.extra_args, vec!["--verbose", "--debug", "--trace"]);
 }
 
+#[test]
+fn builder_remaining_setters() {
+    let config = TaskConfig::builder("task", AgentKind::OpenCode)
+        .image("a.png")
+        .image("b.png")
+        .command_prefix(vec!["nice".into(), "-n10".into()])
+        .stream_stderr(true)
+        .agent_profile("reviewer")
+        .resume_session_id("sess-1")
+        .build();
+    assert_eq!(
+        config.images,
+        vec![std::path::PathBuf::from("a.png"), "b.png".into()]
+    );
+    assert_eq!(config.command_prefix, vec!["nice", "-n10"]);
+    assert!(config.stream_stderr);
+    assert_eq!(config.agent_profile.as_deref(), Some("reviewer"));
+    assert_eq!(config.resume_session_id.as_deref(), Some("sess-1"));
+}
+
+#[test]
+fn build_validated_rejects_hard_errors() {
+    let err = TaskConfig::builder("  ", AgentKind::Claude)
+        .build_validated()
+        .unwrap_err();
+    assert!(err.to_string().contains("prompt is empty"), "{err}");
+
+    for budget in [0.0, -1.0, f64::NAN] {
+        assert!(TaskConfig::builder("task", AgentKind::Claude)
+            .max_budget_usd(budget)
+            .build_validated()
+            .is_err());
+    }
+    assert!(TaskConfig::builder("task", AgentKind::Claude)
+        .max_turns(0)
+        .build_validated()
+        .is_err());
+
+    let config = TaskConfig::builder("task", AgentKind::Claude)
+        .max_budget_usd(1.5)
+        .max_turns(3)
+        .build_validated()
+        .unwrap();
+    assert_eq!(config.max_turns, Some(3));
+}
+
+#[test]
+fn builder_accumulates_across_calls() {
+    let config = TaskConfig::builder("task", AgentKind::Claude)
+        .extra_args(vec!["--a".into()])
+        .env("ONE", "1")
+        .extra_args(vec!["--b".into()])
+        .extra_arg("--c")
+        .env("TWO", "2")
+        .build();
+    assert_eq!(config.extra_args, vec!["--a", "--b", "--c"]);
+    assert_eq!(config.env.len(), 2);
+}
+
 #[test]
 fn builder_read_only_shorthand() {
     let config = TaskConfig::builder("analyze", AgentKind::OpenCode)
@@ -242,3 +337,30 @@ fn builder_accepts_string_types() {
     assert_eq!(config.model.as_deref(), Some("sonnet"));
     assert_eq!(config.system_prompt.as_deref(), Some("system"));
 }
+
+// ─── Version heuristics ─────────────────────────────────────────
+
+#[test]
+fn parse_version_handles_agent_formats() {
+    assert_eq!(parse_version("1.0.43 (Claude Code)"), Some((1, 0, 43)));
+    assert_eq!(parse_version("codex-cli 0.46.0"), Some((0, 46, 0)));
+    assert_eq!(parse_version("v0.3"), Some((0, 3, 0)));
+    assert_eq!(parse_version("2025.09.04-fc40cd1"), Some((2025, 9, 4)));
+    assert_eq!(parse_version("unknown"), None);
+}
+
+#[test]
+fn recommended_timeout_longer_for_old_version() {
+    let old = AgentKind::Claude.recommended_timeout_secs(Some("0.2.9 (Claude Code)"));
+    let new = AgentKind::Claude.recommended_timeout_secs(Some("1.0.43 (Claude Code)"));
+    assert!(old > new, "old={old} new={new}");
+    assert_eq!(AgentKind::Codex.recommended_timeout_secs(Some("codex-cli 0.10.0")), 1800);
+}
+
+#[test]
+fn recommended_timeout_unknown_version_uses_base() {
+    assert_eq!(
+        AgentKind::Claude.recommended_timeout_secs(None),
+        AgentKind::Claude.recommended_timeout_secs(Some("garbage"))
+    );
+}
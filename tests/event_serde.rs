@@ -8,7 +8,10 @@ fn session_start_round_trip() {
         agent: "claude".into(),
         model: Some("opus".into()),
         cwd: Some("/tmp".into()),
+        permission_mode: None,
+        agent_version: None,
         timestamp_ms: 0,
+        raw_type: None,
     });
     let json = serde_json::to_string(&event).unwrap();
     let parsed: Event = serde_json::from_str(&json).unwrap();
@@ -20,6 +23,7 @@ fn text_delta_round_trip() {
     let event = Event::TextDelta(TextDeltaEvent {
         text: "Hello, world!".into(),
         timestamp_ms: 0,
+        raw_type: None,
     });
     let json = serde_json::to_string(&event).unwrap();
     let parsed: Event = serde_json::from_str(&json).unwrap();
@@ -33,6 +37,7 @@ fn message_round_trip() {
         text: "I found the bug in line 42.".into(),
         usage: None,
         timestamp_ms: 0,
+        raw_type: None,
     });
     let json = serde_json::to_string(&event).unwrap();
     let parsed: Event = serde_json::from_str(&json).unwrap();
@@ -46,6 +51,7 @@ fn tool_start_round_trip() {
         tool_name: "bash".into(),
         input: Some(serde_json::json!({"command": "ls -la"})),
         timestamp_ms: 0,
+        raw_type: None,
     });
     let json = serde_json::to_string(&event).unwrap();
     let parsed: Event = serde_json::from_str(&json).unwrap();
@@ -61,6 +67,7 @@ fn tool_end_round_trip() {
         output: Some("file.txt\nREADME.md".into()),
         usage: None,
         timestamp_ms: 0,
+        raw_type: None,
     });
     let json = serde_json::to_string(&event).unwrap();
     let parsed: Event = serde_json::from_str(&json).unwrap();
@@ -69,15 +76,11 @@ fn tool_end_round_trip() {
 
 #[test]
 fn result_success_round_trip() {
-    let event = Event::Result(ResultEvent {
-        success: true,
-        text: "Done!".into(),
-        session_id: "s-1".into(),
-        duration_ms: Some(1234),
-        total_cost_usd: Some(0.03),
-        usage: None,
-        timestamp_ms: 0,
-    });
+    let event = Event::Result(
+        ResultEvent::new(true, "Done!", "s-1")
+            .duration_ms(1234)
+            .total_cost_usd(0.03),
+    );
     let json = serde_json::to_string(&event).unwrap();
     let parsed: Event = serde_json::from_str(&json).unwrap();
     assert_eq!(event, parsed);
@@ -85,15 +88,7 @@ fn result_success_round_trip() {
 
 #[test]
 fn result_error_round_trip() {
-    let event = Event::Result(ResultEvent {
-        success: false,
-        text: String::new(),
-        session_id: "s-1".into(),
-        duration_ms: None,
-        total_cost_usd: None,
-        usage: None,
-        timestamp_ms: 0,
-    });
+    let event = Event::Result(ResultEvent::new(false, String::new(), "s-1"));
     let json = serde_json::to_string(&event).unwrap();
     let parsed: Event = serde_json::from_str(&json).unwrap();
     assert_eq!(event, parsed);
@@ -105,6 +100,7 @@ fn error_event_round_trip() {
         message: "rate limit".into(),
         code: Some("429".into()),
         timestamp_ms: 0,
+        raw_type: None,
     });
     let json = serde_json::to_string(&event).unwrap();
     let parsed: Event = serde_json::from_str(&json).unwrap();
@@ -118,7 +114,10 @@ fn optional_fields_omitted_in_json() {
         agent: "codex".into(),
         model: None,
         cwd: None,
+        permission_mode: None,
+        agent_version: None,
         timestamp_ms: 0,
+        raw_type: None,
     });
     let json = serde_json::to_string(&event).unwrap();
     // model and cwd should not appear in the JSON.
@@ -134,23 +133,29 @@ fn event_display_trait() {
             agent: "claude".into(),
             model: None,
             cwd: None,
+            permission_mode: None,
+            agent_version: None,
             timestamp_ms: 0,
+            raw_type: None,
         }),
         Event::TextDelta(TextDeltaEvent {
             text: "hi".into(),
             timestamp_ms: 0,
+            raw_type: None,
         }),
         Event::Message(MessageEvent {
             role: Role::Assistant,
             text: "hello".into(),
             usage: None,
             timestamp_ms: 0,
+            raw_type: None,
         }),
         Event::ToolStart(ToolStartEvent {
             call_id: "c-1".into(),
             tool_name: "bash".into(),
             input: None,
             timestamp_ms: 0,
+            raw_type: None,
         }),
         Event::ToolEnd(ToolEndEvent {
             call_id: "c-1".into(),
@@ -159,20 +164,14 @@ fn event_display_trait() {
             output: None,
             usage: None,
             timestamp_ms: 0,
+            raw_type: None,
         }),
-        Event::Result(ResultEvent {
-            success: true,
-            text: "ok".into(),
-            session_id: "s-1".into(),
-            duration_ms: None,
-            total_cost_usd: None,
-            usage: None,
-            timestamp_ms: 0,
-        }),
+        Event::Result(ResultEvent::new(true, "ok", "s-1")),
         Event::Error(ErrorEvent {
             message: "oops".into(),
             code: None,
             timestamp_ms: 0,
+            raw_type: None,
         }),
     ];
 
@@ -192,7 +191,10 @@ fn json_type_tag_is_snake_case() {
                 agent: "a".into(),
                 model: None,
                 cwd: None,
+                permission_mode: None,
+                agent_version: None,
                 timestamp_ms: 0,
+                raw_type: None,
             }),
             "session_start",
         ),
@@ -200,6 +202,7 @@ fn json_type_tag_is_snake_case() {
             Event::TextDelta(TextDeltaEvent {
                 text: "x".into(),
                 timestamp_ms: 0,
+                raw_type: None,
             }),
             "text_delta",
         ),
@@ -209,6 +212,7 @@ fn json_type_tag_is_snake_case() {
                 text: "x".into(),
                 usage: None,
                 timestamp_ms: 0,
+                raw_type: None,
             }),
             "message",
         ),
@@ -218,6 +222,7 @@ fn json_type_tag_is_snake_case() {
                 tool_name: "t".into(),
                 input: None,
                 timestamp_ms: 0,
+                raw_type: None,
             }),
             "tool_start",
         ),
@@ -229,26 +234,17 @@ fn json_type_tag_is_snake_case() {
                 output: None,
                 usage: None,
                 timestamp_ms: 0,
+                raw_type: None,
             }),
             "tool_end",
         ),
-        (
-            Event::Result(ResultEvent {
-                success: true,
-                text: "x".into(),
-                session_id: "s".into(),
-                duration_ms: None,
-                total_cost_usd: None,
-                usage: None,
-                timestamp_ms: 0,
-            }),
-            "result",
-        ),
+        (Event::Result(ResultEvent::new(true, "x", "s")), "result"),
         (
             Event::Error(ErrorEvent {
                 message: "x".into(),
                 code: None,
                 timestamp_ms: 0,
+                raw_type: None,
             }),
             "error",
         ),
@@ -279,14 +275,297 @@ fn deserialize_without_timestamp_ms() {
     }
 }
 
+#[test]
+fn summary_round_trip_and_defaults() {
+    let event = Event::Summary(SummaryEvent {
+        input_tokens: 150,
+        output_tokens: 50,
+        cache_read_tokens: 20,
+        cache_creation_tokens: 0,
+        total_cost_usd: 0.03,
+        tool_calls: 2,
+        messages: 3,
+        duration_ms: Some(3000),
+        timestamp_ms: 4000,
+        raw_type: None,
+    });
+    let value = serde_json::to_value(&event).unwrap();
+    assert_eq!(value["type"], "summary");
+    assert_eq!(validate_event_json(&value), Ok(()));
+    let parsed: Event = serde_json::from_value(value).unwrap();
+    assert_eq!(event, parsed);
+
+    // Every field is optional on the way in.
+    let bare: Event = serde_json::from_str(r#"{"type":"summary"}"#).unwrap();
+    assert_eq!(bare, Event::Summary(SummaryEvent::default()));
+}
+
 /// Test that timestamp_ms round-trips correctly.
 #[test]
 fn timestamp_ms_round_trip() {
     let event = Event::TextDelta(TextDeltaEvent {
         text: "hi".into(),
         timestamp_ms: 1234567890123,
+        raw_type: None,
     });
     let json = serde_json::to_string(&event).unwrap();
     let parsed: Event = serde_json::from_str(&json).unwrap();
     assert_eq!(event, parsed);
 }
+
+// ─── Canonical tool names ────────────────────────────────────────
+
+#[test]
+fn canonical_tool_name_maps_agent_specific_names() {
+    assert_eq!(canonical_tool_name("Bash"), "shell");
+    assert_eq!(canonical_tool_name("shell"), "shell");
+    assert_eq!(canonical_tool_name("bash"), "shell");
+    assert_eq!(canonical_tool_name("Read"), "read");
+    assert_eq!(canonical_tool_name("file_change"), "edit");
+    assert_eq!(canonical_tool_name("Grep"), "search");
+    assert_eq!(canonical_tool_name("mcp__custom"), "mcp__custom");
+}
+
+// ─── Schema validation ──────────────────────────────────────────
+
+#[test]
+fn validate_event_json_accepts_serialized_events() {
+    let event = Event::Result(
+        ResultEvent::new(true, "done", "s1")
+            .duration_ms(10)
+            .total_cost_usd(0.01)
+            .usage(UsageData {
+                input_tokens: Some(5),
+                ..Default::default()
+            })
+            .timestamp_ms(1)
+            .raw_type("result"),
+    );
+    let value = serde_json::to_value(&event).unwrap();
+    assert_eq!(validate_event_json(&value), Ok(()));
+}
+
+/// One sample of every variant with every optional field set, so the
+/// validator's field table is checked against what serde actually writes.
+fn every_variant() -> Vec<Event> {
+    let usage = || UsageData {
+        input_tokens: Some(10),
+        output_tokens: Some(5),
+        cache_read_tokens: Some(2),
+        cache_creation_tokens: Some(1),
+        cost_usd: Some(0.01),
+    };
+    let raw_type = || Some("native".to_string());
+    vec![
+        Event::SessionStart(SessionStartEvent {
+            session_id: "s1".into(),
+            agent: "claude".into(),
+            model: Some("opus".into()),
+            cwd: Some("/tmp".into()),
+            permission_mode: Some(harness::config::PermissionMode::ReadOnly),
+            agent_version: Some("1.0.0".into()),
+            timestamp_ms: 1,
+            raw_type: raw_type(),
+        }),
+        Event::TextDelta(TextDeltaEvent {
+            text: "hi".into(),
+            timestamp_ms: 1,
+            raw_type: raw_type(),
+        }),
+        Event::Message(MessageEvent {
+            role: Role::Assistant,
+            text: "hi".into(),
+            usage: Some(usage()),
+            timestamp_ms: 1,
+            raw_type: raw_type(),
+        }),
+        Event::Reasoning(ReasoningEvent {
+            text: "hmm".into(),
+            timestamp_ms: 1,
+            raw_type: raw_type(),
+        }),
+        Event::ToolStart(ToolStartEvent {
+            call_id: "c1".into(),
+            tool_name: "Bash".into(),
+            input: Some(serde_json::json!({"command": "ls"})),
+            timestamp_ms: 1,
+            raw_type: raw_type(),
+        }),
+        Event::ToolEnd(ToolEndEvent {
+            call_id: "c1".into(),
+            tool_name: "Bash".into(),
+            success: true,
+            output: Some("a.txt".into()),
+            usage: Some(usage()),
+            timestamp_ms: 1,
+            raw_type: raw_type(),
+        }),
+        Event::TurnStart(TurnStartEvent {
+            timestamp_ms: 1,
+            raw_type: raw_type(),
+        }),
+        Event::TurnEnd(TurnEndEvent {
+            usage: Some(usage()),
+            timestamp_ms: 1,
+            raw_type: raw_type(),
+        }),
+        Event::UsageDelta(UsageDeltaEvent {
+            usage: usage(),
+            timestamp_ms: 1,
+            raw_type: raw_type(),
+        }),
+        Event::Result(
+            ResultEvent::new(true, "done", "s1")
+                .duration_ms(10)
+                .total_cost_usd(0.01)
+                .usage(usage())
+                .exit_code(3)
+                .partial(true)
+                .timestamp_ms(1)
+                .raw_type("native"),
+        ),
+        Event::Summary(SummaryEvent {
+            input_tokens: 10,
+            output_tokens: 5,
+            cache_read_tokens: 2,
+            cache_creation_tokens: 1,
+            total_cost_usd: 0.01,
+            tool_calls: 1,
+            messages: 1,
+            duration_ms: Some(10),
+            timestamp_ms: 1,
+            raw_type: raw_type(),
+        }),
+        Event::Error(ErrorEvent {
+            message: "boom".into(),
+            code: Some("rate_limit".into()),
+            timestamp_ms: 1,
+            raw_type: raw_type(),
+        }),
+    ]
+}
+
+/// Position of the event's variant; a new variant fails to compile here
+/// until [`every_variant`] gets a sample of it.
+fn variant_index(event: &Event) -> usize {
+    match event {
+        Event::SessionStart(_) => 0,
+        Event::TextDelta(_) => 1,
+        Event::Message(_) => 2,
+        Event::Reasoning(_) => 3,
+        Event::ToolStart(_) => 4,
+        Event::ToolEnd(_) => 5,
+        Event::TurnStart(_) => 6,
+        Event::TurnEnd(_) => 7,
+        Event::UsageDelta(_) => 8,
+        Event::Result(_) => 9,
+        Event::Summary(_) => 10,
+        Event::Error(_) => 11,
+    }
+}
+
+#[test]
+fn validate_event_json_accepts_every_variant_fully_populated() {
+    let events = every_variant();
+    let indices: Vec<usize> = events.iter().map(variant_index).collect();
+    assert_eq!(indices, (0..=11).collect::<Vec<_>>());
+    for event in &events {
+        let value = serde_json::to_value(event).unwrap();
+        assert_eq!(validate_event_json(&value), Ok(()), "{value}");
+    }
+}
+
+#[test]
+fn validate_event_json_catches_malformed_events() {
+    let missing = serde_json::json!({"type": "tool_start", "tool_name": "Bash"});
+    let err = validate_event_json(&missing).unwrap_err();
+    assert!(err.contains("missing required field `call_id`"), "{err}");
+
+    let wrong_type = serde_json::json!({"type": "tool_end", "call_id": "c1", "tool_name": "Bash", "success": "yes"});
+    let err = validate_event_json(&wrong_type).unwrap_err();
+    assert!(err.contains("`success` has the wrong type"), "{err}");
+
+    let extra = serde_json::json!({"type": "text_delta", "text": "hi", "delta": "hi"});
+    let err = validate_event_json(&extra).unwrap_err();
+    assert!(err.contains("unknown field `delta`"), "{err}");
+
+    let unknown = serde_json::json!({"type": "thinking", "text": "hmm"});
+    assert!(validate_event_json(&unknown).is_err());
+}
+
+// ─── Filtering ──────────────────────────────────────────────────
+
+#[tokio::test]
+async fn filter_events_drops_only_rejected_events() {
+    use futures::StreamExt;
+
+    let tool = |id: &str, name: &str| {
+        [
+            Event::ToolStart(ToolStartEvent {
+                call_id: id.into(),
+                tool_name: name.into(),
+                input: None,
+                timestamp_ms: 0,
+                raw_type: None,
+            }),
+            // Gemini-style end without a tool name.
+            Event::ToolEnd(ToolEndEvent {
+                call_id: id.into(),
+                tool_name: String::new(),
+                success: true,
+                output: None,
+                usage: None,
+                timestamp_ms: 0,
+                raw_type: None,
+            }),
+        ]
+    };
+    let message = Event::Message(MessageEvent {
+        role: Role::Assistant,
+        text: "done".into(),
+        usage: None,
+        timestamp_ms: 0,
+        raw_type: None,
+    });
+    let delta = Event::TextDelta(TextDeltaEvent {
+        text: "do".into(),
+        timestamp_ms: 0,
+        raw_type: None,
+    });
+    let mut events = vec![delta.clone()];
+    events.extend(tool("t1", "Bash"));
+    events.extend(tool("t2", "Read"));
+    events.push(message.clone());
+
+    let run = |filter: EventFilter| {
+        let stream: harness::runner::EventStream =
+            Box::pin(futures::stream::iter(events.clone().into_iter().map(Ok)));
+        filter_events(stream, filter)
+            .map(|r| r.unwrap())
+            .collect::<Vec<_>>()
+    };
+
+    let kept = run(EventFilter::only(&[EventCategory::Message])).await;
+    assert_eq!(kept.len(), 1);
+    assert_eq!(kept[0], message);
+
+    let kept = run(EventFilter::new()
+        .drop(EventCategory::TextDelta)
+        .deny_tool("shell"))
+    .await;
+    assert_eq!(kept.len(), 3);
+    assert!(matches!(&kept[0], Event::ToolStart(t) if t.call_id == "t2"));
+    assert!(matches!(&kept[1], Event::ToolEnd(t) if t.call_id == "t2"));
+    assert_eq!(kept[2], message);
+
+    let kept = run(EventFilter::new().allow_tool("Bash")).await;
+    assert_eq!(kept.len(), 4);
+    assert_eq!(kept[0], delta);
+    assert!(kept[1..3]
+        .iter()
+        .all(|e| !matches!(e, Event::ToolStart(t) if t.call_id == "t2")));
+
+    assert_eq!(run(EventFilter::new()).await, events);
+    assert!("messages".parse::<EventCategory>().is_ok());
+    assert!("bogus".parse::<EventCategory>().is_err());
+}
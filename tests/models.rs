@@ -63,6 +63,32 @@ provider = "test"
     assert!(entry.codex.is_none());
 }
 
+#[test]
+fn parse_deprecated_entry() {
+    let toml = r#"
+[models.old]
+description = "Old"
+provider = "test"
+claude = "old-id"
+deprecated = true
+replacement = "new"
+
+[models.new]
+description = "New"
+provider = "test"
+claude = "new-id"
+"#;
+    let reg = ModelRegistry::from_toml(toml).unwrap();
+    assert!(reg.models["old"].deprecated);
+    assert!(!reg.models["new"].deprecated);
+    assert_eq!(
+        reg.deprecation_warning("old").as_deref(),
+        Some("model `old` is deprecated; use `new` instead")
+    );
+    assert!(reg.deprecation_warning("new").is_none());
+    assert!(reg.deprecation_warning("unknown").is_none());
+}
+
 // ─── Merge behavior ──────────────────────────────────────────────
 
 #[test]
@@ -163,6 +189,57 @@ fn resolve_raw_model_id_passthrough() {
     assert_eq!(res.model_id(), "claude-opus-4-6");
 }
 
+// ─── Reverse resolution ──────────────────────────────────────────
+
+#[test]
+fn reverse_resolve_unique_match() {
+    let reg = ModelRegistry::from_toml(
+        r#"
+[models.opus]
+claude = "claude-opus-4-6"
+"#,
+    )
+    .unwrap();
+    let res = reg.reverse_resolve("claude-opus-4-6", AgentKind::Claude);
+    assert_eq!(
+        res,
+        ModelResolution::Resolved {
+            canonical_name: "opus".into(),
+            agent_id: "claude-opus-4-6".into(),
+        }
+    );
+}
+
+#[test]
+fn reverse_resolve_ambiguous_when_entries_share_id() {
+    let reg = ModelRegistry::from_toml(
+        r#"
+[models.opus]
+claude = "claude-opus-4-6"
+
+[models.opus-latest]
+claude = "claude-opus-4-6"
+"#,
+    )
+    .unwrap();
+    let res = reg.reverse_resolve("claude-opus-4-6", AgentKind::Claude);
+    assert_eq!(
+        res,
+        ModelResolution::Ambiguous {
+            raw: "claude-opus-4-6".into(),
+            candidates: vec!["opus".into(), "opus-latest".into()],
+        }
+    );
+    assert_eq!(res.model_id(), "claude-opus-4-6");
+}
+
+#[test]
+fn reverse_resolve_unknown_id_passes_through() {
+    let reg = ModelRegistry::builtin();
+    let res = reg.reverse_resolve("no-such-model", AgentKind::Claude);
+    assert!(matches!(res, ModelResolution::Passthrough { .. }));
+}
+
 #[test]
 fn resolve_opus_for_opencode() {
     let reg = ModelRegistry::builtin();
@@ -208,6 +285,12 @@ fn model_entry_agent_model() {
         codex: Some("x-id".into()),
         opencode: None,
         cursor: None,
+        gemini: None,
+        deprecated: false,
+        replacement: None,
+        input_price_per_mtok: None,
+        output_price_per_mtok: None,
+        cached_input_price_per_mtok: None,
     };
     assert_eq!(entry.agent_model(AgentKind::Claude), Some("c-id"));
     assert_eq!(entry.agent_model(AgentKind::Codex), Some("x-id"));
@@ -224,6 +307,12 @@ fn model_entry_supported_agents() {
         codex: None,
         opencode: Some("o".into()),
         cursor: Some("u".into()),
+        gemini: None,
+        deprecated: false,
+        replacement: None,
+        input_price_per_mtok: None,
+        output_price_per_mtok: None,
+        cached_input_price_per_mtok: None,
     };
     let agents = entry.supported_agents();
     assert_eq!(agents.len(), 3);
@@ -256,8 +345,121 @@ fn models_for_agent_claude() {
 fn models_for_agent_codex() {
     let reg = ModelRegistry::builtin();
     let models = reg.models_for_agent(AgentKind::Codex);
-    // No models mapped for codex in the registry
-    assert!(models.is_empty());
+    // Only the OpenAI models are mapped for codex
+    assert!(models.contains(&("gpt-5", "gpt-5")));
+    assert!(models.iter().all(|(name, _)| name.starts_with("gpt-")));
+}
+
+#[test]
+fn estimate_cost_from_pricing() {
+    let reg = ModelRegistry::from_toml(
+        r#"
+[models.gpt-5]
+codex = "gpt-5-codex"
+input_price_per_mtok = 1.25
+output_price_per_mtok = 10.0
+
+[models.free]
+codex = "free-model"
+input_price_per_mtok = 1.0
+"#,
+    )
+    .unwrap();
+
+    let cost = reg
+        .estimate_cost("gpt-5", AgentKind::Codex, 2_000_000, 500_000)
+        .unwrap();
+    assert!((cost - 7.5).abs() < 1e-9);
+    // Agent-specific IDs are reverse-resolved to their entry.
+    let cost = reg
+        .estimate_cost("gpt-5-codex", AgentKind::Codex, 1_000, 0)
+        .unwrap();
+    assert!((cost - 0.00125).abs() < 1e-12);
+
+    // Missing output price, unknown model, or wrong agent's ID: no estimate.
+    assert_eq!(reg.estimate_cost("free", AgentKind::Codex, 10, 10), None);
+    assert_eq!(reg.estimate_cost("nope", AgentKind::Codex, 10, 10), None);
+    assert_eq!(reg.estimate_cost("gpt-5-codex", AgentKind::Claude, 10, 10), None);
+}
+
+#[test]
+fn estimate_usage_cost_bills_cache_reads_at_the_cached_price() {
+    let reg = ModelRegistry::from_toml(
+        r#"
+[models.gpt-5]
+codex = "gpt-5"
+claude = "claude-id"
+input_price_per_mtok = 1.0
+output_price_per_mtok = 10.0
+cached_input_price_per_mtok = 0.1
+"#,
+    )
+    .unwrap();
+    let usage = harness::event::UsageData {
+        input_tokens: Some(1_000_000),
+        output_tokens: Some(100_000),
+        cache_read_tokens: Some(800_000),
+        cache_creation_tokens: None,
+        cost_usd: None,
+    };
+
+    // Codex counts the 800k cached tokens inside its 1M input tokens.
+    let cost = reg
+        .estimate_usage_cost("gpt-5", AgentKind::Codex, &usage)
+        .unwrap();
+    assert!((cost - (0.2 + 0.08 + 1.0)).abs() < 1e-9, "{cost}");
+    // Claude reports them beside the input tokens.
+    let cost = reg
+        .estimate_usage_cost("claude-id", AgentKind::Claude, &usage)
+        .unwrap();
+    assert!((cost - (1.0 + 0.08 + 1.0)).abs() < 1e-9, "{cost}");
+
+    // Without a cached price, cache reads cost the full input price.
+    let reg = ModelRegistry::from_toml(
+        "[models.m]\ncodex = \"m\"\ninput_price_per_mtok = 1.0\noutput_price_per_mtok = 10.0\n",
+    )
+    .unwrap();
+    let cost = reg.estimate_usage_cost("m", AgentKind::Codex, &usage).unwrap();
+    assert!((cost - 2.0).abs() < 1e-9, "{cost}");
+}
+
+#[test]
+fn builtin_models_are_priced_for_every_agent_they_map() {
+    let reg = ModelRegistry::builtin();
+    for (name, entry) in &reg.models {
+        assert!(
+            entry.input_price_per_mtok.is_some()
+                && entry.output_price_per_mtok.is_some()
+                && entry.cached_input_price_per_mtok.is_some(),
+            "{name} is unpriced"
+        );
+    }
+    for agent in [AgentKind::Codex, AgentKind::Gemini, AgentKind::OpenCode] {
+        assert!(!reg.models_for_agent(agent).is_empty(), "{agent}");
+    }
+}
+
+#[test]
+fn to_toml_string_round_trips() {
+    let reg = ModelRegistry::builtin().merge(
+        &ModelRegistry::from_toml(
+            r#"
+[models.team-fast]
+description = "Team default"
+provider = "anthropic"
+claude = "claude-haiku-4-5"
+deprecated = true
+replacement = "haiku"
+"#,
+        )
+        .unwrap(),
+    );
+    let toml = reg.to_toml_string().unwrap();
+    assert_eq!(ModelRegistry::from_toml(&toml).unwrap(), reg);
+    // Sorted by name, so exports diff cleanly.
+    assert_eq!(toml, reg.to_toml_string().unwrap());
+    let first = toml.find("[models.").unwrap();
+    assert!(toml[first..].starts_with(&format!("[models.{}]", reg.names()[0])));
 }
 
 // ─── CLI tests ───────────────────────────────────────────────────
@@ -381,6 +583,61 @@ fn models_help() {
         .stdout(predicate::str::contains("model registry"));
 }
 
+#[test]
+fn models_export_then_import_round_trips() {
+    let source = tempfile::tempdir().unwrap();
+    std::fs::write(
+        source.path().join("harness.toml"),
+        "[models.team-fast]\nprovider = \"anthropic\"\nclaude = \"team-haiku\"\n",
+    )
+    .unwrap();
+    let exported = source.path().join("shared.toml");
+    harness_cmd()
+        .current_dir(source.path())
+        .env("HOME", source.path())
+        .args(["--offline", "models", "export"])
+        .arg(&exported)
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("Exported"));
+    let shared = ModelRegistry::from_toml(&std::fs::read_to_string(&exported).unwrap()).unwrap();
+    assert!(shared.models.contains_key("team-fast"));
+    assert!(shared.models.contains_key("opus"));
+
+    // Into a project: merged into harness.toml, other settings kept.
+    let project = tempfile::tempdir().unwrap();
+    std::fs::write(project.path().join("harness.toml"), "default_agent = \"claude\"\n").unwrap();
+    harness_cmd()
+        .current_dir(project.path())
+        .env("HOME", project.path())
+        .args(["--offline", "models", "import"])
+        .arg(&exported)
+        .assert()
+        .success();
+    let config = std::fs::read_to_string(project.path().join("harness.toml")).unwrap();
+    assert!(config.contains("default_agent = \"claude\""), "{config}");
+    harness_cmd()
+        .current_dir(project.path())
+        .env("HOME", project.path())
+        .args(["--offline", "models", "resolve", "team-fast", "--agent", "claude"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("team-haiku"));
+
+    // Into the cached registry under HOME.
+    let home = tempfile::tempdir().unwrap();
+    harness_cmd()
+        .current_dir(home.path())
+        .env("HOME", home.path())
+        .args(["--offline", "models", "import", "--into", "canonical"])
+        .arg(&exported)
+        .assert()
+        .success();
+    let cached = std::fs::read_to_string(home.path().join(".harness").join("models.toml")).unwrap();
+    let cached = ModelRegistry::from_toml(&cached).unwrap();
+    assert_eq!(cached.models["team-fast"].claude.as_deref(), Some("team-haiku"));
+}
+
 // ─── Dry-run with model resolution ──────────────────────────────
 
 #[test]
@@ -430,6 +687,34 @@ fn dry_run_passthrough_raw_model() {
     }
 }
 
+#[test]
+fn refresh_registry_is_skipped_offline() {
+    let home = tempfile::tempdir().unwrap();
+    std::fs::create_dir(home.path().join(".harness")).unwrap();
+    std::fs::write(
+        home.path().join(".harness/models.toml"),
+        "[models.fast]\nclaude = \"cached-fast-id\"\n",
+    )
+    .unwrap();
+
+    harness_cmd()
+        .env("HOME", home.path())
+        .args([
+            "--offline",
+            "run",
+            "--agent", "claude",
+            "--model", "fast",
+            "--prompt", "hello",
+            "--refresh-registry",
+            "--binary", "/bin/echo",
+            "--dry-run",
+        ])
+        .assert()
+        .success()
+        .stderr(predicate::str::contains("registry refresh skipped: offline"))
+        .stdout(predicate::str::contains("cached-fast-id"));
+}
+
 // ─── Config init (harness.toml) ─────────────────────────────────
 
 #[test]
@@ -461,6 +746,35 @@ fn config_init_rejects_existing() {
         .stderr(predicate::str::contains("already exists"));
 }
 
+#[test]
+fn config_validate_reports_problems_and_fails() {
+    let tmp = tempfile::tempdir().unwrap();
+    let sub = tmp.path().join("sub");
+    std::fs::create_dir(&sub).unwrap();
+    let path = tmp.path().join("harness.toml");
+    std::fs::write(&path, "default_agent = \"claude\"\n").unwrap();
+    harness_cmd()
+        .args(["config", "validate"])
+        .current_dir(&sub)
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("harness.toml: ok"));
+
+    std::fs::write(
+        &path,
+        "defualt_agent = \"claude\"\ndefault_permissions = \"write\"\n[models.x]\nprovider = \"p\"\n",
+    )
+    .unwrap();
+    harness_cmd()
+        .args(["config", "validate"])
+        .current_dir(&sub)
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("defualt_agent: unknown key"))
+        .stderr(predicate::str::contains("default_permissions: unknown permission mode"))
+        .stderr(predicate::str::contains("models.x: no agent mapping"));
+}
+
 // ─── Project config walk-up ─────────────────────────────────────
 
 #[test]
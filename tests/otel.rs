@@ -0,0 +1,107 @@
+#![cfg(feature = "otel")]
+
+use std::sync::{Arc, Mutex};
+
+use futures::StreamExt;
+use harness::config::{AgentKind, TaskConfig};
+use opentelemetry::Value;
+use opentelemetry_sdk::error::OTelSdkResult;
+use opentelemetry_sdk::trace::{SdkTracerProvider, SpanData, SpanExporter};
+
+/// Keeps exported spans in memory for inspection.
+#[derive(Debug, Clone, Default)]
+struct MemoryExporter(Arc<Mutex<Vec<SpanData>>>);
+
+impl SpanExporter for MemoryExporter {
+    async fn export(&self, batch: Vec<SpanData>) -> OTelSdkResult {
+        self.0.lock().unwrap().extend(batch);
+        Ok(())
+    }
+}
+
+fn attribute<'a>(span: &'a SpanData, key: &str) -> Option<&'a Value> {
+    span.attributes
+        .iter()
+        .find(|kv| kv.key.as_str() == key)
+        .map(|kv| &kv.value)
+}
+
+fn write_script(path: &std::path::Path, script: &str) {
+    std::fs::write(path, script).unwrap();
+    use std::os::unix::fs::PermissionsExt;
+    std::fs::set_permissions(path, std::fs::Permissions::from_mode(0o755)).unwrap();
+}
+
+#[tokio::test]
+async fn run_is_traced_with_one_span_per_completed_tool_call() {
+    let exporter = MemoryExporter::default();
+    let provider = SdkTracerProvider::builder()
+        .with_simple_exporter(exporter.clone())
+        .build();
+    opentelemetry::global::set_tracer_provider(provider.clone());
+
+    let dir = tempfile::tempdir().unwrap();
+    let claude = dir.path().join("claude");
+    write_script(
+        &claude,
+        r#"#!/bin/bash
+echo '{"type":"system","subtype":"init","session_id":"s1","model":"mock-model"}'
+echo '{"type":"assistant","message":{"role":"assistant","content":[{"type":"tool_use","id":"t1","name":"Bash","input":{"command":"ls"}},{"type":"tool_use","id":"t2","name":"Read","input":{}}]}}'
+echo '{"type":"user","message":{"role":"user","content":[{"type":"tool_result","tool_use_id":"t1","content":"a.txt"},{"type":"tool_result","tool_use_id":"t2","is_error":true,"content":"missing"}]}}'
+echo '{"type":"assistant","message":{"role":"assistant","content":[{"type":"tool_use","id":"t3","name":"Bash","input":{}}]}}'
+echo '{"type":"result","subtype":"success","result":"Done.","session_id":"s1","total_cost_usd":0.01}'
+"#,
+    );
+    let mut config = TaskConfig::new("list files", AgentKind::Claude);
+    config.binary_path = Some(claude);
+    config.model = Some("mock-model".into());
+    config.cwd = Some(dir.path().to_path_buf());
+
+    let events: Vec<_> = harness::run_task(&config).await.unwrap().collect().await;
+    assert!(events.iter().all(|e| e.is_ok()), "{events:?}");
+    provider.force_flush().unwrap();
+
+    let spans = exporter.0.lock().unwrap().clone();
+    let root = spans
+        .iter()
+        .find(|s| s.name == "harness.run")
+        .expect("root span");
+    assert_eq!(
+        attribute(root, "harness.agent"),
+        Some(&Value::from("claude"))
+    );
+    assert_eq!(
+        attribute(root, "harness.model"),
+        Some(&Value::from("mock-model"))
+    );
+    assert_eq!(
+        attribute(root, "harness.prompt_length"),
+        Some(&Value::I64("list files".len() as i64))
+    );
+    assert_eq!(attribute(root, "harness.success"), Some(&Value::Bool(true)));
+
+    let tools: Vec<&SpanData> = spans
+        .iter()
+        .filter(|s| s.name.starts_with("tool "))
+        .collect();
+    assert!(tools
+        .iter()
+        .all(|s| s.parent_span_id == root.span_context.span_id()
+            && s.span_context.trace_id() == root.span_context.trace_id()));
+    let completed: Vec<(&str, bool)> = tools
+        .iter()
+        .filter_map(|s| match attribute(s, "harness.tool.success") {
+            Some(Value::Bool(ok)) => Some((s.name.as_ref(), *ok)),
+            _ => None,
+        })
+        .collect();
+    assert_eq!(completed.len(), 2, "{tools:?}");
+    assert!(completed.contains(&("tool Bash", true)));
+    assert!(completed.contains(&("tool Read", false)));
+    assert!(tools
+        .iter()
+        .filter(|s| attribute(s, "harness.tool.success").is_some())
+        .all(|s| attribute(s, "harness.tool.duration_ms").is_some()));
+    // The call that never finished is ended with the run, unmarked.
+    assert_eq!(tools.len(), 3);
+}
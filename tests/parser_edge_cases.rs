@@ -170,3 +170,60 @@ mod opencode {
         assert_eq!(args.last().unwrap(), "my prompt");
     }
 }
+
+// ─── Prompt passing ──────────────────────────────────────────────
+
+mod prompt_passing {
+    use harness::agents::create_runner;
+    use harness::config::{AgentKind, TaskConfig};
+    use harness::runner::PromptPassing;
+
+    #[test]
+    fn each_runner_declares_its_strategy() {
+        let expected = [
+            (AgentKind::Claude, PromptPassing::Flag("-p")),
+            (AgentKind::Codex, PromptPassing::Positional),
+            (AgentKind::OpenCode, PromptPassing::Positional),
+            (AgentKind::Cursor, PromptPassing::Positional),
+            (AgentKind::Gemini, PromptPassing::Flag("--prompt")),
+        ];
+        for (kind, strategy) in expected {
+            assert_eq!(create_runner(kind).prompt_passing(), strategy, "{kind:?}");
+        }
+    }
+
+    #[test]
+    fn build_args_place_prompt_per_strategy() {
+        for kind in [
+            AgentKind::Claude,
+            AgentKind::Codex,
+            AgentKind::OpenCode,
+            AgentKind::Cursor,
+            AgentKind::Gemini,
+        ] {
+            let runner = create_runner(kind);
+            let mut config = TaskConfig::new("the prompt", kind);
+            config.extra_args = vec!["--extra".into()];
+            let args = runner.build_args(&config);
+            match runner.prompt_passing() {
+                PromptPassing::Flag(flag) => {
+                    assert_eq!(&args[..2], [flag, "the prompt"], "{kind:?}");
+                }
+                PromptPassing::Positional => {
+                    assert_eq!(args.last().map(String::as_str), Some("the prompt"), "{kind:?}");
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn apply_inserts_flag_first_or_appends() {
+        let mut args = vec!["--verbose".to_string()];
+        PromptPassing::Flag("-p").apply(&mut args, "hi");
+        assert_eq!(args, ["-p", "hi", "--verbose"]);
+
+        let mut args = vec!["exec".to_string()];
+        PromptPassing::Positional.apply(&mut args, "hi");
+        assert_eq!(args, ["exec", "hi"]);
+    }
+}
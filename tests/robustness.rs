@@ -2,9 +2,10 @@
 // concurrent session logging, event aggregation.
 
 use futures::StreamExt;
-use harness::config::{AgentKind, TaskConfig};
+use harness::config::{AgentKind, PermissionMode, TaskConfig};
 use harness::event::*;
 use harness::runner::AgentRunner;
+use serde_json::json;
 
 // ─── Helpers ────────────────────────────────────────────────────
 
@@ -397,6 +398,46 @@ fn validate_config_opencode_no_system_prompt() {
     assert!(warnings.iter().any(|w| w.message.contains("system-prompt")));
 }
 
+#[test]
+fn validate_config_codex_no_resume() {
+    let mut config = TaskConfig::new("task", AgentKind::Codex);
+    config.resume_session_id = Some("th-1".into());
+    let warnings = harness::agents::codex::CodexRunner.validate_config(&config);
+    assert!(warnings.iter().any(|w| w.message.contains("--resume")));
+}
+
+#[test]
+fn validate_config_opencode_no_reasoning() {
+    let mut config = TaskConfig::new("task", AgentKind::OpenCode);
+    config.include_reasoning = true;
+    let warnings = harness::agents::opencode::OpenCodeRunner.validate_config(&config);
+    assert!(warnings.iter().any(|w| w.message.contains("--include-reasoning")));
+
+    let mut config = TaskConfig::new("task", AgentKind::Codex);
+    config.include_reasoning = true;
+    let warnings = harness::agents::codex::CodexRunner.validate_config(&config);
+    assert!(warnings.is_empty());
+}
+
+#[test]
+fn validate_config_agent_profile_only_for_opencode() {
+    let mut config = TaskConfig::new("task", AgentKind::Codex);
+    config.agent_profile = Some("reviewer".into());
+    let warnings = harness::agents::codex::CodexRunner.validate_config(&config);
+    assert!(warnings.iter().any(|w| w.message.contains("agent-profile")));
+
+    let mut config = TaskConfig::new("task", AgentKind::OpenCode);
+    config.agent_profile = Some("reviewer".into());
+    let warnings = harness::agents::opencode::OpenCodeRunner.validate_config(&config);
+    assert!(warnings.is_empty());
+
+    config.permission_mode = PermissionMode::ReadOnly;
+    let warnings = harness::agents::opencode::OpenCodeRunner.validate_config(&config);
+    assert!(warnings
+        .iter()
+        .any(|w| w.message.contains("ignored in read-only mode")));
+}
+
 #[test]
 fn validate_config_opencode_no_append_system_prompt() {
     let mut config = TaskConfig::new("task", AgentKind::OpenCode);
@@ -428,6 +469,52 @@ fn validate_config_claude_all_supported() {
     );
 }
 
+#[test]
+fn capabilities_report_resume_allowlist_and_reasoning() {
+    // (agent, resume, tool allowlist, reasoning)
+    let expected = [
+        (AgentKind::Claude, true, true, true),
+        (AgentKind::Codex, false, false, true),
+        (AgentKind::OpenCode, false, false, false),
+        (AgentKind::Cursor, true, false, false),
+        (AgentKind::Gemini, false, false, false),
+    ];
+    for (kind, resume, allowlist, reasoning) in expected {
+        let runner = harness::agents::create_runner(kind);
+        assert_eq!(runner.supports_resume(), resume, "{kind}");
+
+        let caps = serde_json::to_value(runner.capabilities()).unwrap();
+        assert_eq!(caps["supports_resume"], resume, "{kind}");
+        assert_eq!(caps["supports_tool_allowlist"], allowlist, "{kind}");
+        assert_eq!(caps["supports_reasoning"], reasoning, "{kind}");
+    }
+
+    // Capabilities serialized before these fields existed still parse.
+    let old: harness::runner::AgentCapabilities = serde_json::from_value(json!({
+        "supports_system_prompt": true,
+        "supports_budget": false,
+        "supports_model": true,
+        "supports_max_turns": false,
+        "supports_append_system_prompt": false
+    }))
+    .unwrap();
+    assert!(!old.supports_resume && !old.supports_tool_allowlist && !old.supports_reasoning);
+}
+
+#[test]
+fn validate_config_resume_follows_capabilities() {
+    for kind in AgentKind::ALL {
+        let mut config = TaskConfig::new("task", kind);
+        config.resume_session_id = Some("sess-1".into());
+        let runner = harness::agents::create_runner(kind);
+        let warned = runner
+            .validate_config(&config)
+            .iter()
+            .any(|w| w.message.contains("--resume"));
+        assert_eq!(warned, !runner.capabilities().supports_resume, "{kind}");
+    }
+}
+
 // ─── Event aggregation tests ────────────────────────────────────
 
 #[test]
@@ -444,6 +531,7 @@ fn sum_costs_usage_deltas() {
                 ..Default::default()
             },
             timestamp_ms: 0,
+            raw_type: None,
         }),
         Event::UsageDelta(UsageDeltaEvent {
             usage: UsageData {
@@ -451,16 +539,9 @@ fn sum_costs_usage_deltas() {
                 ..Default::default()
             },
             timestamp_ms: 0,
+            raw_type: None,
         }),
-        Event::Result(ResultEvent {
-            success: true,
-            text: "done".into(),
-            session_id: "s".into(),
-            duration_ms: None,
-            total_cost_usd: Some(0.05),
-            usage: None,
-            timestamp_ms: 0,
-        }),
+        Event::Result(ResultEvent::new(true, "done", "s").total_cost_usd(0.05)),
     ];
     let total = sum_costs(&events);
     assert!((total - 0.08).abs() < 1e-10);
@@ -476,6 +557,7 @@ fn total_tokens_counts() {
                 ..Default::default()
             },
             timestamp_ms: 0,
+            raw_type: None,
         }),
         Event::UsageDelta(UsageDeltaEvent {
             usage: UsageData {
@@ -484,6 +566,7 @@ fn total_tokens_counts() {
                 ..Default::default()
             },
             timestamp_ms: 0,
+            raw_type: None,
         }),
     ];
     let (input, output) = total_tokens(&events);
@@ -499,12 +582,14 @@ fn extract_tool_calls_pairs() {
             tool_name: "bash".into(),
             input: None,
             timestamp_ms: 0,
+            raw_type: None,
         }),
         Event::ToolStart(ToolStartEvent {
             call_id: "c2".into(),
             tool_name: "read".into(),
             input: None,
             timestamp_ms: 0,
+            raw_type: None,
         }),
         Event::ToolEnd(ToolEndEvent {
             call_id: "c1".into(),
@@ -513,6 +598,7 @@ fn extract_tool_calls_pairs() {
             output: None,
             usage: None,
             timestamp_ms: 0,
+            raw_type: None,
         }),
     ];
     let pairs = extract_tool_calls(&events);
@@ -523,6 +609,371 @@ fn extract_tool_calls_pairs() {
     assert!(pairs[1].1.is_none()); // c2 never completed
 }
 
+// ─── File change extraction ────────────────────────────────────
+
+fn tool_start(call_id: &str, tool_name: &str, input: serde_json::Value) -> Event {
+    Event::ToolStart(ToolStartEvent {
+        call_id: call_id.into(),
+        tool_name: tool_name.into(),
+        input: Some(input),
+        timestamp_ms: 0,
+        raw_type: None,
+    })
+}
+
+fn tool_end(call_id: &str, tool_name: &str, success: bool) -> Event {
+    Event::ToolEnd(ToolEndEvent {
+        call_id: call_id.into(),
+        tool_name: tool_name.into(),
+        success,
+        output: None,
+        usage: None,
+        timestamp_ms: 0,
+        raw_type: None,
+    })
+}
+
+fn change(path: &str, kind: FileChangeKind) -> FileChange {
+    FileChange {
+        path: path.into(),
+        kind,
+    }
+}
+
+#[test]
+fn extract_file_changes_claude_tools() {
+    let events = vec![
+        tool_start("tu-1", "Read", json!({"file_path": "/repo/README.md"})),
+        tool_end("tu-1", "Read", true),
+        tool_start(
+            "tu-2",
+            "Write",
+            json!({"file_path": "/repo/new.rs", "content": "fn x() {}"}),
+        ),
+        tool_end("tu-2", "Write", true),
+        tool_start(
+            "tu-3",
+            "Edit",
+            json!({"file_path": "/repo/src/lib.rs", "old_string": "a", "new_string": "b"}),
+        ),
+        tool_end("tu-3", "Edit", true),
+        tool_start(
+            "tu-4",
+            "MultiEdit",
+            json!({"file_path": "/repo/new.rs", "edits": []}),
+        ),
+        tool_end("tu-4", "MultiEdit", true),
+    ];
+    assert_eq!(
+        extract_file_changes(&events),
+        vec![
+            change("/repo/new.rs", FileChangeKind::Created),
+            change("/repo/src/lib.rs", FileChangeKind::Modified),
+        ]
+    );
+}
+
+#[test]
+fn extract_file_changes_codex_items() {
+    let events = vec![
+        tool_start(
+            "item_3",
+            "file_change",
+            json!({
+                "path": "",
+                "changes": [
+                    {"path": "src/added.rs", "kind": "add"},
+                    {"path": "src/main.rs", "kind": "update"},
+                    {"path": "src/old.rs", "kind": "delete"}
+                ]
+            }),
+        ),
+        tool_end("item_3", "file_change", true),
+        tool_start("fc-1", "file_change", json!({"path": "Cargo.toml"})),
+        tool_end("fc-1", "file_change", true),
+        tool_start(
+            "call_9",
+            "apply_patch",
+            json!({"input": "*** Begin Patch\n*** Add File: docs/new.md\n+hi\n*** End Patch"}),
+        ),
+    ];
+    assert_eq!(
+        extract_file_changes(&events),
+        vec![
+            change("src/added.rs", FileChangeKind::Created),
+            change("src/main.rs", FileChangeKind::Modified),
+            change("src/old.rs", FileChangeKind::Deleted),
+            change("Cargo.toml", FileChangeKind::Modified),
+            change("docs/new.md", FileChangeKind::Created),
+        ]
+    );
+}
+
+#[test]
+fn extract_file_changes_cursor_tools() {
+    let events = vec![
+        tool_start("c-1", "read", json!({"path": "src/main.rs"})),
+        tool_end("c-1", "read", true),
+        tool_start(
+            "c-2",
+            "edit",
+            json!({"path": "src/main.rs", "streamContent": "fn main() {}"}),
+        ),
+        tool_end("c-2", "edit", true),
+        tool_start("c-3", "write", json!({"path": "tmp.txt", "fileText": "x"})),
+        tool_end("c-3", "write", true),
+        tool_start("c-4", "delete", json!({"path": "tmp.txt"})),
+        tool_end("c-4", "delete", true),
+    ];
+    // tmp.txt was created and then deleted, so it nets out.
+    assert_eq!(
+        extract_file_changes(&events),
+        vec![change("src/main.rs", FileChangeKind::Modified)]
+    );
+}
+
+#[test]
+fn extract_file_changes_opencode_tools() {
+    let events = vec![
+        tool_start(
+            "toolu_01",
+            "edit",
+            json!({"filePath": "/repo/src/app.ts", "oldString": "a", "newString": "b"}),
+        ),
+        tool_end("toolu_01", "edit", true),
+        tool_start(
+            "toolu_02",
+            "write",
+            json!({"filePath": "/repo/src/app.ts", "content": "export {}"}),
+        ),
+        tool_end("toolu_02", "write", true),
+        tool_start("toolu_03", "bash", json!({"command": "rm -rf build"})),
+        tool_end("toolu_03", "bash", true),
+    ];
+    // Overwriting a file harness already saw edited is a modification.
+    assert_eq!(
+        extract_file_changes(&events),
+        vec![change("/repo/src/app.ts", FileChangeKind::Modified)]
+    );
+}
+
+#[test]
+fn extract_file_changes_gemini_tools() {
+    let events = vec![
+        tool_start(
+            "t-1",
+            "write_file",
+            json!({"file_path": "/repo/notes.md", "content": "x"}),
+        ),
+        tool_end("t-1", "", true),
+        tool_start(
+            "t-2",
+            "replace",
+            json!({"file_path": "/repo/main.py", "old_string": "a", "new_string": "b"}),
+        ),
+        tool_end("t-2", "", true),
+    ];
+    assert_eq!(
+        extract_file_changes(&events),
+        vec![
+            change("/repo/notes.md", FileChangeKind::Created),
+            change("/repo/main.py", FileChangeKind::Modified),
+        ]
+    );
+}
+
+#[test]
+fn extract_file_changes_skips_failed_calls() {
+    let events = vec![
+        tool_start("tu-1", "Edit", json!({"file_path": "/repo/a.rs"})),
+        tool_end("tu-1", "Edit", false),
+        tool_start("tu-2", "Write", json!({})),
+        tool_end("tu-2", "Write", true),
+    ];
+    assert!(extract_file_changes(&events).is_empty());
+}
+
+/// Run a mock `agent` that prints `lines` verbatim and collect its events.
+async fn replay_agent_lines(agent: AgentKind, lines: &[&str]) -> Vec<Event> {
+    let dir = tempfile::tempdir().unwrap();
+    let script = format!("#!/bin/bash\ncat <<'EOF'\n{}\nEOF\n", lines.join("\n"));
+    let binary = create_mock_binary(dir.path(), agent.default_binary(), &script);
+    let mut config = TaskConfig::new("test", agent);
+    config.binary_path = Some(binary);
+    config.cwd = Some(dir.path().to_path_buf());
+
+    let mut stream = run_task_retry(&config).await;
+    let mut events = Vec::new();
+    while let Some(Ok(event)) = stream.next().await {
+        events.push(event);
+    }
+    events
+}
+
+#[tokio::test]
+async fn extract_file_changes_from_recorded_claude_lines() {
+    let events = replay_agent_lines(
+        AgentKind::Claude,
+        &[
+            r#"{"type":"system","subtype":"init","session_id":"s1","model":"claude-sonnet-4-5"}"#,
+            r#"{"type":"assistant","message":{"role":"assistant","content":[{"type":"tool_use","id":"toolu_01","name":"Write","input":{"file_path":"/repo/new.rs","content":"fn x() {}"}}]}}"#,
+            r#"{"type":"user","message":{"role":"user","content":[{"tool_use_id":"toolu_01","type":"tool_result","content":"File created successfully at: /repo/new.rs"}]}}"#,
+            r#"{"type":"assistant","message":{"role":"assistant","content":[{"type":"tool_use","id":"toolu_02","name":"Write","input":{"file_path":"/repo/old.rs","content":"fn y() {}"}}]}}"#,
+            r#"{"type":"user","message":{"role":"user","content":[{"tool_use_id":"toolu_02","type":"tool_result","content":"The file /repo/old.rs has been updated. Here's the result of running `cat -n` on a snippet of the edited file:\n     1\tfn y() {}"}]}}"#,
+            r#"{"type":"result","subtype":"success","result":"done","session_id":"s1"}"#,
+        ],
+    )
+    .await;
+    assert_eq!(
+        extract_file_changes(&events),
+        vec![
+            change("/repo/new.rs", FileChangeKind::Created),
+            change("/repo/old.rs", FileChangeKind::Modified),
+        ]
+    );
+}
+
+#[tokio::test]
+async fn extract_file_changes_from_recorded_gemini_lines() {
+    let events = replay_agent_lines(
+        AgentKind::Gemini,
+        &[
+            r#"{"type":"init","session_id":"g1","model":"gemini-2.5-pro"}"#,
+            r#"{"type":"tool_use","tool_name":"write_file","tool_id":"write_file-1","parameters":{"file_path":"/repo/new.md","content":"x"}}"#,
+            r#"{"type":"tool_result","tool_id":"write_file-1","status":"success","output":"Successfully created and wrote to new file: /repo/new.md."}"#,
+            r#"{"type":"tool_use","tool_name":"write_file","tool_id":"write_file-2","parameters":{"file_path":"/repo/old.md","content":"y"}}"#,
+            r#"{"type":"tool_result","tool_id":"write_file-2","status":"success","output":"Successfully overwrote file: /repo/old.md."}"#,
+            r#"{"type":"tool_use","tool_name":"replace","tool_id":"replace-3","parameters":{"file_path":"/repo/main.py","old_string":"a","new_string":"b"}}"#,
+            r#"{"type":"tool_result","tool_id":"replace-3","status":"success","output":"Successfully modified file: /repo/main.py (1 replacements)."}"#,
+            r#"{"type":"result","status":"success","stats":{"input_tokens":10,"output_tokens":5,"duration_ms":100}}"#,
+        ],
+    )
+    .await;
+    assert_eq!(
+        extract_file_changes(&events),
+        vec![
+            change("/repo/new.md", FileChangeKind::Created),
+            change("/repo/old.md", FileChangeKind::Modified),
+            change("/repo/main.py", FileChangeKind::Modified),
+        ]
+    );
+}
+
+#[tokio::test]
+async fn extract_file_changes_from_recorded_codex_lines() {
+    let events = replay_agent_lines(
+        AgentKind::Codex,
+        &[
+            r#"{"type":"thread.started","thread_id":"t1"}"#,
+            r#"{"type":"item.completed","item":{"id":"item_1","type":"file_change","changes":[{"path":"/repo/new.rs","kind":"add"},{"path":"/repo/old.rs","kind":"update"},{"path":"/repo/gone.rs","kind":"delete"}],"status":"completed"}}"#,
+            r#"{"type":"turn.completed","usage":{"input_tokens":10,"cached_input_tokens":0,"output_tokens":5}}"#,
+        ],
+    )
+    .await;
+    assert_eq!(
+        extract_file_changes(&events),
+        vec![
+            change("/repo/new.rs", FileChangeKind::Created),
+            change("/repo/old.rs", FileChangeKind::Modified),
+            change("/repo/gone.rs", FileChangeKind::Deleted),
+        ]
+    );
+}
+
+#[tokio::test]
+async fn extract_file_changes_from_recorded_cursor_lines() {
+    let events = replay_agent_lines(
+        AgentKind::Cursor,
+        &[
+            r#"{"type":"system","subtype":"init","session_id":"c1","model":"gpt-5"}"#,
+            r#"{"type":"tool_call","subtype":"started","call_id":"call_1","tool_call":{"writeToolCall":{"args":{"path":"/repo/new.txt","fileText":"x"}}},"session_id":"c1"}"#,
+            r#"{"type":"tool_call","subtype":"completed","call_id":"call_1","tool_call":{"writeToolCall":{"result":{"success":{"path":"/repo/new.txt","linesCreated":1,"fileSize":1}}}},"session_id":"c1"}"#,
+            r#"{"type":"tool_call","subtype":"started","call_id":"call_2","tool_call":{"editToolCall":{"args":{"path":"/repo/old.txt","streamContent":"y"}}},"session_id":"c1"}"#,
+            r#"{"type":"tool_call","subtype":"completed","call_id":"call_2","tool_call":{"editToolCall":{"result":{"success":{"path":"/repo/old.txt","linesAdded":1,"linesRemoved":1}}}},"session_id":"c1"}"#,
+            r#"{"type":"result","subtype":"success","is_error":false,"result":"done","session_id":"c1"}"#,
+        ],
+    )
+    .await;
+    assert_eq!(
+        extract_file_changes(&events),
+        vec![
+            change("/repo/new.txt", FileChangeKind::Created),
+            change("/repo/old.txt", FileChangeKind::Modified),
+        ]
+    );
+}
+
+#[tokio::test]
+async fn extract_file_changes_from_recorded_opencode_lines() {
+    let events = replay_agent_lines(
+        AgentKind::OpenCode,
+        &[
+            r#"{"type":"step_start","sessionID":"o1","part":{"type":"step-start"}}"#,
+            r#"{"type":"tool_use","sessionID":"o1","part":{"callID":"call_1","tool":"write","state":{"status":"completed","input":{"filePath":"/repo/new.ts","content":"export {}"},"output":""}}}"#,
+            r#"{"type":"tool_use","sessionID":"o1","part":{"callID":"call_2","tool":"edit","state":{"status":"completed","input":{"filePath":"/repo/old.ts","oldString":"a","newString":"b"},"output":""}}}"#,
+            r#"{"type":"step_finish","sessionID":"o1","part":{"type":"step-finish","reason":"stop","cost":0,"tokens":{"input":1,"output":1,"reasoning":0,"cache":{"read":0,"write":0}}}}"#,
+        ],
+    )
+    .await;
+    assert_eq!(
+        extract_file_changes(&events),
+        vec![
+            change("/repo/new.ts", FileChangeKind::Created),
+            change("/repo/old.ts", FileChangeKind::Modified),
+        ]
+    );
+}
+
+// ─── Working directory checks ──────────────────────────────────
+
+/// A missing directory or a file given as `cwd` fails before spawning.
+#[tokio::test]
+async fn invalid_cwd_fails_with_invalid_work_dir() {
+    let dir = tempfile::tempdir().unwrap();
+    let file = dir.path().join("not-a-dir.txt");
+    std::fs::write(&file, "x").unwrap();
+
+    for cwd in [dir.path().join("missing"), file] {
+        let mut config = TaskConfig::new("test", AgentKind::Claude);
+        config.binary_path = Some("/bin/echo".into());
+        config.cwd = Some(cwd.clone());
+        match harness::run_task_with_cancel(&config, None).await {
+            Err(e @ harness::Error::InvalidWorkDir(_)) => {
+                assert_eq!(e.code(), "E006");
+                assert!(matches!(e, harness::Error::InvalidWorkDir(p) if p == cwd));
+            }
+            Err(e) => panic!("expected InvalidWorkDir, got {e}"),
+            Ok(_) => panic!("expected InvalidWorkDir for {}", cwd.display()),
+        }
+    }
+}
+
+/// A non-canonical `cwd` is canonicalized before it reaches the event stream.
+#[tokio::test]
+async fn cwd_is_canonicalized() {
+    let dir = tempfile::tempdir().unwrap();
+    let binary = create_mock_binary(
+        dir.path(),
+        "claude",
+        r#"#!/bin/bash
+echo '{"type":"system","subtype":"init","session_id":"s1"}'
+echo '{"type":"result","subtype":"success","result":"ok","session_id":"s1"}'
+"#,
+    );
+    let mut config = TaskConfig::new("test", AgentKind::Claude);
+    config.binary_path = Some(binary);
+    config.cwd = Some(dir.path().join("."));
+
+    let mut stream = run_task_retry(&config).await;
+    match stream.next().await {
+        Some(Ok(Event::SessionStart(s))) => assert_eq!(
+            s.cwd.as_deref(),
+            Some(dir.path().canonicalize().unwrap().to_str().unwrap())
+        ),
+        other => panic!("expected SessionStart, got {other:?}"),
+    }
+}
+
 // ─── Cancellation tests ────────────────────────────────────────
 
 /// Cancelling the token should stop the stream.
@@ -76,6 +76,21 @@ echo '{"type":"result","subtype":"success","is_error":false,"duration_ms":800,"r
     path
 }
 
+/// Create a mock Gemini CLI binary that outputs stream-json events.
+fn create_mock_gemini_binary(dir: &std::path::Path) -> PathBuf {
+    let path = dir.join("gemini");
+    let script = r#"#!/bin/bash
+echo '{"type":"init","timestamp":"2025-10-10T12:00:00.000Z","session_id":"gem-mock","model":"gemini-2.5-pro"}'
+echo '{"type":"message","role":"user","content":"add tests"}'
+echo '{"type":"tool_use","tool_name":"run_shell_command","tool_id":"t-1","parameters":{"command":"cargo test"}}'
+echo '{"type":"tool_result","tool_id":"t-1","status":"success","output":"ok"}'
+echo '{"type":"message","role":"assistant","content":"Added the tests."}'
+echo '{"type":"result","status":"success","stats":{"total_tokens":120,"input_tokens":90,"output_tokens":30,"duration_ms":700,"tool_calls":1}}'
+"#;
+    write_script(&path, script);
+    path
+}
+
 /// Create a mock OpenCode binary that outputs JSON events (current format).
 fn create_mock_opencode_binary(dir: &std::path::Path) -> PathBuf {
     let path = dir.join("opencode");
@@ -160,6 +175,67 @@ async fn codex_mock_stream() {
     assert!(events.iter().any(|e| matches!(e, Event::Result(r) if r.success)));
 }
 
+#[tokio::test]
+async fn gemini_mock_stream() {
+    let dir = tempfile::tempdir().unwrap();
+    let binary = create_mock_gemini_binary(dir.path());
+
+    let mut config = TaskConfig::new("add tests", AgentKind::Gemini);
+    config.binary_path = Some(binary);
+    config.cwd = Some(dir.path().to_path_buf());
+
+    let mut stream = run_task_retry(&config).await;
+    let mut events = Vec::new();
+    while let Some(result) = stream.next().await {
+        match result {
+            Ok(event) => events.push(event),
+            Err(e) => panic!("unexpected error: {e}"),
+        }
+    }
+
+    // Expected: SessionStart, Message(user), ToolStart, ToolEnd, Message(assistant), UsageDelta, Result
+    assert!(events.len() >= 6, "expected >= 6 events, got {}: {events:?}", events.len());
+
+    assert!(matches!(&events[0], Event::SessionStart(s) if s.session_id == "gem-mock" && s.agent == "gemini"));
+    assert!(matches!(&events[1], Event::Message(m) if m.role == Role::User && m.text == "add tests"));
+    assert!(matches!(&events[2], Event::ToolStart(t) if t.tool_name == "run_shell_command"));
+    assert!(matches!(&events[3], Event::ToolEnd(t) if t.call_id == "t-1" && t.success));
+    assert!(matches!(&events[4], Event::Message(m) if m.text == "Added the tests."));
+    assert!(events.iter().any(|e| matches!(e, Event::Result(r)
+        if r.success && r.session_id == "gem-mock" && r.text == "Added the tests.")));
+}
+
+#[tokio::test]
+async fn gemini_delta_only_answer_fills_result_text() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("gemini");
+    write_script(
+        &path,
+        r#"#!/bin/bash
+echo '{"type":"init","session_id":"gem-delta","model":"gemini-2.5-pro"}'
+echo '{"type":"message","role":"assistant","content":"Checking. ","delta":true}'
+echo '{"type":"tool_use","tool_name":"read_file","tool_id":"t-1","parameters":{}}'
+echo '{"type":"tool_result","tool_id":"t-1","status":"success","output":"ok"}'
+echo '{"type":"error","severity":"warning","message":"slow response"}'
+echo '{"type":"message","role":"assistant","content":"All ","delta":true}'
+echo '{"type":"message","role":"assistant","content":"good.","delta":true}'
+echo '{"type":"result","status":"success","stats":{"input_tokens":9,"output_tokens":3}}'
+"#,
+    );
+    let mut config = TaskConfig::new("check", AgentKind::Gemini);
+    config.binary_path = Some(path);
+    config.cwd = Some(dir.path().to_path_buf());
+
+    let events: Vec<Event> = run_task_retry(&config)
+        .await
+        .map(|r| r.unwrap())
+        .collect()
+        .await;
+    assert!(!events.iter().any(|e| matches!(e, Event::Error(_))), "{events:?}");
+    assert!(events.iter().any(|e| matches!(e, Event::Result(r)
+        if r.success && r.text == "All good.")), "{events:?}");
+}
+
 #[tokio::test]
 async fn cursor_mock_stream() {
     let dir = tempfile::tempdir().unwrap();
@@ -295,3 +371,302 @@ echo '{"type":"result","subtype":"success","result":"args: '"$*"'","session_id":
         "extra args not passed through: {result_text}"
     );
 }
+
+#[tokio::test]
+async fn command_prefix_wraps_agent_binary() {
+    let dir = tempfile::tempdir().unwrap();
+    let binary = create_mock_claude_binary(dir.path());
+
+    // The wrapper records its argv, then runs the real command.
+    let log = dir.path().join("wrapper.log");
+    let wrapper = dir.path().join("wrap.sh");
+    write_script(
+        &wrapper,
+        &format!("#!/bin/bash\necho \"$@\" > '{}'\nexec \"$@\"\n", log.display()),
+    );
+
+    let mut config = TaskConfig::new("analyze code", AgentKind::Claude);
+    config.binary_path = Some(binary.clone());
+    config.cwd = Some(dir.path().to_path_buf());
+    config.command_prefix = vec![wrapper.display().to_string()];
+
+    let mut stream = run_task_retry(&config).await;
+    let mut saw_result = false;
+    while let Some(Ok(event)) = stream.next().await {
+        saw_result |= matches!(event, Event::Result(ref r) if r.text == "Analysis complete.");
+    }
+    assert!(saw_result, "wrapped agent did not produce its result");
+
+    let recorded = std::fs::read_to_string(&log).unwrap();
+    assert!(
+        recorded.starts_with(&format!("{} -p analyze code", binary.display())),
+        "wrapper argv: {recorded}"
+    );
+}
+
+// ─── Raw mode ────────────────────────────────────────────────────
+
+#[tokio::test]
+async fn raw_mode_skips_normalization() {
+    let dir = tempfile::tempdir().unwrap();
+    let binary = dir.path().join("claude");
+    write_script(
+        &binary,
+        r#"#!/bin/bash
+echo '{"type":"system","subtype":"init","session_id":"mock-session","model":"mock-model"}'
+echo '{"type":"assistant","message":{"role":"assistant","content":[{"type":"text","text":"Looks good."}]}}'
+echo '{"type":"result","subtype":"success","result":"Looks good.","session_id":"mock-session","usage":{"input_tokens":10,"output_tokens":4}}'
+"#,
+    );
+    let config = TaskConfig::builder("review", AgentKind::Claude)
+        .binary_path(&binary)
+        .cwd(dir.path())
+        .build();
+
+    // The normalized run synthesizes the prompt message and a usage delta
+    // (and gets any ETXTBSY retries out of the way).
+    let normalized: Vec<Event> = run_task_retry(&config)
+        .await
+        .map(|r| r.unwrap())
+        .collect()
+        .await;
+    assert!(normalized
+        .iter()
+        .any(|e| matches!(e, Event::Message(m) if m.role == Role::User)));
+    assert!(normalized.iter().any(|e| matches!(e, Event::UsageDelta(_))));
+
+    let handle = harness::run_task_raw(&config, None).await.unwrap();
+    let raw: Vec<Event> = handle.stream.map(|r| r.unwrap()).collect().await;
+    assert_eq!(raw.len(), 3, "{raw:?}");
+    assert!(matches!(&raw[0], Event::SessionStart(s) if s.session_id == "mock-session"));
+    assert!(matches!(&raw[1], Event::Message(m) if m.role == Role::Assistant));
+    assert!(matches!(&raw[2], Event::Result(r) if r.success));
+}
+
+#[tokio::test]
+async fn events_are_stamped_with_non_decreasing_receive_times() {
+    let dir = tempfile::tempdir().unwrap();
+    let binary = dir.path().join("claude");
+    write_script(
+        &binary,
+        r#"#!/bin/bash
+echo '{"type":"system","subtype":"init","session_id":"mock-session","model":"mock-model"}'
+sleep 0.05
+echo '{"type":"assistant","message":{"role":"assistant","content":[{"type":"text","text":"Working."}]}}'
+echo '{"type":"assistant","message":{"role":"assistant","content":[{"type":"tool_use","id":"t1","name":"Bash","input":{"command":"ls"}}]}}'
+sleep 0.05
+echo '{"type":"result","subtype":"success","result":"Done.","session_id":"mock-session"}'
+"#,
+    );
+    let config = TaskConfig::builder("review", AgentKind::Claude)
+        .binary_path(&binary)
+        .cwd(dir.path())
+        .build();
+    // Absorb any ETXTBSY retries before timing the raw stream.
+    let _ = run_task_retry(&config).await.collect::<Vec<_>>().await;
+
+    let before = now_ms();
+    let handle = harness::run_task_raw(&config, None).await.unwrap();
+    let raw: Vec<Event> = handle.stream.map(|r| r.unwrap()).collect().await;
+    assert_eq!(raw.len(), 4, "{raw:?}");
+    let stamps: Vec<u64> = raw.iter().map(Event::timestamp_ms).collect();
+    assert!(stamps[0] >= before, "{stamps:?}");
+    assert!(stamps.windows(2).all(|w| w[0] <= w[1]), "{stamps:?}");
+    // The sleeps show up as gaps, so these are receive times, not one stamp.
+    assert!(stamps[3] - stamps[0] >= 100, "{stamps:?}");
+}
+
+#[tokio::test]
+async fn raw_stream_is_still_redacted() {
+    let dir = tempfile::tempdir().unwrap();
+    let binary = dir.path().join("claude");
+    write_script(
+        &binary,
+        r#"#!/bin/bash
+echo '{"type":"assistant","message":{"role":"assistant","content":[{"type":"text","text":"key sk-ant-REDACTED"}]}}'
+echo '{"type":"result","subtype":"success","result":"Done.","session_id":"mock-session"}'
+"#,
+    );
+    let config = TaskConfig::builder("review", AgentKind::Claude)
+        .binary_path(&binary)
+        .cwd(dir.path())
+        .build();
+    let _ = run_task_retry(&config).await.collect::<Vec<_>>().await;
+
+    let handle = harness::run_task_raw(&config, None).await.unwrap();
+    let raw: Vec<Event> = handle.stream.map(|r| r.unwrap()).collect().await;
+    assert_eq!(raw.len(), 2, "{raw:?}");
+    assert!(
+        matches!(&raw[0], Event::Message(m) if m.text == "key ***"),
+        "{raw:?}"
+    );
+}
+
+// ─── Stall timeout ───────────────────────────────────────────────
+
+#[tokio::test]
+async fn stall_timeout_cancels_quiet_agent() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("claude");
+    write_script(
+        &path,
+        r#"#!/bin/bash
+echo '{"type":"system","subtype":"init","session_id":"s1","model":"m"}'
+sleep 30
+echo '{"type":"result","subtype":"success","result":"late","session_id":"s1"}'
+"#,
+    );
+    let config = TaskConfig::builder("hello", AgentKind::Claude)
+        .binary_path(&path)
+        .cwd(dir.path())
+        .stall_timeout_secs(1)
+        .build();
+
+    let start = std::time::Instant::now();
+    let events: Vec<Event> = run_task_retry(&config)
+        .await
+        .filter_map(|r| async move { r.ok() })
+        .collect()
+        .await;
+    assert!(start.elapsed() < std::time::Duration::from_secs(10));
+    assert!(matches!(events.first(), Some(Event::SessionStart(_))));
+    // The stop error is followed only by the run's Summary.
+    match &events[events.len().saturating_sub(2)..] {
+        [Event::Error(e), Event::Summary(_)] => {
+            assert_eq!(e.code.as_deref(), Some("stall_timeout"))
+        }
+        other => panic!("expected a stall_timeout error and a summary, got {other:?}"),
+    }
+    assert!(!events.iter().any(|e| matches!(e, Event::Result(_))));
+}
+
+// ─── Retry ───────────────────────────────────────────────────────
+
+#[tokio::test]
+async fn retry_policy_reruns_after_rate_limit() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("claude");
+    let marker = dir.path().join("attempted");
+    let script = format!(
+        r#"#!/bin/bash
+echo '{{"type":"system","subtype":"init","session_id":"s1","model":"m"}}'
+if [ ! -f "{marker}" ]; then
+  touch "{marker}"
+  echo '{{"type":"result","subtype":"error_during_execution","is_error":true,"result":"API Error: rate limit exceeded","session_id":"s1"}}'
+  exit 0
+fi
+echo '{{"type":"result","subtype":"success","result":"recovered","session_id":"s1"}}'
+"#,
+        marker = marker.display()
+    );
+    write_script(&path, &script);
+
+    let config = TaskConfig::builder("hello", AgentKind::Claude)
+        .binary_path(&path)
+        .cwd(dir.path())
+        .retry(harness::RetryPolicy {
+            max_retries: 2,
+            retry_on: vec!["rate_limit".into()],
+            delay: std::time::Duration::ZERO,
+            ..Default::default()
+        })
+        .build();
+
+    let events: Vec<Event> = run_task_retry(&config)
+        .await
+        .filter_map(|r| async move { r.ok() })
+        .collect()
+        .await;
+    let results: Vec<&ResultEvent> = events
+        .iter()
+        .filter_map(|e| match e {
+            Event::Result(r) => Some(r),
+            _ => None,
+        })
+        .collect();
+    assert_eq!(results.len(), 1, "{events:?}");
+    assert!(results[0].success);
+    assert_eq!(results[0].text, "recovered");
+    // The retried attempt leaves nothing behind.
+    let count = |kind: &str| {
+        events
+            .iter()
+            .filter(|e| serde_json::to_value(e).unwrap()["type"] == kind)
+            .count()
+    };
+    assert_eq!(count("session_start"), 1, "{events:?}");
+    assert_eq!(count("result"), 1, "{events:?}");
+    assert_eq!(count("summary"), 1, "{events:?}");
+    assert_eq!(count("message"), 1, "{events:?}");
+}
+
+#[tokio::test]
+async fn retry_policy_does_not_retry_setup_errors() {
+    let config = TaskConfig::builder("hello", AgentKind::Claude)
+        .binary_path("/nonexistent/path/claude_binary_xyz")
+        .cwd(std::env::temp_dir())
+        .retry(harness::RetryPolicy {
+            max_retries: 5,
+            delay: std::time::Duration::from_secs(10),
+            ..Default::default()
+        })
+        .build();
+
+    let start = std::time::Instant::now();
+    assert!(harness::run_task(&config).await.is_err());
+    assert!(start.elapsed() < std::time::Duration::from_secs(5));
+}
+
+// ─── Fanout ──────────────────────────────────────────────────────
+
+#[tokio::test]
+async fn fanout_runs_all_configs_in_order() {
+    let dir = tempfile::tempdir().unwrap();
+    let claude = create_mock_claude_binary(dir.path());
+    let codex = create_mock_codex_binary(dir.path());
+
+    let mut claude_config = TaskConfig::new("analyze code", AgentKind::Claude);
+    claude_config.binary_path = Some(claude);
+    let mut codex_config = TaskConfig::new("fix bug", AgentKind::Codex);
+    codex_config.binary_path = Some(codex);
+    // Absorb any ETXTBSY retries before the fanout spawns the mocks.
+    for config in [&claude_config, &codex_config] {
+        let _ = run_task_retry(config).await.collect::<Vec<_>>().await;
+    }
+
+    let outcomes = harness::fanout::run_all(vec![claude_config, codex_config], 1).await;
+    assert_eq!(outcomes.len(), 2);
+    assert_eq!(outcomes[0].config.agent, AgentKind::Claude);
+    assert_eq!(outcomes[1].config.agent, AgentKind::Codex);
+    for outcome in &outcomes {
+        assert!(outcome.error.is_none(), "unexpected error: {:?}", outcome.error);
+        assert!(outcome.result().is_some(), "missing result: {:?}", outcome.events);
+    }
+}
+
+// ─── Concatenated JSON ───────────────────────────────────────────
+
+#[tokio::test]
+async fn concatenated_json_objects_on_one_line_are_all_parsed() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("claude");
+    let script = r#"#!/bin/bash
+echo '{"type":"system","subtype":"init","session_id":"cat-1","model":"m"}{"type":"assistant","message":{"role":"assistant","content":[{"type":"text","text":"first"}]}}'
+echo '{"type":"result","subtype":"success","result":"done","session_id":"cat-1"}'
+"#;
+    write_script(&path, script);
+
+    let mut config = TaskConfig::new("go", AgentKind::Claude);
+    config.binary_path = Some(path);
+    let stream = run_task_retry(&config).await;
+    let events: Vec<Event> = stream.map(|r| r.unwrap()).collect().await;
+
+    assert!(
+        events.iter().any(|e| matches!(e, Event::SessionStart(s) if s.session_id == "cat-1")),
+        "session start missing: {events:?}"
+    );
+    assert!(
+        events.iter().any(|e| matches!(e, Event::Message(m) if m.text == "first")),
+        "assistant message missing: {events:?}"
+    );
+}